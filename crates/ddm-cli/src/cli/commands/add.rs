@@ -1,17 +1,235 @@
 //! `ddm add <url>` – add a new download job.
 
-use anyhow::Result;
-use ddm_core::resume_db::{JobSettings, ResumeDb};
+use anyhow::{bail, Context, Result};
+use ddm_core::checksum::{self, ChecksumAlgo};
+use ddm_core::host_policy::HostPolicy;
+use ddm_core::resume_db::{JobSettings, JobState, ResumeDb};
+use ddm_core::scheduler::JobConfig;
+use ddm_core::{bench, fetch_head, scheduler, url_model};
+use std::collections::HashMap;
 use std::path::Path;
 
-/// Adds a job for the given URL. If `download_dir` is None, the job will use
-/// the current directory at run time (legacy behavior).
-pub async fn run_add(db: &ResumeDb, url: &str, download_dir: Option<&Path>) -> Result<()> {
-    let mut settings = JobSettings::default();
+/// Adds a job for the given URL with the per-job `settings` built from the `add` flags
+/// (priority, note, deadline, segment count and bounds). If `download_dir` is None, the job
+/// will use the current directory at run time (legacy behavior). With `start_paused` the job
+/// is inserted as Paused instead of Queued. A `--output` name is stored sanitized.
+pub async fn run_add(
+    db: &ResumeDb,
+    url: &str,
+    download_dir: Option<&Path>,
+    mut settings: JobSettings,
+    start_paused: bool,
+) -> Result<()> {
+    validate_segment_bounds(settings.min_segments, settings.max_segments)?;
+    if let Some(name) = settings.output_filename.take() {
+        let forced = url_model::forced_filename(&name)
+            .ok_or_else(|| anyhow::anyhow!("--output {:?} is not a usable filename", name))?;
+        settings.output_filename = Some(forced);
+    }
     if let Some(dir) = download_dir {
         settings.download_dir = Some(dir.to_string_lossy().to_string());
     }
-    let id = db.add_job(url, &settings).await?;
-    println!("Added job {id} for URL: {url}");
+    if start_paused {
+        let id = db
+            .add_job_in_state(url, &settings, JobState::Paused)
+            .await?;
+        println!("Added job {id} (paused; start it with `ddm resume {id}`) for URL: {url}");
+    } else {
+        let id = db.add_job(url, &settings).await?;
+        println!("Added job {id} for URL: {url}");
+    }
+    Ok(())
+}
+
+/// Rejects zero bounds and `--min-segments` above `--max-segments`.
+pub(crate) fn validate_segment_bounds(min: Option<usize>, max: Option<usize>) -> Result<()> {
+    if min == Some(0) || max == Some(0) {
+        bail!("--min-segments and --max-segments must be at least 1");
+    }
+    if let (Some(min), Some(max)) = (min, max) {
+        if min > max {
+            bail!("--min-segments ({min}) is greater than --max-segments ({max})");
+        }
+    }
+    Ok(())
+}
+
+/// `ddm add --headers-file`: read `Name: value` lines into a header map. Blank lines and
+/// lines starting with `#` are skipped.
+pub fn load_headers_file(path: &Path) -> Result<HashMap<String, String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("read headers file {}", path.display()))?;
+    parse_headers_text(&text).with_context(|| format!("headers file {}", path.display()))
+}
+
+/// Parse `Name: value` lines; malformed lines fail with their 1-based line number.
+pub(crate) fn parse_headers_text(text: &str) -> Result<HashMap<String, String>> {
+    let mut headers = HashMap::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow::anyhow!("line {}: expected `Name: value`", i + 1))?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("line {}: invalid header name {:?}", i + 1, name);
+        }
+        headers.insert(name.to_string(), value.trim().to_string());
+    }
+    Ok(headers)
+}
+
+/// `ddm add --md5/--sha1/--sha256`: the expected whole-file digest, validated and lowercased.
+/// At most one algorithm may be given.
+pub fn expected_checksum(
+    md5: Option<String>,
+    sha1: Option<String>,
+    sha256: Option<String>,
+) -> Result<Option<(ChecksumAlgo, String)>> {
+    let given: Vec<(ChecksumAlgo, String)> = [
+        (ChecksumAlgo::Md5, md5),
+        (ChecksumAlgo::Sha1, sha1),
+        (ChecksumAlgo::Sha256, sha256),
+    ]
+    .into_iter()
+    .filter_map(|(algo, hex)| hex.map(|h| (algo, h)))
+    .collect();
+    match given.as_slice() {
+        [] => Ok(None),
+        [(algo, hex)] => Ok(Some((*algo, algo.normalize_hex(hex)?))),
+        _ => bail!("give only one of --md5, --sha1, --sha256"),
+    }
+}
+
+/// `ddm add --checksums-url`: fetch the sums file and return the entry for the filename
+/// `url` will be saved under. Fails when the file has no line for it.
+pub async fn checksum_from_sums_url(
+    cfg: &JobConfig<'_>,
+    sums_url: &str,
+    url: &str,
+) -> Result<(ChecksumAlgo, String)> {
+    let text = tokio::task::spawn_blocking({
+        let sums_url = sums_url.to_string();
+        let curl = cfg.curl_options(1);
+        move || fetch_head::fetch_text(&sums_url, &HashMap::new(), &curl)
+    })
+    .await
+    .context("checksums fetch task join")?
+    .with_context(|| format!("could not fetch checksums from {sums_url}"))?;
+    let filename = url_model::derive_filename(url, None);
+    let (algo, hex) = checksum::find_in_sums(&text, &filename)
+        .ok_or_else(|| anyhow::anyhow!("{sums_url} has no checksum line for {filename:?}"))?;
+    println!("Expected {}: {hex} (from {sums_url})", algo.as_str());
+    Ok((algo, hex))
+}
+
+/// `ddm add --mirror-list`: read one mirror URL per line. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn load_mirror_list(path: &Path) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("read mirror list {}", path.display()))?;
+    parse_mirror_list_text(&text).with_context(|| format!("mirror list {}", path.display()))
+}
+
+/// Parse mirror URLs; lines that are not http/https/file URLs fail with their 1-based line number.
+pub(crate) fn parse_mirror_list_text(text: &str) -> Result<Vec<String>> {
+    let mut mirrors = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !url_model::is_download_url(line) {
+            bail!("line {}: not an http, https or file URL: {:?}", i + 1, line);
+        }
+        if !mirrors.iter().any(|m| m == line) {
+            mirrors.push(line.to_string());
+        }
+    }
+    if mirrors.is_empty() {
+        bail!("no mirror URLs found");
+    }
+    Ok(mirrors)
+}
+
+/// `ddm add --auto-bench`: benchmark the URL (capped at the default bench size) and return
+/// the recommended segment count. Returns None (adaptive choice at run time) when the
+/// server lacks Range support or the bench fails.
+pub async fn auto_bench_segment_count(
+    cfg: &JobConfig<'_>,
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> Option<usize> {
+    let result = tokio::task::spawn_blocking({
+        let url = url.to_string();
+        let cfg = cfg.clone().into_owned();
+        let headers = headers.clone();
+        move || bench::run_bench(&url, &headers, &cfg, None, &bench::DEFAULT_BENCH_COUNTS)
+    })
+    .await
+    .context("bench task join")
+    .and_then(|r| r);
+    match result {
+        Ok(results) => {
+            let rec = bench::recommend_segment_count(&results);
+            match rec {
+                Some(n) => println!("Auto-bench: using {n} segments"),
+                None => println!("Auto-bench: no result; using adaptive segment count"),
+            }
+            rec
+        }
+        Err(e) => {
+            println!("Auto-bench skipped ({e:#}); using adaptive segment count");
+            None
+        }
+    }
+}
+
+/// `ddm add --dry-run`: probe the URL and report what a job would do, without adding it.
+pub async fn run_add_dry_run(
+    cfg: &JobConfig<'_>,
+    url: &str,
+    headers: &HashMap<String, String>,
+) -> Result<()> {
+    let head = tokio::task::spawn_blocking({
+        let url = url.to_string();
+        let curl = cfg.curl_options(1);
+        let headers = headers.clone();
+        let strategy = cfg.probe_strategy.unwrap_or_default();
+        move || fetch_head::probe_with_strategy(&url, &headers, &curl, strategy)
+    })
+    .await
+    .context("probe task join")?
+    .context("probe failed")?;
+
+    let host_policy = HostPolicy::default_path()
+        .and_then(|p| HostPolicy::load_from_path(&p, cfg.min_segments, cfg.max_segments))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| HostPolicy::new(cfg.min_segments, cfg.max_segments));
+    let filename = url_model::derive_filename(url, head.content_disposition.as_deref());
+    let segments = match head.content_length {
+        Some(len) if head.accepts_ranges() => {
+            scheduler::choose_segment_count(len, &JobSettings::default(), cfg, url, &host_policy)
+                .to_string()
+        }
+        _ => "1 (single stream; no usable Range support)".to_string(),
+    };
+
+    println!("URL:            {url}");
+    println!("Filename:       {filename}");
+    println!(
+        "Content-Length: {}",
+        head.content_length
+            .map(|n| n.to_string())
+            .unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("Range support:  {:?}", head.range_support);
+    println!("ETag:           {}", head.etag.as_deref().unwrap_or("-"));
+    println!("Segments:       {segments}");
+    println!("Dry run: no job added.");
     Ok(())
 }
@@ -0,0 +1,69 @@
+//! `ddm add-index <url>` – add a job per file listed on a directory index page.
+
+use anyhow::{bail, Context, Result};
+use ddm_core::resume_db::{JobSettings, ResumeDb};
+use ddm_core::scheduler::JobConfig;
+use ddm_core::{fetch_head, url_model};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use super::add::run_add;
+
+/// Fetches the Apache/nginx autoindex page at `url` and adds a job for each file it links
+/// to (only names matching `pattern`, when given). Files that already have a job are
+/// skipped, so running it again after an interruption only adds what is missing. The page
+/// is fetched with `cfg`'s curl settings; the jobs keep its `insecure`.
+pub async fn run_add_index(
+    db: &ResumeDb,
+    cfg: &JobConfig<'_>,
+    url: &str,
+    pattern: Option<&str>,
+    download_dir: Option<&Path>,
+) -> Result<()> {
+    if !url_model::is_download_url(url) {
+        bail!("not an http, https or file URL: {url}");
+    }
+    let html = tokio::task::spawn_blocking({
+        let url = url.to_string();
+        let curl = cfg.curl_options(1);
+        move || fetch_head::fetch_text(&url, &HashMap::new(), &curl)
+    })
+    .await
+    .context("index fetch task join")?
+    .with_context(|| format!("could not fetch index page {url}"))?;
+    let links = url_model::index_file_links(&html, url, pattern)?;
+    if links.is_empty() {
+        match pattern {
+            Some(p) => bail!("no file links matching {p:?} on {url}"),
+            None => bail!("no file links on {url}"),
+        }
+    }
+    let settings = JobSettings {
+        insecure: cfg.insecure,
+        ..JobSettings::default()
+    };
+    let (created, skipped) = add_index_links(db, &links, download_dir, &settings).await?;
+    if skipped > 0 {
+        println!("Created {created} job(s) from {url} ({skipped} already had a job)");
+    } else {
+        println!("Created {created} job(s) from {url}");
+    }
+    Ok(())
+}
+
+/// Adds a job with `settings` per link that no existing job downloads; returns
+/// (created, skipped).
+pub(crate) async fn add_index_links(
+    db: &ResumeDb,
+    links: &[String],
+    download_dir: Option<&Path>,
+    settings: &JobSettings,
+) -> Result<(usize, usize)> {
+    let existing: HashSet<String> = db.list_jobs().await?.into_iter().map(|j| j.url).collect();
+    let mut created = 0;
+    for link in links.iter().filter(|l| !existing.contains(*l)) {
+        run_add(db, link, download_dir, settings.clone(), false).await?;
+        created += 1;
+    }
+    Ok((created, links.len() - created))
+}
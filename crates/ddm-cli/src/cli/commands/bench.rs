@@ -2,20 +2,38 @@
 
 use anyhow::{Context, Result};
 use ddm_core::bench::{self, BenchResult};
-use ddm_core::config;
+use ddm_core::config::DdmConfig;
+use ddm_core::scheduler::JobConfig;
 use std::collections::HashMap;
+use std::fmt::Write;
 
-fn print_bench_results(results: &[BenchResult]) {
-    println!(
+/// Output format for `ddm bench --format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum BenchFormat {
+    /// Aligned table followed by the recommendation.
+    #[default]
+    Table,
+    /// `{"results": [...], "recommended_segment_count": N}`.
+    Json,
+    /// One row per run; the `recommended` column marks the recommended count.
+    Csv,
+}
+
+fn render_table(results: &[BenchResult], recommended: Option<usize>) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
         "  {:>6}  {:>10}  {:>8}  {:>8}  {:>8}  {:>8}",
         "Segs", "Bytes", "Time(s)", "MiB/s", "Throttle", "Errors"
     );
-    println!(
+    let _ = writeln!(
+        out,
         "  {}  {}  {}  {}  {}  {}",
         "------", "----------", "--------", "--------", "--------", "------"
     );
     for r in results {
-        println!(
+        let _ = writeln!(
+            out,
             "  {:>6}  {:>10}  {:>8.2}  {:>8.2}  {:>8}  {:>8}",
             r.segment_count,
             r.bytes_downloaded,
@@ -25,21 +43,74 @@ fn print_bench_results(results: &[BenchResult]) {
             r.error_events
         );
     }
+    if let Some(rec) = recommended {
+        let _ = writeln!(out, "Recommended segment count: {}", rec);
+    }
+    out
+}
+
+fn render_csv(results: &[BenchResult], recommended: Option<usize>) -> String {
+    let mut out = String::from(
+        "segment_count,bytes_downloaded,elapsed_secs,throughput_mib_s,throttle_events,error_events,recommended\n",
+    );
+    for r in results {
+        let _ = writeln!(
+            out,
+            "{},{},{:.3},{:.3},{},{},{}",
+            r.segment_count,
+            r.bytes_downloaded,
+            r.elapsed_secs,
+            r.throughput_mib_s,
+            r.throttle_events,
+            r.error_events,
+            recommended == Some(r.segment_count)
+        );
+    }
+    out
 }
 
-pub async fn run_bench(url: &str) -> Result<()> {
-    let cfg = config::load_or_init()?;
+/// Bench results (and the recommended segment count, if any) rendered in `format`.
+pub(crate) fn render_bench(
+    results: &[BenchResult],
+    recommended: Option<usize>,
+    format: BenchFormat,
+) -> Result<String> {
+    Ok(match format {
+        BenchFormat::Table => render_table(results, recommended),
+        BenchFormat::Csv => render_csv(results, recommended),
+        BenchFormat::Json => {
+            let value = serde_json::json!({
+                "results": results,
+                "recommended_segment_count": recommended,
+            });
+            serde_json::to_string_pretty(&value)? + "\n"
+        }
+    })
+}
+
+/// Runs the benchmark for `url`; `max_bytes` overrides the per-run download cap and `counts`
+/// the segment counts tried (default 4, 8, 16).
+pub async fn run_bench(
+    cfg: &DdmConfig,
+    url: &str,
+    max_bytes: Option<u64>,
+    counts: Vec<usize>,
+    format: BenchFormat,
+) -> Result<()> {
     let headers = HashMap::new();
     let results = tokio::task::spawn_blocking({
         let url = url.to_string();
         let cfg = cfg.clone();
-        move || bench::run_bench(&url, &headers, &cfg, None)
+        let counts = if counts.is_empty() {
+            bench::DEFAULT_BENCH_COUNTS.to_vec()
+        } else {
+            counts
+        };
+        move || bench::run_bench(&url, &headers, &JobConfig::from(&cfg), max_bytes, &counts)
     })
     .await
     .context("bench task join")??;
-    print_bench_results(&results);
-    if let Some(rec) = bench::recommend_segment_count(&results) {
-        println!("Recommended segment count: {}", rec);
-    }
+    let recommended = bench::recommend_segment_count(&results);
+    print!("{}", render_bench(&results, recommended, format)?);
     Ok(())
 }
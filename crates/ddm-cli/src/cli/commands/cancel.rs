@@ -0,0 +1,106 @@
+//! `ddm cancel <id>` – stop a job, delete its `.part` file, and remove it from the DB.
+
+use anyhow::{bail, Result};
+use ddm_core::resume_db::{JobDetails, JobState, ResumeDb};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::cli::control_socket;
+
+/// How long `ddm cancel` waits for a running job to stop before giving up.
+const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cancels a job: signals a running `ddm run` to stop it and waits until it has, deletes
+/// the temp (`.part`) file from the job's stored download_dir (or `download_dir`), then
+/// removes the row. The finalized file is only deleted when `purge_final` is true.
+pub async fn run_cancel(
+    db: &ResumeDb,
+    id: i64,
+    purge_final: bool,
+    download_dir: Option<&Path>,
+) -> Result<()> {
+    let job = db
+        .get_job(id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+
+    let path = ddm_core::control::control_socket_path(db.state_dir());
+    let signalled = control_socket::send_cancel(&path, id).await;
+    let job = if is_active(job.state) {
+        if !matches!(signalled, Ok(true)) {
+            bail!(
+                "job {id} is {} but no `ddm run` took the cancel; stop the run (or let it \
+                 recover the job) and try again",
+                job.state.as_str()
+            );
+        }
+        wait_until_stopped(db, id).await?
+    } else {
+        job
+    };
+
+    let dir = job
+        .settings
+        .download_dir
+        .as_deref()
+        .map(Path::new)
+        .or(download_dir)
+        .unwrap_or_else(|| Path::new("."));
+    let mut freed = 0u64;
+    if let Some(ref name) = job.temp_filename {
+        freed += delete_file(&dir.join(name)).await;
+    }
+    if purge_final {
+        if let Some(ref name) = job.final_filename {
+            freed += delete_file(&dir.join(name)).await;
+        }
+    }
+
+    db.remove_job(id).await?;
+    println!("Cancelled job {id} (freed {freed} bytes)");
+    Ok(())
+}
+
+fn is_active(state: JobState) -> bool {
+    matches!(state, JobState::Running | JobState::Verifying)
+}
+
+/// Polls the job until the run has stopped it, so nothing writes the `.part` or the row
+/// while they are removed. Returns the job as the run left it.
+async fn wait_until_stopped(db: &ResumeDb, id: i64) -> Result<JobDetails> {
+    let started = Instant::now();
+    loop {
+        let job = db
+            .get_job(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+        if !is_active(job.state) {
+            return Ok(job);
+        }
+        if started.elapsed() >= STOP_TIMEOUT {
+            bail!(
+                "job {id} did not stop within {}s; nothing was deleted",
+                STOP_TIMEOUT.as_secs()
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Deletes `path` if present and returns its size in bytes (0 if missing or not deleted).
+async fn delete_file(path: &Path) -> u64 {
+    let size = match tokio::fs::metadata(path).await {
+        Ok(m) => m.len(),
+        Err(_) => return 0,
+    };
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {
+            tracing::debug!(path = %path.display(), "deleted file");
+            size
+        }
+        Err(e) => {
+            tracing::warn!(path = %path.display(), "could not delete file: {}", e);
+            0
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! `ddm cleanup` – delete `.part` files in a directory that no job in the DB owns.
+
+use anyhow::{Context, Result};
+use ddm_core::resume_db::ResumeDb;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Lists `.part` files in `dir` that no job claims and deletes them (or only lists them
+/// with `dry_run`). Files of any job still in the DB, whatever its state, are left alone.
+pub async fn run_cleanup(db: &ResumeDb, dir: &Path, dry_run: bool) -> Result<()> {
+    let owned = db.list_temp_filenames().await?;
+    let orphans = find_orphaned_parts(dir, &owned)?;
+    if orphans.is_empty() {
+        println!("No orphaned .part files in {}", dir.display());
+        return Ok(());
+    }
+
+    let mut bytes = 0u64;
+    let mut removed = 0usize;
+    for path in &orphans {
+        let size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if dry_run {
+            println!("Would delete {} ({} bytes)", path.display(), size);
+        } else if let Err(e) = std::fs::remove_file(path) {
+            eprintln!("Could not delete {}: {}", path.display(), e);
+            continue;
+        } else {
+            println!("Deleted {} ({} bytes)", path.display(), size);
+        }
+        bytes += size;
+        removed += 1;
+    }
+    if dry_run {
+        println!("{removed} orphaned .part file(s), {bytes} bytes (dry run; nothing deleted)");
+    } else {
+        println!("Deleted {removed} orphaned .part file(s), freed {bytes} bytes");
+    }
+    Ok(())
+}
+
+/// `.part` files directly in `dir` whose name is not the temp file of a job stored for that
+/// directory. Jobs without a stored download dir (legacy jobs resolve it at run time) protect
+/// their name in every directory; absolute temp names (staged in `temp_dir`) protect that exact
+/// file. Sorted by path.
+pub(crate) fn find_orphaned_parts(
+    dir: &Path,
+    owned: &[(Option<String>, String)],
+) -> Result<Vec<PathBuf>> {
+    let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+    let dir_canonical = canonical(dir);
+    let protected: HashSet<&str> = owned
+        .iter()
+        .filter_map(|(job_dir, name)| {
+            let path = Path::new(name);
+            if path.is_absolute() {
+                let parent = path.parent()?;
+                return (canonical(parent) == dir_canonical)
+                    .then(|| path.file_name()?.to_str())
+                    .flatten();
+            }
+            job_dir
+                .as_deref()
+                .is_none_or(|d| canonical(Path::new(d)) == dir_canonical)
+                .then_some(name.as_str())
+        })
+        .collect();
+
+    let mut orphans = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("read {}", dir.display()))? {
+        let entry = entry.with_context(|| format!("read {}", dir.display()))?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        let name = entry.file_name();
+        // Job temp names are UTF-8, so a non-UTF-8 name cannot be matched; leave it alone.
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        if name.ends_with(".part") && !protected.contains(name) {
+            orphans.push(entry.path());
+        }
+    }
+    orphans.sort();
+    Ok(orphans)
+}
@@ -0,0 +1,29 @@
+//! `ddm config show|path` – inspect the effective configuration.
+
+use anyhow::Result;
+use ddm_core::config::{self, DdmConfig};
+use std::path::{Path, PathBuf};
+
+/// Print the config file in use: `explicit` (`--config`) or the XDG default.
+pub fn run_config_path(explicit: Option<&Path>) -> Result<()> {
+    println!("{}", resolved_config_path(explicit)?.display());
+    Ok(())
+}
+
+/// Print the loaded config as TOML, headed by a comment naming its file.
+pub fn run_config_show(cfg: &DdmConfig, explicit: Option<&Path>) -> Result<()> {
+    print!("{}", render_config(cfg, &resolved_config_path(explicit)?)?);
+    Ok(())
+}
+
+fn resolved_config_path(explicit: Option<&Path>) -> Result<PathBuf> {
+    match explicit {
+        Some(p) => Ok(p.to_path_buf()),
+        None => config::config_path(),
+    }
+}
+
+/// `cfg` as TOML after a `# path` line. Options left unset (defaults apply) are omitted.
+pub(crate) fn render_config(cfg: &DdmConfig, path: &Path) -> Result<String> {
+    Ok(format!("# {}\n{}", path.display(), cfg.to_toml()?))
+}
@@ -0,0 +1,23 @@
+//! `ddm events` – show the log of job state changes.
+
+use anyhow::Result;
+use ddm_core::resume_db::ResumeDb;
+
+pub async fn run_events(db: &ResumeDb, since: Option<i64>, id: Option<i64>) -> Result<()> {
+    let changes = db.list_state_changes(since, id).await?;
+    if changes.is_empty() {
+        println!("No state changes recorded.");
+        return Ok(());
+    }
+    println!("TIMESTAMP       JOB  FROM          TO");
+    for c in changes {
+        println!(
+            "{:<12} {:>6}  {:<10} -> {}",
+            c.timestamp,
+            c.job_id,
+            c.from.map_or("(added)", |s| s.as_str()),
+            c.to.as_str()
+        );
+    }
+    Ok(())
+}
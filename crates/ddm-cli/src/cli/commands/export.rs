@@ -0,0 +1,49 @@
+//! `ddm export <id>` / `ddm import <file>` – move a job's plan (size, validators, segment
+//! layout, segment hashes) between machines as a JSON sidecar.
+
+use anyhow::{Context, Result};
+use ddm_core::resume_db::{JobExport, ResumeDb};
+use std::path::Path;
+
+/// Writes the plan of job `id` as JSON to `output`, or to stdout.
+pub async fn run_export(
+    db: &ResumeDb,
+    id: i64,
+    output: Option<&Path>,
+    include_sensitive: bool,
+) -> Result<()> {
+    let export = db.export_job(id, include_sensitive).await?;
+    let json = serde_json::to_string_pretty(&export)? + "\n";
+    match output {
+        Some(path) => {
+            std::fs::write(path, json)
+                .with_context(|| format!("write export: {}", path.display()))?;
+            println!("Exported job {id} to {}", path.display());
+        }
+        None => print!("{json}"),
+    }
+    Ok(())
+}
+
+/// Creates a queued job from a sidecar written by `ddm export`. A completion hook or
+/// `insecure` in the file is only kept with `trust`.
+pub async fn run_import(db: &ResumeDb, path: &Path, trust: bool) -> Result<()> {
+    let bytes = std::fs::read(path).with_context(|| format!("read export: {}", path.display()))?;
+    let export: JobExport = serde_json::from_slice(&bytes)
+        .with_context(|| format!("parse export: {}", path.display()))?;
+    let id = db
+        .import_job(&export, trust)
+        .await
+        .with_context(|| format!("could not import {}", path.display()))?;
+    if !trust && (export.settings.on_complete.is_some() || export.settings.insecure) {
+        eprintln!("Dropped the completion hook / insecure setting from the plan (use --trust to keep them)");
+    }
+    let hashed = export.segment_hashes.iter().flatten().count();
+    println!(
+        "Added job {id} for URL: {} ({} segments, {} hashed)",
+        export.url,
+        export.segments.len(),
+        hashed
+    );
+    Ok(())
+}
@@ -0,0 +1,34 @@
+//! `ddm history <id>` – show recorded download runs for a job.
+
+use anyhow::Result;
+use ddm_core::resume_db::ResumeDb;
+
+pub async fn run_history(db: &ResumeDb, id: i64) -> Result<()> {
+    let events = db.list_job_events(id).await?;
+    if events.is_empty() {
+        println!("No history for job {id}.");
+        return Ok(());
+    }
+    println!(
+        "{:<12} {:>12} {:>9} {:>11} {:>9} {:>7} {:>9}",
+        "TIMESTAMP", "BYTES", "SECS", "MiB/s", "SEGMENTS", "ERRORS", "THROTTLED"
+    );
+    for e in events {
+        let mib_per_sec = if e.duration_secs > 0.0 {
+            e.bytes_this_run as f64 / e.duration_secs / (1024.0 * 1024.0)
+        } else {
+            0.0
+        };
+        println!(
+            "{:<12} {:>12} {:>9.1} {:>11.2} {:>9} {:>7} {:>9}",
+            e.timestamp,
+            e.bytes_this_run,
+            e.duration_secs,
+            mib_per_sec,
+            e.segment_count,
+            e.error_events,
+            e.throttle_events
+        );
+    }
+    Ok(())
+}
@@ -1,12 +1,20 @@
 //! `ddm import-har <path>` – create job from HAR file.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use ddm_core::har;
 use ddm_core::resume_db::{JobSettings, ResumeDb};
 use std::path::Path;
 
-pub async fn run_import_har(db: &ResumeDb, path: &Path, allow_cookies: bool) -> Result<()> {
-    let spec = har::resolve_har(path, allow_cookies)?;
+pub async fn run_import_har(
+    db: &ResumeDb,
+    path: &Path,
+    allow_cookies: bool,
+    allow_post: bool,
+) -> Result<()> {
+    let spec = har::resolve_har(path, allow_cookies, allow_post)
+        .with_context(|| format!("could not import HAR {}", path.display()))?;
+    // Cookies go through curl's cookie engine so they survive redirects to other hosts.
+    let cookie_session = spec.headers.contains_key("Cookie");
     let settings = JobSettings {
         note: None,
         custom_headers: if spec.headers.is_empty() {
@@ -15,16 +23,30 @@ pub async fn run_import_har(db: &ResumeDb, path: &Path, allow_cookies: bool) ->
             Some(spec.headers)
         },
         download_dir: None,
+        priority: 0,
+        post: spec.post.clone(),
+        segment_count: None,
+        deadline_secs: None,
+        mirrors: None,
+        expected_checksum: None,
+        output_filename: None,
+        expected_size: None,
+        insecure: false,
+        min_segments: None,
+        max_segments: None,
+        user_agent: None,
+        on_complete: None,
+        order: Default::default(),
+        cookie_session,
+        max_bytes_per_sec: None,
     };
     let id = db.add_job(&spec.url, &settings).await?;
     println!("Added job {id} for URL: {}", spec.url);
-    if allow_cookies
-        && !settings
-            .custom_headers
-            .as_ref()
-            .map_or(true, |h| h.is_empty())
-    {
-        println!("  (cookies included; stored with job)");
+    if settings.cookie_session {
+        println!("  (cookies included; stored with job and resent across redirects)");
+    }
+    if settings.post.is_some() {
+        println!("  (POST body stored with job; replayed on each run to get the download URL)");
     }
     Ok(())
 }
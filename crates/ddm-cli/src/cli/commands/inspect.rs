@@ -0,0 +1,134 @@
+//! `ddm inspect <id>` – print every stored field of a job for debugging.
+
+use anyhow::Result;
+use ddm_core::resume_db::ResumeDb;
+use ddm_core::segmenter::SegmentBitmap;
+
+/// Prints the full job record: validators, filenames, segment layout, bitmap fill and settings.
+/// Custom header values are not printed (they may hold cookies or tokens).
+pub async fn run_inspect(db: &ResumeDb, id: i64) -> Result<()> {
+    let job = db
+        .get_job(id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+    let opt = |v: &Option<String>| v.clone().unwrap_or_else(|| "-".to_string());
+
+    println!("Job {}", job.id);
+    println!("  url:             {}", job.url);
+    println!("  state:           {}", job.state.as_str());
+    match (&job.last_error, job.error_category) {
+        (Some(msg), Some(cat)) => println!("  last_error:      [{}] {}", cat.as_str(), msg),
+        (Some(msg), None) => println!("  last_error:      {}", msg),
+        (None, _) => println!("  last_error:      -"),
+    }
+    println!("  created_at:      {}", job.created_at);
+    println!("  updated_at:      {}", job.updated_at);
+    println!("  final_filename:  {}", opt(&job.final_filename));
+    println!("  temp_filename:   {}", opt(&job.temp_filename));
+    println!(
+        "  total_size:      {}",
+        job.total_size
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+    println!("  etag:            {}", opt(&job.etag));
+    println!("  last_modified:   {}", opt(&job.last_modified));
+
+    let segment_count = job.segment_count.max(0) as usize;
+    let bitmap = SegmentBitmap::from_bytes(&job.completed_bitmap, segment_count);
+    let incomplete = bitmap.incomplete_indices(segment_count);
+    let done = segment_count - incomplete.len();
+    println!("  segment_count:   {}", segment_count);
+    if segment_count > 0 {
+        println!(
+            "  completed:       {}/{} ({:.1}%)",
+            done,
+            segment_count,
+            done as f64 * 100.0 / segment_count as f64
+        );
+        println!("  incomplete:      {}", format_index_ranges(&incomplete));
+    }
+    let hashed = job.segment_hashes.iter().filter(|h| h.is_some()).count();
+    println!("  segment_hashes:  {}", hashed);
+
+    let s = &job.settings;
+    println!("  priority:        {}", s.priority);
+    println!("  download_dir:    {}", opt(&s.download_dir));
+    println!("  note:            {}", opt(&s.note));
+    println!("  output:          {}", opt(&s.output_filename));
+    let opt_num = |v: Option<u64>| v.map_or_else(|| "-".to_string(), |n| n.to_string());
+    println!(
+        "  segments (set):  {}",
+        opt_num(s.segment_count.map(|n| n as u64))
+    );
+    println!(
+        "  segment bounds:  {}..{}",
+        opt_num(s.min_segments.map(|n| n as u64)),
+        opt_num(s.max_segments.map(|n| n as u64))
+    );
+    println!("  deadline_secs:   {}", opt_num(s.deadline_secs));
+    println!("  max_bytes/sec:   {}", opt_num(s.max_bytes_per_sec));
+    match s.mirrors.as_ref().filter(|m| !m.is_empty()) {
+        Some(mirrors) => {
+            println!("  mirrors:         {}", mirrors.len());
+            for m in mirrors {
+                println!("    {m}");
+            }
+        }
+        None => println!("  mirrors:         -"),
+    }
+    match &s.expected_checksum {
+        Some((algo, hex)) => println!("  checksum:        {} {}", algo.as_str(), hex),
+        None => println!("  checksum:        -"),
+    }
+    println!("  expected_size:   {}", opt_num(s.expected_size));
+    println!("  order:           {}", s.order.as_str());
+    println!("  user_agent:      {}", opt(&s.user_agent));
+    if s.insecure {
+        println!("  insecure:        yes (TLS certificates not verified)");
+    }
+    if s.cookie_session {
+        println!("  cookies:         sent across redirects (cookie engine)");
+    }
+    println!("  on_complete:     {}", opt(&s.on_complete));
+    println!(
+        "  post:            {}",
+        if s.post.is_some() { "yes" } else { "-" }
+    );
+    match s.custom_headers.as_ref().filter(|h| !h.is_empty()) {
+        Some(headers) => {
+            let mut names: Vec<&str> = headers.keys().map(String::as_str).collect();
+            names.sort_unstable();
+            println!("  custom_headers:  {} (values hidden)", names.join(", "));
+        }
+        None => println!("  custom_headers:  -"),
+    }
+    Ok(())
+}
+
+/// Compact ascending indices into ranges, e.g. `[0, 1, 2, 5, 7, 8]` -> `"0-2, 5, 7-8"`.
+pub(crate) fn format_index_ranges(indices: &[usize]) -> String {
+    if indices.is_empty() {
+        return "none".to_string();
+    }
+    let mut parts = Vec::new();
+    let mut start = indices[0];
+    let mut prev = start;
+    for &i in &indices[1..] {
+        if i != prev + 1 {
+            parts.push(range_part(start, prev));
+            start = i;
+        }
+        prev = i;
+    }
+    parts.push(range_part(start, prev));
+    parts.join(", ")
+}
+
+fn range_part(start: usize, end: usize) -> String {
+    if start == end {
+        start.to_string()
+    } else {
+        format!("{start}-{end}")
+    }
+}
@@ -1,21 +1,64 @@
 //! CLI command handlers. Each command is in its own file for clarity and line limit.
 
 mod add;
+mod add_index;
 mod bench;
+mod cancel;
 mod checksum;
+mod cleanup;
+mod config;
+mod events;
+mod export;
+mod history;
 mod import_har;
+mod inspect;
 mod pause;
+mod queue;
 mod remove;
 mod resume;
+mod retry;
 mod run;
 mod status;
+mod verify;
 
-pub use add::run_add;
-pub use bench::run_bench;
+pub use add::{
+    auto_bench_segment_count, checksum_from_sums_url, expected_checksum, load_headers_file,
+    load_mirror_list, run_add, run_add_dry_run,
+};
+#[cfg(test)]
+pub(crate) use add::{parse_headers_text, parse_mirror_list_text, validate_segment_bounds};
+#[cfg(test)]
+pub(crate) use add_index::add_index_links;
+pub use add_index::run_add_index;
+#[cfg(test)]
+pub(crate) use bench::render_bench;
+pub use bench::{run_bench, BenchFormat};
+pub use cancel::run_cancel;
 pub use checksum::run_checksum;
+#[cfg(test)]
+pub(crate) use cleanup::find_orphaned_parts;
+pub use cleanup::run_cleanup;
+#[cfg(test)]
+pub(crate) use config::render_config;
+pub use config::{run_config_path, run_config_show};
+pub use events::run_events;
+pub use export::{run_export, run_import};
+pub use history::run_history;
 pub use import_har::run_import_har;
+#[cfg(test)]
+pub(crate) use inspect::format_index_ranges;
+pub use inspect::run_inspect;
 pub use pause::run_pause;
+pub use queue::run_queue_move;
 pub use remove::run_remove;
 pub use resume::run_resume;
-pub use run::run_scheduler;
-pub use status::run_status;
+#[cfg(test)]
+pub(crate) use retry::check_retryable;
+pub use retry::run_retry;
+#[cfg(test)]
+pub(crate) use run::{aggregate_progress_line, progress_json_line};
+pub use run::{run_scheduler, RunFlags};
+pub use status::{run_status, run_status_live};
+#[cfg(test)]
+pub(crate) use status::{state_label, truncate_note};
+pub use verify::run_verify;
@@ -0,0 +1,11 @@
+//! `ddm queue move <id> --front|--back` – reorder a queued job among jobs of its priority.
+
+use anyhow::Result;
+use ddm_core::resume_db::ResumeDb;
+
+pub async fn run_queue_move(db: &ResumeDb, id: i64, front: bool) -> Result<()> {
+    db.move_in_queue(id, front).await?;
+    let end = if front { "front" } else { "back" };
+    println!("Moved job {id} to the {end} of the queue");
+    Ok(())
+}
@@ -3,8 +3,17 @@
 use anyhow::Result;
 use ddm_core::resume_db::{JobState, ResumeDb};
 
-pub async fn run_resume(db: &ResumeDb, id: i64) -> Result<()> {
+/// Queue the job again. With `replan`, its progress and segment plan are discarded first so
+/// the next run re-probes the URL and picks a segment count from the current config.
+pub async fn run_resume(db: &ResumeDb, id: i64, replan: bool) -> Result<()> {
+    if replan {
+        db.reset_plan(id).await?;
+    }
     db.set_state(id, JobState::Queued).await?;
-    println!("Resumed job {id}");
+    if replan {
+        println!("Resumed job {id} (re-planned; download restarts from scratch)");
+    } else {
+        println!("Resumed job {id}");
+    }
     Ok(())
 }
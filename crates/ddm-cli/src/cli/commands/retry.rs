@@ -0,0 +1,31 @@
+//! `ddm retry <id>` – requeue a failed job.
+
+use anyhow::Result;
+use ddm_core::resume_db::{JobState, ResumeDb};
+
+/// Move a job in the Error state back to Queued and clear its recorded error. The
+/// completed-segment bitmap is kept, so the next run fetches only the missing segments.
+pub async fn run_retry(db: &ResumeDb, id: i64) -> Result<()> {
+    let job = db
+        .get_job(id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+    check_retryable(id, job.state)?;
+    db.clear_error(id).await?;
+    db.set_state(id, JobState::Queued).await?;
+    println!("Requeued job {id}");
+    Ok(())
+}
+
+/// Only failed jobs can be retried; other states get a hint at the right command.
+pub(crate) fn check_retryable(id: i64, state: JobState) -> Result<()> {
+    match state {
+        JobState::Error => Ok(()),
+        JobState::Completed => anyhow::bail!("job {id} is already completed; nothing to retry"),
+        JobState::Queued => anyhow::bail!("job {id} is already queued"),
+        JobState::Running | JobState::Verifying => {
+            anyhow::bail!("job {id} is {}", state.as_str())
+        }
+        JobState::Paused => anyhow::bail!("job {id} is paused, not failed; use `ddm resume {id}`"),
+    }
+}
@@ -4,22 +4,46 @@ use anyhow::Result;
 use ddm_core::config::DdmConfig;
 use ddm_core::control::JobControl;
 use ddm_core::host_policy::HostPolicy;
-use ddm_core::resume_db::ResumeDb;
-use ddm_core::scheduler::{self, GlobalConnectionBudget, ProgressStats};
+use ddm_core::resume_db::{JobState, ResumeDb, RunLock};
+use ddm_core::scheduler::{
+    self, AggregateProgress, GlobalConnectionBudget, ProgressStats, RunOptions,
+};
 use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
 use crate::cli::control_socket;
 
+/// Per-run `ddm run` flags that are not config overrides.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunFlags {
+    /// Discard progress of jobs whose remote file changed (`--force-restart`).
+    pub force_restart: bool,
+    /// Jobs run concurrently (`--jobs`).
+    pub jobs: usize,
+    /// Replace existing final files (`--overwrite`).
+    pub overwrite: bool,
+    /// Stop after starting this many jobs (`--max-jobs`).
+    pub max_jobs: Option<u32>,
+    /// Print progress as NDJSON instead of the human line (`--progress-json`).
+    pub progress_json: bool,
+}
+
 pub async fn run_scheduler(
     db: &ResumeDb,
     cfg: &DdmConfig,
+    run: &RunOptions,
     download_dir: &Path,
-    force_restart: bool,
-    jobs: usize,
-    overwrite: bool,
+    flags: RunFlags,
 ) -> Result<()> {
+    let RunFlags {
+        force_restart,
+        jobs,
+        overwrite,
+        max_jobs,
+        progress_json,
+    } = flags;
+    let _run_lock = RunLock::acquire_at(&RunLock::path_in(db.state_dir()))?;
     let recovered = db.recover_running_jobs().await?;
     if recovered > 0 {
         tracing::info!("recovered {} job(s) from previous run", recovered);
@@ -34,20 +58,46 @@ pub async fn run_scheduler(
         }
         _ => HostPolicy::new(cfg.min_segments, cfg.max_segments),
     };
+    if let Some(secs) = cfg.throttle_decay_secs {
+        host_policy.set_throttle_decay(std::time::Duration::from_secs(secs));
+    }
 
     let job_control = Arc::new(JobControl::new());
-    if let Ok(socket_path) = ddm_core::control::default_control_socket_path() {
-        if control_socket::spawn_control_listener(Arc::clone(&job_control), &socket_path).is_ok() {
-            tracing::debug!(path = %socket_path.display(), "control socket listening");
-        }
+    let socket_path = ddm_core::control::control_socket_path(db.state_dir());
+    if control_socket::spawn_control_listener(Arc::clone(&job_control), &socket_path).is_ok() {
+        tracing::debug!(path = %socket_path.display(), "control socket listening");
     }
 
+    // First Ctrl-C pauses running jobs (progress saved) and stops the run; a second exits at once.
+    let signal_control = Arc::clone(&job_control);
+    let signal_handle = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        eprintln!("\nInterrupted: pausing running jobs (press Ctrl-C again to exit immediately)");
+        signal_control.request_shutdown();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+
     let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel::<ProgressStats>(16);
     const PROGRESS_INTERVAL_MS: u64 = 500;
+    let live_progress = Arc::clone(&job_control);
     let progress_handle = tokio::spawn(async move {
         let mut last_print = Instant::now();
+        // With --jobs N, a combined line follows each job's line.
+        let mut aggregate = (jobs > 1).then(AggregateProgress::new);
         while let Some(stats) = progress_rx.recv().await {
+            live_progress.record_progress(&stats);
+            if progress_json {
+                println!("{}", progress_json_line(&stats));
+                continue;
+            }
             let now = Instant::now();
+            if let Some(agg) = aggregate.as_mut() {
+                agg.record(&stats, now);
+            }
             if now.duration_since(last_print).as_millis() as u64 >= PROGRESS_INTERVAL_MS
                 || stats.bytes_done >= stats.total_bytes
             {
@@ -68,16 +118,22 @@ pub async fn run_scheduler(
                     "\r  {:.1} / {:.1} MiB ({:.1}%)  {:.2} MiB/s  ETA {}  ",
                     done_mib, total_mib, pct, rate_mib, eta
                 );
+                if let Some(agg) = aggregate.as_ref() {
+                    println!("{}", aggregate_progress_line(agg, now));
+                }
                 last_print = now;
             }
         }
-        println!();
+        if !progress_json {
+            println!();
+        }
     });
 
     let run_count = if jobs > 1 {
         scheduler::run_jobs_parallel(
             db,
             cfg,
+            run,
             download_dir.to_path_buf(),
             &mut host_policy,
             force_restart,
@@ -85,24 +141,27 @@ pub async fn run_scheduler(
             Some(progress_tx),
             Arc::clone(&global_budget),
             jobs,
+            max_jobs,
             Some(Arc::clone(&job_control)),
         )
         .await?
     } else {
         let mut run_count = 0u32;
         let budget_ref = global_budget.as_ref();
-        while scheduler::run_next_job(
-            db,
-            force_restart,
-            overwrite,
-            cfg,
-            download_dir,
-            &mut host_policy,
-            Some(&progress_tx),
-            Some(budget_ref),
-            Some(Arc::clone(&job_control)),
-        )
-        .await?
+        while max_jobs.is_none_or(|max| run_count < max)
+            && scheduler::run_next_job(
+                db,
+                force_restart,
+                overwrite,
+                cfg,
+                run,
+                download_dir,
+                &mut host_policy,
+                Some(&progress_tx),
+                Some(budget_ref),
+                Some(Arc::clone(&job_control)),
+            )
+            .await?
         {
             run_count += 1;
         }
@@ -111,6 +170,7 @@ pub async fn run_scheduler(
     };
 
     let _ = progress_handle.await;
+    signal_handle.abort();
 
     if let Ok(path) = HostPolicy::default_path() {
         if host_policy.save_to_path(&path).is_err() {
@@ -118,10 +178,79 @@ pub async fn run_scheduler(
         }
     }
 
-    if run_count == 0 {
-        println!("No queued jobs.");
+    // With --progress-json, stdout carries only the progress stream.
+    let say = |msg: &str| {
+        if progress_json {
+            eprintln!("{msg}");
+        } else {
+            println!("{msg}");
+        }
+    };
+    if job_control.is_shutting_down() {
+        say("Run interrupted; paused jobs continue after `ddm resume <id>`.");
+    } else if run_count == 0 {
+        say("No queued jobs.");
     } else {
         tracing::info!("run completed {} job(s)", run_count);
+        if max_jobs.is_some_and(|max| run_count >= max) {
+            let remaining = db
+                .list_jobs()
+                .await?
+                .iter()
+                .filter(|j| j.state == JobState::Queued && run.host_filter.matches(&j.url))
+                .count();
+            if remaining > 0 {
+                say(&format!(
+                    "Reached --max-jobs {run_count}; {remaining} job(s) left queued."
+                ));
+            }
+        }
+    }
+    let unreachable = host_policy.open_circuit_hosts(std::time::Instant::now());
+    if !unreachable.is_empty() {
+        say(&format!(
+            "Host(s) unreachable, their other jobs left queued: {}",
+            unreachable.join(", ")
+        ));
     }
     Ok(())
 }
+
+/// One `--progress-json` line: the job's `ProgressStats` plus the rate (bytes/s, including
+/// in-flight bytes) and ETA (seconds, null while unknown) the human progress line shows.
+pub(crate) fn progress_json_line(stats: &ProgressStats) -> String {
+    let rate = if stats.elapsed_secs > 0.0 {
+        stats.effective_bytes() as f64 / stats.elapsed_secs
+    } else {
+        0.0
+    };
+    serde_json::json!({
+        "job_id": stats.job_id,
+        "bytes_done": stats.bytes_done,
+        "bytes_in_flight": stats.bytes_in_flight,
+        "total_bytes": stats.total_bytes,
+        "elapsed_secs": stats.elapsed_secs,
+        "bytes_per_sec": rate,
+        "eta_secs": stats.eta_secs(),
+        "segments_done": stats.segments_done,
+        "segment_count": stats.segment_count,
+    })
+    .to_string()
+}
+
+/// Combined progress line for a parallel run: bytes across every job seen, the summed rate
+/// of the jobs still downloading, and their ETA at that rate.
+pub(crate) fn aggregate_progress_line(agg: &AggregateProgress, now: Instant) -> String {
+    let eta = agg
+        .eta_secs(now)
+        .map(|s| format!("{:.0}s", s))
+        .unwrap_or_else(|| "?".to_string());
+    format!(
+        "  all jobs: {:.1} / {:.1} MiB  {:.2} MiB/s across {} active  ETA {}",
+        agg.bytes_done() as f64 / 1_048_576.0,
+        agg.total_bytes() as f64 / 1_048_576.0,
+        agg.bytes_per_sec(now) / 1_048_576.0,
+        agg.active_jobs(now),
+        eta
+    )
+}
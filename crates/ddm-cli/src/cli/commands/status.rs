@@ -1,27 +1,103 @@
 //! `ddm status` – show status of all jobs.
 
 use anyhow::Result;
-use ddm_core::resume_db::ResumeDb;
+use ddm_core::resume_db::{JobState, JobSummary, ResumeDb};
+
+use crate::cli::control_socket;
+
+/// Width of the NOTE column; longer notes are cut with "…" (full text in `ddm inspect`).
+const NOTE_WIDTH: usize = 16;
+
+pub(crate) fn truncate_note(note: Option<&str>, width: usize) -> String {
+    let note = note.unwrap_or("-");
+    if note.chars().count() <= width {
+        return note.to_string();
+    }
+    let mut out: String = note.chars().take(width.saturating_sub(1)).collect();
+    out.push('…');
+    out
+}
+
+/// Width of the STATE column (fits "error:remote_changed").
+const STATE_WIDTH: usize = 20;
+
+/// STATE column text: the state, plus the failure category for failed jobs (e.g. "error:network").
+pub(crate) fn state_label(job: &JobSummary) -> String {
+    match (job.state, job.error_category) {
+        (JobState::Error, Some(cat)) => format!("error:{}", cat.as_str()),
+        (state, _) => state.as_str().to_string(),
+    }
+}
 
 pub async fn run_status(db: &ResumeDb) -> Result<()> {
     let jobs = db.list_jobs().await?;
     if jobs.is_empty() {
         println!("No jobs in database.");
     } else {
-        println!("{:<6} {:<10} {:<10} {}", "ID", "STATE", "SIZE", "URL");
+        println!(
+            "{:<6} {:<STATE_WIDTH$} {:<5} {:<10} {:<NOTE_WIDTH$} {}",
+            "ID", "STATE", "PRIO", "SIZE", "NOTE", "URL"
+        );
         for j in jobs {
             let size_str = j
                 .total_size
                 .map(|s| format!("{s}"))
                 .unwrap_or_else(|| "-".to_string());
             println!(
-                "{:<6} {:<10} {:<10} {}",
+                "{:<6} {:<STATE_WIDTH$} {:<5} {:<10} {:<NOTE_WIDTH$} {}",
                 j.id,
-                format!("{:?}", j.state).to_lowercase(),
+                state_label(&j),
+                j.priority,
                 size_str,
+                truncate_note(j.note.as_deref(), NOTE_WIDTH),
                 j.url
             );
         }
     }
     Ok(())
 }
+
+/// `ddm status --live`: live progress of jobs in the active `ddm run`, via the control socket.
+pub async fn run_status_live(db: &ResumeDb) -> Result<()> {
+    let path = ddm_core::control::control_socket_path(db.state_dir());
+    let Some(jobs) = control_socket::query_status(&path).await? else {
+        println!("No active `ddm run` (control socket not reachable).");
+        return Ok(());
+    };
+    if jobs.is_empty() {
+        println!("No jobs downloading.");
+        return Ok(());
+    }
+    println!(
+        "{:<6} {:>10} {:>10} {:>7} {:>9} {:>8} {:>9}",
+        "ID", "DONE MiB", "TOTAL MiB", "PCT", "MiB/s", "ETA", "SEGS"
+    );
+    for s in jobs {
+        let done = s.effective_bytes();
+        let rate = if s.elapsed_secs > 0.0 {
+            done as f64 / s.elapsed_secs
+        } else {
+            0.0
+        };
+        let eta = s
+            .eta_secs()
+            .map(|e| format!("{:.0}s", e))
+            .unwrap_or_else(|| "?".to_string());
+        let pct = if s.total_bytes > 0 {
+            (done as f64 / s.total_bytes as f64 * 100.0).min(100.0)
+        } else {
+            100.0
+        };
+        println!(
+            "{:<6} {:>10.1} {:>10.1} {:>6.1}% {:>9.2} {:>8} {:>9}",
+            s.job_id,
+            done as f64 / 1_048_576.0,
+            s.total_bytes as f64 / 1_048_576.0,
+            pct,
+            rate / 1_048_576.0,
+            eta,
+            format!("{}/{}", s.segments_done, s.segment_count)
+        );
+    }
+    Ok(())
+}
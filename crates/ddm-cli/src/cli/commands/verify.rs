@@ -0,0 +1,98 @@
+//! `ddm verify <id>` – re-check a job's file against its stored per-segment SHA-256 hashes
+//! and, once completed, its expected whole-file checksum.
+
+use anyhow::{Context, Result};
+use ddm_core::checksum;
+use ddm_core::resume_db::{JobState, ResumeDb};
+use ddm_core::segmenter::{plan_segments, SegmentBitmap};
+use std::path::Path;
+
+/// Verifies the job's final file (or `.part` while incomplete) segment by segment.
+/// Only segments with a stored hash are checked; fails if any of them mismatch. A completed
+/// job with an expected checksum (`ddm add --sha256` etc.) is also hashed as a whole.
+pub async fn run_verify(db: &ResumeDb, id: i64, download_dir: Option<&Path>) -> Result<()> {
+    let job = db
+        .get_job(id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+    let hashed = job.segment_hashes.iter().filter(|h| h.is_some()).count();
+    let whole = job
+        .settings
+        .expected_checksum
+        .clone()
+        .filter(|_| job.state == JobState::Completed);
+    if hashed == 0 && whole.is_none() {
+        println!("Job {id} has no segment hashes (enable `hash_segments` in config).");
+        return Ok(());
+    }
+
+    let name = if job.state == JobState::Completed {
+        job.final_filename.as_deref()
+    } else {
+        job.temp_filename.as_deref()
+    };
+    let name = name.ok_or_else(|| anyhow::anyhow!("job {} has no file name yet", id))?;
+    let dir = job
+        .settings
+        .download_dir
+        .as_deref()
+        .map(Path::new)
+        .or(download_dir)
+        .unwrap_or_else(|| Path::new("."));
+    let path = dir.join(name);
+
+    if let Some((algo, hex)) = whole {
+        tokio::task::spawn_blocking({
+            let path = path.clone();
+            move || checksum::verify_file(&path, algo, &hex)
+        })
+        .await?
+        .with_context(|| format!("job {id}"))?;
+        println!(
+            "Job {id}: {} checksum OK ({})",
+            algo.as_str(),
+            path.display()
+        );
+        if hashed == 0 {
+            return Ok(());
+        }
+    }
+
+    let (Some(total_size), true) = (job.total_size, job.segment_count > 0) else {
+        anyhow::bail!("job {} has no segment layout yet", id);
+    };
+    let segment_count = job.segment_count as usize;
+    let segments = plan_segments(total_size as u64, segment_count);
+    let bitmap = SegmentBitmap::from_bytes(&job.completed_bitmap, segment_count);
+    // Only completed segments hold final bytes; ignore stale hashes for the rest.
+    let hashes: Vec<Option<String>> = job
+        .segment_hashes
+        .iter()
+        .enumerate()
+        .map(|(i, h)| h.clone().filter(|_| bitmap.is_completed(i)))
+        .collect();
+    let checked = hashes.iter().filter(|h| h.is_some()).count();
+
+    let bad = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || checksum::verify_segments(&path, &segments, &hashes)
+    })
+    .await?;
+    if bad.is_empty() {
+        println!(
+            "Job {id}: {checked} of {segment_count} segments verified OK ({})",
+            path.display()
+        );
+        Ok(())
+    } else {
+        for i in &bad {
+            println!("segment {i}: checksum mismatch");
+        }
+        anyhow::bail!(
+            "job {}: {} of {} checked segments failed verification",
+            id,
+            bad.len(),
+            checked
+        )
+    }
+}
@@ -1,15 +1,19 @@
-//! Control socket: server (during `ddm run`) and client (for `ddm pause`).
-//! Protocol: one line per command: "pause <id>" or "cancel <id>".
+//! Control socket: server (during `ddm run`) and client (for `ddm pause` / `ddm cancel` /
+//! `ddm status --live`).
+//! Protocol: one line per command: "pause <id>", "cancel <id>", or "status" (answered
+//! with one line of JSON: the running jobs' `ProgressStats`).
 
 use anyhow::Result;
 use ddm_core::control::JobControl;
+use ddm_core::scheduler::ProgressStats;
 use std::path::Path;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::UnixListener;
 
 /// Spawns a task that listens on `path` and calls `job_control.request_abort(id)`
-/// for each "pause <id>" or "cancel <id>" line. Ignores malformed lines.
+/// for each "pause <id>" or "cancel <id>" line, and replies to "status" with the live
+/// progress JSON. Ignores malformed lines.
 pub fn spawn_control_listener(
     job_control: Arc<JobControl>,
     path: impl AsRef<Path>,
@@ -29,10 +33,16 @@ pub fn spawn_control_listener(
                 Ok((stream, _)) => {
                     let control = Arc::clone(&job_control);
                     tokio::spawn(async move {
-                        let mut reader = BufReader::new(stream).lines();
+                        let (read_half, mut write_half) = stream.into_split();
+                        let mut reader = BufReader::new(read_half).lines();
                         while let Ok(Some(line)) = reader.next_line().await {
                             let line = line.trim();
-                            if line.starts_with("pause ") {
+                            if line == "status" {
+                                let reply = format!("{}\n", control.status_json());
+                                if write_half.write_all(reply.as_bytes()).await.is_err() {
+                                    break;
+                                }
+                            } else if line.starts_with("pause ") {
                                 if let Ok(id) = line[6..].trim().parse::<i64>() {
                                     control.request_abort(id);
                                 }
@@ -51,13 +61,40 @@ pub fn spawn_control_listener(
     Ok(handle)
 }
 
-/// Sends "pause <job_id>\n" to the control socket. No-op if the path does not exist.
-pub async fn send_pause(socket_path: &Path, job_id: i64) -> Result<()> {
+/// Sends "pause <job_id>\n" to the control socket. No-op (Ok(false)) if the path does not
+/// exist.
+pub async fn send_pause(socket_path: &Path, job_id: i64) -> Result<bool> {
+    send_command(socket_path, "pause", job_id).await
+}
+
+/// Sends "cancel <job_id>\n" to the control socket. No-op (Ok(false)) if the path does not
+/// exist.
+pub async fn send_cancel(socket_path: &Path, job_id: i64) -> Result<bool> {
+    send_command(socket_path, "cancel", job_id).await
+}
+
+/// Asks a running `ddm run` for live progress. Returns None when no run is listening.
+pub async fn query_status(socket_path: &Path) -> Result<Option<Vec<ProgressStats>>> {
+    let Ok(stream) = tokio::net::UnixStream::connect(socket_path).await else {
+        return Ok(None);
+    };
+    let (read_half, mut write_half) = stream.into_split();
+    write_half.write_all(b"status\n").await?;
+    let line = BufReader::new(read_half)
+        .lines()
+        .next_line()
+        .await?
+        .unwrap_or_default();
+    Ok(Some(ddm_core::control::parse_status_json(&line)?))
+}
+
+/// Returns true when a listening `ddm run` took the command.
+async fn send_command(socket_path: &Path, command: &str, job_id: i64) -> Result<bool> {
     if !socket_path.exists() {
-        return Ok(());
+        return Ok(false);
     }
     let mut stream = tokio::net::UnixStream::connect(socket_path).await?;
-    let msg = format!("pause {}\n", job_id);
+    let msg = format!("{} {}\n", command, job_id);
     tokio::io::AsyncWriteExt::write_all(&mut stream, msg.as_bytes()).await?;
-    Ok(())
+    Ok(true)
 }
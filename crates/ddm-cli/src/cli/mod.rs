@@ -4,14 +4,20 @@ mod commands;
 mod control_socket;
 
 use anyhow::Result;
-use clap::{CommandFactory, Parser, Subcommand};
-use ddm_core::config;
-use ddm_core::resume_db::ResumeDb;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use ddm_core::host_policy::HostFilter;
+use ddm_core::resume_db::{JobSettings, ResumeDb};
+use ddm_core::scheduler::{JobConfig, RunOptions};
+use ddm_core::segmenter::SegmentOrder;
+use ddm_core::{config, logging};
 use std::path::Path;
 
 use commands::{
-    run_add, run_bench, run_checksum, run_import_har, run_pause, run_remove, run_resume,
-    run_scheduler, run_status,
+    auto_bench_segment_count, checksum_from_sums_url, expected_checksum, load_headers_file,
+    load_mirror_list, run_add, run_add_dry_run, run_add_index, run_bench, run_cancel, run_checksum,
+    run_cleanup, run_config_path, run_config_show, run_events, run_export, run_history, run_import,
+    run_import_har, run_inspect, run_pause, run_queue_move, run_remove, run_resume, run_retry,
+    run_scheduler, run_status, run_status_live, run_verify, BenchFormat, RunFlags,
 };
 
 /// Top-level CLI for the DDM download manager.
@@ -21,17 +27,162 @@ use commands::{
 pub struct Cli {
     #[command(subcommand)]
     pub command: CliCommand,
+    /// Log errors only.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+    /// Log more: -v for debug, -vv for trace.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Load config from PATH instead of `~/.config/ddm/config.toml`.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<std::path::PathBuf>,
+    /// Create the `--config` file with defaults if it does not exist.
+    #[arg(long, global = true, requires = "config")]
+    pub init_config: bool,
+    /// Use the jobs database at PATH instead of `~/.local/state/ddm/jobs.db`.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub state_db: Option<std::path::PathBuf>,
+}
+
+impl Cli {
+    /// Log filter chosen by `--quiet` / `-v`; None keeps `RUST_LOG` or the default.
+    pub fn log_level(&self) -> Option<&'static str> {
+        match (self.quiet, self.verbose) {
+            (true, _) => Some("error"),
+            (false, 0) => None,
+            (false, 1) => Some("debug"),
+            (false, _) => Some("trace"),
+        }
+    }
+}
+
+/// `ddm queue` subcommands.
+#[derive(Debug, Subcommand)]
+pub enum QueueAction {
+    /// Move a queued job ahead of or behind the other queued jobs of its priority.
+    Move {
+        /// Job identifier.
+        id: i64,
+        /// Run it before the other queued jobs of the same priority.
+        #[arg(long, conflicts_with = "back", required_unless_present = "back")]
+        front: bool,
+        /// Run it after the other queued jobs of the same priority.
+        #[arg(long)]
+        back: bool,
+    },
+}
+
+/// `ddm config` subcommands.
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Print the effective configuration as TOML (unset options use their defaults).
+    Show,
+    /// Print the path of the config file in use.
+    Path,
+}
+
+/// `ddm add` expected whole-file digest; at most one of these may be given.
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct ChecksumArgs {
+    /// Fail the job unless the finished file has this MD5 digest (hex).
+    #[arg(long, value_name = "HEX")]
+    pub md5: Option<String>,
+    /// Fail the job unless the finished file has this SHA-1 digest (hex).
+    #[arg(long, value_name = "HEX")]
+    pub sha1: Option<String>,
+    /// Fail the job unless the finished file has this SHA-256 digest (hex).
+    #[arg(long, value_name = "HEX")]
+    pub sha256: Option<String>,
+    /// Take the expected digest from the `SHA256SUMS`/`SHA1SUMS`/`MD5SUMS` file at URL (line for this file's name).
+    #[arg(long, value_name = "URL")]
+    pub checksums_url: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
 pub enum CliCommand {
     /// Add a new download job.
     Add {
-        /// Direct HTTP/HTTPS URL to download.
+        /// Direct HTTP/HTTPS URL to download (or `file://` URL to copy a local file).
         url: String,
         /// Directory where the file will be saved (default: current directory). Stored with the job so resume works from any working directory.
         #[arg(long, value_name = "DIR")]
         download_dir: Option<std::path::PathBuf>,
+        /// Probe the URL and print size, range support, ETag, filename, and segment count without adding a job.
+        #[arg(long)]
+        dry_run: bool,
+        /// Scheduling priority; higher-priority jobs run first (default 0, FIFO among equals).
+        #[arg(
+            long,
+            default_value = "0",
+            value_name = "N",
+            allow_negative_numbers = true
+        )]
+        priority: i32,
+        /// Benchmark 4/8/16 segments (capped download) and store the best count for this job.
+        #[arg(long, conflicts_with = "dry_run")]
+        auto_bench: bool,
+        /// Free-form label stored with the job (shown in `status` and `inspect`).
+        #[arg(long, value_name = "TEXT")]
+        note: Option<String>,
+        /// Stop a run of this job after DURATION (e.g. 600, 600s, 10m, 1h); progress is kept.
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration_secs)]
+        deadline: Option<u64>,
+        /// Use at least N segments for this job (overrides `min_segments` in config).
+        #[arg(long, value_name = "N")]
+        min_segments: Option<usize>,
+        /// Use at most N segments for this job (overrides `max_segments` in config; also caps host-policy adaptation).
+        #[arg(long, value_name = "N")]
+        max_segments: Option<usize>,
+        /// Send the `Name: value` headers listed in FILE (one per line) with every request for this job.
+        #[arg(long, value_name = "FILE")]
+        headers_file: Option<std::path::PathBuf>,
+        /// Also fetch segments from the mirror URLs listed in FILE (one per line) serving the same file.
+        #[arg(long, value_name = "FILE")]
+        mirror_list: Option<std::path::PathBuf>,
+        /// Send this User-Agent for this job (overrides `user_agent` in config).
+        #[arg(long, value_name = "UA")]
+        user_agent: Option<String>,
+        /// Run CMD (via `sh -c`) when this job completes; `{path}` and `{url}` are substituted (overrides `on_complete` in config).
+        #[arg(long, value_name = "CMD")]
+        on_complete: Option<String>,
+        #[command(flatten)]
+        checksum: Box<ChecksumArgs>,
+        /// Add the job as paused; `ddm run` skips it until `ddm resume <id>`.
+        #[arg(long, conflicts_with = "dry_run")]
+        start_paused: bool,
+        /// Do not verify TLS certificates for this job (self-signed internal mirrors). Unsafe.
+        #[arg(long)]
+        insecure: bool,
+        /// Save as NAME instead of the name derived from the URL or Content-Disposition.
+        #[arg(long, value_name = "NAME")]
+        output: Option<String>,
+        /// Fail the job if the server reports a size other than BYTES (e.g. an HTML error page).
+        #[arg(long, value_name = "BYTES")]
+        expected_size: Option<u64>,
+        /// Segment order: `parallel` (default) or `sequential` (fill the file from the front, e.g. to preview it early).
+        #[arg(long, value_name = "ORDER", default_value = "parallel", value_parser = parse_segment_order)]
+        order: SegmentOrder,
+        /// Low-impact background download: at most 2 segments, capped at 256 KiB/s, and
+        /// queued behind default-priority jobs (explicit stricter values are kept).
+        #[arg(long, conflicts_with = "dry_run")]
+        trickle: bool,
+    },
+
+    /// Add a job for each file listed on an Apache/nginx directory index page.
+    AddIndex {
+        /// URL of the directory index page (e.g. a Debian pool or release directory).
+        url: String,
+        /// Only add files whose name matches this glob (`*` and `?`, e.g. '*.deb').
+        #[arg(long, value_name = "GLOB")]
+        pattern: Option<String>,
+        /// Directory where the files will be saved (default: current directory).
+        #[arg(long, value_name = "DIR")]
+        download_dir: Option<std::path::PathBuf>,
+        /// Do not verify TLS certificates for the index page or the jobs added from it
+        /// (self-signed internal mirrors). Unsafe.
+        #[arg(long)]
+        insecure: bool,
     },
 
     /// Run the scheduler/worker loop to process queued jobs.
@@ -43,12 +194,64 @@ pub enum CliCommand {
         #[arg(long, default_value = "1", value_name = "N")]
         jobs: usize,
         /// Overwrite existing final file if it already exists on disk. Without this, run fails when the target file is present.
-        #[arg(long)]
+        #[arg(long, conflicts_with = "on_conflict")]
         overwrite: bool,
+        /// What to do when a job's final file already exists: error, rename, overwrite or skip (overrides `on_conflict` in config).
+        #[arg(long, value_name = "POLICY", value_parser = parse_conflict_policy)]
+        on_conflict: Option<config::ConflictPolicy>,
+        /// Metadata probe before each job: best_effort, head_only or range_only (overrides `probe_strategy` in config).
+        #[arg(long, value_name = "STRATEGY", value_parser = parse_probe_strategy, conflicts_with = "no_probe_merge")]
+        probe_strategy: Option<config::ProbeStrategy>,
+        /// Probe with HEAD only, never a second range request (same as `--probe-strategy head_only`).
+        #[arg(long)]
+        no_probe_merge: bool,
+        /// Connect over IPv4 only (overrides `ip_version` in config).
+        #[arg(long, conflicts_with = "ipv6")]
+        ipv4: bool,
+        /// Connect over IPv6 only (overrides `ip_version` in config).
+        #[arg(long)]
+        ipv6: bool,
+        /// Route probes and downloads through this proxy (http://, socks5://, socks5h://; overrides `proxy` in config).
+        #[arg(long, value_name = "URL")]
+        proxy: Option<String>,
+        /// Connect to CONNECT-HOST:CONNECT-PORT for requests to HOST:PORT, keeping the Host header and TLS name (repeatable; added to `connect_to` in config).
+        #[arg(long, value_name = "HOST:PORT:CONNECT-HOST:CONNECT-PORT", value_parser = parse_connect_to)]
+        connect_to: Vec<String>,
+        /// Maximum attempts per segment for this run (clamped to 1..=50; overrides `[retry] max_attempts`).
+        #[arg(long, value_name = "N")]
+        max_retries: Option<u32>,
+        /// Base backoff delay in milliseconds for this run (clamped to 1..=60000; overrides `[retry] base_delay_secs`).
+        #[arg(long, value_name = "MS")]
+        retry_base_ms: Option<u64>,
+        /// Per-connection receive buffer in bytes for this run, 1024 to 10485760 in whole KiB (overrides `segment_buffer_bytes` in config).
+        #[arg(long, value_name = "BYTES", value_parser = parse_segment_buffer)]
+        segment_buffer: Option<usize>,
+        /// Do not verify TLS certificates for any job in this run (self-signed internal mirrors). Unsafe.
+        #[arg(long)]
+        insecure: bool,
+        /// Before resuming a job, re-check its completed segments against the .part file and re-download any that fail (see `verify_on_resume`).
+        #[arg(long)]
+        verify_on_resume: bool,
+        /// Only run queued jobs whose URL host is HOST (repeatable); other jobs stay queued.
+        #[arg(long = "host", value_name = "HOST")]
+        hosts: Vec<String>,
+        /// Skip queued jobs whose URL host is HOST (repeatable); they stay queued.
+        #[arg(long = "exclude-host", value_name = "HOST")]
+        exclude_hosts: Vec<String>,
+        /// Stop after starting N jobs (finished or failed); the rest stay queued for the next run.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        max_jobs: Option<u32>,
+        /// Print progress as one JSON object per line (NDJSON) on stdout instead of the human progress line.
+        #[arg(long)]
+        progress_json: bool,
     },
 
     /// Show status of all jobs.
-    Status,
+    Status {
+        /// Show live progress (rate, ETA) of jobs in the running `ddm run` instead of the DB view.
+        #[arg(long)]
+        live: bool,
+    },
 
     /// Pause a job by ID. If `ddm run` is active, signals that job to stop within ~1s and saves progress; otherwise the job will not be picked on the next run.
     Pause {
@@ -56,10 +259,65 @@ pub enum CliCommand {
         id: i64,
     },
 
+    /// Show the effective configuration or where it is loaded from.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Reorder queued jobs.
+    Queue {
+        #[command(subcommand)]
+        action: QueueAction,
+    },
+
     /// Resume a paused job by its ID.
     Resume {
         /// Job identifier.
         id: i64,
+        /// Discard progress and re-plan segments with the current config (restarts the download).
+        #[arg(long)]
+        replan: bool,
+    },
+
+    /// Requeue a failed job: clears its recorded error and keeps completed segments.
+    Retry {
+        /// Job identifier.
+        id: i64,
+    },
+
+    /// Cancel a job: stop it if `ddm run` is active, delete its .part file, and remove it. The finished file is kept unless --purge-final is given.
+    Cancel {
+        /// Job identifier.
+        id: i64,
+        /// Also delete the finalized download if it exists.
+        #[arg(long)]
+        purge_final: bool,
+        /// Directory where the job's files live if the job has none stored (default: current directory).
+        #[arg(long, value_name = "DIR")]
+        download_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Show recorded download runs (bytes, duration, errors, throttling) for a job.
+    History {
+        /// Job identifier.
+        id: i64,
+    },
+
+    /// Show the log of job state changes (added, running, completed, error, paused, ...), oldest first.
+    Events {
+        /// Only changes at or after this Unix timestamp (seconds).
+        #[arg(long, value_name = "TS")]
+        since: Option<i64>,
+        /// Only changes of this job.
+        #[arg(long, value_name = "N")]
+        id: Option<i64>,
+    },
+
+    /// Print every stored field of a job (validators, filenames, segments, incomplete indices, settings).
+    Inspect {
+        /// Job identifier.
+        id: i64,
     },
 
     /// Remove a job by ID. With --delete-files, also deletes the job's .part and final file(s) from the current directory or --download-dir.
@@ -74,6 +332,38 @@ pub enum CliCommand {
         download_dir: Option<std::path::PathBuf>,
     },
 
+    /// Delete `.part` files in a directory that no job in the DB owns (left by removed jobs).
+    Cleanup {
+        /// Directory to scan (default: current directory).
+        #[arg(long, value_name = "DIR")]
+        dir: Option<std::path::PathBuf>,
+        /// List the orphaned files without deleting them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write a job's plan (URL, size, validators, segment layout, segment hashes) as JSON for `ddm import`.
+    Export {
+        /// Job identifier.
+        id: i64,
+        /// Write to this file instead of stdout.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+        /// Keep custom headers, cookies, a stored POST body, the completion hook and
+        /// `insecure` (left out by default).
+        #[arg(long)]
+        include_sensitive: bool,
+    },
+
+    /// Create a queued job from a plan written by `ddm export`.
+    Import {
+        /// Path to the exported JSON.
+        path: std::path::PathBuf,
+        /// Keep a completion hook and `insecure` from the plan (dropped by default).
+        #[arg(long)]
+        trust: bool,
+    },
+
     /// Import a HAR file and create download jobs from it.
     ImportHar {
         /// Path to the HAR file.
@@ -82,12 +372,34 @@ pub enum CliCommand {
         /// Allow persisting cookies extracted from the HAR (if needed).
         #[arg(long)]
         allow_cookies: bool,
+
+        /// Allow importing a POST request; its body is stored and replayed on each run.
+        #[arg(long)]
+        allow_post: bool,
     },
 
     /// Benchmark different segment counts for a given URL.
     Bench {
         /// Direct HTTP/HTTPS URL to benchmark.
         url: String,
+        /// Bytes to download per run (default 20 MiB; capped by the file size).
+        #[arg(long, value_name = "BYTES")]
+        max_bytes: Option<u64>,
+        /// Output format: an aligned table, JSON or CSV (both include the recommended count).
+        #[arg(long, value_enum, default_value_t = BenchFormat::Table)]
+        format: BenchFormat,
+        /// Comma-separated segment counts to try (default 4,8,16); each is capped at `max_segments`.
+        #[arg(long, value_name = "N,N,...", value_delimiter = ',', value_parser = parse_segment_count)]
+        counts: Vec<usize>,
+    },
+
+    /// Check a job's file against the per-segment SHA-256 hashes recorded while downloading (`hash_segments`).
+    Verify {
+        /// Job identifier.
+        id: i64,
+        /// Directory containing the job's files when the job has no stored download dir (default: current directory).
+        #[arg(long, value_name = "DIR")]
+        download_dir: Option<std::path::PathBuf>,
     },
 
     /// Compute SHA-256 of a file (e.g. after download).
@@ -110,6 +422,11 @@ pub enum CliCommand {
 impl CliCommand {
     pub async fn run_from_args() -> Result<()> {
         let cli = Cli::parse();
+        let level = cli.log_level();
+        if let Err(e) = logging::init_logging(level) {
+            eprintln!("ddm: log file unavailable ({}), using stderr", e);
+            logging::init_logging_stderr(level);
+        }
 
         // Completions and manpage do not need config or DB.
         match &cli.command {
@@ -124,29 +441,212 @@ impl CliCommand {
                 man.render(&mut std::io::stdout())?;
                 return Ok(());
             }
+            // Before loading, so a config that fails to parse can still be located.
+            CliCommand::Config {
+                action: ConfigAction::Path,
+            } => return run_config_path(cli.config.as_deref()),
             _ => {}
         }
 
-        let cfg = config::load_or_init()?;
+        let cfg = config::load_or_init(cli.config.as_deref(), cli.init_config)?;
         tracing::debug!("loaded config: {:?}", cfg);
-        let db = ResumeDb::open_default().await?;
+        if let CliCommand::Config {
+            action: ConfigAction::Show,
+        } = cli.command
+        {
+            return run_config_show(&cfg, cli.config.as_deref());
+        }
+        let db = match cli.state_db.as_deref() {
+            Some(path) => ResumeDb::open_at(path).await?,
+            None => ResumeDb::open_default().await?,
+        };
 
         match cli.command {
-            CliCommand::Add { url, download_dir } => {
+            CliCommand::Add {
+                url,
+                download_dir,
+                dry_run,
+                priority,
+                auto_bench,
+                note,
+                deadline,
+                min_segments,
+                max_segments,
+                headers_file,
+                mirror_list,
+                user_agent,
+                on_complete,
+                checksum,
+                start_paused,
+                insecure,
+                output,
+                expected_size,
+                order,
+                trickle,
+            } => {
+                if insecure {
+                    warn_insecure();
+                }
+                let job_cfg = JobConfig {
+                    insecure,
+                    ..JobConfig::from(&cfg)
+                };
+                let ChecksumArgs {
+                    md5,
+                    sha1,
+                    sha256,
+                    checksums_url,
+                } = *checksum;
+                let expected_checksum = match checksums_url {
+                    Some(sums_url) => {
+                        Some(checksum_from_sums_url(&job_cfg, &sums_url, &url).await?)
+                    }
+                    None => expected_checksum(md5, sha1, sha256)?,
+                };
+                let custom_headers = headers_file.as_deref().map(load_headers_file).transpose()?;
+                let mirrors = mirror_list.as_deref().map(load_mirror_list).transpose()?;
+                let headers = custom_headers.clone().unwrap_or_default();
+                if dry_run {
+                    run_add_dry_run(&job_cfg, &url, &headers).await?
+                } else {
+                    let dir = download_dir.or_else(|| std::env::current_dir().ok());
+                    let segment_count = if auto_bench {
+                        auto_bench_segment_count(&job_cfg, &url, &headers).await
+                    } else {
+                        None
+                    };
+                    let mut settings = JobSettings {
+                        note,
+                        priority,
+                        segment_count,
+                        deadline_secs: deadline,
+                        min_segments,
+                        max_segments,
+                        custom_headers,
+                        mirrors,
+                        expected_checksum,
+                        output_filename: output,
+                        expected_size,
+                        user_agent,
+                        on_complete,
+                        insecure,
+                        order,
+                        ..JobSettings::default()
+                    };
+                    if trickle {
+                        settings.apply_trickle();
+                    }
+                    run_add(&db, &url, dir.as_deref(), settings, start_paused).await?
+                }
+            }
+            CliCommand::AddIndex {
+                url,
+                pattern,
+                download_dir,
+                insecure,
+            } => {
+                if insecure {
+                    warn_insecure();
+                }
+                let job_cfg = JobConfig {
+                    insecure,
+                    ..JobConfig::from(&cfg)
+                };
                 let dir = download_dir.or_else(|| std::env::current_dir().ok());
-                run_add(&db, &url, dir.as_deref()).await?
+                run_add_index(&db, &job_cfg, &url, pattern.as_deref(), dir.as_deref()).await?;
             }
             CliCommand::Run {
                 force_restart,
                 jobs,
                 overwrite,
+                ipv4,
+                ipv6,
+                proxy,
+                connect_to,
+                max_retries,
+                retry_base_ms,
+                segment_buffer,
+                insecure,
+                verify_on_resume,
+                hosts,
+                exclude_hosts,
+                max_jobs,
+                progress_json,
+                on_conflict,
+                probe_strategy,
+                no_probe_merge,
             } => {
+                let mut cfg = cfg.clone();
+                if on_conflict.is_some() {
+                    cfg.on_conflict = on_conflict;
+                }
+                if no_probe_merge {
+                    cfg.probe_strategy = Some(config::ProbeStrategy::HeadOnly);
+                } else if probe_strategy.is_some() {
+                    cfg.probe_strategy = probe_strategy;
+                }
+                if insecure {
+                    warn_insecure();
+                }
+                let run = RunOptions {
+                    insecure,
+                    host_filter: HostFilter::new(hosts, exclude_hosts),
+                };
+                if ipv4 {
+                    cfg.ip_version = Some(config::IpVersion::V4);
+                } else if ipv6 {
+                    cfg.ip_version = Some(config::IpVersion::V6);
+                }
+                if proxy.is_some() {
+                    cfg.proxy = proxy;
+                }
+                cfg.connect_to.extend(connect_to);
+                if segment_buffer.is_some() {
+                    cfg.segment_buffer_bytes = segment_buffer;
+                }
+                if verify_on_resume {
+                    cfg.verify_on_resume = Some(true);
+                }
+                if max_retries.is_some() || retry_base_ms.is_some() {
+                    let retry = cfg.retry.clone().unwrap_or_default();
+                    cfg.retry = Some(retry.with_overrides(max_retries, retry_base_ms));
+                }
                 let download_dir = std::env::current_dir()?;
-                run_scheduler(&db, &cfg, &download_dir, force_restart, jobs, overwrite).await?;
+                let flags = RunFlags {
+                    force_restart,
+                    jobs,
+                    overwrite,
+                    max_jobs,
+                    progress_json,
+                };
+                run_scheduler(&db, &cfg, &run, &download_dir, flags).await?;
             }
-            CliCommand::Status => run_status(&db).await?,
+            CliCommand::Status { live: false } => run_status(&db).await?,
+            CliCommand::Status { live: true } => run_status_live(&db).await?,
             CliCommand::Pause { id } => run_pause(&db, id).await?,
-            CliCommand::Resume { id } => run_resume(&db, id).await?,
+            CliCommand::Queue {
+                action: QueueAction::Move { id, front, .. },
+            } => run_queue_move(&db, id, front).await?,
+            CliCommand::Resume { id, replan } => run_resume(&db, id, replan).await?,
+            CliCommand::Retry { id } => run_retry(&db, id).await?,
+            CliCommand::Cancel {
+                id,
+                purge_final,
+                download_dir,
+            } => {
+                let dir = download_dir.or_else(|| std::env::current_dir().ok());
+                run_cancel(&db, id, purge_final, dir.as_deref()).await?
+            }
+            CliCommand::History { id } => run_history(&db, id).await?,
+            CliCommand::Events { since, id } => run_events(&db, since, id).await?,
+            CliCommand::Inspect { id } => run_inspect(&db, id).await?,
+            CliCommand::Cleanup { dir, dry_run } => {
+                let dir = match dir {
+                    Some(d) => d,
+                    None => std::env::current_dir()?,
+                };
+                run_cleanup(&db, &dir, dry_run).await?
+            }
             CliCommand::Remove {
                 id,
                 delete_files,
@@ -159,15 +659,31 @@ impl CliCommand {
                 };
                 run_remove(&db, id, delete_files, dir.as_deref()).await?
             }
+            CliCommand::Export {
+                id,
+                output,
+                include_sensitive,
+            } => run_export(&db, id, output.as_deref(), include_sensitive).await?,
+            CliCommand::Import { path, trust } => run_import(&db, &path, trust).await?,
             CliCommand::ImportHar {
                 path,
                 allow_cookies,
+                allow_post,
             } => {
-                run_import_har(&db, Path::new(&path), allow_cookies).await?;
+                run_import_har(&db, Path::new(&path), allow_cookies, allow_post).await?;
+            }
+            CliCommand::Bench {
+                url,
+                max_bytes,
+                format,
+                counts,
+            } => run_bench(&cfg, &url, max_bytes, counts, format).await?,
+            CliCommand::Verify { id, download_dir } => {
+                let dir = download_dir.or_else(|| std::env::current_dir().ok());
+                run_verify(&db, id, dir.as_deref()).await?
             }
-            CliCommand::Bench { url } => run_bench(&url).await?,
             CliCommand::Checksum { path } => run_checksum(Path::new(&path)).await?,
-            CliCommand::Completions { .. } | CliCommand::Manpage => {
+            CliCommand::Completions { .. } | CliCommand::Manpage | CliCommand::Config { .. } => {
                 unreachable!("handled above before opening DB")
             }
         }
@@ -176,5 +692,74 @@ impl CliCommand {
     }
 }
 
+/// Loud stderr notice for `--insecure` (jobs also log a warning each time they run).
+fn warn_insecure() {
+    eprintln!(
+        "WARNING: --insecure: TLS certificate verification is DISABLED; \
+         downloads can be intercepted or tampered with"
+    );
+}
+
+/// Validates a `--connect-to` entry (see [`config::validate_connect_to`]).
+fn parse_connect_to(s: &str) -> Result<String, String> {
+    config::validate_connect_to(s).map_err(|e| format!("{e:#}"))?;
+    Ok(s.to_string())
+}
+
+/// Validates a `--segment-buffer` size (see [`config::validate_segment_buffer`]).
+fn parse_segment_buffer(s: &str) -> Result<usize, String> {
+    let n: usize = s
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid buffer size '{s}' (use a number of bytes)"))?;
+    config::validate_segment_buffer(n).map_err(|e| format!("{e:#}"))?;
+    Ok(n)
+}
+
+fn parse_segment_count(s: &str) -> Result<usize, String> {
+    match s.trim().parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!(
+            "invalid segment count '{s}' (use a positive integer)"
+        )),
+    }
+}
+
+fn parse_conflict_policy(s: &str) -> Result<config::ConflictPolicy, String> {
+    config::ConflictPolicy::from_name(s).ok_or_else(|| {
+        format!("invalid conflict policy '{s}' (use error, rename, overwrite or skip)")
+    })
+}
+
+fn parse_probe_strategy(s: &str) -> Result<config::ProbeStrategy, String> {
+    config::ProbeStrategy::from_name(s).ok_or_else(|| {
+        format!("invalid probe strategy '{s}' (use best_effort, head_only or range_only)")
+    })
+}
+
+fn parse_segment_order(s: &str) -> Result<SegmentOrder, String> {
+    SegmentOrder::from_name(s)
+        .ok_or_else(|| format!("invalid order '{s}' (use parallel or sequential)"))
+}
+
+/// Parses a duration like `600`, `600s`, `10m` or `2h` into seconds.
+fn parse_duration_secs(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (num, mult) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    let n: u64 = num
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}' (use e.g. 600, 600s, 10m, 1h)"))?;
+    if n == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    n.checked_mul(mult)
+        .ok_or_else(|| format!("duration '{s}' is too large"))
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,14 +1,59 @@
 //! Tests for add and run subcommands.
 
 use super::parse;
-use crate::cli::CliCommand;
+use crate::cli::commands::{
+    expected_checksum, parse_headers_text, parse_mirror_list_text, validate_segment_bounds,
+};
+use crate::cli::{Cli, CliCommand};
+use clap::Parser;
+use ddm_core::segmenter::SegmentOrder;
+use std::path::Path;
 
 #[test]
 fn cli_parse_add() {
     match parse(&["ddm", "add", "https://example.com/file.iso"]) {
-        CliCommand::Add { url, download_dir } => {
+        CliCommand::Add {
+            url,
+            download_dir,
+            dry_run,
+            priority,
+            auto_bench,
+            note,
+            deadline,
+            min_segments,
+            max_segments,
+            headers_file,
+            mirror_list,
+            user_agent,
+            on_complete,
+            checksum,
+            start_paused,
+            insecure,
+            output,
+            expected_size,
+            order,
+            trickle,
+        } => {
             assert_eq!(url, "https://example.com/file.iso");
             assert!(download_dir.is_none());
+            assert!(!dry_run);
+            assert_eq!(priority, 0);
+            assert!(!auto_bench);
+            assert!(note.is_none());
+            assert!(deadline.is_none());
+            assert!(min_segments.is_none() && max_segments.is_none());
+            assert!(headers_file.is_none());
+            assert!(mirror_list.is_none());
+            assert!(user_agent.is_none());
+            assert!(on_complete.is_none());
+            assert!(checksum.md5.is_none() && checksum.sha1.is_none() && checksum.sha256.is_none());
+            assert!(checksum.checksums_url.is_none());
+            assert!(!start_paused);
+            assert!(!insecure);
+            assert!(output.is_none());
+            assert!(expected_size.is_none());
+            assert_eq!(order, SegmentOrder::Parallel);
+            assert!(!trickle);
         }
         _ => panic!("expected Add"),
     }
@@ -23,7 +68,9 @@ fn cli_parse_add_download_dir() {
         "--download-dir",
         "/tmp",
     ]) {
-        CliCommand::Add { url, download_dir } => {
+        CliCommand::Add {
+            url, download_dir, ..
+        } => {
             assert_eq!(url, "https://example.com/x");
             assert_eq!(download_dir.as_deref(), Some(std::path::Path::new("/tmp")));
         }
@@ -31,6 +78,285 @@ fn cli_parse_add_download_dir() {
     }
 }
 
+#[test]
+fn cli_parse_add_dry_run() {
+    match parse(&["ddm", "add", "https://example.com/x", "--dry-run"]) {
+        CliCommand::Add { url, dry_run, .. } => {
+            assert_eq!(url, "https://example.com/x");
+            assert!(dry_run);
+        }
+        _ => panic!("expected Add with --dry-run"),
+    }
+}
+
+#[test]
+fn cli_parse_add_auto_bench() {
+    match parse(&["ddm", "add", "https://example.com/x", "--auto-bench"]) {
+        CliCommand::Add { auto_bench, .. } => assert!(auto_bench),
+        _ => panic!("expected Add with --auto-bench"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "add", "u", "--auto-bench", "--dry-run"]).is_err());
+}
+
+#[test]
+fn cli_parse_add_note() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/x",
+        "--note",
+        "nightly build",
+    ]) {
+        CliCommand::Add { note, .. } => assert_eq!(note.as_deref(), Some("nightly build")),
+        _ => panic!("expected Add with --note"),
+    }
+}
+
+#[test]
+fn cli_parse_add_deadline() {
+    for (arg, secs) in [("600", 600), ("600s", 600), ("10m", 600), ("2h", 7200)] {
+        match parse(&["ddm", "add", "https://example.com/x", "--deadline", arg]) {
+            CliCommand::Add { deadline, .. } => assert_eq!(deadline, Some(secs), "{arg}"),
+            _ => panic!("expected Add with --deadline"),
+        }
+    }
+    for bad in ["0", "10x", "m", ""] {
+        assert!(Cli::try_parse_from(["ddm", "add", "u", "--deadline", bad]).is_err());
+    }
+}
+
+#[test]
+fn cli_parse_add_segment_bounds() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/x",
+        "--min-segments",
+        "8",
+        "--max-segments",
+        "32",
+    ]) {
+        CliCommand::Add {
+            min_segments,
+            max_segments,
+            ..
+        } => {
+            assert_eq!(min_segments, Some(8));
+            assert_eq!(max_segments, Some(32));
+        }
+        _ => panic!("expected Add with segment bounds"),
+    }
+    assert!(validate_segment_bounds(Some(8), Some(32)).is_ok());
+    assert!(validate_segment_bounds(Some(8), None).is_ok());
+    assert!(validate_segment_bounds(Some(32), Some(8)).is_err());
+    assert!(validate_segment_bounds(None, Some(0)).is_err());
+}
+
+#[test]
+fn headers_file_parses_and_reports_bad_lines() {
+    let h = parse_headers_text(
+        "# mirror needs these\nUser-Agent: Mozilla/5.0\n\nReferer: https://example.com/a:b\n",
+    )
+    .unwrap();
+    assert_eq!(h.len(), 2);
+    assert_eq!(h["User-Agent"], "Mozilla/5.0");
+    assert_eq!(h["Referer"], "https://example.com/a:b");
+
+    let err = parse_headers_text("Accept: */*\nno colon here\n").unwrap_err();
+    assert!(err.to_string().contains("line 2"), "{err}");
+    let err = parse_headers_text(": value\n").unwrap_err();
+    assert!(err.to_string().contains("line 1"), "{err}");
+    assert!(parse_headers_text("Bad Name: x").is_err());
+}
+
+#[test]
+fn mirror_list_parses_and_rejects_bad_urls() {
+    let m = parse_mirror_list_text(
+        "# Debian mirrors\nhttps://deb.debian.org/a.iso\n\nhttp://ftp.de.debian.org/a.iso\nhttps://deb.debian.org/a.iso\n",
+    )
+    .unwrap();
+    assert_eq!(
+        m,
+        [
+            "https://deb.debian.org/a.iso",
+            "http://ftp.de.debian.org/a.iso"
+        ]
+    );
+
+    let err = parse_mirror_list_text("https://deb.debian.org/a.iso\nftp://x/a.iso\n").unwrap_err();
+    assert!(err.to_string().contains("line 2"), "{err}");
+    assert!(parse_mirror_list_text("# nothing here\n").is_err());
+}
+
+#[test]
+fn cli_parse_add_mirror_list() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://deb.debian.org/a.iso",
+        "--mirror-list",
+        "mirrors.txt",
+    ]) {
+        CliCommand::Add { mirror_list, .. } => {
+            assert_eq!(mirror_list.as_deref(), Some(Path::new("mirrors.txt")))
+        }
+        _ => panic!("expected Add with --mirror-list"),
+    }
+}
+
+#[test]
+fn cli_parse_add_checksum_accepts_one_algorithm() {
+    let md5 = "b1946ac92492d2347c6235b4d2611184";
+    match parse(&["ddm", "add", "https://example.com/x", "--md5", md5]) {
+        CliCommand::Add { checksum, .. } => {
+            assert_eq!(checksum.md5.as_deref(), Some(md5));
+            assert!(checksum.sha1.is_none() && checksum.sha256.is_none());
+        }
+        _ => panic!("expected Add with --md5"),
+    }
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "add",
+        "https://example.com/x",
+        "--md5",
+        md5,
+        "--sha256",
+        "00"
+    ])
+    .is_err());
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/a.iso",
+        "--checksums-url",
+        "https://example.com/SHA256SUMS",
+    ]) {
+        CliCommand::Add { checksum, .. } => assert_eq!(
+            checksum.checksums_url.as_deref(),
+            Some("https://example.com/SHA256SUMS")
+        ),
+        _ => panic!("expected Add with --checksums-url"),
+    }
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "add",
+        "https://example.com/a.iso",
+        "--checksums-url",
+        "https://example.com/SHA256SUMS",
+        "--md5",
+        md5
+    ])
+    .is_err());
+}
+
+#[test]
+fn expected_checksum_validates_and_lowercases() {
+    use ddm_core::checksum::ChecksumAlgo;
+    assert_eq!(expected_checksum(None, None, None).unwrap(), None);
+    assert_eq!(
+        expected_checksum(
+            None,
+            Some("F572D396FAE9206628714FB2CE00F72E94F2258F".into()),
+            None
+        )
+        .unwrap(),
+        Some((
+            ChecksumAlgo::Sha1,
+            "f572d396fae9206628714fb2ce00f72e94f2258f".to_string()
+        ))
+    );
+    assert!(expected_checksum(None, None, Some("abc".into())).is_err());
+    assert!(expected_checksum(Some("0".repeat(32)), None, Some("0".repeat(64))).is_err());
+}
+
+#[test]
+fn cli_parse_add_output() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/download?id=7",
+        "--output",
+        "debian-netinst.iso",
+    ]) {
+        CliCommand::Add { output, .. } => assert_eq!(output.as_deref(), Some("debian-netinst.iso")),
+        _ => panic!("expected Add with --output"),
+    }
+}
+
+#[test]
+fn cli_parse_add_trickle() {
+    match parse(&["ddm", "add", "https://example.com/x.iso", "--trickle"]) {
+        CliCommand::Add { trickle, .. } => assert!(trickle),
+        _ => panic!("expected Add with --trickle"),
+    }
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "add",
+        "https://example.com/x.iso",
+        "--trickle",
+        "--dry-run"
+    ])
+    .is_err());
+}
+
+#[test]
+fn cli_parse_add_expected_size() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/x.iso",
+        "--expected-size",
+        "4700372992",
+    ]) {
+        CliCommand::Add { expected_size, .. } => assert_eq!(expected_size, Some(4_700_372_992)),
+        _ => panic!("expected Add with --expected-size"),
+    }
+}
+
+#[test]
+fn cli_parse_add_start_paused() {
+    match parse(&["ddm", "add", "https://example.com/x", "--start-paused"]) {
+        CliCommand::Add { start_paused, .. } => assert!(start_paused),
+        _ => panic!("expected Add with --start-paused"),
+    }
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "add",
+        "https://example.com/x",
+        "--start-paused",
+        "--dry-run"
+    ])
+    .is_err());
+}
+
+#[test]
+fn cli_parse_add_on_complete() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/x",
+        "--on-complete",
+        "mv {path} /srv/done/",
+    ]) {
+        CliCommand::Add { on_complete, .. } => {
+            assert_eq!(on_complete.as_deref(), Some("mv {path} /srv/done/"))
+        }
+        _ => panic!("expected Add with --on-complete"),
+    }
+}
+
+#[test]
+fn cli_parse_add_priority() {
+    match parse(&["ddm", "add", "https://example.com/x", "--priority", "10"]) {
+        CliCommand::Add { priority, .. } => assert_eq!(priority, 10),
+        _ => panic!("expected Add with --priority"),
+    }
+    match parse(&["ddm", "add", "https://example.com/x", "--priority", "-5"]) {
+        CliCommand::Add { priority, .. } => assert_eq!(priority, -5),
+        _ => panic!("expected Add with negative --priority"),
+    }
+}
+
 #[test]
 fn cli_parse_run() {
     match parse(&["ddm", "run"]) {
@@ -38,6 +364,7 @@ fn cli_parse_run() {
             force_restart,
             jobs,
             overwrite,
+            ..
         } => {
             assert!(!force_restart);
             assert_eq!(jobs, 1);
@@ -54,6 +381,7 @@ fn cli_parse_run_force_restart() {
             force_restart,
             jobs,
             overwrite,
+            ..
         } => {
             assert!(force_restart);
             assert_eq!(jobs, 1);
@@ -71,6 +399,97 @@ fn cli_parse_run_overwrite() {
     }
 }
 
+#[test]
+fn cli_parse_add_index() {
+    match parse(&[
+        "ddm",
+        "add-index",
+        "https://deb.debian.org/debian/pool/main/c/curl/",
+        "--pattern",
+        "*.deb",
+    ]) {
+        CliCommand::AddIndex {
+            url,
+            pattern,
+            download_dir,
+            insecure,
+        } => {
+            assert_eq!(url, "https://deb.debian.org/debian/pool/main/c/curl/");
+            assert_eq!(pattern.as_deref(), Some("*.deb"));
+            assert!(download_dir.is_none());
+            assert!(!insecure);
+        }
+        _ => panic!("expected AddIndex"),
+    }
+    match parse(&[
+        "ddm",
+        "add-index",
+        "https://mirror.internal/pool/",
+        "--insecure",
+    ]) {
+        CliCommand::AddIndex { insecure, .. } => assert!(insecure),
+        _ => panic!("expected AddIndex with --insecure"),
+    }
+}
+
+#[tokio::test]
+async fn add_index_skips_files_that_already_have_a_job() {
+    use crate::cli::commands::add_index_links;
+    use ddm_core::resume_db::{JobSettings, ResumeDb};
+
+    let path = std::env::temp_dir().join(format!("ddm-add-index-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let db = ResumeDb::open_at(&path).await.unwrap();
+    let links: Vec<String> = ["a.deb", "b.deb"]
+        .iter()
+        .map(|n| format!("https://deb.example/pool/{n}"))
+        .collect();
+    assert_eq!(
+        add_index_links(&db, &links[..1], None, &JobSettings::default())
+            .await
+            .unwrap(),
+        (1, 0)
+    );
+    // A second run after an interruption only adds what is missing.
+    assert_eq!(
+        add_index_links(&db, &links, None, &JobSettings::default())
+            .await
+            .unwrap(),
+        (1, 1)
+    );
+    let mut urls: Vec<String> = db
+        .list_jobs()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|j| j.url)
+        .collect();
+    urls.sort();
+    assert_eq!(urls, links);
+    drop(db);
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[test]
+fn cli_parse_run_segment_buffer() {
+    match parse(&["ddm", "run", "--segment-buffer", "262144"]) {
+        CliCommand::Run { segment_buffer, .. } => assert_eq!(segment_buffer, Some(262144)),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run { segment_buffer, .. } => assert_eq!(segment_buffer, None),
+        _ => panic!("expected Run"),
+    }
+    for bad in ["0", "512", "100000", "20971520", "big"] {
+        assert!(
+            Cli::try_parse_from(["ddm", "run", "--segment-buffer", bad]).is_err(),
+            "{bad}"
+        );
+    }
+}
+
 #[test]
 fn cli_parse_run_jobs() {
     match parse(&["ddm", "run", "--jobs", "4"]) {
@@ -78,6 +497,7 @@ fn cli_parse_run_jobs() {
             force_restart,
             jobs,
             overwrite,
+            ..
         } => {
             assert!(!force_restart);
             assert_eq!(jobs, 4);
@@ -86,3 +506,300 @@ fn cli_parse_run_jobs() {
         _ => panic!("expected Run with --jobs 4"),
     }
 }
+
+#[test]
+fn cli_parse_run_probe_strategy() {
+    use ddm_core::config::ProbeStrategy;
+    match parse(&["ddm", "run", "--probe-strategy", "range-only"]) {
+        CliCommand::Run {
+            probe_strategy,
+            no_probe_merge,
+            ..
+        } => {
+            assert_eq!(probe_strategy, Some(ProbeStrategy::RangeOnly));
+            assert!(!no_probe_merge);
+        }
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--no-probe-merge"]) {
+        CliCommand::Run { no_probe_merge, .. } => assert!(no_probe_merge),
+        _ => panic!("expected Run"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "run", "--probe-strategy", "guess"]).is_err());
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "run",
+        "--no-probe-merge",
+        "--probe-strategy",
+        "head_only"
+    ])
+    .is_err());
+}
+
+#[test]
+fn cli_parse_run_ip_family() {
+    match parse(&["ddm", "run", "--ipv4"]) {
+        CliCommand::Run { ipv4, ipv6, .. } => {
+            assert!(ipv4);
+            assert!(!ipv6);
+        }
+        _ => panic!("expected Run with --ipv4"),
+    }
+    match parse(&["ddm", "run", "--ipv6"]) {
+        CliCommand::Run { ipv4, ipv6, .. } => {
+            assert!(!ipv4);
+            assert!(ipv6);
+        }
+        _ => panic!("expected Run with --ipv6"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "run", "--ipv4", "--ipv6"]).is_err());
+}
+
+#[test]
+fn cli_parse_run_proxy() {
+    match parse(&["ddm", "run", "--proxy", "socks5h://127.0.0.1:1080"]) {
+        CliCommand::Run { proxy, .. } => {
+            assert_eq!(proxy.as_deref(), Some("socks5h://127.0.0.1:1080"))
+        }
+        _ => panic!("expected Run with --proxy"),
+    }
+}
+
+#[test]
+fn cli_parse_run_connect_to() {
+    match parse(&[
+        "ddm",
+        "run",
+        "--connect-to",
+        "deb.debian.org:443:10.0.0.5:443",
+        "--connect-to",
+        "::10.0.0.6:",
+    ]) {
+        CliCommand::Run { connect_to, .. } => {
+            assert_eq!(
+                connect_to,
+                ["deb.debian.org:443:10.0.0.5:443", "::10.0.0.6:"]
+            )
+        }
+        _ => panic!("expected Run with --connect-to"),
+    }
+    assert!(
+        Cli::try_parse_from(["ddm", "run", "--connect-to", "deb.debian.org:10.0.0.5"]).is_err()
+    );
+}
+
+#[test]
+fn cli_parse_insecure_is_opt_in() {
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run { insecure, .. } => assert!(!insecure),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--insecure"]) {
+        CliCommand::Run { insecure, .. } => assert!(insecure),
+        _ => panic!("expected Run with --insecure"),
+    }
+    match parse(&["ddm", "add", "https://mirror.internal/a.iso", "--insecure"]) {
+        CliCommand::Add { insecure, .. } => assert!(insecure),
+        _ => panic!("expected Add with --insecure"),
+    }
+}
+
+#[test]
+fn cli_parse_run_verify_on_resume() {
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run {
+            verify_on_resume, ..
+        } => assert!(!verify_on_resume),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--verify-on-resume"]) {
+        CliCommand::Run {
+            verify_on_resume, ..
+        } => assert!(verify_on_resume),
+        _ => panic!("expected Run with --verify-on-resume"),
+    }
+}
+
+#[test]
+fn cli_parse_run_retry_overrides() {
+    match parse(&[
+        "ddm",
+        "run",
+        "--max-retries",
+        "10",
+        "--retry-base-ms",
+        "500",
+    ]) {
+        CliCommand::Run {
+            max_retries,
+            retry_base_ms,
+            ..
+        } => {
+            assert_eq!(max_retries, Some(10));
+            assert_eq!(retry_base_ms, Some(500));
+        }
+        _ => panic!("expected Run with retry overrides"),
+    }
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run {
+            max_retries,
+            retry_base_ms,
+            ..
+        } => {
+            assert!(max_retries.is_none());
+            assert!(retry_base_ms.is_none());
+        }
+        _ => panic!("expected Run"),
+    }
+}
+
+#[test]
+fn cli_parse_run_host_filters() {
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run {
+            hosts,
+            exclude_hosts,
+            ..
+        } => {
+            assert!(hosts.is_empty());
+            assert!(exclude_hosts.is_empty());
+        }
+        _ => panic!("expected Run"),
+    }
+    match parse(&[
+        "ddm",
+        "run",
+        "--host",
+        "cdn.example.com",
+        "--host",
+        "deb.debian.org",
+        "--exclude-host",
+        "slow.example",
+    ]) {
+        CliCommand::Run {
+            hosts,
+            exclude_hosts,
+            ..
+        } => {
+            assert_eq!(hosts, ["cdn.example.com", "deb.debian.org"]);
+            assert_eq!(exclude_hosts, ["slow.example"]);
+        }
+        _ => panic!("expected Run with host filters"),
+    }
+}
+
+#[test]
+fn cli_parse_run_max_jobs() {
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run { max_jobs, .. } => assert_eq!(max_jobs, None),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--max-jobs", "5"]) {
+        CliCommand::Run { max_jobs, .. } => assert_eq!(max_jobs, Some(5)),
+        _ => panic!("expected Run with --max-jobs"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "run", "--max-jobs", "0"]).is_err());
+}
+
+#[test]
+fn cli_parse_add_order() {
+    match parse(&[
+        "ddm",
+        "add",
+        "https://example.com/x.iso",
+        "--order",
+        "sequential",
+    ]) {
+        CliCommand::Add { order, .. } => assert_eq!(order, SegmentOrder::Sequential),
+        _ => panic!("expected Add with --order"),
+    }
+    assert!(Cli::try_parse_from([
+        "ddm",
+        "add",
+        "https://example.com/x.iso",
+        "--order",
+        "random"
+    ])
+    .is_err());
+}
+
+#[test]
+fn cli_parse_run_progress_json() {
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run { progress_json, .. } => assert!(!progress_json),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--progress-json"]) {
+        CliCommand::Run { progress_json, .. } => assert!(progress_json),
+        _ => panic!("expected Run with --progress-json"),
+    }
+}
+
+#[test]
+fn progress_json_line_is_one_parseable_object() {
+    use crate::cli::commands::progress_json_line;
+    use ddm_core::scheduler::ProgressStats;
+
+    let line = progress_json_line(&ProgressStats {
+        job_id: 3,
+        bytes_done: 1024,
+        bytes_in_flight: 1024,
+        total_bytes: 4096,
+        elapsed_secs: 2.0,
+        segments_done: 1,
+        segment_count: 4,
+    });
+    assert!(!line.contains('\n'));
+    let v: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(v["job_id"], 3);
+    assert_eq!(v["bytes_done"], 1024);
+    assert_eq!(v["total_bytes"], 4096);
+    assert_eq!(v["bytes_per_sec"], 1024.0);
+    assert_eq!(v["eta_secs"], 6.0);
+    assert_eq!(v["segments_done"], 1);
+    assert_eq!(v["segment_count"], 4);
+}
+
+#[test]
+fn aggregate_progress_line_sums_parallel_jobs() {
+    use crate::cli::commands::aggregate_progress_line;
+    use ddm_core::scheduler::{AggregateProgress, ProgressStats};
+
+    let mib = 1_048_576;
+    let now = std::time::Instant::now();
+    let mut agg = AggregateProgress::new();
+    for (job_id, done) in [(1, 2 * mib), (2, 4 * mib)] {
+        let stats = ProgressStats {
+            job_id,
+            bytes_done: done,
+            bytes_in_flight: 0,
+            total_bytes: 8 * mib,
+            elapsed_secs: 2.0,
+            segments_done: 0,
+            segment_count: 4,
+        };
+        agg.record(&stats, now);
+    }
+    assert_eq!(
+        aggregate_progress_line(&agg, now),
+        "  all jobs: 6.0 / 16.0 MiB  3.00 MiB/s across 2 active  ETA 3s"
+    );
+}
+
+#[test]
+fn cli_parse_run_on_conflict() {
+    use ddm_core::config::ConflictPolicy;
+
+    match parse(&["ddm", "run"]) {
+        CliCommand::Run { on_conflict, .. } => assert_eq!(on_conflict, None),
+        _ => panic!("expected Run"),
+    }
+    match parse(&["ddm", "run", "--on-conflict", "Rename"]) {
+        CliCommand::Run { on_conflict, .. } => {
+            assert_eq!(on_conflict, Some(ConflictPolicy::Rename))
+        }
+        _ => panic!("expected Run with --on-conflict"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "run", "--on-conflict", "keep"]).is_err());
+    assert!(Cli::try_parse_from(["ddm", "run", "--overwrite", "--on-conflict", "skip"]).is_err());
+}
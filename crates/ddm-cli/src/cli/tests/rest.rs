@@ -1,14 +1,59 @@
-//! Tests for status, pause, resume, remove, import-har, bench, checksum.
+//! Tests for config, status, pause, resume, retry, cancel, history, inspect, remove, cleanup, export, import, import-har, bench, verify, checksum, queue.
 
 use super::parse;
-use crate::cli::CliCommand;
+use crate::cli::commands::find_orphaned_parts;
+use crate::cli::{Cli, CliCommand, ConfigAction, QueueAction};
+use clap::Parser;
+use std::path::Path;
 
 #[test]
 fn cli_parse_status() {
     match parse(&["ddm", "status"]) {
-        CliCommand::Status => {}
+        CliCommand::Status { live } => assert!(!live),
         _ => panic!("expected Status"),
     }
+    match parse(&["ddm", "status", "--live"]) {
+        CliCommand::Status { live } => assert!(live),
+        _ => panic!("expected Status --live"),
+    }
+}
+
+#[test]
+fn cli_parse_config() {
+    match parse(&["ddm", "config", "show"]) {
+        CliCommand::Config {
+            action: ConfigAction::Show,
+        } => {}
+        _ => panic!("expected Config show"),
+    }
+    match parse(&["ddm", "--config", "/tmp/ddm.toml", "config", "path"]) {
+        CliCommand::Config {
+            action: ConfigAction::Path,
+        } => {}
+        _ => panic!("expected Config path"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "config"]).is_err());
+}
+
+#[test]
+fn render_config_names_the_file_and_omits_unset_options() {
+    use crate::cli::commands::render_config;
+    use ddm_core::config::DdmConfig;
+
+    let cfg = DdmConfig {
+        max_segments: 8,
+        proxy: Some("http://proxy:3128".to_string()),
+        ..DdmConfig::default()
+    };
+    let text = render_config(&cfg, Path::new("/home/u/.config/ddm/config.toml")).unwrap();
+    assert!(
+        text.starts_with("# /home/u/.config/ddm/config.toml\n"),
+        "{text}"
+    );
+    assert!(text.contains("max_segments = 8"), "{text}");
+    assert!(text.contains("proxy = \"http://proxy:3128\""), "{text}");
+    assert!(!text.contains("insecure"), "{text}");
+    assert!(!text.contains("ca_bundle"), "{text}");
 }
 
 #[test]
@@ -22,11 +67,166 @@ fn cli_parse_pause() {
 #[test]
 fn cli_parse_resume() {
     match parse(&["ddm", "resume", "1"]) {
-        CliCommand::Resume { id } => assert_eq!(id, 1),
+        CliCommand::Resume { id, replan } => {
+            assert_eq!(id, 1);
+            assert!(!replan);
+        }
+        _ => panic!("expected Resume"),
+    }
+    match parse(&["ddm", "resume", "1", "--replan"]) {
+        CliCommand::Resume { replan, .. } => assert!(replan),
         _ => panic!("expected Resume"),
     }
 }
 
+#[test]
+fn cli_parse_retry() {
+    match parse(&["ddm", "retry", "4"]) {
+        CliCommand::Retry { id } => assert_eq!(id, 4),
+        _ => panic!("expected Retry"),
+    }
+}
+
+#[test]
+fn retry_accepts_only_failed_jobs() {
+    use crate::cli::commands::check_retryable;
+    use ddm_core::resume_db::JobState;
+    assert!(check_retryable(1, JobState::Error).is_ok());
+    let completed = check_retryable(1, JobState::Completed).unwrap_err();
+    assert!(completed.to_string().contains("already completed"));
+    let queued = check_retryable(1, JobState::Queued).unwrap_err();
+    assert!(queued.to_string().contains("already queued"));
+    assert!(check_retryable(1, JobState::Paused).is_err());
+}
+
+#[test]
+fn cli_parse_cancel() {
+    match parse(&["ddm", "cancel", "3"]) {
+        CliCommand::Cancel {
+            id,
+            purge_final,
+            download_dir,
+        } => {
+            assert_eq!(id, 3);
+            assert!(!purge_final);
+            assert!(download_dir.is_none());
+        }
+        _ => panic!("expected Cancel"),
+    }
+}
+
+#[test]
+fn cli_parse_cancel_purge_final() {
+    match parse(&["ddm", "cancel", "3", "--purge-final"]) {
+        CliCommand::Cancel {
+            id, purge_final, ..
+        } => {
+            assert_eq!(id, 3);
+            assert!(purge_final);
+        }
+        _ => panic!("expected Cancel with --purge-final"),
+    }
+}
+
+#[tokio::test]
+async fn cancel_refuses_a_running_job_no_run_is_listening_for() {
+    use crate::cli::commands::run_cancel;
+    use ddm_core::resume_db::{JobSettings, JobState, ResumeDb};
+
+    let dir = std::env::temp_dir().join(format!("ddm-cancel-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = ResumeDb::open_at(&dir.join("jobs.db")).await.unwrap();
+    let id = db
+        .add_job("https://deb.example/a.iso", &JobSettings::default())
+        .await
+        .unwrap();
+    db.set_state(id, JobState::Running).await.unwrap();
+
+    let err = run_cancel(&db, id, false, None).await.unwrap_err();
+    assert!(
+        err.to_string().contains("no `ddm run` took the cancel"),
+        "{err:#}"
+    );
+    assert!(db.get_job(id).await.unwrap().is_some(), "job must be kept");
+
+    db.set_state(id, JobState::Paused).await.unwrap();
+    run_cancel(&db, id, false, None).await.unwrap();
+    assert!(db.get_job(id).await.unwrap().is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn cli_parse_history() {
+    match parse(&["ddm", "history", "7"]) {
+        CliCommand::History { id } => assert_eq!(id, 7),
+        _ => panic!("expected History"),
+    }
+}
+
+#[test]
+fn cli_parse_events() {
+    match parse(&["ddm", "events"]) {
+        CliCommand::Events { since, id } => {
+            assert!(since.is_none());
+            assert!(id.is_none());
+        }
+        _ => panic!("expected Events"),
+    }
+    match parse(&["ddm", "events", "--since", "1700000000", "--id", "3"]) {
+        CliCommand::Events { since, id } => {
+            assert_eq!(since, Some(1_700_000_000));
+            assert_eq!(id, Some(3));
+        }
+        _ => panic!("expected Events with filters"),
+    }
+}
+
+#[test]
+fn cli_parse_export_import() {
+    match parse(&["ddm", "export", "4"]) {
+        CliCommand::Export {
+            id,
+            output,
+            include_sensitive,
+        } => {
+            assert_eq!(id, 4);
+            assert!(output.is_none());
+            assert!(!include_sensitive);
+        }
+        _ => panic!("expected Export"),
+    }
+    match parse(&[
+        "ddm",
+        "export",
+        "4",
+        "-o",
+        "plan.json",
+        "--include-sensitive",
+    ]) {
+        CliCommand::Export {
+            output,
+            include_sensitive,
+            ..
+        } => {
+            assert_eq!(output.as_deref(), Some(Path::new("plan.json")));
+            assert!(include_sensitive);
+        }
+        _ => panic!("expected Export with options"),
+    }
+    match parse(&["ddm", "import", "plan.json"]) {
+        CliCommand::Import { path, trust } => {
+            assert_eq!(path, Path::new("plan.json"));
+            assert!(!trust);
+        }
+        _ => panic!("expected Import"),
+    }
+    match parse(&["ddm", "import", "plan.json", "--trust"]) {
+        CliCommand::Import { trust, .. } => assert!(trust),
+        _ => panic!("expected Import --trust"),
+    }
+}
+
 #[test]
 fn cli_parse_remove() {
     match parse(&["ddm", "remove", "99"]) {
@@ -43,6 +243,61 @@ fn cli_parse_remove() {
     }
 }
 
+#[test]
+fn cli_parse_cleanup() {
+    match parse(&["ddm", "cleanup"]) {
+        CliCommand::Cleanup { dir, dry_run } => {
+            assert!(dir.is_none());
+            assert!(!dry_run);
+        }
+        _ => panic!("expected Cleanup"),
+    }
+    match parse(&["ddm", "cleanup", "--dir", "/srv/iso", "--dry-run"]) {
+        CliCommand::Cleanup { dir, dry_run } => {
+            assert_eq!(dir.as_deref(), Some(std::path::Path::new("/srv/iso")));
+            assert!(dry_run);
+        }
+        _ => panic!("expected Cleanup with --dir --dry-run"),
+    }
+}
+
+#[test]
+fn cleanup_finds_only_unowned_part_files() {
+    let dir = std::env::temp_dir().join(format!("ddm-cleanup-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("nested.part")).unwrap();
+    for name in [
+        "owned.iso.part",
+        "legacy.iso.part",
+        "stray.iso.part",
+        "elsewhere.iso.part",
+        "7-staged.iso.part",
+        "done.iso",
+    ] {
+        std::fs::write(dir.join(name), b"x").unwrap();
+    }
+    let owned = vec![
+        (
+            Some(dir.to_string_lossy().to_string()),
+            "owned.iso.part".to_string(),
+        ),
+        (None, "legacy.iso.part".to_string()),
+        (
+            Some("/elsewhere".to_string()),
+            "elsewhere.iso.part".to_string(),
+        ),
+        (
+            Some("/srv/isos".to_string()),
+            dir.join("7-staged.iso.part").to_string_lossy().to_string(),
+        ),
+    ];
+    let orphans = find_orphaned_parts(&dir, &owned).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(
+        orphans,
+        vec![dir.join("elsewhere.iso.part"), dir.join("stray.iso.part")]
+    );
+}
+
 #[test]
 fn cli_parse_remove_delete_files() {
     match parse(&["ddm", "remove", "1", "--delete-files"]) {
@@ -88,9 +343,11 @@ fn cli_parse_import_har_without_cookies() {
         CliCommand::ImportHar {
             path,
             allow_cookies,
+            allow_post,
         } => {
             assert_eq!(path, "/path/to/file.har");
             assert!(!allow_cookies);
+            assert!(!allow_post);
         }
         _ => panic!("expected ImportHar"),
     }
@@ -102,6 +359,7 @@ fn cli_parse_import_har_allow_cookies() {
         CliCommand::ImportHar {
             path,
             allow_cookies,
+            ..
         } => {
             assert_eq!(path, "x.har");
             assert!(allow_cookies);
@@ -110,12 +368,158 @@ fn cli_parse_import_har_allow_cookies() {
     }
 }
 
+#[test]
+fn cli_parse_import_har_allow_post() {
+    match parse(&["ddm", "import-har", "x.har", "--allow-post"]) {
+        CliCommand::ImportHar {
+            allow_cookies,
+            allow_post,
+            ..
+        } => {
+            assert!(!allow_cookies);
+            assert!(allow_post);
+        }
+        _ => panic!("expected ImportHar"),
+    }
+}
+
 #[test]
 fn cli_parse_bench() {
     match parse(&["ddm", "bench", "https://example.com/large.bin"]) {
-        CliCommand::Bench { url } => assert_eq!(url, "https://example.com/large.bin"),
+        CliCommand::Bench {
+            url,
+            max_bytes,
+            format,
+            counts,
+        } => {
+            assert_eq!(url, "https://example.com/large.bin");
+            assert!(max_bytes.is_none());
+            assert_eq!(format, crate::cli::commands::BenchFormat::Table);
+            assert!(counts.is_empty());
+        }
         _ => panic!("expected Bench"),
     }
+    match parse(&[
+        "ddm",
+        "bench",
+        "https://example.com/x",
+        "--max-bytes",
+        "1048576",
+    ]) {
+        CliCommand::Bench { max_bytes, .. } => assert_eq!(max_bytes, Some(1_048_576)),
+        _ => panic!("expected Bench with --max-bytes"),
+    }
+    match parse(&["ddm", "bench", "https://example.com/x", "--format", "csv"]) {
+        CliCommand::Bench { format, .. } => {
+            assert_eq!(format, crate::cli::commands::BenchFormat::Csv)
+        }
+        _ => panic!("expected Bench with --format"),
+    }
+    assert!(
+        Cli::try_parse_from(["ddm", "bench", "https://example.com/x", "--format", "xml"]).is_err()
+    );
+    match parse(&[
+        "ddm",
+        "bench",
+        "https://example.com/x",
+        "--counts",
+        "2,4,8,16,32",
+    ]) {
+        CliCommand::Bench { counts, .. } => assert_eq!(counts, [2, 4, 8, 16, 32]),
+        _ => panic!("expected Bench with --counts"),
+    }
+    assert!(
+        Cli::try_parse_from(["ddm", "bench", "https://example.com/x", "--counts", "4,0"]).is_err()
+    );
+}
+
+#[test]
+fn bench_results_render_as_csv_and_json() {
+    use crate::cli::commands::{render_bench, BenchFormat};
+    use ddm_core::bench::BenchResult;
+    let results: Vec<BenchResult> = [(4, 10.0), (8, 20.5)]
+        .into_iter()
+        .map(|(segment_count, throughput_mib_s)| BenchResult {
+            segment_count,
+            bytes_downloaded: 1024,
+            elapsed_secs: 0.5,
+            throughput_mib_s,
+            throttle_events: 0,
+            error_events: 1,
+        })
+        .collect();
+
+    let csv = render_bench(&results, Some(8), BenchFormat::Csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("segment_count,"));
+    assert_eq!(lines[1], "4,1024,0.500,10.000,0,1,false");
+    assert_eq!(lines[2], "8,1024,0.500,20.500,0,1,true");
+
+    let json = render_bench(&results, Some(8), BenchFormat::Json).unwrap();
+    assert!(json.contains("\"recommended_segment_count\": 8"));
+    assert!(json.contains("\"throughput_mib_s\": 20.5"));
+
+    let table = render_bench(&results, None, BenchFormat::Table).unwrap();
+    assert!(!table.contains("Recommended"));
+}
+
+#[test]
+fn cli_parse_verify() {
+    match parse(&["ddm", "verify", "4", "--download-dir", "/data"]) {
+        CliCommand::Verify { id, download_dir } => {
+            assert_eq!(id, 4);
+            assert_eq!(download_dir.as_deref(), Some(std::path::Path::new("/data")));
+        }
+        _ => panic!("expected Verify"),
+    }
+}
+
+#[test]
+fn cli_parse_inspect() {
+    match parse(&["ddm", "inspect", "12"]) {
+        CliCommand::Inspect { id } => assert_eq!(id, 12),
+        _ => panic!("expected Inspect"),
+    }
+}
+
+#[test]
+fn truncate_note_for_status_column() {
+    use crate::cli::commands::truncate_note;
+    assert_eq!(truncate_note(None, 8), "-");
+    assert_eq!(truncate_note(Some("nightly"), 8), "nightly");
+    assert_eq!(truncate_note(Some("nightly build"), 8), "nightly…");
+}
+
+#[test]
+fn state_label_shows_error_category() {
+    use crate::cli::commands::state_label;
+    use ddm_core::resume_db::{ErrorCategory, JobState, JobSummary};
+    let mut job = JobSummary {
+        id: 1,
+        url: "https://example.com/f".to_string(),
+        state: JobState::Error,
+        final_filename: None,
+        total_size: None,
+        priority: 0,
+        queue_position: 1,
+        note: None,
+        error_category: Some(ErrorCategory::Storage),
+    };
+    assert_eq!(state_label(&job), "error:storage");
+    job.error_category = None;
+    assert_eq!(state_label(&job), "error");
+    job.state = JobState::Queued;
+    job.error_category = Some(ErrorCategory::Network);
+    assert_eq!(state_label(&job), "queued");
+}
+
+#[test]
+fn format_index_ranges_compacts_runs() {
+    use crate::cli::commands::format_index_ranges;
+    assert_eq!(format_index_ranges(&[]), "none");
+    assert_eq!(format_index_ranges(&[3]), "3");
+    assert_eq!(format_index_ranges(&[0, 1, 2, 5, 7, 8]), "0-2, 5, 7-8");
 }
 
 #[test]
@@ -125,3 +529,94 @@ fn cli_parse_checksum() {
         _ => panic!("expected Checksum"),
     }
 }
+
+#[tokio::test]
+async fn control_socket_answers_status_query() {
+    use crate::cli::control_socket::{query_status, spawn_control_listener};
+    use ddm_core::control::JobControl;
+    use ddm_core::scheduler::ProgressStats;
+    use std::sync::Arc;
+
+    let path = std::env::temp_dir().join(format!("ddm-status-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    assert!(query_status(&path).await.unwrap().is_none());
+
+    let control = Arc::new(JobControl::new());
+    control.register(7);
+    control.record_progress(&ProgressStats {
+        job_id: 7,
+        bytes_done: 512,
+        bytes_in_flight: 0,
+        total_bytes: 1024,
+        elapsed_secs: 1.0,
+        segments_done: 2,
+        segment_count: 4,
+    });
+    let _server = spawn_control_listener(Arc::clone(&control), &path).unwrap();
+    let mut reply = None;
+    for _ in 0..50 {
+        reply = query_status(&path).await.unwrap();
+        if reply.is_some() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    let jobs = reply.expect("listener reachable");
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0].job_id, 7);
+    assert_eq!(jobs[0].bytes_done, 512);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn cli_parse_log_level_flags() {
+    let level = |args: &[&str]| Cli::try_parse_from(args).unwrap().log_level();
+    assert_eq!(level(&["ddm", "status"]), None);
+    assert_eq!(level(&["ddm", "--quiet", "status"]), Some("error"));
+    assert_eq!(level(&["ddm", "status", "-q"]), Some("error"));
+    assert_eq!(level(&["ddm", "-v", "run"]), Some("debug"));
+    assert_eq!(level(&["ddm", "run", "-vv"]), Some("trace"));
+    assert!(Cli::try_parse_from(["ddm", "-q", "-v", "status"]).is_err());
+}
+
+#[test]
+fn cli_parse_config_override() {
+    let cli = Cli::try_parse_from(["ddm", "--config", "/tmp/p.toml", "status"]).unwrap();
+    assert_eq!(cli.config.as_deref(), Some(Path::new("/tmp/p.toml")));
+    assert!(!cli.init_config);
+    let cli = Cli::try_parse_from(["ddm", "run", "--config", "p.toml", "--init-config"]).unwrap();
+    assert!(cli.init_config);
+    assert!(Cli::try_parse_from(["ddm", "status"])
+        .unwrap()
+        .config
+        .is_none());
+    assert!(Cli::try_parse_from(["ddm", "--init-config", "status"]).is_err());
+}
+
+#[test]
+fn cli_parse_state_db_override() {
+    let cli = Cli::try_parse_from(["ddm", "status", "--state-db", "/tmp/jobs.db"]).unwrap();
+    assert_eq!(cli.state_db.as_deref(), Some(Path::new("/tmp/jobs.db")));
+    assert!(Cli::try_parse_from(["ddm", "status"])
+        .unwrap()
+        .state_db
+        .is_none());
+}
+
+#[test]
+fn cli_parse_queue_move() {
+    match parse(&["ddm", "queue", "move", "7", "--front"]) {
+        CliCommand::Queue {
+            action: QueueAction::Move { id, front, back },
+        } => assert_eq!((id, front, back), (7, true, false)),
+        other => panic!("expected Queue Move, got {other:?}"),
+    }
+    match parse(&["ddm", "queue", "move", "7", "--back"]) {
+        CliCommand::Queue {
+            action: QueueAction::Move { front, back, .. },
+        } => assert_eq!((front, back), (false, true)),
+        other => panic!("expected Queue Move, got {other:?}"),
+    }
+    assert!(Cli::try_parse_from(["ddm", "queue", "move", "7"]).is_err());
+    assert!(Cli::try_parse_from(["ddm", "queue", "move", "7", "--front", "--back"]).is_err());
+}
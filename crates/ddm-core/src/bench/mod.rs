@@ -1,6 +1,6 @@
 //! Benchmark mode: try different segment counts and report throughput + events.
 //!
-//! Runs controlled downloads (4, 8, 16 segments) over a capped byte range so
+//! Runs controlled downloads (4, 8, 16 segments by default) over a capped byte range so
 //! the benchmark doesn't download the whole file multiple times. Reports
 //! throughput (MiB/s), throttle events, error events, and a recommended
 //! segment count.
@@ -9,18 +9,21 @@ use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::time::Instant;
 
-use crate::config::DdmConfig;
 use crate::downloader::{self, DownloadSummary};
 use crate::fetch_head;
 use crate::retry::RetryPolicy;
+use crate::scheduler::JobConfig;
 use crate::segmenter;
 use crate::storage;
 
 /// Default cap for benchmark download size (20 MiB per run) so 4/8/16 runs stay bounded.
 const DEFAULT_BENCH_BYTES: u64 = 20 * 1024 * 1024;
 
+/// Segment counts benchmarked when none are given (`ddm bench --counts`).
+pub const DEFAULT_BENCH_COUNTS: [usize; 3] = [4, 8, 16];
+
 /// Result of one benchmark run (one segment count).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize)]
 pub struct BenchResult {
     pub segment_count: usize,
     pub bytes_downloaded: u64,
@@ -30,18 +33,21 @@ pub struct BenchResult {
     pub error_events: u32,
 }
 
-/// Runs benchmark: HEAD, then for each segment count in [4, 8, 16] downloads
+/// Runs benchmark: HEAD, then for each of `segment_counts` (see [`bench_counts`]) downloads
 /// up to `max_bytes` (capped by content length), measures throughput and events.
 /// Uses empty headers unless provided. Runs on the current thread (call from
 /// `spawn_blocking` if used from async).
 pub fn run_bench(
     url: &str,
     headers: &HashMap<String, String>,
-    cfg: &DdmConfig,
+    cfg: &JobConfig<'_>,
     max_bytes: Option<u64>,
+    segment_counts: &[usize],
 ) -> Result<Vec<BenchResult>> {
-    let head = fetch_head::probe(url, headers).context("HEAD request failed")?;
-    if !head.accept_ranges {
+    let segment_counts = bench_counts(segment_counts, cfg.clamp_segments(cfg.max_segments));
+    let head =
+        fetch_head::probe(url, headers, &cfg.curl_options(1)).context("HEAD request failed")?;
+    if !head.accepts_ranges() {
         anyhow::bail!("server does not support Range requests (Accept-Ranges: bytes)");
     }
     let total_size = head
@@ -52,7 +58,6 @@ pub fn run_bench(
         anyhow::bail!("content length is 0");
     }
 
-    let segment_counts = [4_usize, 8, 16];
     let mut results = Vec::with_capacity(segment_counts.len());
     let retry_policy = RetryPolicy::default();
 
@@ -74,8 +79,9 @@ pub fn run_bench(
 
         let start = Instant::now();
         let download_result = downloader::download_segments(
-            url,
+            &downloader::SourceUrls::from(url),
             headers,
+            None,
             &segments,
             &storage_writer,
             &mut bitmap,
@@ -89,7 +95,12 @@ pub fn run_bench(
             None,
             None,
             None,
-            downloader::CurlOptions::default(),
+            // No rate cap while benchmarking; everything else follows config.
+            downloader::CurlOptions {
+                max_recv_speed: None,
+                ..cfg.curl_options(segment_count)
+            },
+            downloader::SegmentRunContext::from_config(cfg),
         );
         let elapsed = start.elapsed().as_secs_f64();
 
@@ -124,6 +135,19 @@ pub fn run_bench(
     Ok(results)
 }
 
+/// Counts to benchmark: zeros dropped, each clamped to `max_segments`, duplicates removed
+/// (first occurrence kept).
+pub fn bench_counts(counts: &[usize], max_segments: usize) -> Vec<usize> {
+    let mut out = Vec::with_capacity(counts.len());
+    for n in counts.iter().filter(|&&n| n > 0) {
+        let n = (*n).min(max_segments.max(1));
+        if !out.contains(&n) {
+            out.push(n);
+        }
+    }
+    out
+}
+
 /// Picks a recommended segment count: prefer best throughput among runs with no errors;
 /// if all have errors, return best throughput overall.
 pub fn recommend_segment_count(results: &[BenchResult]) -> Option<usize> {
@@ -152,6 +176,13 @@ pub fn recommend_segment_count(results: &[BenchResult]) -> Option<usize> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn bench_counts_clamp_to_max_segments_and_dedupe() {
+        assert_eq!(bench_counts(&DEFAULT_BENCH_COUNTS, 16), [4, 8, 16]);
+        assert_eq!(bench_counts(&[2, 4, 8, 16, 32], 16), [2, 4, 8, 16]);
+        assert_eq!(bench_counts(&[0, 4, 4, 2], 8), [4, 2]);
+    }
+
     #[test]
     fn recommend_prefers_no_errors() {
         let results = vec![
@@ -1,21 +1,178 @@
-//! Optional checksum verification (e.g., SHA-256) after completion.
+//! Optional checksum verification (MD5, SHA-1 or SHA-256) after completion.
 //!
-//! This module computes checksums on demand, not inline with the main
-//! download path to avoid impacting throughput.
+//! Whole-file checksums are computed on demand, not inline with the main
+//! download path; a job's expected checksum (`ddm add --sha256` etc.) is checked
+//! once before the file is moved into place. When `hash_segments` is enabled, each segment's bytes are
+//! hashed as they arrive (segments are written out of order, so per-segment
+//! digests are stored instead of a whole-file one) and can be re-checked later.
 
 use anyhow::{Context, Result};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
 use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::Read;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::Mutex;
+
+use crate::segmenter::Segment;
 
 const BUF_SIZE: usize = 64 * 1024;
 
+/// Whole-file digest algorithms (the ones Debian publishes `*SUMS` files for).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChecksumAlgo {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl ChecksumAlgo {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChecksumAlgo::Md5 => "md5",
+            ChecksumAlgo::Sha1 => "sha1",
+            ChecksumAlgo::Sha256 => "sha256",
+        }
+    }
+
+    /// Length of a digest in hex characters.
+    pub fn hex_len(self) -> usize {
+        match self {
+            ChecksumAlgo::Md5 => 32,
+            ChecksumAlgo::Sha1 => 40,
+            ChecksumAlgo::Sha256 => 64,
+        }
+    }
+
+    /// Algorithm whose digests are `len` hex characters long.
+    pub fn from_hex_len(len: usize) -> Option<Self> {
+        [ChecksumAlgo::Md5, ChecksumAlgo::Sha1, ChecksumAlgo::Sha256]
+            .into_iter()
+            .find(|a| a.hex_len() == len)
+    }
+
+    /// Check that `hex` is a digest of this algorithm and return it lowercased.
+    pub fn normalize_hex(self, hex: &str) -> Result<String> {
+        let hex = hex.trim();
+        if hex.len() != self.hex_len() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!(
+                "invalid {} checksum {:?}: expected {} hex characters",
+                self.as_str(),
+                hex,
+                self.hex_len()
+            );
+        }
+        Ok(hex.to_ascii_lowercase())
+    }
+}
+
+/// Expected checksum does not match the downloaded file.
+#[derive(Debug)]
+pub struct ChecksumMismatch {
+    pub algo: ChecksumAlgo,
+    pub expected: String,
+    pub actual: String,
+}
+
+impl std::fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} mismatch: expected {}, got {}",
+            self.algo.as_str(),
+            self.expected,
+            self.actual
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// Find `filename` in a `*SUMS` file (`<hex>  <name>` lines as written by `sha256sum` and
+/// friends; a `*` before the name marks binary mode). Entries with a directory part match on
+/// their last component. The algorithm is inferred from the digest length.
+pub fn find_in_sums(text: &str, filename: &str) -> Option<(ChecksumAlgo, String)> {
+    text.lines().find_map(|line| {
+        let (hex, name) = line.trim().split_once(char::is_whitespace)?;
+        let name = name.trim_start();
+        let name = name.strip_prefix('*').unwrap_or(name);
+        let matches = name == filename || name.rsplit('/').next() == Some(filename);
+        if !matches {
+            return None;
+        }
+        let algo = ChecksumAlgo::from_hex_len(hex.len())?;
+        algo.normalize_hex(hex).ok().map(|h| (algo, h))
+    })
+}
+
+/// Parse an RFC 3230 `Digest` header (`sha-256=<base64>, md5=<base64>`) into the strongest
+/// supported algorithm and its digest as lowercase hex. Unknown algorithms are skipped.
+pub fn parse_digest_header(value: &str) -> Option<(ChecksumAlgo, String)> {
+    value
+        .split(',')
+        .filter_map(|item| {
+            let (token, b64) = item.trim().split_once('=')?;
+            let algo = match token.trim().to_ascii_lowercase().as_str() {
+                "md5" => ChecksumAlgo::Md5,
+                "sha" => ChecksumAlgo::Sha1,
+                "sha-256" => ChecksumAlgo::Sha256,
+                _ => return None,
+            };
+            base64_to_hex(algo, b64).map(|hex| (algo, hex))
+        })
+        .max_by_key(|(algo, _)| algo.hex_len())
+}
+
+/// Parse a `Content-MD5` header (base64 MD5 of the body) into lowercase hex.
+pub fn parse_content_md5(value: &str) -> Option<String> {
+    base64_to_hex(ChecksumAlgo::Md5, value)
+}
+
+fn base64_to_hex(algo: ChecksumAlgo, b64: &str) -> Option<String> {
+    use base64::Engine;
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(b64.trim())
+        .ok()?;
+    (bytes.len() * 2 == algo.hex_len()).then(|| hex::encode(bytes))
+}
+
 /// Compute SHA-256 of a file and return the digest as lowercase hex.
 /// Reads in chunks to keep memory use bounded; suitable for large files.
 pub fn sha256_path(path: &Path) -> Result<String> {
+    hash_path(path, ChecksumAlgo::Sha256)
+}
+
+/// Compute the `algo` digest of a file as lowercase hex.
+pub fn hash_path(path: &Path, algo: ChecksumAlgo) -> Result<String> {
+    match algo {
+        ChecksumAlgo::Md5 => digest_path::<Md5>(path),
+        ChecksumAlgo::Sha1 => digest_path::<Sha1>(path),
+        ChecksumAlgo::Sha256 => digest_path::<Sha256>(path),
+    }
+}
+
+/// Hash `path` against an expected `(algo, hex)` digest; fails with [`ChecksumMismatch`]
+/// when they differ.
+pub fn verify_file(path: &Path, algo: ChecksumAlgo, expected: &str) -> Result<()> {
+    let actual = hash_path(path, algo)?;
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(ChecksumMismatch {
+            algo,
+            expected: expected.to_ascii_lowercase(),
+            actual,
+        }
+        .into())
+    }
+}
+
+fn digest_path<D: Digest>(path: &Path) -> Result<String> {
     let mut f = File::open(path).with_context(|| format!("open {}", path.display()))?;
-    let mut hasher = Sha256::new();
+    let mut hasher = D::new();
     let mut buf = [0u8; BUF_SIZE];
     loop {
         let n = f
@@ -30,6 +187,100 @@ pub fn sha256_path(path: &Path) -> Result<String> {
     Ok(hex::encode(digest))
 }
 
+/// Compute SHA-256 of `len` bytes starting at `start` in `path` (lowercase hex).
+/// Fails if the file is shorter than `start + len`.
+pub fn sha256_range(path: &Path, start: u64, len: u64) -> Result<String> {
+    let mut f = File::open(path).with_context(|| format!("open {}", path.display()))?;
+    f.seek(SeekFrom::Start(start))
+        .with_context(|| format!("seek {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; BUF_SIZE];
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = remaining.min(BUF_SIZE as u64) as usize;
+        let n = f
+            .read(&mut buf[..want])
+            .with_context(|| format!("read {}", path.display()))?;
+        if n == 0 {
+            anyhow::bail!(
+                "{}: unexpected end of file at offset {}",
+                path.display(),
+                start + len - remaining
+            );
+        }
+        hasher.update(&buf[..n]);
+        remaining -= n as u64;
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Returns indices of segments whose stored SHA-256 does not match the bytes in `path`.
+/// Segments without a stored hash (`None`, or beyond `hashes`) are skipped; a segment
+/// that cannot be read (e.g. truncated file) counts as a mismatch.
+pub fn verify_segments(path: &Path, segments: &[Segment], hashes: &[Option<String>]) -> Vec<usize> {
+    segments
+        .iter()
+        .enumerate()
+        .filter_map(|(i, seg)| {
+            let expected = hashes.get(i)?.as_deref()?;
+            match sha256_range(path, seg.start, seg.len()) {
+                Ok(actual) if actual.eq_ignore_ascii_case(expected) => None,
+                _ => Some(i),
+            }
+        })
+        .collect()
+}
+
+/// Per-segment SHA-256 digests filled in by the downloader as segments complete.
+/// Shared across worker threads; one slot per segment.
+#[derive(Debug)]
+pub struct SegmentHashes {
+    slots: Vec<Mutex<Option<String>>>,
+}
+
+impl SegmentHashes {
+    pub fn new(segment_count: usize) -> Self {
+        Self {
+            slots: (0..segment_count).map(|_| Mutex::new(None)).collect(),
+        }
+    }
+
+    /// Record the digest of a completed segment. Out-of-range indices are ignored.
+    pub fn set(&self, index: usize, hex_digest: String) {
+        if let Some(slot) = self.slots.get(index) {
+            *slot.lock().unwrap() = Some(hex_digest);
+        }
+    }
+
+    /// Snapshot of all slots (None for segments not hashed in this run).
+    pub fn to_vec(&self) -> Vec<Option<String>> {
+        self.slots
+            .iter()
+            .map(|s| s.lock().unwrap().clone())
+            .collect()
+    }
+}
+
+/// Combine digests from earlier runs with this run's: a slot hashed now wins, otherwise the
+/// prior digest is kept. `prior` is ignored unless it has exactly `segment_count` entries.
+pub fn merge_segment_hashes(
+    prior: &[Option<String>],
+    current: Option<&SegmentHashes>,
+    segment_count: usize,
+) -> Vec<Option<String>> {
+    let current = current.map(SegmentHashes::to_vec).unwrap_or_default();
+    let prior_ok = prior.len() == segment_count;
+    (0..segment_count)
+        .map(|i| {
+            current
+                .get(i)
+                .cloned()
+                .flatten()
+                .or_else(|| prior_ok.then(|| prior[i].clone()).flatten())
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +309,141 @@ mod tests {
             "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
         );
     }
+
+    #[test]
+    fn hash_path_dispatches_on_algorithm() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(b"hello\n").unwrap();
+        f.flush().unwrap();
+        assert_eq!(
+            hash_path(f.path(), ChecksumAlgo::Md5).unwrap(),
+            "b1946ac92492d2347c6235b4d2611184"
+        );
+        assert_eq!(
+            hash_path(f.path(), ChecksumAlgo::Sha1).unwrap(),
+            "f572d396fae9206628714fb2ce00f72e94f2258f"
+        );
+        verify_file(
+            f.path(),
+            ChecksumAlgo::Md5,
+            "B1946AC92492D2347C6235B4D2611184",
+        )
+        .unwrap();
+        let err = verify_file(f.path(), ChecksumAlgo::Sha1, &"0".repeat(40)).unwrap_err();
+        assert!(err.downcast_ref::<ChecksumMismatch>().is_some());
+        assert!(err.to_string().starts_with("sha1 mismatch"), "{err}");
+    }
+
+    #[test]
+    fn find_in_sums_matches_text_and_binary_entries() {
+        let sha = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        let sums = format!(
+            "{}  debian-12.7.0-amd64-DVD-1.iso\n{sha}  debian-12.7.0-amd64-netinst.iso\n{} *./firmware/fw.tar.gz\n",
+            "0".repeat(64),
+            "1".repeat(32)
+        );
+        assert_eq!(
+            find_in_sums(&sums, "debian-12.7.0-amd64-netinst.iso"),
+            Some((ChecksumAlgo::Sha256, sha.to_string()))
+        );
+        assert_eq!(
+            find_in_sums(&sums, "fw.tar.gz"),
+            Some((ChecksumAlgo::Md5, "1".repeat(32)))
+        );
+        assert_eq!(find_in_sums(&sums, "debian-12.7.0-amd64"), None);
+        assert_eq!(find_in_sums("xyz  a.iso\n", "a.iso"), None);
+    }
+
+    #[test]
+    fn digest_headers_decode_base64_to_hex() {
+        let sha = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        let md5 = "b1946ac92492d2347c6235b4d2611184";
+        assert_eq!(
+            parse_digest_header(
+                "MD5=sZRqySSS0jR8YjW00mERhA==, SHA-256=WJG1tSLV3whtD/CxEPvZ0hu0/HFjrzTQgoai6Eb2vgM="
+            ),
+            Some((ChecksumAlgo::Sha256, sha.to_string()))
+        );
+        assert_eq!(
+            parse_digest_header("unixsum=30637, md5=sZRqySSS0jR8YjW00mERhA=="),
+            Some((ChecksumAlgo::Md5, md5.to_string()))
+        );
+        // Wrong length for the algorithm, or not base64 at all.
+        assert_eq!(
+            parse_digest_header("sha-256=sZRqySSS0jR8YjW00mERhA=="),
+            None
+        );
+        assert_eq!(parse_digest_header("sha=!!!"), None);
+        assert_eq!(
+            parse_content_md5(" sZRqySSS0jR8YjW00mERhA== "),
+            Some(md5.to_string())
+        );
+    }
+
+    #[test]
+    fn normalize_hex_checks_length_and_digits() {
+        assert_eq!(
+            ChecksumAlgo::Md5
+                .normalize_hex("B1946AC92492D2347C6235B4D2611184")
+                .unwrap(),
+            "b1946ac92492d2347c6235b4d2611184"
+        );
+        assert!(ChecksumAlgo::Sha1
+            .normalize_hex("b1946ac92492d2347c6235b4d2611184")
+            .is_err());
+        assert!(ChecksumAlgo::Sha256.normalize_hex(&"g".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn sha256_range_matches_slice_hash() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(b"xxhello\nyy").unwrap();
+        f.flush().unwrap();
+        assert_eq!(
+            sha256_range(f.path(), 2, 6).unwrap(),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+        assert!(sha256_range(f.path(), 8, 10).is_err());
+    }
+
+    #[test]
+    fn verify_segments_reports_mismatches_only() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(&[7u8; 100]).unwrap();
+        f.flush().unwrap();
+        let segments = crate::segmenter::plan_segments(100, 4);
+        let good = |s: &Segment| hex::encode(Sha256::digest(vec![7u8; s.len() as usize]));
+        let hashes = vec![
+            Some(good(&segments[0])),
+            Some("00".repeat(32)),
+            None,
+            Some(good(&segments[3])),
+        ];
+        assert_eq!(verify_segments(f.path(), &segments, &hashes), vec![1]);
+        assert!(verify_segments(f.path(), &segments, &[]).is_empty());
+    }
+
+    #[test]
+    fn segment_hashes_set_and_snapshot() {
+        let h = SegmentHashes::new(2);
+        h.set(1, "ab".to_string());
+        h.set(5, "ignored".to_string());
+        assert_eq!(h.to_vec(), vec![None, Some("ab".to_string())]);
+    }
+
+    #[test]
+    fn merge_segment_hashes_prefers_current_and_drops_mismatched_prior() {
+        let h = SegmentHashes::new(3);
+        h.set(0, "new".to_string());
+        let prior = vec![Some("old".to_string()), Some("kept".to_string()), None];
+        assert_eq!(
+            merge_segment_hashes(&prior, Some(&h), 3),
+            vec![Some("new".to_string()), Some("kept".to_string()), None]
+        );
+        assert_eq!(
+            merge_segment_hashes(&prior[..2], Some(&h), 3),
+            vec![Some("new".to_string()), None, None]
+        );
+        assert_eq!(merge_segment_hashes(&prior, None, 3), prior);
+    }
 }
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Retry policy parameters (optional section in config.toml).
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +12,21 @@ pub struct RetryConfig {
     pub base_delay_secs: f64,
     /// Maximum backoff delay in seconds.
     pub max_delay_secs: u64,
+    /// Randomize each backoff delay between the base and computed delay (default true).
+    #[serde(default = "default_true")]
+    pub jitter: bool,
+    /// Total retries allowed across all segments of a job run before it fails with
+    /// "retry budget exhausted" (`0` = unlimited).
+    #[serde(default = "default_max_job_retries")]
+    pub max_job_retries: u32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_job_retries() -> u32 {
+    crate::retry::DEFAULT_MAX_JOB_RETRIES
 }
 
 impl Default for RetryConfig {
@@ -20,17 +35,280 @@ impl Default for RetryConfig {
             max_attempts: 5,
             base_delay_secs: 0.25,
             max_delay_secs: 30,
+            jitter: true,
+            max_job_retries: default_max_job_retries(),
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Bounds applied to command-line overrides.
+    pub const MAX_ATTEMPTS_RANGE: std::ops::RangeInclusive<u32> = 1..=50;
+    pub const BASE_DELAY_MS_RANGE: std::ops::RangeInclusive<u64> = 1..=60_000;
+
+    /// Copy with `max_attempts` / base delay (milliseconds) replaced where given, clamped
+    /// to `MAX_ATTEMPTS_RANGE` and `BASE_DELAY_MS_RANGE`.
+    pub fn with_overrides(&self, max_attempts: Option<u32>, base_delay_ms: Option<u64>) -> Self {
+        let mut out = self.clone();
+        if let Some(n) = max_attempts {
+            out.max_attempts = n.clamp(
+                *Self::MAX_ATTEMPTS_RANGE.start(),
+                *Self::MAX_ATTEMPTS_RANGE.end(),
+            );
+        }
+        if let Some(ms) = base_delay_ms {
+            let ms = ms.clamp(
+                *Self::BASE_DELAY_MS_RANGE.start(),
+                *Self::BASE_DELAY_MS_RANGE.end(),
+            );
+            out.base_delay_secs = ms as f64 / 1000.0;
         }
+        out
     }
 }
 
 /// Download backend: Easy+threads (one Easy per segment in OS threads) or curl multi (single-threaded, multiple Easy2).
+/// `Auto` picks multi when a job runs at least `auto_multi_threshold` connections.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum DownloadBackend {
     #[default]
     Easy,
     Multi,
+    Auto,
+}
+
+/// Address family used for connections: "auto" (curl default), "v4", or "v6".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    #[default]
+    Auto,
+    V4,
+    V6,
+}
+
+/// HTTP version to request: "auto" (curl default), "http1_1", "http2", or "http3".
+/// HTTP/2 needs libcurl built with nghttp2 and HTTP/3 one built with an HTTP/3 backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HttpVersion {
+    #[default]
+    Auto,
+    Http1_1,
+    Http2,
+    Http3,
+}
+
+/// What a run does when a job's final file already exists on disk (`on_conflict`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Fail the job (the default).
+    #[default]
+    Error,
+    /// Download to a free `name (1).ext`-style name instead.
+    Rename,
+    /// Replace the existing file.
+    Overwrite,
+    /// Leave the file alone and mark the job completed without downloading.
+    Skip,
+}
+
+impl ConflictPolicy {
+    /// Parses `error`, `rename`, `overwrite` or `skip` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "rename" => Some(Self::Rename),
+            "overwrite" => Some(Self::Overwrite),
+            "skip" => Some(Self::Skip),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Rename => "rename",
+            Self::Overwrite => "overwrite",
+            Self::Skip => "skip",
+        }
+    }
+}
+
+/// How a new `.part` file is sized before segments are written (`preallocation`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preallocation {
+    /// Reserve every block up front with `posix_fallocate` (the default), falling back to
+    /// `set_len` where that is unsupported.
+    #[default]
+    Full,
+    /// Set the length only (a sparse file); blocks are allocated as segments land.
+    Sparse,
+    /// Leave the file empty and let writes grow it.
+    None,
+}
+
+impl Preallocation {
+    /// Parses `full`, `sparse` or `none` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "sparse" => Some(Self::Sparse),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Full => "full",
+            Self::Sparse => "sparse",
+            Self::None => "none",
+        }
+    }
+}
+
+/// Which requests the metadata probe sends before a job starts (`probe_strategy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProbeStrategy {
+    /// HEAD, plus a one-byte range GET when HEAD leaves the length or range support open
+    /// (the default).
+    #[default]
+    BestEffort,
+    /// HEAD only; fails when it lacks the length or range support.
+    HeadOnly,
+    /// The one-byte range GET only, for hosts that mishandle HEAD.
+    RangeOnly,
+}
+
+impl ProbeStrategy {
+    /// Parses `best_effort`, `head_only` or `range_only` (case-insensitive; `-` works for `_`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "best_effort" => Some(Self::BestEffort),
+            "head_only" => Some(Self::HeadOnly),
+            "range_only" => Some(Self::RangeOnly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::BestEffort => "best_effort",
+            Self::HeadOnly => "head_only",
+            Self::RangeOnly => "range_only",
+        }
+    }
+}
+
+/// Time-of-day bandwidth cap (`[[bandwidth_schedule]]` entry). Times are local "HH:MM";
+/// `end` is exclusive and a window with `start > end` wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BandwidthWindow {
+    pub start: String,
+    pub end: String,
+    pub max_bytes_per_sec: u64,
+}
+
+impl BandwidthWindow {
+    /// True if `minute` (minutes since local midnight) falls inside this window.
+    /// Returns false when either time fails to parse.
+    pub fn contains(&self, minute: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_hhmm(&self.start), parse_hhmm(&self.end)) else {
+            return false;
+        };
+        if start <= end {
+            start <= minute && minute < end
+        } else {
+            minute >= start || minute < end
+        }
+    }
+}
+
+/// Parse "HH:MM" (24-hour) into minutes since midnight.
+fn parse_hhmm(s: &str) -> Option<u32> {
+    let (h, m) = s.trim().split_once(':')?;
+    let h: u32 = h.parse().ok()?;
+    let m: u32 = m.parse().ok()?;
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// Check a `HOST:PORT:CONNECT-HOST:CONNECT-PORT` entry (IPv6 hosts in brackets). Hosts may be
+/// empty; ports must be empty or 1-65535.
+pub fn validate_connect_to(entry: &str) -> Result<()> {
+    let mut fields = Vec::new();
+    let mut rest = entry;
+    loop {
+        let end = if rest.starts_with('[') {
+            let close = rest
+                .find(']')
+                .ok_or_else(|| anyhow::anyhow!("{:?}: unclosed '[' in host", entry))?;
+            rest[close..].find(':').map(|i| close + i)
+        } else {
+            rest.find(':')
+        };
+        match end {
+            Some(i) => {
+                fields.push(&rest[..i]);
+                rest = &rest[i + 1..];
+            }
+            None => {
+                fields.push(rest);
+                break;
+            }
+        }
+    }
+    let [_, port, _, connect_port] = fields.as_slice() else {
+        anyhow::bail!("{:?}: expected HOST:PORT:CONNECT-HOST:CONNECT-PORT", entry);
+    };
+    for p in [port, connect_port] {
+        if !p.is_empty() && !p.parse::<u16>().is_ok_and(|n| n > 0) {
+            anyhow::bail!("{:?}: invalid port {:?}", entry, p);
+        }
+    }
+    Ok(())
+}
+
+/// Smallest `segment_buffer_bytes` libcurl accepts (`CURLOPT_BUFFERSIZE`).
+pub const MIN_SEGMENT_BUFFER_BYTES: usize = 1024;
+/// Largest `segment_buffer_bytes` libcurl accepts.
+pub const MAX_SEGMENT_BUFFER_BYTES: usize = 10 * 1024 * 1024;
+
+/// Check a `segment_buffer_bytes` value: 1 KiB to 10 MiB (libcurl's range) in whole KiB.
+pub fn validate_segment_buffer(bytes: usize) -> Result<()> {
+    if !(MIN_SEGMENT_BUFFER_BYTES..=MAX_SEGMENT_BUFFER_BYTES).contains(&bytes) {
+        anyhow::bail!(
+            "{} is outside {}..={} bytes",
+            bytes,
+            MIN_SEGMENT_BUFFER_BYTES,
+            MAX_SEGMENT_BUFFER_BYTES
+        );
+    }
+    if !bytes.is_multiple_of(1024) {
+        anyhow::bail!("{} is not a multiple of 1024 (e.g. 65536 or 262144)", bytes);
+    }
+    Ok(())
+}
+
+/// Minutes since local midnight (UTC where local time is unavailable).
+fn local_minute_of_day() -> u32 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    #[cfg(unix)]
+    {
+        let t = now as libc::time_t;
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        if !unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+            return (tm.tm_hour * 60 + tm.tm_min) as u32;
+        }
+    }
+    ((now % 86_400) / 60) as u32
 }
 
 /// Global configuration loaded from `~/.config/ddm/config.toml`.
@@ -44,6 +322,11 @@ pub struct DdmConfig {
     pub min_segments: usize,
     /// Maximum number of segments per job.
     pub max_segments: usize,
+    /// Hard ceiling on any job's segment count (None = [`DEFAULT_ABSOLUTE_MAX_SEGMENTS`]);
+    /// `max_segments`, per-job `--max-segments`, auto-bench counts and host-policy
+    /// adaptation are all clamped to it.
+    #[serde(default)]
+    pub absolute_max_segments: Option<usize>,
     /// Optional retry policy; if missing, built-in defaults are used.
     #[serde(default)]
     pub retry: Option<RetryConfig>,
@@ -53,11 +336,145 @@ pub struct DdmConfig {
     /// Optional segment read/write buffer size in bytes (None = library default). Applied to curl when set.
     #[serde(default)]
     pub segment_buffer_bytes: Option<usize>,
-    /// Download backend: "easy" (default) or "multi". Easy = one Easy handle per segment in threads; multi = curl multi.
+    /// Download backend: "easy" (default), "multi", or "auto". Easy = one Easy handle per segment in threads; multi = curl multi; auto = multi at `auto_multi_threshold` connections or more.
     #[serde(default)]
     pub download_backend: Option<DownloadBackend>,
+    /// Seconds after a host's last throttle event before its throttle penalty is forgiven (None = 24h).
+    #[serde(default)]
+    pub throttle_decay_secs: Option<u64>,
+    /// Address family for connections: "auto" (default), "v4", or "v6".
+    #[serde(default)]
+    pub ip_version: Option<IpVersion>,
+    /// Milliseconds a connection attempt on the preferred address family (usually IPv6) gets
+    /// before curl also tries the other one (None = curl's default of 200).
+    #[serde(default)]
+    pub happy_eyeballs_timeout_ms: Option<u64>,
+    /// HTTP version for probes and downloads: "auto" (default), "http1_1", "http2", or "http3".
+    #[serde(default)]
+    pub http_version: Option<HttpVersion>,
+    /// Optional proxy URL (e.g. "http://proxy:3128" or "socks5h://127.0.0.1:1080"). When unset, curl's `*_proxy` environment variables still apply.
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Hash each segment (SHA-256) as it downloads and store the digests with the job for `ddm verify`.
+    #[serde(default)]
+    pub hash_segments: Option<bool>,
+    /// Preferred segment size in bytes (e.g. 67108864 for 64 MiB). When set, the segment count is chosen so pieces are near this size, up to `max_segments`.
+    #[serde(default)]
+    pub target_segment_bytes: Option<u64>,
+    /// Time-of-day rate caps; the first window containing the current local time overrides `max_bytes_per_sec`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bandwidth_schedule: Vec<BandwidthWindow>,
+    /// Files smaller than this many bytes download as one plain GET instead of segments (None = 1 MiB, 0 = always segment).
+    #[serde(default)]
+    pub single_stream_threshold_bytes: Option<u64>,
+    /// `User-Agent` sent with every request (None = `ddm/<version>`); `ddm add --user-agent` overrides per job.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    /// With `download_backend = "auto"`, use multi when a job gets at least this many connections (None = 8).
+    #[serde(default)]
+    pub auto_multi_threshold: Option<usize>,
+    /// Shell command run after a job completes; `{path}` and `{url}` are replaced (shell-quoted).
+    /// A failing hook is logged, not fatal. `ddm add --on-complete` overrides per job.
+    #[serde(default)]
+    pub on_complete: Option<String>,
+    /// Persist the completed-segment bitmap after this many segments finish (None = 2). Lower
+    /// gives fresher progress for jobs with few large segments; higher cuts DB writes when there
+    /// are thousands of small ones.
+    #[serde(default)]
+    pub progress_flush_every_segments: Option<usize>,
+    /// `HOST:PORT:CONNECT-HOST:CONNECT-PORT` overrides (curl `--connect-to`): requests for
+    /// HOST:PORT connect to CONNECT-HOST:CONNECT-PORT instead, keeping the URL's Host header and
+    /// TLS name. Empty fields match any host/port or keep the original.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connect_to: Vec<String>,
+    /// PEM file of CA certificates to verify servers against (e.g. a private mirror CA) instead
+    /// of curl's default bundle. Verification stays on.
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// Redirects followed by probes and downloads (None = 10, 0 = never follow; a redirect
+    /// then fails the request with its 3xx status).
+    #[serde(default)]
+    pub max_redirects: Option<u32>,
+    /// Before resuming, re-check segments marked complete against the `.part` file: its length,
+    /// and their stored SHA-256 when `hash_segments` recorded one. Failing segments are
+    /// downloaded again. `ddm run --verify-on-resume` turns it on for one run.
+    #[serde(default)]
+    pub verify_on_resume: Option<bool>,
+    /// Before planning a fresh segmented download, confirm the server honors Range with a
+    /// `bytes=0-0` GET; a 200 reply (Range ignored despite `Accept-Ranges`) makes the job a
+    /// single-stream download instead of failing every segment.
+    #[serde(default)]
+    pub verify_ranges: Option<bool>,
+    /// Milliseconds between a job's first connection starts (None or 0 = open them all at
+    /// once). Spreads the initial burst for mirrors that throttle on it.
+    #[serde(default)]
+    pub connection_ramp_ms: Option<u64>,
+    /// Per-segment throughput floor in bytes/sec (None or 0 = off). A segment averaging less
+    /// over `stall_window_secs` is aborted and retried like a timed-out transfer.
+    #[serde(default)]
+    pub min_segment_bps: Option<u64>,
+    /// Window for `min_segment_bps` in seconds (None = [`DEFAULT_STALL_WINDOW_SECS`]).
+    #[serde(default)]
+    pub stall_window_secs: Option<u64>,
+    /// Slowest rate (bytes/sec) a segment is expected to sustain; when set, each segment's
+    /// timeout is its length at this rate (at least `min_timeout_secs`) instead of a flat hour.
+    #[serde(default)]
+    pub min_expected_bps: Option<u64>,
+    /// Floor for the scaled segment timeout in seconds (None = [`DEFAULT_MIN_TIMEOUT_SECS`]).
+    #[serde(default)]
+    pub min_timeout_secs: Option<u64>,
+    /// Connection failures in a row (within `circuit_window_secs`) after which a host's
+    /// circuit opens and its queued jobs are skipped (None = 3).
+    #[serde(default)]
+    pub circuit_failure_threshold: Option<u32>,
+    /// Window in seconds for counting consecutive connection failures (None = 60).
+    #[serde(default)]
+    pub circuit_window_secs: Option<u64>,
+    /// How long in seconds an open circuit skips the host's jobs (None = 300).
+    #[serde(default)]
+    pub circuit_cooldown_secs: Option<u64>,
+    /// What to do when a job's final file already exists: "error" (default), "rename",
+    /// "overwrite" or "skip". `ddm run --on-conflict` overrides it for one run.
+    #[serde(default)]
+    pub on_conflict: Option<ConflictPolicy>,
+    /// How new `.part` files are preallocated: `full` (default), `sparse` or `none`.
+    #[serde(default)]
+    pub preallocation: Option<Preallocation>,
+    /// Requests the metadata probe sends: `best_effort` (default), `head_only` or
+    /// `range_only`. `ddm run --probe-strategy` overrides it for one run.
+    #[serde(default)]
+    pub probe_strategy: Option<ProbeStrategy>,
+    /// Absolute directory for in-progress `.part` files (e.g. a fast SSD) instead of the
+    /// download directory. Finished files are moved to the download directory, falling back
+    /// to a copy when the two are on different filesystems.
+    #[serde(default)]
+    pub temp_dir: Option<PathBuf>,
 }
 
+/// Default for `single_stream_threshold_bytes`.
+pub const DEFAULT_SINGLE_STREAM_THRESHOLD: u64 = 1024 * 1024;
+
+/// Default for `absolute_max_segments`.
+pub const DEFAULT_ABSOLUTE_MAX_SEGMENTS: usize = 256;
+
+/// Default for `stall_window_secs`.
+pub const DEFAULT_STALL_WINDOW_SECS: u64 = 60;
+
+/// Default for `min_timeout_secs`.
+pub const DEFAULT_MIN_TIMEOUT_SECS: u64 = 30;
+
+/// Default for `auto_multi_threshold`.
+pub const DEFAULT_AUTO_MULTI_THRESHOLD: usize = 8;
+
+/// Default for `progress_flush_every_segments`.
+pub const DEFAULT_PROGRESS_FLUSH_EVERY_SEGMENTS: usize = 2;
+
+/// Default for `max_redirects`.
+pub const DEFAULT_MAX_REDIRECTS: u32 = 10;
+
+/// Default for `user_agent`.
+pub const DEFAULT_USER_AGENT: &str = concat!("ddm/", env!("CARGO_PKG_VERSION"));
+
 impl Default for DdmConfig {
     fn default() -> Self {
         Self {
@@ -65,12 +482,209 @@ impl Default for DdmConfig {
             max_connections_per_host: 16,
             min_segments: 4,
             max_segments: 16,
+            absolute_max_segments: None,
             retry: None,
             max_bytes_per_sec: None,
             segment_buffer_bytes: None,
             download_backend: None,
+            throttle_decay_secs: None,
+            ip_version: None,
+            happy_eyeballs_timeout_ms: None,
+            http_version: None,
+            proxy: None,
+            hash_segments: None,
+            target_segment_bytes: None,
+            bandwidth_schedule: Vec::new(),
+            single_stream_threshold_bytes: None,
+            user_agent: None,
+            auto_multi_threshold: None,
+            on_complete: None,
+            progress_flush_every_segments: None,
+            connect_to: Vec::new(),
+            ca_bundle: None,
+            temp_dir: None,
+            max_redirects: None,
+            verify_on_resume: None,
+            verify_ranges: None,
+            connection_ramp_ms: None,
+            min_segment_bps: None,
+            stall_window_secs: None,
+            min_expected_bps: None,
+            min_timeout_secs: None,
+            circuit_failure_threshold: None,
+            circuit_window_secs: None,
+            circuit_cooldown_secs: None,
+            on_conflict: None,
+            preallocation: None,
+            probe_strategy: None,
+        }
+    }
+}
+
+impl DdmConfig {
+    /// The config as it would be written to `config.toml`. Unset options are left out.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Size below which a job skips segmentation (see `single_stream_threshold_bytes`).
+    pub fn single_stream_threshold(&self) -> u64 {
+        self.single_stream_threshold_bytes
+            .unwrap_or(DEFAULT_SINGLE_STREAM_THRESHOLD)
+    }
+
+    /// `n` capped at `absolute_max_segments`; the final clamp on every chosen segment count.
+    pub fn clamp_segments(&self, n: usize) -> usize {
+        n.min(
+            self.absolute_max_segments
+                .unwrap_or(DEFAULT_ABSOLUTE_MAX_SEGMENTS)
+                .max(1),
+        )
+    }
+
+    /// Per-host circuit breaker settings (`circuit_*`), defaults filled in.
+    pub fn circuit_breaker(&self) -> crate::host_policy::CircuitBreaker {
+        let default = crate::host_policy::CircuitBreaker::default();
+        crate::host_policy::CircuitBreaker {
+            threshold: self.circuit_failure_threshold.unwrap_or(default.threshold),
+            window: self
+                .circuit_window_secs
+                .map_or(default.window, std::time::Duration::from_secs),
+            cooldown: self
+                .circuit_cooldown_secs
+                .map_or(default.cooldown, std::time::Duration::from_secs),
+        }
+    }
+
+    /// True if a job running `concurrent` connections should use the curl multi backend.
+    pub fn use_multi_backend(&self, concurrent: usize) -> bool {
+        match self.download_backend.unwrap_or_default() {
+            DownloadBackend::Easy => false,
+            DownloadBackend::Multi => true,
+            DownloadBackend::Auto => {
+                concurrent
+                    >= self
+                        .auto_multi_threshold
+                        .unwrap_or(DEFAULT_AUTO_MULTI_THRESHOLD)
+            }
         }
     }
+
+    /// `User-Agent` for requests: the configured value, else [`DEFAULT_USER_AGENT`].
+    pub fn user_agent(&self) -> &str {
+        self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT)
+    }
+
+    /// Bandwidth cap at `minute` past local midnight: the first matching schedule window,
+    /// else the global `max_bytes_per_sec`.
+    pub fn max_bytes_per_sec_at(&self, minute: u32) -> Option<u64> {
+        self.bandwidth_schedule
+            .iter()
+            .find(|w| w.contains(minute))
+            .map(|w| w.max_bytes_per_sec)
+            .or(self.max_bytes_per_sec)
+    }
+
+    /// Bandwidth cap for the current local time (evaluated when a job starts).
+    pub fn current_max_bytes_per_sec(&self) -> Option<u64> {
+        self.max_bytes_per_sec_at(local_minute_of_day())
+    }
+
+    /// Check the loaded values: connection and segment limits that are zero or contradict
+    /// each other, zero sizes and rates, a retry policy without attempts, schedule windows
+    /// whose times are not valid "HH:MM", malformed `connect_to` entries, a `ca_bundle` that
+    /// is not a readable file and a `temp_dir` that is not an existing absolute directory.
+    /// The error lists every violation, not just the first.
+    fn validate(&self) -> Result<()> {
+        let mut problems: Vec<String> = Vec::new();
+        for (name, value) in [
+            ("max_total_connections", self.max_total_connections),
+            ("max_connections_per_host", self.max_connections_per_host),
+            ("min_segments", self.min_segments),
+            ("max_segments", self.max_segments),
+        ] {
+            if value == 0 {
+                problems.push(format!("{} must be at least 1", name));
+            }
+        }
+        if self.min_segments > self.max_segments {
+            problems.push(format!(
+                "min_segments ({}) is greater than max_segments ({})",
+                self.min_segments, self.max_segments
+            ));
+        }
+        if self.max_connections_per_host > self.max_total_connections {
+            problems.push(format!(
+                "max_connections_per_host ({}) is greater than max_total_connections ({})",
+                self.max_connections_per_host, self.max_total_connections
+            ));
+        }
+        for (name, value) in [
+            ("max_bytes_per_sec", self.max_bytes_per_sec),
+            ("target_segment_bytes", self.target_segment_bytes),
+            ("happy_eyeballs_timeout_ms", self.happy_eyeballs_timeout_ms),
+            (
+                "absolute_max_segments",
+                self.absolute_max_segments.map(|n| n as u64),
+            ),
+            (
+                "circuit_failure_threshold",
+                self.circuit_failure_threshold.map(u64::from),
+            ),
+        ] {
+            if value == Some(0) {
+                problems.push(format!("{} must be greater than 0 when set", name));
+            }
+        }
+        if let Some(r) = &self.retry {
+            if r.max_attempts == 0 {
+                problems.push("retry.max_attempts must be at least 1".to_string());
+            }
+            if !r.base_delay_secs.is_finite() || r.base_delay_secs < 0.0 {
+                problems.push(format!(
+                    "retry.base_delay_secs ({}) must be a non-negative number",
+                    r.base_delay_secs
+                ));
+            }
+        }
+        if let Some(dir) = &self.temp_dir {
+            if !dir.is_absolute() || !dir.is_dir() {
+                problems.push(format!(
+                    "temp_dir: {} is not an existing absolute directory",
+                    dir.display()
+                ));
+            }
+        }
+        if let Some(ca) = &self.ca_bundle {
+            if !ca.is_file() {
+                problems.push(format!("ca_bundle: {} is not a file", ca.display()));
+            }
+        }
+        if let Some(n) = self.segment_buffer_bytes {
+            if let Err(e) = validate_segment_buffer(n) {
+                problems.push(format!("segment_buffer_bytes: {:#}", e));
+            }
+        }
+        for entry in &self.connect_to {
+            if let Err(e) = validate_connect_to(entry) {
+                problems.push(format!("connect_to: {:#}", e));
+            }
+        }
+        for w in &self.bandwidth_schedule {
+            for t in [&w.start, &w.end] {
+                if parse_hhmm(t).is_none() {
+                    problems.push(format!(
+                        "bandwidth_schedule: invalid time {:?} (expected HH:MM)",
+                        t
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!("invalid config:\n  - {}", problems.join("\n  - "))
+    }
 }
 
 pub fn config_path() -> Result<PathBuf> {
@@ -78,12 +692,22 @@ pub fn config_path() -> Result<PathBuf> {
     Ok(xdg_dirs.place_config_file("config.toml")?)
 }
 
-/// Load configuration from disk, creating a default file if none exists.
-pub fn load_or_init() -> Result<DdmConfig> {
-    let path = config_path()?;
+/// Load configuration from `path`, or from [`config_path`] when None. A missing default
+/// config is created with defaults; a missing explicit `path` is an error unless `create`.
+pub fn load_or_init(path: Option<&Path>, create: bool) -> Result<DdmConfig> {
+    let (path, create) = match path {
+        Some(p) => (p.to_path_buf(), create),
+        None => (config_path()?, true),
+    };
     if !path.exists() {
+        if !create {
+            anyhow::bail!(
+                "config file not found: {} (pass --init-config to create it)",
+                path.display()
+            );
+        }
         let default_cfg = DdmConfig::default();
-        let toml = toml::to_string_pretty(&default_cfg)?;
+        let toml = default_cfg.to_toml()?;
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -94,6 +718,7 @@ pub fn load_or_init() -> Result<DdmConfig> {
 
     let data = fs::read_to_string(&path)?;
     let cfg: DdmConfig = toml::from_str(&data)?;
+    cfg.validate()?;
     Ok(cfg)
 }
 
@@ -101,6 +726,21 @@ pub fn load_or_init() -> Result<DdmConfig> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn load_or_init_explicit_path_creates_only_on_request() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("profile.toml");
+        let err = load_or_init(Some(&path), false).unwrap_err();
+        assert!(err.to_string().contains("config file not found"), "{err}");
+        assert!(!path.exists());
+
+        load_or_init(Some(&path), true).unwrap();
+        assert!(path.exists());
+        let text = fs::read_to_string(&path).unwrap();
+        fs::write(&path, text.replace("max_segments = 16", "max_segments = 8")).unwrap();
+        assert_eq!(load_or_init(Some(&path), false).unwrap().max_segments, 8);
+    }
+
     #[test]
     fn default_config_values() {
         let cfg = DdmConfig::default();
@@ -141,6 +781,38 @@ mod tests {
         assert!(cfg.max_bytes_per_sec.is_none());
     }
 
+    #[test]
+    fn auto_backend_switches_at_threshold() {
+        let mut cfg = DdmConfig {
+            download_backend: Some(DownloadBackend::Auto),
+            ..DdmConfig::default()
+        };
+        assert!(!cfg.use_multi_backend(7));
+        assert!(cfg.use_multi_backend(8));
+        cfg.auto_multi_threshold = Some(16);
+        assert!(!cfg.use_multi_backend(8));
+        assert!(cfg.use_multi_backend(16));
+        cfg.download_backend = None;
+        assert!(!cfg.use_multi_backend(64));
+        cfg.download_backend = Some(DownloadBackend::Multi);
+        assert!(cfg.use_multi_backend(1));
+    }
+
+    #[test]
+    fn user_agent_defaults_to_ddm_version() {
+        let cfg = DdmConfig::default();
+        assert!(cfg.user_agent().starts_with("ddm/"));
+        let toml = r#"
+            max_total_connections = 8
+            max_connections_per_host = 4
+            min_segments = 2
+            max_segments = 16
+            user_agent = "Mozilla/5.0"
+        "#;
+        let cfg: DdmConfig = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.user_agent(), "Mozilla/5.0");
+    }
+
     #[test]
     fn config_toml_download_backend() {
         let toml = r#"
@@ -161,6 +833,60 @@ mod tests {
         "#;
         let cfg_easy: DdmConfig = toml::from_str(toml_easy).unwrap();
         assert_eq!(cfg_easy.download_backend, Some(DownloadBackend::Easy));
+        let cfg_auto: DdmConfig =
+            toml::from_str(&toml_easy.replace("\"easy\"", "\"auto\"")).unwrap();
+        assert_eq!(cfg_auto.download_backend, Some(DownloadBackend::Auto));
+    }
+
+    #[test]
+    fn config_toml_http_version() {
+        let base = r#"
+            max_total_connections = 8
+            max_connections_per_host = 4
+            min_segments = 2
+            max_segments = 16
+        "#;
+        let cfg: DdmConfig = toml::from_str(base).unwrap();
+        assert_eq!(cfg.http_version, None);
+        for (name, v) in [
+            ("auto", HttpVersion::Auto),
+            ("http1_1", HttpVersion::Http1_1),
+            ("http2", HttpVersion::Http2),
+            ("http3", HttpVersion::Http3),
+        ] {
+            let cfg: DdmConfig =
+                toml::from_str(&format!("{base}\nhttp_version = \"{name}\"")).unwrap();
+            assert_eq!(cfg.http_version, Some(v));
+        }
+        assert!(toml::from_str::<DdmConfig>(&format!("{base}\nhttp_version = \"h2\"")).is_err());
+    }
+
+    #[test]
+    fn config_toml_probe_strategy() {
+        let base = r#"
+            max_total_connections = 8
+            max_connections_per_host = 4
+            min_segments = 2
+            max_segments = 16
+        "#;
+        let cfg: DdmConfig = toml::from_str(base).unwrap();
+        assert_eq!(
+            cfg.probe_strategy.unwrap_or_default(),
+            ProbeStrategy::BestEffort
+        );
+        for v in [
+            ProbeStrategy::BestEffort,
+            ProbeStrategy::HeadOnly,
+            ProbeStrategy::RangeOnly,
+        ] {
+            let cfg: DdmConfig =
+                toml::from_str(&format!("{base}\nprobe_strategy = \"{}\"", v.as_str())).unwrap();
+            assert_eq!(cfg.probe_strategy, Some(v));
+            assert_eq!(ProbeStrategy::from_name(v.as_str()), Some(v));
+        }
+        assert!(
+            toml::from_str::<DdmConfig>(&format!("{base}\nprobe_strategy = \"head\"")).is_err()
+        );
     }
 
     #[test]
@@ -172,6 +898,7 @@ mod tests {
             max_segments = 16
             max_bytes_per_sec = 1_000_000
             segment_buffer_bytes = 65536
+            progress_flush_every_segments = 16
 
             [retry]
             max_attempts = 3
@@ -181,9 +908,263 @@ mod tests {
         let cfg: DdmConfig = toml::from_str(toml).unwrap();
         assert_eq!(cfg.max_bytes_per_sec, Some(1_000_000));
         assert_eq!(cfg.segment_buffer_bytes, Some(65536));
+        assert_eq!(cfg.progress_flush_every_segments, Some(16));
         let retry = cfg.retry.as_ref().unwrap();
         assert_eq!(retry.max_attempts, 3);
+        assert_eq!(retry.max_job_retries, crate::retry::DEFAULT_MAX_JOB_RETRIES);
         assert!((retry.base_delay_secs - 0.5).abs() < 1e-9);
         assert_eq!(retry.max_delay_secs, 15);
+        assert!(retry.jitter, "jitter defaults to on when omitted");
+    }
+
+    #[test]
+    fn connect_to_entries_are_validated() {
+        for ok in [
+            "cdn.example.com:443:10.0.0.5:443",
+            "::10.0.0.5:",
+            "[2001:db8::1]:443:[2001:db8::2]:8443",
+        ] {
+            validate_connect_to(ok).unwrap_or_else(|e| panic!("{ok}: {e:#}"));
+        }
+        for bad in [
+            "cdn.example.com:443:10.0.0.5",
+            "a:443:b:443:c",
+            "a:https:b:443",
+            "a:443:b:0",
+            "[2001:db8::1:443:b:443",
+        ] {
+            assert!(validate_connect_to(bad).is_err(), "{bad}");
+        }
+        let cfg: DdmConfig = toml::from_str(
+            r#"
+            max_total_connections = 16
+            max_connections_per_host = 8
+            min_segments = 2
+            max_segments = 16
+            connect_to = ["deb.debian.org:443:10.0.0.5:443"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(cfg.connect_to, ["deb.debian.org:443:10.0.0.5:443"]);
+        cfg.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_contradictory_limits() {
+        let cfg = DdmConfig {
+            min_segments: 8,
+            max_segments: 4,
+            ..DdmConfig::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(
+            err.contains("min_segments (8) is greater than max_segments (4)"),
+            "{err}"
+        );
+
+        let cfg = DdmConfig {
+            max_connections_per_host: 32,
+            max_total_connections: 16,
+            ..DdmConfig::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("max_connections_per_host (32)"), "{err}");
+    }
+
+    #[test]
+    fn validate_rejects_zero_limits_and_sizes() {
+        let zeroed = [
+            DdmConfig {
+                max_total_connections: 0,
+                max_connections_per_host: 0,
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                max_connections_per_host: 0,
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                min_segments: 0,
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                max_bytes_per_sec: Some(0),
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                target_segment_bytes: Some(0),
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                segment_buffer_bytes: Some(0),
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                absolute_max_segments: Some(0),
+                ..DdmConfig::default()
+            },
+            DdmConfig {
+                happy_eyeballs_timeout_ms: Some(0),
+                ..DdmConfig::default()
+            },
+        ];
+        for cfg in zeroed {
+            assert!(cfg.validate().is_err(), "{cfg:?}");
+        }
+    }
+
+    #[test]
+    fn segment_buffer_must_fit_libcurl() {
+        for ok in [
+            1024,
+            65536,
+            262144,
+            3 * 1024 * 1024,
+            MAX_SEGMENT_BUFFER_BYTES,
+        ] {
+            validate_segment_buffer(ok).unwrap_or_else(|e| panic!("{ok}: {e:#}"));
+        }
+        for bad in [512, 65537, MAX_SEGMENT_BUFFER_BYTES + 1024] {
+            assert!(validate_segment_buffer(bad).is_err(), "{bad}");
+        }
+        let cfg = DdmConfig {
+            segment_buffer_bytes: Some(1000),
+            ..DdmConfig::default()
+        };
+        let err = cfg.validate().unwrap_err();
+        assert!(
+            format!("{err:#}").contains("segment_buffer_bytes"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn validate_rejects_retry_without_attempts_or_with_negative_delay() {
+        for retry in [
+            RetryConfig {
+                max_attempts: 0,
+                ..RetryConfig::default()
+            },
+            RetryConfig {
+                base_delay_secs: -1.0,
+                ..RetryConfig::default()
+            },
+            RetryConfig {
+                base_delay_secs: f64::NAN,
+                ..RetryConfig::default()
+            },
+        ] {
+            let cfg = DdmConfig {
+                retry: Some(retry),
+                ..DdmConfig::default()
+            };
+            assert!(cfg.validate().is_err(), "{:?}", cfg.retry);
+        }
+    }
+
+    #[test]
+    fn validate_lists_every_violation() {
+        let cfg = DdmConfig {
+            min_segments: 20,
+            max_connections_per_host: 100,
+            connect_to: vec!["bad".to_string()],
+            ..DdmConfig::default()
+        };
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.starts_with("invalid config:"), "{err}");
+        assert!(err.contains("min_segments (20)"), "{err}");
+        assert!(err.contains("max_connections_per_host (100)"), "{err}");
+        assert!(err.contains("connect_to"), "{err}");
+        assert_eq!(err.matches("\n  - ").count(), 3, "{err}");
+    }
+
+    #[test]
+    fn ca_bundle_must_be_a_file() {
+        let ca = tempfile::NamedTempFile::new().unwrap();
+        let mut cfg = DdmConfig {
+            ca_bundle: Some(ca.path().to_path_buf()),
+            ..DdmConfig::default()
+        };
+        cfg.validate().unwrap();
+        let toml = toml::to_string_pretty(&cfg).unwrap();
+        let parsed: DdmConfig = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.ca_bundle, cfg.ca_bundle);
+        cfg.ca_bundle = Some(ca.path().with_extension("missing"));
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn temp_dir_must_be_an_absolute_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cfg = DdmConfig {
+            temp_dir: Some(dir.path().to_path_buf()),
+            ..DdmConfig::default()
+        };
+        cfg.validate().unwrap();
+        cfg.temp_dir = Some(dir.path().join("missing"));
+        assert!(cfg.validate().is_err());
+        cfg.temp_dir = Some(PathBuf::from("relative/staging"));
+        assert!(cfg.validate().is_err());
+    }
+
+    #[test]
+    fn retry_overrides_clamp_and_keep_unset_fields() {
+        let base = RetryConfig::default();
+        let r = base.with_overrides(Some(10), Some(500));
+        assert_eq!(r.max_attempts, 10);
+        assert!((r.base_delay_secs - 0.5).abs() < 1e-9);
+        assert_eq!(r.max_delay_secs, base.max_delay_secs);
+
+        let r = base.with_overrides(Some(0), None);
+        assert_eq!(r.max_attempts, 1);
+        assert!((r.base_delay_secs - base.base_delay_secs).abs() < 1e-9);
+        let r = base.with_overrides(Some(1000), Some(0));
+        assert_eq!(r.max_attempts, 50);
+        assert!((r.base_delay_secs - 0.001).abs() < 1e-9);
+    }
+
+    #[test]
+    fn config_toml_bandwidth_schedule() {
+        let toml = r#"
+            max_total_connections = 16
+            max_connections_per_host = 8
+            min_segments = 2
+            max_segments = 16
+            max_bytes_per_sec = 5_000_000
+
+            [[bandwidth_schedule]]
+            start = "08:00"
+            end = "18:00"
+            max_bytes_per_sec = 2_000_000
+
+            [[bandwidth_schedule]]
+            start = "22:30"
+            end = "06:00"
+            max_bytes_per_sec = 50_000_000
+        "#;
+        let cfg: DdmConfig = toml::from_str(toml).unwrap();
+        assert_eq!(cfg.bandwidth_schedule.len(), 2);
+        cfg.validate().unwrap();
+        assert_eq!(cfg.max_bytes_per_sec_at(8 * 60), Some(2_000_000));
+        assert_eq!(cfg.max_bytes_per_sec_at(17 * 60 + 59), Some(2_000_000));
+        assert_eq!(cfg.max_bytes_per_sec_at(18 * 60), Some(5_000_000));
+        assert_eq!(cfg.max_bytes_per_sec_at(23 * 60), Some(50_000_000));
+        assert_eq!(cfg.max_bytes_per_sec_at(60), Some(50_000_000));
+        assert_eq!(cfg.max_bytes_per_sec_at(6 * 60), Some(5_000_000));
+        let roundtrip: DdmConfig = toml::from_str(&toml::to_string_pretty(&cfg).unwrap()).unwrap();
+        assert_eq!(roundtrip.bandwidth_schedule, cfg.bandwidth_schedule);
+    }
+
+    #[test]
+    fn bandwidth_schedule_rejects_bad_times() {
+        let mut cfg = DdmConfig::default();
+        cfg.bandwidth_schedule.push(BandwidthWindow {
+            start: "8am".to_string(),
+            end: "24:00".to_string(),
+            max_bytes_per_sec: 1,
+        });
+        assert!(cfg.validate().is_err());
+        assert!(!cfg.bandwidth_schedule[0].contains(8 * 60));
+        assert_eq!(cfg.max_bytes_per_sec_at(8 * 60), None);
     }
 }
@@ -5,10 +5,12 @@
 //! request abort for a job; the download loop checks the token and stops.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 
+use crate::scheduler::ProgressStats;
+
 /// Error returned when a download is stopped by user (pause/cancel).
 #[derive(Debug)]
 pub struct JobAborted;
@@ -26,6 +28,8 @@ impl std::error::Error for JobAborted {}
 #[derive(Default)]
 pub struct JobControl {
     jobs: RwLock<HashMap<i64, Arc<AtomicBool>>>,
+    shutdown: AtomicBool,
+    progress: RwLock<HashMap<i64, ProgressStats>>,
 }
 
 impl JobControl {
@@ -36,7 +40,7 @@ impl JobControl {
     /// Register a running job; returns the abort token to pass to the download phase.
     /// Call when starting a job; the token is set to true when pause/cancel is requested.
     pub fn register(&self, job_id: i64) -> Arc<AtomicBool> {
-        let token = Arc::new(AtomicBool::new(false));
+        let token = Arc::new(AtomicBool::new(self.is_shutting_down()));
         self.jobs
             .write()
             .unwrap()
@@ -47,6 +51,43 @@ impl JobControl {
     /// Unregister a job (call when the job finishes, success or failure).
     pub fn unregister(&self, job_id: i64) {
         self.jobs.write().unwrap().remove(&job_id);
+        self.progress.write().unwrap().remove(&job_id);
+    }
+
+    /// Stop the whole run (e.g. Ctrl-C): abort every running job (they are paused with
+    /// progress saved) and tell the scheduler not to start new ones.
+    pub fn request_shutdown(&self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for token in self.jobs.read().unwrap().values() {
+            token.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// True after `request_shutdown`; the scheduler stops claiming queued jobs.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.load(Ordering::Relaxed)
+    }
+
+    /// Remember the latest progress of a running job (ignored once the job is unregistered).
+    pub fn record_progress(&self, stats: &ProgressStats) {
+        if self.jobs.read().unwrap().contains_key(&stats.job_id) {
+            self.progress
+                .write()
+                .unwrap()
+                .insert(stats.job_id, stats.clone());
+        }
+    }
+
+    /// Latest progress of every running job, by job id.
+    pub fn progress_snapshot(&self) -> Vec<ProgressStats> {
+        let mut out: Vec<ProgressStats> = self.progress.read().unwrap().values().cloned().collect();
+        out.sort_by_key(|s| s.job_id);
+        out
+    }
+
+    /// JSON reply to the control socket `status` request: an array of `ProgressStats`.
+    pub fn status_json(&self) -> String {
+        serde_json::to_string(&self.progress_snapshot()).unwrap_or_else(|_| "[]".to_string())
     }
 
     /// Request abort for a job (e.g. from control socket). The download loop will
@@ -58,10 +99,60 @@ impl JobControl {
     }
 }
 
-/// Default path for the control socket (same XDG state dir as the DB).
-pub fn default_control_socket_path() -> std::io::Result<PathBuf> {
-    let dir = xdg::BaseDirectories::with_prefix("ddm")?
-        .get_state_home()
-        .join("ddm");
-    Ok(dir.join("control.sock"))
+/// Parses a `status` reply produced by [`JobControl::status_json`].
+pub fn parse_status_json(json: &str) -> anyhow::Result<Vec<ProgressStats>> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Control socket path for the database in `state_dir`: `control.sock` beside it
+/// (`~/.local/state/ddm/control.sock` for the default DB).
+pub fn control_socket_path(state_dir: &Path) -> PathBuf {
+    state_dir.join("control.sock")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(job_id: i64, bytes_done: u64) -> ProgressStats {
+        ProgressStats {
+            job_id,
+            bytes_done,
+            bytes_in_flight: 0,
+            total_bytes: 100,
+            elapsed_secs: 1.0,
+            segments_done: 0,
+            segment_count: 4,
+        }
+    }
+
+    #[test]
+    fn shutdown_aborts_running_and_later_jobs() {
+        let control = JobControl::new();
+        let running = control.register(1);
+        assert!(!control.is_shutting_down());
+        control.request_shutdown();
+        assert!(running.load(Ordering::Relaxed));
+        assert!(control.is_shutting_down());
+        assert!(control.register(2).load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn progress_tracked_only_while_registered() {
+        let control = JobControl::new();
+        control.record_progress(&stats(1, 10));
+        assert!(control.progress_snapshot().is_empty());
+
+        control.register(2);
+        control.register(1);
+        control.record_progress(&stats(2, 20));
+        control.record_progress(&stats(1, 10));
+        control.record_progress(&stats(1, 30));
+        let parsed = parse_status_json(&control.status_json()).unwrap();
+        let ids: Vec<(i64, u64)> = parsed.iter().map(|s| (s.job_id, s.bytes_done)).collect();
+        assert_eq!(ids, vec![(1, 30), (2, 20)]);
+
+        control.unregister(1);
+        assert_eq!(control.progress_snapshot().len(), 1);
+    }
 }
@@ -0,0 +1,171 @@
+//! Per-job state for one download run, passed next to the handle-level [`CurlOptions`].
+//!
+//! [`CurlOptions`]: super::CurlOptions
+
+use crate::config::{DdmConfig, DEFAULT_MIN_TIMEOUT_SECS, DEFAULT_PROGRESS_FLUSH_EVERY_SEGMENTS};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What the segment loops need to know about the job they run: its abort token, deadline,
+/// file size, start order and pacing, and the stall flags the scheduler's watchdog raises.
+#[derive(Debug, Clone, Default)]
+pub struct SegmentRunContext {
+    /// Job deadline (`ddm add --deadline`); transfers started before it time out when it passes.
+    pub deadline: Option<Instant>,
+    /// Job abort token (pause, cancel, Ctrl-C); transfers stop mid-body once it is set and
+    /// the run returns `JobAborted`.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Known size of the whole file. A range response whose `Content-Range` total differs
+    /// fails with `SegmentError::TotalSizeMismatch` before any byte is written.
+    pub expected_total: Option<u64>,
+    /// Send the completed-segment bitmap to the progress writer after this many segments
+    /// finish (None = [`DEFAULT_PROGRESS_FLUSH_EVERY_SEGMENTS`]).
+    pub progress_flush_every_segments: Option<usize>,
+    /// Sequential segment order (`ddm add --order sequential`): a segment starts only while its
+    /// index is less than this far past the lowest unfinished one. None = any order.
+    pub segment_lookahead: Option<usize>,
+    /// Delay between the first connection starts of a job (`connection_ramp_ms` in config), so a
+    /// host does not see every connection open at once. None = start them together.
+    pub connection_ramp: Option<Duration>,
+    /// Per-segment stall flags raised by the throughput watchdog (`min_segment_bps` in config);
+    /// a flagged segment's transfer stops and fails with `SegmentError::Stalled`.
+    pub stalled: Option<Arc<Vec<AtomicBool>>>,
+    /// Slowest rate (bytes/sec) a segment should need (`min_expected_bps` in config); scales
+    /// each segment's timeout with its length. None = flat [`Self::transfer_timeout`].
+    pub min_expected_bps: Option<u64>,
+    /// Floor for the scaled segment timeout (`min_timeout_secs` in config; None =
+    /// [`DEFAULT_MIN_TIMEOUT_SECS`]).
+    pub min_timeout: Option<Duration>,
+}
+
+impl SegmentRunContext {
+    /// The config-derived part of the context; the scheduler fills in the job's abort token,
+    /// deadline, size, order and stall flags.
+    pub fn from_config(cfg: &DdmConfig) -> Self {
+        Self {
+            progress_flush_every_segments: cfg.progress_flush_every_segments,
+            connection_ramp: cfg
+                .connection_ramp_ms
+                .filter(|&ms| ms > 0)
+                .map(Duration::from_millis),
+            min_expected_bps: cfg.min_expected_bps,
+            min_timeout: cfg.min_timeout_secs.map(Duration::from_secs),
+            ..Self::default()
+        }
+    }
+
+    /// Whole-transfer timeout for a handle started now: one hour, or less if the job deadline is sooner.
+    pub fn transfer_timeout(&self) -> Duration {
+        self.until_deadline(Duration::from_secs(3600))
+    }
+
+    /// Timeout for a segment of `len` bytes started now. With `min_expected_bps` set it is the
+    /// time to move `len` at that rate (at least `min_timeout`), so small segments on a dead
+    /// connection fail fast and large ones are not cut off at an hour; otherwise
+    /// [`Self::transfer_timeout`]. Never runs past the job deadline.
+    pub fn segment_timeout(&self, len: u64) -> Duration {
+        let Some(bps) = self.min_expected_bps.filter(|&b| b > 0) else {
+            return self.transfer_timeout();
+        };
+        let min = self
+            .min_timeout
+            .unwrap_or(Duration::from_secs(DEFAULT_MIN_TIMEOUT_SECS));
+        self.until_deadline(Duration::from_secs(len.div_ceil(bps)).max(min))
+    }
+
+    /// `limit`, or the time left until the job deadline (at least 1s) if that is sooner.
+    fn until_deadline(&self, limit: Duration) -> Duration {
+        match self.deadline {
+            Some(d) => d
+                .saturating_duration_since(Instant::now())
+                .clamp(Duration::from_secs(1), limit.max(Duration::from_secs(1))),
+            None => limit,
+        }
+    }
+
+    /// Completed segments between bitmap flushes (at least 1).
+    pub fn progress_flush_every(&self) -> usize {
+        self.progress_flush_every_segments
+            .unwrap_or(DEFAULT_PROGRESS_FLUSH_EVERY_SEGMENTS)
+            .max(1)
+    }
+
+    /// True once the job's abort token has been set.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|c| c.load(Ordering::Relaxed))
+    }
+
+    /// True if the watchdog flagged segment `index` as stalled.
+    pub fn is_stalled(&self, index: usize) -> bool {
+        self.stalled
+            .as_ref()
+            .and_then(|v| v.get(index))
+            .is_some_and(|f| f.load(Ordering::Relaxed))
+    }
+
+    /// Clears segment `index`'s stall flag as a new attempt starts.
+    pub fn clear_stall(&self, index: usize) {
+        if let Some(f) = self.stalled.as_ref().and_then(|v| v.get(index)) {
+            f.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_timeout_respects_deadline() {
+        let mut c = SegmentRunContext::default();
+        assert_eq!(c.transfer_timeout().as_secs(), 3600);
+        c.deadline = Some(Instant::now() + Duration::from_secs(90));
+        let t = c.transfer_timeout().as_secs();
+        assert!((88..=90).contains(&t), "{t}");
+        c.deadline = Some(Instant::now());
+        assert_eq!(c.transfer_timeout().as_secs(), 1);
+    }
+
+    #[test]
+    fn segment_timeout_scales_with_length() {
+        let mut c = SegmentRunContext::default();
+        assert_eq!(c.segment_timeout(1 << 30).as_secs(), 3600);
+        c.min_expected_bps = Some(10_000);
+        assert_eq!(c.segment_timeout(1000).as_secs(), DEFAULT_MIN_TIMEOUT_SECS);
+        assert_eq!(c.segment_timeout(10_000_001).as_secs(), 1001);
+        assert_eq!(c.segment_timeout(1_000_000_000).as_secs(), 100_000);
+        c.min_timeout = Some(Duration::from_secs(5));
+        assert_eq!(c.segment_timeout(1000).as_secs(), 5);
+        c.deadline = Some(Instant::now() + Duration::from_secs(90));
+        let t = c.segment_timeout(1_000_000_000).as_secs();
+        assert!((88..=90).contains(&t), "{t}");
+    }
+
+    #[test]
+    fn progress_flush_every_defaults_and_floors_at_one() {
+        let c = SegmentRunContext::default();
+        assert_eq!(
+            c.progress_flush_every(),
+            DEFAULT_PROGRESS_FLUSH_EVERY_SEGMENTS
+        );
+        let cfg = DdmConfig {
+            progress_flush_every_segments: Some(64),
+            ..DdmConfig::default()
+        };
+        assert_eq!(
+            SegmentRunContext::from_config(&cfg).progress_flush_every(),
+            64
+        );
+        let cfg = DdmConfig {
+            progress_flush_every_segments: Some(0),
+            ..DdmConfig::default()
+        };
+        assert_eq!(
+            SegmentRunContext::from_config(&cfg).progress_flush_every(),
+            1
+        );
+    }
+}
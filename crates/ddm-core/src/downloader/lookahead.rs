@@ -0,0 +1,51 @@
+//! Start gate for sequential segment order (`SegmentRunContext::segment_lookahead`).
+
+use std::collections::BTreeSet;
+
+/// Tracks unfinished segments; a segment may start only while its index is less than
+/// `window` past the lowest unfinished one.
+#[derive(Debug)]
+pub(crate) struct Lookahead {
+    window: usize,
+    unfinished: BTreeSet<usize>,
+}
+
+impl Lookahead {
+    pub(crate) fn new(window: usize, indices: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            window: window.max(1),
+            unfinished: indices.into_iter().collect(),
+        }
+    }
+
+    /// True if segment `index` may start now.
+    pub(crate) fn allows(&self, index: usize) -> bool {
+        self.unfinished
+            .first()
+            .is_none_or(|&lowest| index < lowest + self.window)
+    }
+
+    /// Marks `index` finished (completed or failed for good), sliding the window forward.
+    pub(crate) fn finish(&mut self, index: usize) {
+        self.unfinished.remove(&index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_slides_past_finished_segments() {
+        let mut l = Lookahead::new(3, [1, 2, 4, 6]);
+        assert!(l.allows(1));
+        assert!(l.allows(2));
+        assert!(!l.allows(4));
+        l.finish(2);
+        assert!(!l.allows(4), "segment 1 still holds the window");
+        l.finish(1);
+        assert!(l.allows(4));
+        assert!(l.allows(6));
+        assert!(!l.allows(7));
+    }
+}
@@ -5,14 +5,28 @@
 //! offset and updates the completion bitmap. Supports retry with backoff via
 //! optional `RetryPolicy`.
 
+mod context;
+mod lookahead;
+mod options;
+mod ramp;
 mod run;
 mod segment;
 mod single;
+mod sources;
 
 /// Curl multi backend (phase 1: skeleton; phase 2: curl::multi implementation).
 pub mod multi;
+pub use context::SegmentRunContext;
+pub use options::CurlOptions;
+
+/// Durable completed-bitmap updates from the download loops to the progress writer. Unbounded
+/// so no update is dropped and segment threads never block on the DB; blobs are small and
+/// sent at most once per completed segment.
+pub type BitmapSender = tokio::sync::mpsc::UnboundedSender<Vec<u8>>;
 pub use single::download_single;
+pub use sources::SourceUrls;
 
+use crate::checksum::SegmentHashes;
 use crate::retry::{RetryPolicy, SegmentError};
 use crate::segmenter::{Segment, SegmentBitmap};
 use crate::storage::StorageWriter;
@@ -24,59 +38,43 @@ use std::sync::Arc;
 /// Result of a single segment download (used for retry classification).
 pub type SegmentResult = Result<(), SegmentError>;
 
-/// Curl/libcurl tuning options applied per handle.
-#[derive(Debug, Clone, Copy, Default)]
-pub struct CurlOptions {
-    /// Maximum receive speed (bytes/sec) for this curl handle.
-    pub max_recv_speed: Option<u64>,
-    /// Curl receive buffer size (bytes) for this curl handle.
-    pub buffer_size: Option<usize>,
-}
-
-impl CurlOptions {
-    /// Derive per-handle options from a global cap and concurrency.
-    pub fn per_handle(
-        global_max_bytes_per_sec: Option<u64>,
-        concurrency: usize,
-        buffer_size: Option<usize>,
-    ) -> Self {
-        let concurrency_u = (concurrency.max(1)) as u64;
-        let max_recv_speed =
-            global_max_bytes_per_sec.map(|bps| (bps + concurrency_u - 1) / concurrency_u);
-        Self {
-            max_recv_speed,
-            buffer_size,
-        }
-    }
-}
-
-/// Summary of a download run for adaptive policy: throttle and error counts.
+/// Summary of a download run for adaptive policy: throttle and error counts, plus the
+/// retries spent across all segments (checked against `RetryPolicy::max_job_retries`).
 #[derive(Debug, Clone, Default)]
 pub struct DownloadSummary {
     pub throttle_events: u32,
     pub error_events: u32,
+    pub retry_attempts: u32,
 }
 
 /// Downloads all segments that are not yet completed, writing to `storage` and updating `bitmap`.
 /// When `max_concurrent` is `Some(n)`, at most `n` segment downloads run at once. When `None`,
 /// one thread per incomplete segment (unbounded). Fills `summary_out` with throttle/error counts.
 /// If `progress_tx` is `Some`, the current bitmap is sent after each completed segment
-/// (coalesced every N completions) so the caller can persist progress.
+/// (coalesced every N completions, plus a final send) so the caller can persist progress;
+/// sends are never dropped.
 /// If `in_flight_bytes` is `Some`, each segment updates its slot as bytes are received for smoother progress.
-/// If `abort` is set and becomes true during the run, the download stops and returns `Err(JobAborted)`.
+/// If `ctx.cancel` is set and becomes true during the run, the download stops and returns
+/// `Err(JobAborted)`.
+/// Segments are fetched from `urls` (the primary URL, plus mirrors if any; see [`SourceUrls`]).
+/// If `if_range` is `Some`, each Range request to the primary URL carries it as `If-Range`
+/// (see `safe_resume::if_range_validator`).
+/// If `segment_hashes` is `Some`, each completed segment's SHA-256 is recorded in its slot.
 pub fn download_segments(
-    url: &str,
+    urls: &SourceUrls,
     custom_headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     segments: &[Segment],
     storage: &StorageWriter,
     bitmap: &mut SegmentBitmap,
     max_concurrent: Option<usize>,
     retry_policy: Option<&RetryPolicy>,
     summary_out: &mut DownloadSummary,
-    progress_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    progress_tx: Option<&crate::downloader::BitmapSender>,
     in_flight_bytes: Option<Arc<Vec<AtomicU64>>>,
-    abort: Option<Arc<std::sync::atomic::AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<()> {
     let incomplete: Vec<(usize, Segment)> = segments
         .iter()
@@ -90,16 +88,19 @@ pub fn download_segments(
     }
     *summary_out = DownloadSummary::default();
 
-    let url = url.to_string();
+    let urls = urls.clone();
     let headers = custom_headers.clone();
+    let if_range = if_range.map(str::to_string);
     let storage = storage.clone();
     let segment_count = segments.len();
     let policy = retry_policy.copied();
 
-    if let Some(max) = max_concurrent {
+    // Sequential order needs the bounded pool: its window caps how many segments start.
+    if let Some(max) = max_concurrent.or(ctx.segment_lookahead) {
         run::run_concurrent(
-            url,
+            urls,
             headers,
+            if_range,
             storage,
             incomplete,
             segment_count,
@@ -109,13 +110,15 @@ pub fn download_segments(
             summary_out,
             progress_tx,
             in_flight_bytes,
-            abort,
+            segment_hashes,
             curl,
+            ctx,
         )
     } else {
         run::run_unbounded(
-            url,
+            urls,
             headers,
+            if_range,
             storage,
             incomplete,
             segment_count,
@@ -124,8 +127,9 @@ pub fn download_segments(
             summary_out,
             progress_tx,
             in_flight_bytes,
-            abort,
+            segment_hashes,
             curl,
+            ctx,
         )
     }
 }
@@ -146,6 +150,45 @@ mod tests {
         assert_eq!(segment::parse_content_range(&headers_lower), Some((0, 99)));
     }
 
+    #[test]
+    fn content_range_total_mismatch_needs_a_known_differing_total() {
+        let headers = vec!["Content-Range: bytes 0-99/2000".to_string()];
+        assert_eq!(
+            segment::content_range_total_mismatch(&headers, Some(1000)),
+            Some(2000)
+        );
+        assert_eq!(
+            segment::content_range_total_mismatch(&headers, Some(2000)),
+            None
+        );
+        assert_eq!(segment::content_range_total_mismatch(&headers, None), None);
+        let unknown = vec!["Content-Range: bytes 0-99/*".to_string()];
+        assert_eq!(
+            segment::content_range_total_mismatch(&unknown, Some(1000)),
+            None
+        );
+    }
+
+    #[test]
+    fn unexpected_content_encoding_detects_non_identity() {
+        assert_eq!(
+            segment::unexpected_content_encoding("Content-Encoding: gzip").as_deref(),
+            Some("gzip")
+        );
+        assert_eq!(
+            segment::unexpected_content_encoding("content-encoding: deflate").as_deref(),
+            Some("deflate")
+        );
+        assert_eq!(
+            segment::unexpected_content_encoding("Content-Encoding: identity"),
+            None
+        );
+        assert_eq!(
+            segment::unexpected_content_encoding("Content-Length: 10"),
+            None
+        );
+    }
+
     #[test]
     fn download_segments_updates_bitmap() {
         let segments = plan_segments(1000, 4);
@@ -164,4 +207,60 @@ mod tests {
         assert!(bitmap.is_completed(2));
         assert!(!bitmap.is_completed(3));
     }
+
+    #[test]
+    fn bitmap_updates_are_never_dropped() {
+        // More completions than the old bounded channel (8) held, with nobody reading until
+        // the download ends: every flush must still arrive, for both backends.
+        let body: Vec<u8> = (0u8..=255).cycle().take(24 * 100).collect();
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src.bin");
+        std::fs::write(&src, &body).unwrap();
+        let url = url::Url::from_file_path(&src).unwrap().to_string();
+        let segments = plan_segments(body.len() as u64, 24);
+        let ctx = SegmentRunContext {
+            progress_flush_every_segments: Some(1),
+            ..SegmentRunContext::default()
+        };
+        for multi in [false, true] {
+            let tp = crate::storage::temp_path(&dir.path().join(format!("out-{multi}.bin")));
+            let mut builder = crate::storage::StorageWriterBuilder::create(&tp).unwrap();
+            builder.preallocate(body.len() as u64).unwrap();
+            let storage = builder.build();
+            let mut bitmap = SegmentBitmap::new(24);
+            let mut summary = DownloadSummary::default();
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let run = if multi {
+                multi::download_segments_multi
+            } else {
+                download_segments
+            };
+            run(
+                &SourceUrls::from(url.as_str()),
+                &HashMap::new(),
+                None,
+                &segments,
+                &storage,
+                &mut bitmap,
+                Some(4),
+                None,
+                &mut summary,
+                Some(&tx),
+                None,
+                None,
+                CurlOptions::default(),
+                ctx.clone(),
+            )
+            .unwrap();
+            drop(tx);
+            let mut blobs = Vec::new();
+            while let Ok(blob) = rx.try_recv() {
+                blobs.push(blob);
+            }
+            assert_eq!(blobs.len(), 24, "multi={multi}");
+            let last = SegmentBitmap::from_bytes(blobs.last().unwrap(), 24);
+            assert!(last.all_completed(24), "multi={multi}");
+            assert_eq!(std::fs::read(&tp).unwrap(), body);
+        }
+    }
 }
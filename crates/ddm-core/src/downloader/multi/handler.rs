@@ -1,6 +1,7 @@
 //! Easy2 Handler for a single segment in the curl multi backend.
 //! Validates 206 and Content-Range before writing; writes to storage at segment offset.
 
+use sha2::{Digest, Sha256};
 use std::str;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
@@ -8,7 +9,10 @@ use std::sync::Arc;
 use crate::segmenter::Segment;
 use crate::storage::StorageWriter;
 
-use super::super::segment::{parse_content_range, parse_http_status};
+use super::super::segment::{
+    content_range_total_mismatch, parse_content_range, parse_http_status,
+    unexpected_content_encoding,
+};
 
 /// Handler state for one segment transfer. Implements curl's Handler for Easy2.
 pub struct SegmentHandler {
@@ -20,6 +24,19 @@ pub struct SegmentHandler {
     pub(super) range_ok: Option<bool>,
     pub(super) bytes_written: u64,
     pub(super) in_flight: Option<Arc<Vec<AtomicU64>>>,
+    /// True when the request carried `If-Range`; a 200 then means the remote changed.
+    pub(super) if_range_sent: bool,
+    /// True for `file://` sources: no status or Content-Range to validate.
+    pub(super) local_file: bool,
+    /// Set when the response had a non-identity Content-Encoding (transfer aborted).
+    pub(super) unexpected_encoding: Option<String>,
+    /// Running SHA-256 of the bytes written, when segment hashing is enabled.
+    pub(super) hasher: Option<Sha256>,
+    /// Known file size to check the Content-Range total against
+    /// (`SegmentRunContext::expected_total`).
+    pub(super) expected_total: Option<u64>,
+    /// Content-Range total that contradicted `expected_total` (transfer aborted).
+    pub(super) total_mismatch: Option<u64>,
 }
 
 impl SegmentHandler {
@@ -37,6 +54,12 @@ impl SegmentHandler {
             range_ok: None,
             bytes_written: 0,
             in_flight,
+            if_range_sent: false,
+            local_file: false,
+            unexpected_encoding: None,
+            hasher: None,
+            expected_total: None,
+            total_mismatch: None,
         }
     }
 }
@@ -45,6 +68,10 @@ impl curl::easy::Handler for SegmentHandler {
     fn header(&mut self, data: &[u8]) -> bool {
         if let Ok(s) = str::from_utf8(data) {
             let line = s.trim_end();
+            if let Some(enc) = unexpected_content_encoding(line) {
+                self.unexpected_encoding = Some(enc);
+                return false;
+            }
             if line.starts_with("HTTP/") {
                 self.response_headers.clear();
                 self.response_headers.push(line.to_string());
@@ -61,7 +88,12 @@ impl curl::easy::Handler for SegmentHandler {
             let content_ok = parse_content_range(&self.response_headers)
                 .map(|(s, e)| s == self.segment.start && e == self.segment.end.saturating_sub(1))
                 .unwrap_or(false);
-            self.range_ok = Some(status == Some(206) && content_ok);
+            self.range_ok = Some(self.local_file || (status == Some(206) && content_ok));
+            self.total_mismatch =
+                content_range_total_mismatch(&self.response_headers, self.expected_total);
+            if self.total_mismatch.is_some() {
+                self.range_ok = Some(false);
+            }
         }
         if self.range_ok == Some(false) {
             return Ok(0);
@@ -71,6 +103,9 @@ impl curl::easy::Handler for SegmentHandler {
             Ok(()) => {
                 let n = data.len();
                 self.bytes_written += n as u64;
+                if let Some(ref mut h) = self.hasher {
+                    h.update(data);
+                }
                 if let Some(ref v) = self.in_flight {
                     if let Some(a) = v.get(self.segment_index) {
                         a.store(self.bytes_written, Ordering::Relaxed);
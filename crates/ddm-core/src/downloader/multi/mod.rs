@@ -12,29 +12,35 @@ use std::collections::HashMap;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
+use crate::checksum::SegmentHashes;
 use crate::retry::RetryPolicy;
 use crate::segmenter::{Segment, SegmentBitmap};
 use crate::storage::StorageWriter;
 
-use super::CurlOptions;
 use super::DownloadSummary;
+use super::SourceUrls;
+use super::{CurlOptions, SegmentRunContext};
 
 /// Runs segment downloads via the curl multi backend (Easy2 + Multi handle).
 /// When retry_policy is Some, retryable segment failures are retried with backoff.
-/// If abort is set and becomes true, the run stops with JobAborted.
+/// If `ctx.cancel` is set and becomes true, the run stops with JobAborted.
+/// If `segment_hashes` is Some, each completed segment's SHA-256 is recorded in its slot.
+/// Segments are spread over `urls` as in [`super::download_segments`].
 pub fn download_segments_multi(
-    url: &str,
+    urls: &SourceUrls,
     custom_headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     segments: &[Segment],
     storage: &StorageWriter,
     bitmap: &mut SegmentBitmap,
     max_concurrent: Option<usize>,
     retry_policy: Option<&RetryPolicy>,
     summary_out: &mut DownloadSummary,
-    progress_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    progress_tx: Option<&crate::downloader::BitmapSender>,
     in_flight_bytes: Option<Arc<Vec<AtomicU64>>>,
-    abort: Option<Arc<std::sync::atomic::AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<()> {
     let incomplete: Vec<(usize, Segment)> = segments
         .iter()
@@ -50,8 +56,9 @@ pub fn download_segments_multi(
 
     let max = max_concurrent.unwrap_or_else(|| incomplete.len()).max(1);
     run::run_multi(
-        url,
+        urls,
         custom_headers,
+        if_range,
         storage,
         incomplete,
         segments.len(),
@@ -60,9 +67,10 @@ pub fn download_segments_multi(
         summary_out,
         progress_tx,
         in_flight_bytes,
-        abort,
+        segment_hashes,
         retry_policy.copied(),
         curl,
+        ctx,
     )
 }
 
@@ -87,8 +95,9 @@ mod tests {
         builder.preallocate(1000).unwrap();
         let storage = builder.build();
         let result = download_segments_multi(
-            "http://example.com/file",
+            &SourceUrls::from("http://example.com/file"),
             &headers,
+            None,
             &segments,
             &storage,
             &mut bitmap,
@@ -99,6 +108,7 @@ mod tests {
             None,
             None,
             CurlOptions::default(),
+            SegmentRunContext::default(),
         );
         assert!(
             result.is_ok(),
@@ -7,10 +7,14 @@ use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use sha2::{Digest, Sha256};
+
 use crate::segmenter::Segment;
 use crate::storage::StorageWriter;
 
-use super::super::CurlOptions;
+use super::super::lookahead::Lookahead;
+use super::super::ramp::Ramp;
+use super::super::{CurlOptions, SegmentRunContext, SourceUrls};
 use super::handler::SegmentHandler;
 
 /// Active entry in the multi event loop: handle + segment index + metadata.
@@ -33,55 +37,61 @@ pub(super) fn next_retry_wait_ms(retry_after: &[(Instant, usize, Segment, u32)])
 }
 
 /// Add a new Easy handle for the given segment to the multi handle, configuring
-/// range, headers, timeouts and optional bandwidth/buffer settings.
+/// range, headers (plus `If-Range` when given), timeouts and `CurlOptions`.
+/// When `hash_segments` is true the handler hashes the body as it is written.
 pub(super) fn add_easy_to_multi(
     multi: &curl::multi::Multi,
-    url: &str,
+    urls: &SourceUrls,
     headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     storage: &StorageWriter,
     in_flight_bytes: Option<&Arc<Vec<AtomicU64>>>,
-    index: usize,
-    segment: Segment,
-    curl: CurlOptions,
+    hash_segments: bool,
+    (index, segment, attempt): (usize, Segment, u32),
+    curl: &CurlOptions,
+    ctx: &SegmentRunContext,
 ) -> Result<curl::multi::Easy2Handle<SegmentHandler>> {
+    // Attempts count from 1; each retry moves on to the next source URL.
+    let url = urls.for_segment(index, attempt - 1);
+    ctx.clear_stall(index);
+    let if_range = urls.if_range_for(index, attempt - 1, if_range);
     let handler = SegmentHandler::new(
         index,
         segment,
         storage.clone(),
         in_flight_bytes.map(Arc::clone),
     );
+    let mut handler = handler;
+    handler.if_range_sent = if_range.is_some();
+    handler.local_file = crate::url_model::is_file_url(url);
+    handler.hasher = hash_segments.then(Sha256::new);
+    handler.expected_total = ctx.expected_total;
     let mut easy = curl::easy::Easy2::new(handler);
     easy.url(url)
         .map_err(|e| anyhow::anyhow!("curl url: {}", e))?;
-    easy.follow_location(true)
-        .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    easy.max_redirections(10)
+    curl.apply_to_easy2(&mut easy)
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    if let Some(speed) = curl.max_recv_speed {
-        easy.max_recv_speed(speed)
-            .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    }
-    if let Some(sz) = curl.buffer_size {
-        easy.buffer_size(sz)
-            .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    }
     easy.connect_timeout(Duration::from_secs(30))
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
     easy.low_speed_limit(1024)
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
     easy.low_speed_time(Duration::from_secs(60))
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    easy.timeout(Duration::from_secs(3600))
+    easy.timeout(ctx.segment_timeout(segment.len()))
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
     let end = segment.end.saturating_sub(1);
     easy.range(&format!("{}-{}", segment.start, end))
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    if !headers.is_empty() {
+    if !headers.is_empty() || if_range.is_some() {
         let mut list = curl::easy::List::new();
         for (k, v) in headers {
             list.append(&format!("{}: {}", k.trim(), v.trim()))
                 .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
         }
+        if let Some(v) = if_range {
+            list.append(&format!("If-Range: {}", v))
+                .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
+        }
         easy.http_headers(list)
             .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
     }
@@ -91,44 +101,66 @@ pub(super) fn add_easy_to_multi(
 }
 
 /// Refill the active set with pending or ready-to-retry segments until
-/// `max_concurrent` is reached or there is nothing left to schedule.
+/// `max_concurrent` is reached or there is nothing left to schedule. With a `lookahead`
+/// (sequential order), a pending segment waits until it falls inside the window; with a
+/// `ramp`, starts wait for their turn in the connection warm-up.
 pub(super) fn refill_active(
     multi: &curl::multi::Multi,
-    url: &str,
+    urls: &SourceUrls,
     headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     storage: &StorageWriter,
     in_flight_bytes: Option<&Arc<Vec<AtomicU64>>>,
+    hash_segments: bool,
     max_concurrent: usize,
     active: &mut Vec<ActiveItem>,
     pending: &mut VecDeque<(usize, Segment)>,
     retry_after: &mut Vec<(Instant, usize, Segment, u32)>,
-    curl: CurlOptions,
+    lookahead: Option<&Lookahead>,
+    mut ramp: Option<&mut Ramp>,
+    curl: &CurlOptions,
+    ctx: &SegmentRunContext,
 ) -> Result<()> {
     let now = Instant::now();
     while active.len() < max_concurrent {
-        if let Some((index, segment)) = pending.pop_front() {
+        let next_allowed = pending
+            .front()
+            .is_some_and(|&(i, _)| lookahead.is_none_or(|l| l.allows(i)));
+        let retry_ready = retry_after.iter().any(|(t, ..)| now >= *t);
+        // Only a start that will happen takes a warm-up slot.
+        if !next_allowed && !retry_ready {
+            break;
+        }
+        if ramp.as_deref_mut().is_some_and(|r| !r.try_start()) {
+            break;
+        }
+        if let Some((index, segment)) = next_allowed.then(|| pending.pop_front()).flatten() {
             let h = add_easy_to_multi(
                 multi,
-                url,
+                urls,
                 headers,
+                if_range,
                 storage,
                 in_flight_bytes,
-                index,
-                segment,
+                hash_segments,
+                (index, segment, 1),
                 curl,
+                ctx,
             )?;
             active.push((h, index, segment, 1));
         } else if let Some(pos) = retry_after.iter().position(|(t, ..)| now >= *t) {
             let (_, index, segment, attempt) = retry_after.remove(pos);
             let h = add_easy_to_multi(
                 multi,
-                url,
+                urls,
                 headers,
+                if_range,
                 storage,
                 in_flight_bytes,
-                index,
-                segment,
+                hash_segments,
+                (index, segment, attempt),
                 curl,
+                ctx,
             )?;
             active.push((h, index, segment, attempt));
         } else {
@@ -137,3 +169,50 @@ pub(super) fn refill_active(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_held_by_the_lookahead_does_not_take_a_ramp_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let tp = crate::storage::temp_path(&dir.path().join("out.bin"));
+        let mut builder = crate::storage::StorageWriterBuilder::create(&tp).unwrap();
+        builder.preallocate(100).unwrap();
+        let storage = builder.build();
+        let multi = curl::multi::Multi::new();
+        // Segment 0 is still running elsewhere; segment 1 is outside a window of 1.
+        let lookahead = Lookahead::new(1, [0, 1]);
+        let mut pending = VecDeque::from([(
+            1,
+            Segment {
+                start: 50,
+                end: 100,
+            },
+        )]);
+        let mut ramp = Ramp::new(Duration::from_secs(60), 2);
+        let mut active = Vec::new();
+        refill_active(
+            &multi,
+            &SourceUrls::from("http://example.com/file"),
+            &HashMap::new(),
+            None,
+            &storage,
+            None,
+            false,
+            2,
+            &mut active,
+            &mut pending,
+            &mut Vec::new(),
+            Some(&lookahead),
+            Some(&mut ramp),
+            &CurlOptions::default(),
+            &SegmentRunContext::default(),
+        )
+        .unwrap();
+        assert!(active.is_empty());
+        assert_eq!(pending.len(), 1);
+        assert!(ramp.try_start(), "the first warm-up slot is still free");
+    }
+}
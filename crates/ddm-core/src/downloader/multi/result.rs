@@ -12,11 +12,25 @@ pub(super) fn segment_result_from_easy(
     segment: &Segment,
     handler: &SegmentHandler,
 ) -> SegmentResult {
-    if code < 200 || code >= 300 {
-        return Err(SegmentError::Http(code));
+    if let Some(ref enc) = handler.unexpected_encoding {
+        return Err(SegmentError::UnexpectedEncoding(enc.clone()));
     }
-    if code != 206 {
-        return Err(SegmentError::InvalidRangeResponse(code));
+    if let Some(reported) = handler.total_mismatch {
+        return Err(SegmentError::TotalSizeMismatch {
+            expected: handler.expected_total.unwrap_or_default(),
+            reported,
+        });
+    }
+    if !handler.local_file {
+        if code < 200 || code >= 300 {
+            return Err(SegmentError::Http(code));
+        }
+        if code == 200 && handler.if_range_sent {
+            return Err(SegmentError::RemoteChanged);
+        }
+        if code != 206 {
+            return Err(SegmentError::InvalidRangeResponse(code));
+        }
     }
     let expected = segment.len();
     let received = handler.bytes_written;
@@ -25,3 +39,100 @@ pub(super) fn segment_result_from_easy(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::segmenter::plan_segments;
+
+    fn handler(segment: Segment) -> SegmentHandler {
+        let dir = tempfile::tempdir().unwrap();
+        let tp = crate::storage::temp_path(&dir.path().join("out.bin"));
+        let mut builder = crate::storage::StorageWriterBuilder::create(&tp).unwrap();
+        builder.preallocate(1000).unwrap();
+        SegmentHandler::new(0, segment, builder.build(), None)
+    }
+
+    #[test]
+    fn status_200_with_if_range_is_remote_changed() {
+        let seg = plan_segments(1000, 1)[0];
+        let mut h = handler(seg);
+        h.if_range_sent = true;
+        assert!(matches!(
+            segment_result_from_easy(200, &seg, &h),
+            Err(SegmentError::RemoteChanged)
+        ));
+    }
+
+    #[test]
+    fn unexpected_encoding_wins_over_status() {
+        let seg = plan_segments(1000, 1)[0];
+        let mut h = handler(seg);
+        assert!(!curl::easy::Handler::header(
+            &mut h,
+            b"Content-Encoding: gzip\r\n"
+        ));
+        assert!(matches!(
+            segment_result_from_easy(206, &seg, &h),
+            Err(SegmentError::UnexpectedEncoding(ref e)) if e == "gzip"
+        ));
+    }
+
+    #[test]
+    fn content_range_total_mismatch_aborts_before_write() {
+        let seg = plan_segments(1000, 2)[1];
+        let mut h = handler(seg);
+        h.expected_total = Some(1000);
+        assert!(curl::easy::Handler::header(
+            &mut h,
+            b"HTTP/1.1 206 Partial Content\r\n"
+        ));
+        assert!(curl::easy::Handler::header(
+            &mut h,
+            b"Content-Range: bytes 500-999/2000\r\n"
+        ));
+        assert_eq!(curl::easy::Handler::write(&mut h, b"abc").unwrap(), 0);
+        assert!(matches!(
+            segment_result_from_easy(206, &seg, &h),
+            Err(SegmentError::TotalSizeMismatch {
+                expected: 1000,
+                reported: 2000
+            })
+        ));
+    }
+
+    #[test]
+    fn status_200_without_if_range_is_invalid_range() {
+        let seg = plan_segments(1000, 1)[0];
+        let h = handler(seg);
+        assert!(matches!(
+            segment_result_from_easy(200, &seg, &h),
+            Err(SegmentError::InvalidRangeResponse(200))
+        ));
+    }
+
+    #[test]
+    fn short_transfer_is_partial_even_with_206() {
+        let seg = plan_segments(1000, 1)[0];
+        let mut h = handler(seg);
+        assert!(curl::easy::Handler::header(
+            &mut h,
+            b"HTTP/1.1 206 Partial Content\r\n"
+        ));
+        assert!(curl::easy::Handler::header(
+            &mut h,
+            b"Content-Range: bytes 0-999/1000\r\n"
+        ));
+        assert_eq!(
+            curl::easy::Handler::write(&mut h, &[7u8; 400]).unwrap(),
+            400
+        );
+        assert!(matches!(
+            segment_result_from_easy(206, &seg, &h),
+            Err(SegmentError::PartialTransfer {
+                expected: 1000,
+                received: 400
+            })
+        ));
+    }
+}
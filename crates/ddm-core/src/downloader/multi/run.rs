@@ -3,46 +3,65 @@
 
 use anyhow::Result;
 use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use sha2::Digest;
+
+use crate::checksum::SegmentHashes;
 use crate::control::JobAborted;
-use crate::retry::{classify, ErrorKind, RetryDecision, RetryPolicy};
+use crate::retry::{classify, ErrorKind, RetryBudget, RetryDecision, RetryPolicy, SegmentError};
 use crate::segmenter::{Segment, SegmentBitmap};
 use crate::storage::StorageWriter;
 
-use super::super::CurlOptions;
+use super::super::lookahead::Lookahead;
+use super::super::ramp::Ramp;
 use super::super::DownloadSummary;
+use super::super::{CurlOptions, SegmentRunContext, SourceUrls};
 use super::handler::SegmentHandler;
 use super::refill;
 use super::result;
 
-const COALESCE_PROGRESS_EVERY: usize = 2;
-
 /// Run incomplete segments using curl multi: add up to max_concurrent Easy2 handles,
 /// perform/wait/messages loop, process completions and add more until done or error.
 /// When retry_policy is Some, retryable failures are re-queued with backoff.
 pub(super) fn run_multi(
-    url: &str,
+    urls: &SourceUrls,
     headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     storage: &StorageWriter,
     incomplete: Vec<(usize, Segment)>,
     segment_count: usize,
     max_concurrent: usize,
     bitmap: &mut SegmentBitmap,
     summary_out: &mut DownloadSummary,
-    progress_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    progress_tx: Option<&crate::downloader::BitmapSender>,
     in_flight_bytes: Option<Arc<Vec<AtomicU64>>>,
-    abort: Option<Arc<AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     retry_policy: Option<RetryPolicy>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<()> {
     if incomplete.is_empty() {
         return Ok(());
     }
 
-    let multi = curl::multi::Multi::new();
+    let mut multi = curl::multi::Multi::new();
+    // Cap sockets, not just transfers: idle cached connections count against the limit too,
+    // so segments beyond `max_concurrent` reuse connections as they free up.
+    multi
+        .set_max_host_connections(max_concurrent)
+        .map_err(|e| anyhow::anyhow!("curl multi options: {}", e))?;
+    multi
+        .set_max_connects(max_concurrent)
+        .map_err(|e| anyhow::anyhow!("curl multi options: {}", e))?;
+    if curl.multiplexes() {
+        // HTTP/2 and HTTP/3: run many segments as streams over the same connection.
+        multi
+            .pipelining(false, true)
+            .map_err(|e| anyhow::anyhow!("curl multi options: {}", e))?;
+    }
     let mut pending: VecDeque<(usize, Segment)> = incomplete.into_iter().collect();
     let mut retry_after: Vec<(Instant, usize, Segment, u32)> = Vec::new();
     let mut active: Vec<(
@@ -53,30 +72,36 @@ pub(super) fn run_multi(
     )> = Vec::new();
     let mut first_error: Option<anyhow::Error> = None;
     let mut completed_since_send = 0usize;
+    let flush_every = ctx.progress_flush_every();
+    let budget = RetryBudget::new(retry_policy.map_or(0, |p| p.max_job_retries));
 
-    let to_add = max_concurrent.min(pending.len());
-    for _ in 0..to_add {
-        if let Some((index, segment)) = pending.pop_front() {
-            let h = refill::add_easy_to_multi(
-                &multi,
-                url,
-                headers,
-                storage,
-                in_flight_bytes.as_ref(),
-                index,
-                segment,
-                curl,
-            )?;
-            active.push((h, index, segment, 1));
-        }
-    }
+    let mut lookahead = ctx
+        .segment_lookahead
+        .map(|window| Lookahead::new(window, pending.iter().map(|&(i, _)| i)));
+    let mut ramp = ctx
+        .connection_ramp
+        .map(|interval| Ramp::new(interval, max_concurrent.min(pending.len())));
 
-    while !active.is_empty() {
-        if abort
-            .as_ref()
-            .map(|a| a.load(Ordering::Relaxed))
-            .unwrap_or(false)
-        {
+    refill::refill_active(
+        &multi,
+        urls,
+        headers,
+        if_range,
+        storage,
+        in_flight_bytes.as_ref(),
+        segment_hashes.is_some(),
+        max_concurrent,
+        &mut active,
+        &mut pending,
+        &mut retry_after,
+        lookahead.as_ref(),
+        ramp.as_mut(),
+        &curl,
+        &ctx,
+    )?;
+
+    while !active.is_empty() || !retry_after.is_empty() || !pending.is_empty() {
+        if ctx.is_cancelled() {
             if first_error.is_none() {
                 first_error = Some(anyhow::anyhow!(JobAborted));
             }
@@ -94,6 +119,16 @@ pub(super) fn run_multi(
                 }
             }
         });
+        // Transfers the stall watchdog flagged are removed and fail like finished ones.
+        let stalled: Vec<usize> = active
+            .iter()
+            .enumerate()
+            .filter(|(i, (_, seg_index, ..))| {
+                ctx.is_stalled(*seg_index) && !completed_indices.contains(i)
+            })
+            .map(|(i, _)| i)
+            .collect();
+        completed_indices.extend_from_slice(&stalled);
         completed_indices.sort_by(|a, b| b.cmp(a));
         for &i in &completed_indices {
             let (handle, seg_index, segment, attempt) = active.remove(i);
@@ -101,15 +136,34 @@ pub(super) fn run_multi(
                 .remove2(handle)
                 .map_err(|e| anyhow::anyhow!("curl multi remove: {}", e))?;
             let code = easy.response_code().unwrap_or(0);
+            // Attempts count from 1; this is the source `add_easy_to_multi` requested.
+            super::super::options::log_redirects(
+                urls.for_segment(seg_index, attempt - 1),
+                easy.redirect_count().unwrap_or(0),
+                easy.effective_url().ok().flatten(),
+            );
             let handler = easy.get_mut();
-            let res = result::segment_result_from_easy(code, &segment, handler);
+            let res = if stalled.contains(&i) {
+                Err(SegmentError::Stalled {
+                    received: handler.bytes_written,
+                })
+            } else {
+                result::segment_result_from_easy(code, &segment, handler)
+            };
             match res {
                 Ok(()) => {
+                    if let (Some(sink), Some(h)) = (segment_hashes.as_ref(), handler.hasher.take())
+                    {
+                        sink.set(seg_index, hex::encode(h.finalize()));
+                    }
                     bitmap.set_completed(seg_index);
+                    if let Some(ref mut l) = lookahead {
+                        l.finish(seg_index);
+                    }
                     completed_since_send += 1;
                     if let Some(ref tx) = progress_tx {
-                        if completed_since_send >= COALESCE_PROGRESS_EVERY {
-                            let _ = tx.try_send(bitmap.to_bytes(segment_count));
+                        if completed_since_send >= flush_every {
+                            let _ = tx.send(bitmap.to_bytes(segment_count));
                             completed_since_send = 0;
                         }
                     }
@@ -121,54 +175,69 @@ pub(super) fn run_multi(
                     } else if kind != ErrorKind::Other {
                         summary_out.error_events += 1;
                     }
-                    let will_retry = retry_policy.as_ref().and_then(|policy| {
-                        match policy.decide(attempt, kind) {
-                            RetryDecision::RetryAfter(d) => {
-                                Some((Instant::now() + d, seg_index, segment, attempt + 1))
-                            }
-                            RetryDecision::NoRetry => None,
-                        }
-                    });
-                    if let Some(entry) = will_retry {
-                        retry_after.push(entry);
-                    } else {
-                        if first_error.is_none() {
-                            first_error = Some(
-                                anyhow::anyhow!("{}", e).context(format!("segment {}", seg_index)),
-                            );
+                    let decision = retry_policy
+                        .as_ref()
+                        .map_or(RetryDecision::NoRetry, |p| p.decide(attempt, kind));
+                    let e = match decision {
+                        RetryDecision::RetryAfter(d) if budget.try_spend() => {
+                            retry_after.push((Instant::now() + d, seg_index, segment, attempt + 1));
+                            continue;
                         }
+                        RetryDecision::RetryAfter(_) => SegmentError::budget_exhausted(&budget, e),
+                        RetryDecision::NoRetry => e,
+                    };
+                    if let Some(ref mut l) = lookahead {
+                        l.finish(seg_index);
+                    }
+                    if first_error.is_none() {
+                        first_error =
+                            Some(anyhow::Error::new(e).context(format!("segment {}", seg_index)));
                     }
                 }
             }
         }
         refill::refill_active(
             &multi,
-            url,
+            urls,
             headers,
+            if_range,
             storage,
             in_flight_bytes.as_ref(),
+            segment_hashes.is_some(),
             max_concurrent,
             &mut active,
             &mut pending,
             &mut retry_after,
-            curl,
+            lookahead.as_ref(),
+            ramp.as_mut(),
+            &curl,
+            &ctx,
         )?;
         if first_error.is_some() {
             break;
         }
+        let ramp_ms = ramp
+            .as_ref()
+            .and_then(Ramp::wait)
+            .map_or(100, |d| d.as_millis() as u64);
+        let wait_ms = refill::next_retry_wait_ms(&retry_after).min(ramp_ms);
         if running > 0 {
-            let wait_ms = refill::next_retry_wait_ms(&retry_after).min(100);
             multi
                 .wait(&mut [], Duration::from_millis(wait_ms))
                 .map_err(|e| anyhow::anyhow!("curl multi wait: {}", e))?;
+        } else if active.is_empty() {
+            // Every remaining segment is backing off or waiting on the connection ramp;
+            // sleep until the earliest one may start.
+            std::thread::sleep(Duration::from_millis(wait_ms));
         }
     }
 
     if completed_since_send > 0 {
         if let Some(ref tx) = progress_tx {
-            let _ = tx.try_send(bitmap.to_bytes(segment_count));
+            let _ = tx.send(bitmap.to_bytes(segment_count));
         }
     }
+    summary_out.retry_attempts = budget.used();
     if let Some(e) = first_error {
         return Err(e);
     }
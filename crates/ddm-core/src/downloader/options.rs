@@ -0,0 +1,310 @@
+//! Per-handle curl options shared by probes, segment downloads, and the multi backend.
+//! Per-job run state (abort token, deadline, pacing) lives in [`super::SegmentRunContext`].
+
+use crate::config::{DdmConfig, HttpVersion, IpVersion, DEFAULT_MAX_REDIRECTS};
+
+/// Sets every [`CurlOptions`] field on `$easy`, which is an `Easy` or an `Easy2`: the
+/// `curl` crate gives the two handle types the same setters but no common trait.
+macro_rules! apply_options {
+    ($opts:ident, $easy:ident) => {{
+        if let Some(speed) = $opts.max_recv_speed {
+            $easy.max_recv_speed(speed)?;
+        }
+        if let Some(sz) = $opts.buffer_size {
+            $easy.buffer_size(sz)?;
+        }
+        $easy.ip_resolve($opts.ip_resolve())?;
+        if let Some(t) = $opts.happy_eyeballs_timeout {
+            set_happy_eyeballs_timeout($easy.raw(), t)?;
+        }
+        if let Some(v) = $opts.curl_http_version() {
+            $easy.http_version(v)?;
+        }
+        // Wait for an existing connection to multiplex on instead of opening another.
+        if $opts.multiplexes() {
+            $easy.pipewait(true)?;
+        }
+        $easy.follow_location($opts.redirect_limit() > 0)?;
+        $easy.max_redirections($opts.redirect_limit())?;
+        if let Some(ref proxy) = $opts.proxy {
+            $easy.proxy(proxy)?;
+        }
+        if let Some(ref ua) = $opts.user_agent {
+            $easy.useragent(ua)?;
+        }
+        if !$opts.connect_to.is_empty() {
+            $easy.connect_to($opts.connect_to_list()?)?;
+        }
+        if let Some(ref ca) = $opts.ca_bundle {
+            $easy.cainfo(ca)?;
+        }
+        if $opts.insecure {
+            $easy.ssl_verify_peer(false)?;
+            $easy.ssl_verify_host(false)?;
+        }
+        if let Some(ref cookie) = $opts.cookie {
+            // An empty cookie file turns the engine on without reading one.
+            $easy.cookie_file("")?;
+            $easy.cookie(cookie)?;
+        }
+        Ok(())
+    }};
+}
+
+/// Curl/libcurl tuning options applied per handle.
+#[derive(Debug, Clone, Default)]
+pub struct CurlOptions {
+    /// Maximum receive speed (bytes/sec) for this curl handle.
+    pub max_recv_speed: Option<u64>,
+    /// Curl receive buffer size (bytes) for this curl handle.
+    pub buffer_size: Option<usize>,
+    /// Address family to resolve/connect with.
+    pub ip_version: IpVersion,
+    /// Head start of the preferred address family before the other is tried
+    /// (`happy_eyeballs_timeout_ms` in config); None = curl's default.
+    pub happy_eyeballs_timeout: Option<std::time::Duration>,
+    /// HTTP version to request (`http_version` in config).
+    pub http_version: HttpVersion,
+    /// Proxy URL (`http://`, `socks5://`, `socks5h://`, ...). When None, curl's
+    /// environment handling (`http_proxy`, `https_proxy`, `no_proxy`) applies.
+    pub proxy: Option<String>,
+    /// `User-Agent` header; None leaves libcurl's default (no header).
+    pub user_agent: Option<String>,
+    /// `HOST:PORT:CONNECT-HOST:CONNECT-PORT` connection overrides (`connect_to` in config).
+    pub connect_to: Vec<String>,
+    /// CA bundle to verify TLS peers against (`ca_bundle` in config); None = curl's default.
+    pub ca_bundle: Option<std::path::PathBuf>,
+    /// Disable TLS peer and host verification (`--insecure`); never set from config.
+    pub insecure: bool,
+    /// Redirects to follow (`max_redirects` in config); None = [`DEFAULT_MAX_REDIRECTS`],
+    /// 0 = do not follow.
+    pub max_redirects: Option<u32>,
+    /// Cookies sent with every request, redirects included, through curl's cookie engine
+    /// (which also keeps cookies set along a redirect chain); a job's `cookie_session`.
+    /// None = engine off.
+    pub cookie: Option<String>,
+}
+
+impl CurlOptions {
+    /// Derive per-handle options from a global cap and concurrency.
+    pub fn per_handle(
+        global_max_bytes_per_sec: Option<u64>,
+        concurrency: usize,
+        buffer_size: Option<usize>,
+    ) -> Self {
+        let concurrency_u = (concurrency.max(1)) as u64;
+        let max_recv_speed =
+            global_max_bytes_per_sec.map(|bps| (bps + concurrency_u - 1) / concurrency_u);
+        Self {
+            max_recv_speed,
+            buffer_size,
+            ..Self::default()
+        }
+    }
+
+    /// Per-handle options from config for `concurrency` simultaneous handles.
+    pub fn from_config(cfg: &DdmConfig, concurrency: usize) -> Self {
+        Self {
+            ip_version: cfg.ip_version.unwrap_or_default(),
+            happy_eyeballs_timeout: cfg
+                .happy_eyeballs_timeout_ms
+                .map(std::time::Duration::from_millis),
+            http_version: cfg.http_version.unwrap_or_default(),
+            proxy: cfg.proxy.clone(),
+            user_agent: Some(cfg.user_agent().to_string()),
+            connect_to: cfg.connect_to.clone(),
+            ca_bundle: cfg.ca_bundle.clone(),
+            max_redirects: cfg.max_redirects,
+            ..Self::per_handle(
+                cfg.current_max_bytes_per_sec(),
+                concurrency,
+                cfg.segment_buffer_bytes,
+            )
+        }
+    }
+
+    /// Redirects a handle may follow (0 = redirects are not followed).
+    pub fn redirect_limit(&self) -> u32 {
+        self.max_redirects.unwrap_or(DEFAULT_MAX_REDIRECTS)
+    }
+
+    fn connect_to_list(&self) -> Result<curl::easy::List, curl::Error> {
+        let mut list = curl::easy::List::new();
+        for entry in &self.connect_to {
+            list.append(entry)?;
+        }
+        Ok(list)
+    }
+
+    fn ip_resolve(&self) -> curl::easy::IpResolve {
+        match self.ip_version {
+            IpVersion::Auto => curl::easy::IpResolve::Any,
+            IpVersion::V4 => curl::easy::IpResolve::V4,
+            IpVersion::V6 => curl::easy::IpResolve::V6,
+        }
+    }
+
+    /// True when the configured version multiplexes transfers over one connection
+    /// (HTTP/2 or HTTP/3).
+    pub fn multiplexes(&self) -> bool {
+        matches!(self.http_version, HttpVersion::Http2 | HttpVersion::Http3)
+    }
+
+    /// curl version for `http_version`; None (auto) leaves curl's default.
+    fn curl_http_version(&self) -> Option<curl::easy::HttpVersion> {
+        match self.http_version {
+            HttpVersion::Auto => None,
+            HttpVersion::Http1_1 => Some(curl::easy::HttpVersion::V11),
+            HttpVersion::Http2 => Some(curl::easy::HttpVersion::V2),
+            HttpVersion::Http3 => Some(curl::easy::HttpVersion::V3),
+        }
+    }
+
+    /// Apply these options to an `Easy` handle.
+    pub fn apply_to_easy(&self, easy: &mut curl::easy::Easy) -> Result<(), curl::Error> {
+        apply_options!(self, easy)
+    }
+
+    /// Apply these options to an `Easy2` handle (multi backend).
+    pub fn apply_to_easy2<H>(&self, easy: &mut curl::easy::Easy2<H>) -> Result<(), curl::Error> {
+        apply_options!(self, easy)
+    }
+}
+
+/// `CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS`, which the `curl` crate does not wrap.
+const CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS: curl_sys::CURLoption = curl_sys::CURLOPTTYPE_LONG + 271;
+
+fn set_happy_eyeballs_timeout(
+    handle: *mut curl_sys::CURL,
+    timeout: std::time::Duration,
+) -> Result<(), curl::Error> {
+    let ms = timeout.as_millis().min(libc::c_long::MAX as u128) as libc::c_long;
+    // The handle is live for the duration of the call and the option takes a long.
+    let rc = unsafe { curl_sys::curl_easy_setopt(handle, CURLOPT_HAPPY_EYEBALLS_TIMEOUT_MS, ms) };
+    if rc == curl_sys::CURLE_OK {
+        Ok(())
+    } else {
+        Err(curl::Error::new(rc))
+    }
+}
+
+/// Debug-logs where a transfer of `url` ended up when curl followed redirects to get there.
+pub(crate) fn log_redirects(url: &str, redirects: u32, effective_url: Option<&str>) {
+    if redirects > 0 {
+        tracing::debug!(
+            url,
+            redirects,
+            effective_url = effective_url.unwrap_or("?"),
+            "followed redirects"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn per_handle_splits_rate_cap() {
+        let o = CurlOptions::per_handle(Some(1000), 3, Some(4096));
+        assert_eq!(o.max_recv_speed, Some(334));
+        assert_eq!(o.buffer_size, Some(4096));
+        assert_eq!(o.ip_version, IpVersion::Auto);
+    }
+
+    #[test]
+    fn from_config_carries_proxy() {
+        let cfg = DdmConfig {
+            proxy: Some("socks5h://127.0.0.1:1080".to_string()),
+            ..DdmConfig::default()
+        };
+        let o = CurlOptions::from_config(&cfg, 4);
+        assert_eq!(o.proxy.as_deref(), Some("socks5h://127.0.0.1:1080"));
+        assert_eq!(o.redirect_limit(), crate::config::DEFAULT_MAX_REDIRECTS);
+        assert_eq!(
+            o.user_agent.as_deref(),
+            Some(crate::config::DEFAULT_USER_AGENT)
+        );
+        let mut easy = curl::easy::Easy2::new(NoopHandler);
+        o.apply_to_easy2(&mut easy).unwrap();
+    }
+
+    struct NoopHandler;
+    impl curl::easy::Handler for NoopHandler {}
+
+    #[test]
+    fn insecure_applies_to_easy() {
+        assert!(!CurlOptions::from_config(&DdmConfig::default(), 1).insecure);
+        let o = CurlOptions {
+            insecure: true,
+            cookie: Some("session=abc".to_string()),
+            ..CurlOptions::default()
+        };
+        let mut easy = curl::easy::Easy::new();
+        o.apply_to_easy(&mut easy).unwrap();
+    }
+
+    #[test]
+    fn from_config_carries_ca_bundle() {
+        let ca = tempfile::NamedTempFile::new().unwrap();
+        let cfg = DdmConfig {
+            ca_bundle: Some(ca.path().to_path_buf()),
+            ..DdmConfig::default()
+        };
+        let o = CurlOptions::from_config(&cfg, 1);
+        assert_eq!(o.ca_bundle.as_deref(), Some(ca.path()));
+        let mut easy = curl::easy::Easy2::new(NoopHandler);
+        o.apply_to_easy2(&mut easy).unwrap();
+    }
+
+    #[test]
+    fn from_config_carries_http_version() {
+        let o = CurlOptions::from_config(&DdmConfig::default(), 1);
+        assert_eq!(o.http_version, HttpVersion::Auto);
+        assert!(!o.multiplexes());
+        let cfg = DdmConfig {
+            http_version: Some(HttpVersion::Http1_1),
+            ..DdmConfig::default()
+        };
+        let o = CurlOptions::from_config(&cfg, 1);
+        assert_eq!(o.http_version, HttpVersion::Http1_1);
+        let mut easy = curl::easy::Easy::new();
+        o.apply_to_easy(&mut easy).unwrap();
+        let h2 = CurlOptions {
+            http_version: HttpVersion::Http2,
+            ..CurlOptions::default()
+        };
+        assert!(h2.multiplexes());
+    }
+
+    #[test]
+    fn from_config_carries_ip_version() {
+        let cfg = DdmConfig {
+            ip_version: Some(IpVersion::V4),
+            ..DdmConfig::default()
+        };
+        let o = CurlOptions::from_config(&cfg, 1);
+        assert_eq!(o.ip_version, IpVersion::V4);
+        assert!(o.proxy.is_none());
+        let mut easy = curl::easy::Easy::new();
+        o.apply_to_easy(&mut easy).unwrap();
+    }
+
+    #[test]
+    fn happy_eyeballs_timeout_applies_to_both_handle_kinds() {
+        let cfg = DdmConfig {
+            happy_eyeballs_timeout_ms: Some(50),
+            ..DdmConfig::default()
+        };
+        let o = CurlOptions::from_config(&cfg, 1);
+        assert_eq!(
+            o.happy_eyeballs_timeout,
+            Some(std::time::Duration::from_millis(50))
+        );
+        assert!(CurlOptions::default().happy_eyeballs_timeout.is_none());
+        o.apply_to_easy(&mut curl::easy::Easy::new()).unwrap();
+        struct Sink;
+        impl curl::easy::Handler for Sink {}
+        o.apply_to_easy2(&mut curl::easy::Easy2::new(Sink)).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+//! Staggered connection warm-up (`SegmentRunContext::connection_ramp`).
+
+use std::time::{Duration, Instant};
+
+/// Spaces the first `connections` transfer starts `interval` apart; later starts are not
+/// delayed.
+#[derive(Debug)]
+pub(crate) struct Ramp {
+    interval: Duration,
+    next: Instant,
+    left: usize,
+}
+
+impl Ramp {
+    pub(crate) fn new(interval: Duration, connections: usize) -> Self {
+        Self {
+            interval,
+            next: Instant::now(),
+            left: connections,
+        }
+    }
+
+    /// True if a transfer may start now; counts it as started.
+    pub(crate) fn try_start(&mut self) -> bool {
+        if self.left == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        if now < self.next {
+            return false;
+        }
+        self.left -= 1;
+        self.next = now + self.interval;
+        true
+    }
+
+    /// Time until the next ramped start is allowed; None once the ramp is over.
+    pub(crate) fn wait(&self) -> Option<Duration> {
+        (self.left > 0).then(|| self.next.saturating_duration_since(Instant::now()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_are_spaced_until_the_ramp_ends() {
+        let mut r = Ramp::new(Duration::from_millis(20), 2);
+        assert!(r.try_start());
+        assert!(!r.try_start(), "second start waits one interval");
+        assert!(r.wait().is_some_and(|d| d <= Duration::from_millis(20)));
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(r.try_start());
+        assert_eq!(r.wait(), None);
+        assert!(r.try_start());
+        assert!(r.try_start());
+    }
+}
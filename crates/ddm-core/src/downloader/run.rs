@@ -6,26 +6,28 @@ use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 
-use crate::retry::{classify, run_with_retry, ErrorKind, RetryPolicy};
+use crate::checksum::SegmentHashes;
+use crate::retry::{classify, run_with_retry, ErrorKind, RetryBudget, RetryPolicy};
 use crate::segmenter::{Segment, SegmentBitmap};
 use crate::storage::StorageWriter;
 
+use super::lookahead::Lookahead;
 use super::segment;
-use super::CurlOptions;
 use super::DownloadSummary;
 use super::SegmentResult;
+use super::SourceUrls;
+use super::{CurlOptions, SegmentRunContext};
 use crate::control::JobAborted;
 
 mod unbounded;
 pub(super) use unbounded::run_unbounded;
 
-pub(super) const COALESCE_PROGRESS_EVERY: usize = 2;
-
 /// Run incomplete segments with a bounded worker pool. Process results as they
 /// arrive; on ErrorKind::Other drain the queue and reduce expected count to avoid deadlock.
 pub(super) fn run_concurrent(
-    url: String,
+    urls: SourceUrls,
     headers: HashMap<String, String>,
+    if_range: Option<String>,
     storage: StorageWriter,
     incomplete: Vec<(usize, Segment)>,
     segment_count: usize,
@@ -33,61 +35,127 @@ pub(super) fn run_concurrent(
     retry_policy: Option<RetryPolicy>,
     bitmap: &mut SegmentBitmap,
     summary_out: &mut DownloadSummary,
-    progress_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    progress_tx: Option<&crate::downloader::BitmapSender>,
     in_flight_bytes: Option<Arc<Vec<AtomicU64>>>,
-    abort: Option<Arc<AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<()> {
     let count = incomplete.len();
+    let lookahead = ctx.segment_lookahead.map(|window| {
+        Arc::new(Mutex::new(Lookahead::new(
+            window,
+            incomplete.iter().map(|&(i, _)| i),
+        )))
+    });
     let work: Arc<Mutex<VecDeque<(usize, Segment)>>> =
         Arc::new(Mutex::new(incomplete.into_iter().collect()));
     let abort_requested = Arc::new(AtomicBool::new(false));
-    let user_abort = abort.unwrap_or_else(|| Arc::new(AtomicBool::new(false)));
+    let user_abort = ctx
+        .cancel
+        .clone()
+        .unwrap_or_else(|| Arc::new(AtomicBool::new(false)));
+    let budget = Arc::new(RetryBudget::new(
+        retry_policy.map_or(0, |p| p.max_job_retries),
+    ));
     let (tx, rx) = mpsc::channel();
     let num_workers = max_concurrent.min(count);
     let mut handles = Vec::with_capacity(num_workers);
-    for _ in 0..num_workers {
+    for worker in 0..num_workers {
+        let ramp_delay = ctx.connection_ramp.map(|d| d * worker as u32);
         let work = Arc::clone(&work);
         let tx = tx.clone();
         let abort = Arc::clone(&abort_requested);
         let user_abort = Arc::clone(&user_abort);
-        let u = url.clone();
+        let u = urls.clone();
         let h = headers.clone();
+        let ir = if_range.clone();
         let st = storage.clone();
         let policy = retry_policy;
-        let curl_opts = curl;
+        let curl_opts = curl.clone();
+        let seg_ctx = ctx.clone();
         let in_flight = in_flight_bytes.as_ref().map(Arc::clone);
-        handles.push(std::thread::spawn(move || loop {
-            if abort.load(Ordering::Relaxed) || user_abort.load(Ordering::Relaxed) {
-                break;
+        let hashes = segment_hashes.as_ref().map(Arc::clone);
+        let budget = Arc::clone(&budget);
+        let lookahead = lookahead.as_ref().map(Arc::clone);
+        handles.push(std::thread::spawn(move || {
+            // One handle per worker: consecutive segments reuse its connection.
+            let mut easy = curl::easy::Easy::new();
+            if let Some(d) = ramp_delay {
+                // Connection ramp: worker N opens its first connection N intervals in.
+                std::thread::sleep(d);
             }
-            let (index, segment) = match work.lock().unwrap().pop_front() {
-                Some(p) => p,
-                None => break,
-            };
-            let in_flight_seg = in_flight.as_ref().map(|v| (Arc::clone(v), index));
-            let res: SegmentResult = match policy.as_ref() {
-                Some(p) => run_with_retry(p, || {
-                    segment::download_one_segment(
-                        &u,
+            loop {
+                if abort.load(Ordering::Relaxed) || user_abort.load(Ordering::Relaxed) {
+                    break;
+                }
+                let next = {
+                    let mut q = work.lock().unwrap();
+                    match q.front() {
+                        Some(&(i, _))
+                            if lookahead
+                                .as_ref()
+                                .is_some_and(|l| !l.lock().unwrap().allows(i)) =>
+                        {
+                            None
+                        }
+                        Some(_) => q.pop_front(),
+                        None => break,
+                    }
+                };
+                let Some((index, segment)) = next else {
+                    // Sequential order: wait for the window to reach the next segment.
+                    std::thread::sleep(std::time::Duration::from_millis(10));
+                    continue;
+                };
+                let in_flight_seg = in_flight.as_ref().map(|v| (Arc::clone(v), index));
+                let hash_seg = hashes.as_ref().map(|h| (Arc::clone(h), index));
+                let res: SegmentResult = match policy.as_ref() {
+                    Some(p) => {
+                        // Each retry moves on to the next source URL (mirror failover).
+                        let mut attempt = 0;
+                        run_with_retry(p, &budget, || {
+                            let n = attempt;
+                            attempt += 1;
+                            segment::download_one_segment(
+                                &mut easy,
+                                u.for_segment(index, n),
+                                &h,
+                                u.if_range_for(index, n, ir.as_deref()),
+                                &segment,
+                                &st,
+                                in_flight_seg.clone(),
+                                hash_seg.clone(),
+                                &curl_opts,
+                                &seg_ctx,
+                            )
+                        })
+                    }
+                    None => segment::download_one_segment(
+                        &mut easy,
+                        u.for_segment(index, 0),
                         &h,
+                        u.if_range_for(index, 0, ir.as_deref()),
                         &segment,
                         &st,
-                        in_flight_seg.clone(),
-                        curl_opts,
-                    )
-                }),
-                None => {
-                    segment::download_one_segment(&u, &h, &segment, &st, in_flight_seg, curl_opts)
+                        in_flight_seg,
+                        hash_seg,
+                        &curl_opts,
+                        &seg_ctx,
+                    ),
+                };
+                if let Some(ref l) = lookahead {
+                    l.lock().unwrap().finish(index);
                 }
-            };
-            let _ = tx.send((index, res));
+                let _ = tx.send((index, res));
+            }
         }));
     }
     drop(tx);
 
     let mut first_error: Option<anyhow::Error> = None;
     let mut completed_since_send = 0usize;
+    let flush_every = ctx.progress_flush_every();
     let mut to_receive = count;
     while to_receive > 0 {
         let (index, res) = match rx.recv() {
@@ -105,8 +173,8 @@ pub(super) fn run_concurrent(
                 bitmap.set_completed(index);
                 completed_since_send += 1;
                 if let Some(progress_tx) = progress_tx {
-                    if completed_since_send >= COALESCE_PROGRESS_EVERY {
-                        let _ = progress_tx.try_send(bitmap.to_bytes(segment_count));
+                    if completed_since_send >= flush_every {
+                        let _ = progress_tx.send(bitmap.to_bytes(segment_count));
                         completed_since_send = 0;
                     }
                 }
@@ -131,8 +199,7 @@ pub(super) fn run_concurrent(
                     to_receive = to_receive.saturating_sub(drained);
                 }
                 if first_error.is_none() {
-                    first_error =
-                        Some(anyhow::anyhow!("{}", e).context(format!("segment {}", index)));
+                    first_error = Some(anyhow::Error::new(e).context(format!("segment {}", index)));
                 }
             }
         }
@@ -145,7 +212,7 @@ pub(super) fn run_concurrent(
     }
     if completed_since_send > 0 {
         if let Some(progress_tx) = progress_tx {
-            let _ = progress_tx.try_send(bitmap.to_bytes(segment_count));
+            let _ = progress_tx.send(bitmap.to_bytes(segment_count));
         }
     }
     for h in handles {
@@ -155,6 +222,11 @@ pub(super) fn run_concurrent(
             }
         }
     }
+    summary_out.retry_attempts = budget.used();
+    if user_abort.load(Ordering::Relaxed) {
+        // Segment errors from transfers cut short by the abort are expected; report the pause.
+        return Err(anyhow::anyhow!(JobAborted));
+    }
     if let Some(e) = first_error {
         return Err(e);
     }
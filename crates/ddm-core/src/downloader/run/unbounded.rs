@@ -1,59 +1,91 @@
 use anyhow::Result;
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
 
+use crate::checksum::SegmentHashes;
 use crate::control::JobAborted;
 use crate::downloader::segment;
-use crate::downloader::{CurlOptions, DownloadSummary, SegmentResult};
-use crate::retry::{classify, run_with_retry, ErrorKind, RetryPolicy};
+use crate::downloader::{
+    CurlOptions, DownloadSummary, SegmentResult, SegmentRunContext, SourceUrls,
+};
+use crate::retry::{classify, run_with_retry, ErrorKind, RetryBudget, RetryPolicy};
 use crate::segmenter::{Segment, SegmentBitmap};
 use crate::storage::StorageWriter;
 
 /// Run incomplete segments with one thread per segment (unbounded parallelism).
 pub fn run_unbounded(
-    url: String,
+    urls: SourceUrls,
     headers: HashMap<String, String>,
+    if_range: Option<String>,
     storage: StorageWriter,
     incomplete: Vec<(usize, Segment)>,
     segment_count: usize,
     retry_policy: Option<RetryPolicy>,
     bitmap: &mut SegmentBitmap,
     summary_out: &mut DownloadSummary,
-    progress_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    progress_tx: Option<&crate::downloader::BitmapSender>,
     in_flight_bytes: Option<Arc<Vec<AtomicU64>>>,
-    abort: Option<Arc<std::sync::atomic::AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<()> {
-    if abort
-        .as_ref()
-        .map(|a| a.load(Ordering::Relaxed))
-        .unwrap_or(false)
-    {
+    if ctx.is_cancelled() {
         return Err(anyhow::anyhow!(JobAborted));
     }
+    let budget = Arc::new(RetryBudget::new(
+        retry_policy.map_or(0, |p| p.max_job_retries),
+    ));
     type JoinErr = Box<dyn std::any::Any + Send>;
     let join_results: Vec<Result<(usize, SegmentResult), JoinErr>> = incomplete
         .into_iter()
         .map(|(index, segment)| {
-            let u = url.clone();
+            let u = urls.clone();
             let h = headers.clone();
+            let ir = if_range.clone();
             let st = storage.clone();
             let policy = retry_policy.clone();
-            let curl_opts = curl;
+            let curl_opts = curl.clone();
+            let seg_ctx = ctx.clone();
             let in_flight = in_flight_bytes.as_ref().map(|v| (Arc::clone(v), index));
-            std::thread::spawn(move || match policy.as_ref() {
-                Some(p) => run_with_retry(p, || {
-                    segment::download_one_segment(
-                        &u,
+            let hash_seg = segment_hashes.as_ref().map(|h| (Arc::clone(h), index));
+            let budget = Arc::clone(&budget);
+            std::thread::spawn(move || {
+                let mut easy = curl::easy::Easy::new();
+                match policy.as_ref() {
+                    Some(p) => {
+                        // Each retry moves on to the next source URL (mirror failover).
+                        let mut attempt = 0;
+                        run_with_retry(p, &budget, || {
+                            let n = attempt;
+                            attempt += 1;
+                            segment::download_one_segment(
+                                &mut easy,
+                                u.for_segment(index, n),
+                                &h,
+                                u.if_range_for(index, n, ir.as_deref()),
+                                &segment,
+                                &st,
+                                in_flight.clone(),
+                                hash_seg.clone(),
+                                &curl_opts,
+                                &seg_ctx,
+                            )
+                        })
+                    }
+                    None => segment::download_one_segment(
+                        &mut easy,
+                        u.for_segment(index, 0),
                         &h,
+                        u.if_range_for(index, 0, ir.as_deref()),
                         &segment,
                         &st,
-                        in_flight.clone(),
-                        curl_opts,
-                    )
-                }),
-                None => segment::download_one_segment(&u, &h, &segment, &st, in_flight, curl_opts),
+                        in_flight,
+                        hash_seg,
+                        &curl_opts,
+                        &seg_ctx,
+                    ),
+                }
             })
             .join()
             .map(|res| (index, res))
@@ -62,6 +94,7 @@ pub fn run_unbounded(
 
     let mut first_error: Option<anyhow::Error> = None;
     let mut completed_since_send = 0usize;
+    let flush_every = ctx.progress_flush_every();
     for join_result in join_results {
         let (index, res) = match join_result {
             Ok(pair) => pair,
@@ -77,8 +110,8 @@ pub fn run_unbounded(
                 bitmap.set_completed(index);
                 completed_since_send += 1;
                 if let Some(tx) = progress_tx {
-                    if completed_since_send >= super::COALESCE_PROGRESS_EVERY {
-                        let _ = tx.try_send(bitmap.to_bytes(segment_count));
+                    if completed_since_send >= flush_every {
+                        let _ = tx.send(bitmap.to_bytes(segment_count));
                         completed_since_send = 0;
                     }
                 }
@@ -91,17 +124,17 @@ pub fn run_unbounded(
                     summary_out.error_events += 1;
                 }
                 if first_error.is_none() {
-                    first_error =
-                        Some(anyhow::anyhow!("{}", e).context(format!("segment {}", index)));
+                    first_error = Some(anyhow::Error::new(e).context(format!("segment {}", index)));
                 }
             }
         }
     }
     if completed_since_send > 0 {
         if let Some(tx) = progress_tx {
-            let _ = tx.try_send(bitmap.to_bytes(segment_count));
+            let _ = tx.send(bitmap.to_bytes(segment_count));
         }
     }
+    summary_out.retry_attempts = budget.used();
     if let Some(e) = first_error {
         return Err(e);
     }
@@ -4,11 +4,17 @@
 //! Partial Content to avoid servers that ignore Range and return 200 with the
 //! full body (which would corrupt the temp file when written at segment offset).
 //! Validation is done in the write callback before writing any byte (pre-write).
+//! When an `If-Range` validator is sent, a 200 means the remote file changed.
+//! A non-identity `Content-Encoding` aborts the transfer in the header callback.
+//! A Content-Range total that differs from the known file size aborts before the first write.
+//! `file://` URLs have no status line or Content-Range; only the byte count is checked.
 
-use super::CurlOptions;
+use super::{CurlOptions, SegmentRunContext};
+use crate::checksum::SegmentHashes;
 use crate::retry::SegmentError;
 use crate::segmenter::Segment;
 use crate::storage::StorageWriter;
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::str;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -21,16 +27,27 @@ pub(super) type SegmentResult = Result<(), SegmentError>;
 /// Optional in-flight counter: (per-segment bytes vec, segment index). Updated in write callback.
 pub(super) type InFlightRef = Option<(Arc<Vec<AtomicU64>>, usize)>;
 
+/// Optional per-segment hash sink: (shared digests, segment index). Set only on success.
+pub(super) type HashRef = Option<(Arc<SegmentHashes>, usize)>;
+
 /// Downloads a single segment: GET with Range header, write body to storage at segment offset.
+/// `easy` is reset first, so a worker can pass the same handle for every segment it pulls and
+/// keep its connection (and TLS session) alive between requests.
 /// Validates 206 and Content-Range before writing any body; aborts on first write if not honored.
 /// If `in_flight` is Some, the segment's byte count is written so progress can sum in-flight bytes.
+/// If `if_range` is Some, it is sent as `If-Range` and a 200 reply fails with `RemoteChanged`.
+/// If `hashes` is Some, the body is hashed as it is written and the SHA-256 stored on success.
 pub(super) fn download_one_segment(
+    easy: &mut curl::easy::Easy,
     url: &str,
     custom_headers: &HashMap<String, String>,
+    if_range: Option<&str>,
     segment: &Segment,
     storage: &StorageWriter,
     in_flight: InFlightRef,
-    curl: CurlOptions,
+    hashes: HashRef,
+    curl: &CurlOptions,
+    ctx: &SegmentRunContext,
 ) -> SegmentResult {
     let bytes_written = Arc::new(AtomicU64::new(0));
     let bytes_written_in_cb = Arc::clone(&bytes_written);
@@ -39,28 +56,40 @@ pub(super) fn download_one_segment(
     let response_headers: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
     let response_headers_header = Arc::clone(&response_headers);
     let response_headers_write = Arc::clone(&response_headers);
+    let bad_encoding: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    let bad_encoding_cb = Arc::clone(&bad_encoding);
     let range_check: Arc<Mutex<Option<Result<(), u32>>>> = Arc::new(Mutex::new(None));
     let range_check_cb = Arc::clone(&range_check);
+    let total_mismatch: Arc<Mutex<Option<u64>>> = Arc::new(Mutex::new(None));
+    let total_mismatch_cb = Arc::clone(&total_mismatch);
+    let expected_total = ctx.expected_total;
+    let hasher: Option<Arc<Mutex<Sha256>>> =
+        hashes.as_ref().map(|_| Arc::new(Mutex::new(Sha256::new())));
+    let hasher_cb = hasher.clone();
     let segment_start = segment.start;
     let segment_end_inclusive = segment.end.saturating_sub(1);
     let storage = storage.clone();
+    let local = crate::url_model::is_file_url(url);
+    // The watchdog addresses segments by their in-flight slot.
+    let stall_index = in_flight
+        .as_ref()
+        .map(|(_, i)| *i)
+        .filter(|_| ctx.stalled.is_some());
+    if let Some(i) = stall_index {
+        ctx.clear_stall(i);
+    }
+    let stalled = || stall_index.is_some_and(|i| ctx.is_stalled(i));
 
-    let mut easy = curl::easy::Easy::new();
+    // Clears options but keeps the handle's connection cache.
+    easy.reset();
     easy.url(url).map_err(SegmentError::Curl)?;
-    easy.follow_location(true).map_err(SegmentError::Curl)?;
-    easy.max_redirections(10).map_err(SegmentError::Curl)?;
-    if let Some(speed) = curl.max_recv_speed {
-        easy.max_recv_speed(speed).map_err(SegmentError::Curl)?;
-    }
-    if let Some(sz) = curl.buffer_size {
-        easy.buffer_size(sz).map_err(SegmentError::Curl)?;
-    }
+    curl.apply_to_easy(easy).map_err(SegmentError::Curl)?;
     easy.connect_timeout(Duration::from_secs(30))
         .map_err(SegmentError::Curl)?;
     easy.low_speed_limit(1024).map_err(SegmentError::Curl)?;
     easy.low_speed_time(Duration::from_secs(60))
         .map_err(SegmentError::Curl)?;
-    easy.timeout(Duration::from_secs(3600))
+    easy.timeout(ctx.segment_timeout(segment.len()))
         .map_err(SegmentError::Curl)?;
 
     let range_str = format!("{}-{}", segment.start, segment_end_inclusive);
@@ -71,16 +100,34 @@ pub(super) fn download_one_segment(
         list.append(&format!("{}: {}", k.trim(), v.trim()))
             .map_err(SegmentError::Curl)?;
     }
-    if !custom_headers.is_empty() {
+    if let Some(v) = if_range {
+        list.append(&format!("If-Range: {}", v))
+            .map_err(SegmentError::Curl)?;
+    }
+    if !custom_headers.is_empty() || if_range.is_some() {
         easy.http_headers(list).map_err(SegmentError::Curl)?;
     }
 
+    let watch = ctx.cancel.is_some() || stall_index.is_some();
+    if watch {
+        easy.progress(true).map_err(SegmentError::Curl)?;
+    }
     {
         let mut transfer = easy.transfer();
+        if watch {
+            // Returning false aborts the transfer; bytes already written stay in the partial offsets.
+            transfer
+                .progress_function(|_, _, _, _| !ctx.is_cancelled() && !stalled())
+                .map_err(SegmentError::Curl)?;
+        }
         transfer
             .header_function(move |data| {
                 if let Ok(s) = str::from_utf8(data) {
                     let line = s.trim_end();
+                    if let Some(enc) = unexpected_content_encoding(line) {
+                        *bad_encoding_cb.lock().unwrap() = Some(enc);
+                        return false;
+                    }
                     if line.starts_with("HTTP/") {
                         let mut vec = response_headers_header.lock().unwrap();
                         vec.clear();
@@ -104,23 +151,29 @@ pub(super) fn download_one_segment(
                     let content_ok = parse_content_range(&headers)
                         .map(|(s, e)| s == segment_start && e == segment_end_inclusive)
                         .unwrap_or(false);
-                    let ok = status == Some(206) && content_ok;
+                    let ok = local || (status == Some(206) && content_ok);
                     *check = Some(if ok { Ok(()) } else { Err(status.unwrap_or(0)) });
+                    if let Some(reported) = content_range_total_mismatch(&headers, expected_total) {
+                        *total_mismatch_cb.lock().unwrap() = Some(reported);
+                        *check = Some(Err(206));
+                    }
                 }
                 if let Some(Err(_)) = *check {
                     return Ok(0);
                 }
                 let off = bytes_written_in_cb.fetch_add(data.len() as u64, Ordering::Relaxed);
-                if let Some((ref v, idx)) = in_flight {
-                    v.get(idx).map(|a| {
-                        a.store(
-                            bytes_written_in_cb.load(Ordering::Relaxed),
-                            Ordering::Relaxed,
-                        )
-                    });
-                }
                 match storage.write_at(segment_start + off, data) {
-                    Ok(()) => Ok(data.len()),
+                    Ok(()) => {
+                        // Published after the write so persisted partial offsets never run ahead of the file.
+                        if let Some((ref v, idx)) = in_flight {
+                            v.get(idx)
+                                .map(|a| a.store(off + data.len() as u64, Ordering::Relaxed));
+                        }
+                        if let Some(ref h) = hasher_cb {
+                            h.lock().unwrap().update(data);
+                        }
+                        Ok(data.len())
+                    }
                     Err(e) => {
                         let io_err = e.downcast::<std::io::Error>().unwrap_or_else(|e| {
                             std::io::Error::new(std::io::ErrorKind::Other, e.to_string())
@@ -133,29 +186,69 @@ pub(super) fn download_one_segment(
             .map_err(SegmentError::Curl)?;
         let perform_result = transfer.perform();
         if let Err(e) = perform_result {
+            if let Some(enc) = bad_encoding.lock().unwrap().take() {
+                return Err(SegmentError::UnexpectedEncoding(enc));
+            }
+            if e.is_aborted_by_callback() && !ctx.is_cancelled() && stalled() {
+                return Err(SegmentError::Stalled {
+                    received: bytes_written.load(Ordering::Relaxed),
+                });
+            }
             if e.is_write_error() {
+                if let Some(reported) = total_mismatch.lock().unwrap().take() {
+                    return Err(SegmentError::TotalSizeMismatch {
+                        expected: expected_total.unwrap_or_default(),
+                        reported,
+                    });
+                }
                 if let Some(Err(code)) = range_check.lock().unwrap().take() {
+                    if code == 200 && if_range.is_some() {
+                        return Err(SegmentError::RemoteChanged);
+                    }
                     return Err(SegmentError::InvalidRangeResponse(code));
                 }
                 if let Some(io_err) = storage_error.lock().unwrap().take() {
                     return Err(SegmentError::Storage(io_err));
                 }
             }
+            if e.is_partial_file() {
+                return Err(SegmentError::PartialTransfer {
+                    expected: segment.len(),
+                    received: bytes_written.load(Ordering::Relaxed),
+                });
+            }
             return Err(SegmentError::Curl(e));
         }
     }
 
-    let code = easy.response_code().map_err(SegmentError::Curl)? as u32;
-    if code < 200 || code >= 300 {
-        return Err(SegmentError::Http(code));
-    }
-    if code != 206 {
-        return Err(SegmentError::InvalidRangeResponse(code));
-    }
-    if let Some((start, end)) = parse_content_range(&response_headers.lock().unwrap()) {
-        if start != segment.start || end != segment_end_inclusive {
+    if !local {
+        super::options::log_redirects(
+            url,
+            easy.redirect_count().unwrap_or(0),
+            easy.effective_url().ok().flatten(),
+        );
+        let code = easy.response_code().map_err(SegmentError::Curl)? as u32;
+        if code < 200 || code >= 300 {
+            return Err(SegmentError::Http(code));
+        }
+        if code == 200 && if_range.is_some() {
+            return Err(SegmentError::RemoteChanged);
+        }
+        if code != 206 {
             return Err(SegmentError::InvalidRangeResponse(code));
         }
+        let headers = response_headers.lock().unwrap();
+        if let Some(reported) = content_range_total_mismatch(&headers, expected_total) {
+            return Err(SegmentError::TotalSizeMismatch {
+                expected: expected_total.unwrap_or_default(),
+                reported,
+            });
+        }
+        if let Some((start, end)) = parse_content_range(&headers) {
+            if start != segment.start || end != segment_end_inclusive {
+                return Err(SegmentError::InvalidRangeResponse(code));
+            }
+        }
     }
 
     let received = bytes_written.load(Ordering::Relaxed);
@@ -164,6 +257,11 @@ pub(super) fn download_one_segment(
         return Err(SegmentError::PartialTransfer { expected, received });
     }
 
+    if let (Some((sink, index)), Some(h)) = (hashes, hasher) {
+        let digest = std::mem::take(&mut *h.lock().unwrap()).finalize();
+        sink.set(index, hex::encode(digest));
+    }
+
     Ok(())
 }
 
@@ -174,22 +272,48 @@ pub(crate) fn parse_http_status(headers: &[String]) -> Option<u32> {
     part.parse().ok()
 }
 
+/// Returns the encoding if `line` is a `Content-Encoding` header other than `identity`.
+pub(crate) fn unexpected_content_encoding(line: &str) -> Option<String> {
+    const PREFIX: &str = "Content-Encoding:";
+    let line = line.trim();
+    if line.len() < PREFIX.len() || !line[..PREFIX.len()].eq_ignore_ascii_case(PREFIX) {
+        return None;
+    }
+    let value = line[PREFIX.len()..].trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("identity") {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Value of the first Content-Range header, e.g. "bytes 0-1023/4096".
+fn content_range_value(headers: &[String]) -> Option<&str> {
+    const PREFIX: &str = "Content-Range:";
+    headers.iter().map(|l| l.trim()).find_map(|line| {
+        (line.len() >= PREFIX.len() && line[..PREFIX.len()].eq_ignore_ascii_case(PREFIX))
+            .then(|| line[PREFIX.len()..].trim())
+    })
+}
+
 /// Parse Content-Range from response headers. Returns (start, end_inclusive) if present and valid.
 /// Format: "Content-Range: bytes start-end/total" or "bytes start-end/*".
 pub(crate) fn parse_content_range(headers: &[String]) -> Option<(u64, u64)> {
-    const PREFIX: &str = "Content-Range:";
-    for line in headers {
-        let line = line.trim();
-        if line.len() >= PREFIX.len() && line[..PREFIX.len()].eq_ignore_ascii_case(PREFIX) {
-            let rest = line[PREFIX.len()..].trim();
-            let rest = rest.strip_prefix("bytes")?.trim();
-            let (range, _) = rest.split_once('/')?;
-            let range = range.trim();
-            let (start_str, end_str) = range.split_once('-')?;
-            let start: u64 = start_str.trim().parse().ok()?;
-            let end_inclusive: u64 = end_str.trim().parse().ok()?;
-            return Some((start, end_inclusive));
-        }
-    }
-    None
+    let rest = content_range_value(headers)?.strip_prefix("bytes")?.trim();
+    let (range, _) = rest.split_once('/')?;
+    let (start_str, end_str) = range.trim().split_once('-')?;
+    let start: u64 = start_str.trim().parse().ok()?;
+    let end_inclusive: u64 = end_str.trim().parse().ok()?;
+    Some((start, end_inclusive))
+}
+
+/// The Content-Range total when it contradicts `expected` (None if they agree or either is unknown).
+pub(crate) fn content_range_total_mismatch(
+    headers: &[String],
+    expected: Option<u64>,
+) -> Option<u64> {
+    let expected = expected?;
+    let reported =
+        content_range_value(headers).and_then(crate::fetch_head::parse_content_range_total)?;
+    (reported != expected).then_some(reported)
 }
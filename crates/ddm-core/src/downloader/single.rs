@@ -2,7 +2,8 @@
 //!
 //! Writes the response body sequentially to storage starting at offset 0.
 
-use super::CurlOptions;
+use super::{CurlOptions, SegmentRunContext};
+use crate::control::JobAborted;
 use crate::storage::StorageWriter;
 use anyhow::{Context, Result};
 use std::collections::HashMap;
@@ -12,13 +13,14 @@ use std::sync::Arc;
 use std::time::Duration;
 
 /// Downloads a URL with a single GET (no Range), writing sequentially to `storage`.
-/// Returns the number of bytes written.
+/// Returns the number of bytes written, or `Err(JobAborted)` once `ctx.cancel` is set.
 pub fn download_single(
     url: &str,
     custom_headers: &HashMap<String, String>,
     storage: &StorageWriter,
     expected_len: Option<u64>,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> Result<u64> {
     let offset = Arc::new(AtomicU64::new(0));
     let offset_cb = Arc::clone(&offset);
@@ -26,21 +28,13 @@ pub fn download_single(
 
     let mut easy = curl::easy::Easy::new();
     easy.url(url).context("invalid URL")?;
-    easy.follow_location(true)?;
-    easy.max_redirections(10)?;
-    if let Some(speed) = curl.max_recv_speed {
-        easy.max_recv_speed(speed)
-            .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    }
-    if let Some(sz) = curl.buffer_size {
-        easy.buffer_size(sz)
-            .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
-    }
+    curl.apply_to_easy(&mut easy)?;
     easy.connect_timeout(Duration::from_secs(30))?;
     easy.low_speed_limit(1024)
         .map_err(|e| anyhow::anyhow!("curl: {}", e))?;
     easy.low_speed_time(Duration::from_secs(60))?;
-    easy.timeout(Duration::from_secs(3600))?;
+    easy.timeout(ctx.transfer_timeout())?;
+    easy.progress(ctx.cancel.is_some())?;
 
     let mut list = curl::easy::List::new();
     for (k, v) in custom_headers {
@@ -58,7 +52,11 @@ pub fn download_single(
             let _ = str::from_utf8(data);
             true
         })?;
-        transfer.write_function(move |data| {
+        transfer.progress_function(|_, _, _, _| !ctx.is_cancelled())?;
+        transfer.write_function(|data| {
+            if ctx.is_cancelled() {
+                return Ok(0);
+            }
             let off = offset_cb.fetch_add(data.len() as u64, Ordering::Relaxed);
             match storage.write_at(off, data) {
                 Ok(()) => Ok(data.len()),
@@ -68,11 +66,22 @@ pub fn download_single(
                 }
             }
         })?;
-        transfer.perform().context("GET request failed")?;
+        if let Err(e) = transfer.perform() {
+            if ctx.is_cancelled() {
+                return Err(anyhow::Error::new(JobAborted));
+            }
+            return Err(e).context("GET request failed");
+        }
     }
 
+    super::options::log_redirects(
+        url,
+        easy.redirect_count().unwrap_or(0),
+        easy.effective_url().ok().flatten(),
+    );
     let code = easy.response_code().context("no response code")?;
-    if code < 200 || code >= 300 {
+    // file:// transfers report no status code.
+    if !crate::url_model::is_file_url(url) && (code < 200 || code >= 300) {
         anyhow::bail!("GET {} returned HTTP {}", url, code);
     }
 
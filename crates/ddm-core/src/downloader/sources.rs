@@ -0,0 +1,90 @@
+//! Source URLs for one file: the primary URL plus optional mirrors serving the same bytes.
+
+/// URLs a job's segments are fetched from. Index 0 is the primary URL (the one probed, whose
+/// validators drive `If-Range`); the rest are mirrors. Segments are spread round-robin over all
+/// of them, and each retry of a segment moves on to the next URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceUrls {
+    urls: Vec<String>,
+}
+
+impl SourceUrls {
+    /// Primary URL plus mirrors (duplicates of the primary or of each other are dropped).
+    pub fn new(primary: &str, mirrors: &[String]) -> Self {
+        let mut urls = vec![primary.to_string()];
+        for m in mirrors {
+            if !urls.contains(m) {
+                urls.push(m.clone());
+            }
+        }
+        Self { urls }
+    }
+
+    /// The primary URL.
+    pub fn primary(&self) -> &str {
+        &self.urls[0]
+    }
+
+    /// True when at least one mirror besides the primary URL is configured.
+    pub fn has_mirrors(&self) -> bool {
+        self.urls.len() > 1
+    }
+
+    /// Index into the URL list for `attempt` (0-based) of segment `index`.
+    fn slot(&self, index: usize, attempt: u32) -> usize {
+        (index + attempt as usize) % self.urls.len()
+    }
+
+    /// URL for `attempt` (0-based) of segment `index`.
+    pub fn for_segment(&self, index: usize, attempt: u32) -> &str {
+        &self.urls[self.slot(index, attempt)]
+    }
+
+    /// `if_range` when that attempt goes to the primary URL; mirrors have their own validators.
+    pub fn if_range_for<'a>(
+        &self,
+        index: usize,
+        attempt: u32,
+        if_range: Option<&'a str>,
+    ) -> Option<&'a str> {
+        if_range.filter(|_| self.slot(index, attempt) == 0)
+    }
+}
+
+impl From<&str> for SourceUrls {
+    fn from(url: &str) -> Self {
+        Self::new(url, &[])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segments_round_robin_and_retries_rotate() {
+        let mirrors = vec![
+            "http://m1/f".to_string(),
+            "http://primary/f".to_string(),
+            "http://m2/f".to_string(),
+        ];
+        let urls = SourceUrls::new("http://primary/f", &mirrors);
+        assert!(urls.has_mirrors());
+        assert_eq!(urls.for_segment(0, 0), "http://primary/f");
+        assert_eq!(urls.for_segment(1, 0), "http://m1/f");
+        assert_eq!(urls.for_segment(5, 0), "http://m2/f");
+        assert_eq!(urls.for_segment(1, 1), "http://m2/f");
+        assert_eq!(urls.for_segment(1, 2), "http://primary/f");
+        assert_eq!(urls.if_range_for(0, 0, Some("\"e\"")), Some("\"e\""));
+        assert_eq!(urls.if_range_for(1, 0, Some("\"e\"")), None);
+        assert_eq!(urls.if_range_for(1, 2, Some("\"e\"")), Some("\"e\""));
+    }
+
+    #[test]
+    fn single_url_always_primary() {
+        let urls = SourceUrls::from("http://primary/f");
+        assert!(!urls.has_mirrors());
+        assert_eq!(urls.for_segment(7, 3), "http://primary/f");
+        assert_eq!(urls.if_range_for(7, 3, Some("v")), Some("v"));
+    }
+}
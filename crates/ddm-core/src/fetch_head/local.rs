@@ -0,0 +1,51 @@
+//! Metadata for `file://` URLs: stat the source instead of issuing a HEAD.
+
+use anyhow::{Context, Result};
+use std::time::UNIX_EPOCH;
+
+use super::{HeadResult, RangeSupport};
+use crate::url_model;
+
+/// Stat the file named by a `file://` URL. Ranges are always supported; the ETag is
+/// derived from mtime and size so resume notices when the source file changes.
+pub(super) fn probe_file(url: &str) -> Result<HeadResult> {
+    let path =
+        url_model::file_url_path(url).ok_or_else(|| anyhow::anyhow!("invalid file URL: {url}"))?;
+    let meta = std::fs::metadata(&path).with_context(|| format!("stat {}", path.display()))?;
+    if !meta.is_file() {
+        anyhow::bail!("{} is not a regular file", path.display());
+    }
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+    Ok(HeadResult {
+        content_length: Some(meta.len()),
+        range_support: RangeSupport::Supported,
+        etag: Some(format!("{:x}-{:x}", mtime, meta.len())),
+        last_modified: None,
+        content_disposition: None,
+        server_digest: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_regular_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("src.bin");
+        std::fs::write(&path, vec![7u8; 1234]).unwrap();
+        let url = url::Url::from_file_path(&path).unwrap().to_string();
+        let head = probe_file(&url).unwrap();
+        assert_eq!(head.content_length, Some(1234));
+        assert!(head.accepts_ranges());
+        assert!(head.etag.is_some());
+        assert!(probe_file("file:///nonexistent/ddm-test").is_err());
+        let dir_url = url::Url::from_file_path(dir.path()).unwrap().to_string();
+        assert!(probe_file(&dir_url).is_err());
+    }
+}
@@ -1,32 +1,65 @@
 //! HTTP HEAD / metadata probing.
 //!
 //! Uses the curl crate (libcurl) to fetch response headers and confirm
-//! `Content-Length`, `Accept-Ranges: bytes`, and capture ETag/Last-Modified
-//! for resume safety.
+//! `Content-Length`, range support (`Accept-Ranges`), and capture
+//! ETag/Last-Modified for resume safety. `file://` URLs are answered by
+//! stat-ing the local file instead (see `local`).
 
+mod local;
 mod parse;
+mod post;
+mod text;
+
+pub use post::resolve_post;
+pub use text::fetch_text;
 
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::str;
 use std::time::Duration;
 
+use crate::checksum::ChecksumAlgo;
+use crate::config::ProbeStrategy;
+use crate::downloader::CurlOptions;
+
+/// Observed range support for a response or host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+pub enum RangeSupport {
+    /// No information (no `Accept-Ranges` header); ranges may still work.
+    #[default]
+    Unknown,
+    /// Server has advertised `Accept-Ranges: bytes` or answered a Range request with 206.
+    Supported,
+    /// Server has explicitly refused ranges (`Accept-Ranges: none`, or 200 to a Range request).
+    NotSupported,
+}
+
 /// Result of a HEAD request: key headers needed for segmented download and resume.
 #[derive(Debug, Clone)]
 pub struct HeadResult {
     /// Total size in bytes, if `Content-Length` is present.
     pub content_length: Option<u64>,
-    /// True if server sent `Accept-Ranges: bytes`.
-    pub accept_ranges: bool,
+    /// Range support as advertised by `Accept-Ranges` (or discovered by a range probe).
+    pub range_support: RangeSupport,
     /// `ETag` value if present (used for resume validation).
     pub etag: Option<String>,
     /// `Last-Modified` value if present (used for resume validation).
     pub last_modified: Option<String>,
     /// `Content-Disposition` value if present (filename hint).
     pub content_disposition: Option<String>,
+    /// Whole-file digest the server advertised (`Digest` or `Content-MD5`), as lowercase hex.
+    pub server_digest: Option<(ChecksumAlgo, String)>,
+}
+
+impl HeadResult {
+    /// True if the server is known to honor byte ranges.
+    pub fn accepts_ranges(&self) -> bool {
+        self.range_support == RangeSupport::Supported
+    }
 }
 
-fn parse_content_range_total(value: &str) -> Option<u64> {
+/// Total size from a `Content-Range` value (`bytes a-b/total`); None when absent or `*`.
+pub(crate) fn parse_content_range_total(value: &str) -> Option<u64> {
     // Examples:
     // - "bytes 0-0/12345"
     // - "bytes 0-1023/12345"
@@ -41,16 +74,24 @@ fn parse_content_range_total(value: &str) -> Option<u64> {
 
 /// Performs a HEAD request and returns parsed metadata.
 ///
-/// Follows redirects. Optional custom headers can be passed (e.g. from a resolver).
+/// Follows redirects up to `curl.redirect_limit()`. Optional custom headers can be passed (e.g. from a resolver).
 /// Runs in the current thread; call from `spawn_blocking` if used from async code.
-pub fn probe(url: &str, custom_headers: &HashMap<String, String>) -> Result<HeadResult> {
+/// For `file://` URLs the file is stat-ed instead.
+pub fn probe(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
+) -> Result<HeadResult> {
+    if crate::url_model::is_file_url(url) {
+        return local::probe_file(url);
+    }
     let mut headers: Vec<String> = Vec::new();
+    let mut hops: Vec<String> = Vec::new();
 
     let mut easy = curl::easy::Easy::new();
     easy.url(url).context("invalid URL")?;
     easy.nobody(true)?; // HEAD request
-    easy.follow_location(true)?;
-    easy.max_redirections(10)?;
+    curl.apply_to_easy(&mut easy)?;
     easy.connect_timeout(Duration::from_secs(15))?;
     easy.timeout(Duration::from_secs(30))?;
 
@@ -73,12 +114,20 @@ pub fn probe(url: &str, custom_headers: &HashMap<String, String>) -> Result<Head
                 if line.starts_with("HTTP/") {
                     headers.clear();
                 }
+                if let Some((name, target)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("location") {
+                        hops.push(target.trim().to_string());
+                    }
+                }
                 headers.push(line.to_string());
             }
             true
         })?;
         transfer.perform().context("HEAD request failed")?;
     }
+    if !hops.is_empty() {
+        tracing::debug!(url, chain = %hops.join(" -> "), "probe followed redirects");
+    }
 
     let code = easy.response_code().context("no response code")?;
     if code < 200 || code >= 300 {
@@ -91,14 +140,22 @@ pub fn probe(url: &str, custom_headers: &HashMap<String, String>) -> Result<Head
 /// Performs a lightweight GET probe for metadata by requesting the first byte (`Range: bytes=0-0`).
 /// Useful when HEAD is blocked, or when HEAD does not advertise ranges/length but ranged GET does.
 ///
-/// This does not write a file; the response body (if any) is discarded.
-pub fn probe_range0(url: &str, custom_headers: &HashMap<String, String>) -> Result<HeadResult> {
+/// This does not write a file; the transfer stops at the first body chunk, so a server
+/// that ignores the Range and replies 200 does not send its whole body.
+pub fn probe_range0(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
+) -> Result<HeadResult> {
+    if crate::url_model::is_file_url(url) {
+        return local::probe_file(url);
+    }
     let mut headers: Vec<String> = Vec::new();
+    let mut hops: Vec<String> = Vec::new();
 
     let mut easy = curl::easy::Easy::new();
     easy.url(url).context("invalid URL")?;
-    easy.follow_location(true)?;
-    easy.max_redirections(10)?;
+    curl.apply_to_easy(&mut easy)?;
     easy.connect_timeout(Duration::from_secs(15))?;
     easy.timeout(Duration::from_secs(30))?;
     easy.range("0-0")?;
@@ -120,12 +177,25 @@ pub fn probe_range0(url: &str, custom_headers: &HashMap<String, String>) -> Resu
                 if line.starts_with("HTTP/") {
                     headers.clear();
                 }
+                if let Some((name, target)) = line.split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("location") {
+                        hops.push(target.trim().to_string());
+                    }
+                }
                 headers.push(line.to_string());
             }
             true
         })?;
-        transfer.write_function(|data| Ok(data.len()))?;
-        transfer.perform().context("GET range probe failed")?;
+        // Stop at the first body chunk; the status code and headers decide.
+        transfer.write_function(|_| Ok(0))?;
+        if let Err(e) = transfer.perform() {
+            if !e.is_write_error() {
+                return Err(e).context("GET range probe failed");
+            }
+        }
+    }
+    if !hops.is_empty() {
+        tracing::debug!(url, chain = %hops.join(" -> "), "probe followed redirects");
     }
 
     let code = easy.response_code().context("no response code")?;
@@ -134,41 +204,143 @@ pub fn probe_range0(url: &str, custom_headers: &HashMap<String, String>) -> Resu
     }
 
     let mut r = parse::parse_headers(&headers)?;
-    if code == 206 {
+    if code != 206 {
+        // Server ignored the Range request and sent the whole body.
+        r.range_support = RangeSupport::NotSupported;
+    } else {
         // Server honored the Range request: treat as range-capable even if Accept-Ranges is missing.
-        r.accept_ranges = true;
-        for line in &headers {
-            if let Some((name, value)) = line.split_once(':') {
-                if name.trim().eq_ignore_ascii_case("content-range") {
-                    if let Some(total) = parse_content_range_total(value.trim()) {
-                        r.content_length = Some(total);
-                    }
+        // Content-Length here is the 1-byte slice, so only the Content-Range total counts.
+        r.range_support = RangeSupport::Supported;
+        r.content_length = content_range_total(&headers);
+        r.server_digest = parse::server_digest(&headers, true);
+    }
+    Ok(r)
+}
+
+/// Asks for the whole body as an open-ended range (`Range: bytes=0-`) and returns the total
+/// size once the response headers arrive; the transfer is aborted before the body is read.
+/// The `Content-Range` total is used when present; with `bytes 0-N/*` the 206's own
+/// `Content-Length` (which then spans the whole file) stands in. None unless the reply is 206.
+pub fn probe_total_size(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
+) -> Result<Option<u64>> {
+    if crate::url_model::is_file_url(url) {
+        return Ok(local::probe_file(url)?.content_length);
+    }
+    let mut headers: Vec<String> = Vec::new();
+
+    let mut easy = curl::easy::Easy::new();
+    easy.url(url).context("invalid URL")?;
+    curl.apply_to_easy(&mut easy)?;
+    easy.connect_timeout(Duration::from_secs(15))?;
+    easy.timeout(Duration::from_secs(30))?;
+    easy.range("0-")?;
+
+    let mut list = curl::easy::List::new();
+    for (k, v) in custom_headers {
+        list.append(&format!("{}: {}", k.trim(), v.trim()))?;
+    }
+    if !custom_headers.is_empty() {
+        easy.http_headers(list)?;
+    }
+
+    {
+        let mut transfer = easy.transfer();
+        transfer.header_function(|data| {
+            if let Ok(s) = str::from_utf8(data) {
+                let line = s.trim_end();
+                if line.starts_with("HTTP/") {
+                    headers.clear();
                 }
+                headers.push(line.to_string());
+            }
+            true
+        })?;
+        // Stop at the first body chunk; only the headers are needed.
+        transfer.write_function(|_| Ok(0))?;
+        if let Err(e) = transfer.perform() {
+            if !e.is_write_error() {
+                return Err(e).context("GET open range probe failed");
             }
         }
     }
-    Ok(r)
+
+    let code = easy.response_code().context("no response code")?;
+    if code != 206 {
+        return Ok(None);
+    }
+    Ok(content_range_total(&headers).or(parse::parse_headers(&headers)?.content_length))
+}
+
+/// Total from the `Content-Range` header among `lines`, if any.
+fn content_range_total(lines: &[String]) -> Option<u64> {
+    lines.iter().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if name.trim().eq_ignore_ascii_case("content-range") {
+            parse_content_range_total(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+/// Metadata probe following `strategy`. `HeadOnly` never sends a second request and fails
+/// when HEAD leaves the length or range support unknown; an explicit `Accept-Ranges: none`
+/// counts as known.
+pub fn probe_with_strategy(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
+    strategy: ProbeStrategy,
+) -> Result<HeadResult> {
+    match strategy {
+        ProbeStrategy::BestEffort => probe_best_effort(url, custom_headers, curl),
+        ProbeStrategy::RangeOnly => probe_range0(url, custom_headers, curl),
+        ProbeStrategy::HeadOnly => {
+            let r = probe(url, custom_headers, curl)?;
+            if r.content_length.is_none() {
+                anyhow::bail!(
+                    "HEAD {} sent no Content-Length (probe_strategy = head_only)",
+                    url
+                );
+            }
+            if r.range_support == RangeSupport::Unknown {
+                anyhow::bail!(
+                    "HEAD {} sent no Accept-Ranges (probe_strategy = head_only)",
+                    url
+                );
+            }
+            Ok(r)
+        }
+    }
 }
 
 /// Best-effort metadata probe.
 ///
 /// - Tries HEAD first.
+/// - If HEAD succeeds but doesn't provide enough info (range support unknown or no
+///   length), also tries `probe_range0` and merges the results. An explicit
+///   `Accept-Ranges: none` skips the range probe.
 /// - If HEAD fails, falls back to `probe_range0`.
-/// - If HEAD succeeds but doesn't provide enough info (no ranges or no length),
-///   also tries `probe_range0` and merges the results.
 pub fn probe_best_effort(
     url: &str,
     custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
 ) -> Result<HeadResult> {
-    let head = probe(url, custom_headers);
+    let head = probe(url, custom_headers, curl);
     match head {
         Ok(mut r) => {
-            if r.accept_ranges && r.content_length.is_some() {
+            let complete = r.accepts_ranges() && r.content_length.is_some();
+            if complete || r.range_support == RangeSupport::NotSupported {
                 return Ok(r);
             }
-            if let Ok(r2) = probe_range0(url, custom_headers) {
-                // Merge: prefer the more capable/more complete result.
-                r.accept_ranges |= r2.accept_ranges;
+            if let Ok(r2) = probe_range0(url, custom_headers, curl) {
+                // Merge: the range probe settles unknown support; keep HEAD's other fields.
+                if r2.range_support != RangeSupport::Unknown {
+                    r.range_support = r2.range_support;
+                }
                 if r.content_length.is_none() {
                     r.content_length = r2.content_length;
                 }
@@ -181,9 +353,12 @@ pub fn probe_best_effort(
                 if r.last_modified.is_none() {
                     r.last_modified = r2.last_modified;
                 }
+                if r.server_digest.is_none() {
+                    r.server_digest = r2.server_digest;
+                }
             }
             Ok(r)
         }
-        Err(_) => probe_range0(url, custom_headers),
+        Err(_) => probe_range0(url, custom_headers, curl),
     }
 }
@@ -2,12 +2,13 @@
 
 use anyhow::Result;
 
-use super::HeadResult;
+use super::{HeadResult, RangeSupport};
+use crate::checksum::{self, ChecksumAlgo};
 
 /// Parse collected header lines into HeadResult.
 pub(crate) fn parse_headers(lines: &[String]) -> Result<HeadResult> {
     let mut content_length = None;
-    let mut accept_ranges = false;
+    let mut range_support = RangeSupport::Unknown;
     let mut etag = None;
     let mut last_modified = None;
     let mut content_disposition = None;
@@ -26,7 +27,12 @@ pub(crate) fn parse_headers(lines: &[String]) -> Result<HeadResult> {
                 }
             }
             if name.eq_ignore_ascii_case("accept-ranges") {
-                accept_ranges = value.eq_ignore_ascii_case("bytes");
+                // "bytes" = supported, "none" = explicit refusal; other units tell us nothing.
+                if value.eq_ignore_ascii_case("bytes") {
+                    range_support = RangeSupport::Supported;
+                } else if value.eq_ignore_ascii_case("none") {
+                    range_support = RangeSupport::NotSupported;
+                }
             }
             if name.eq_ignore_ascii_case("etag") {
                 etag = Some(value.trim_matches('"').to_string());
@@ -42,13 +48,36 @@ pub(crate) fn parse_headers(lines: &[String]) -> Result<HeadResult> {
 
     Ok(HeadResult {
         content_length,
-        accept_ranges,
+        range_support,
         etag,
         last_modified,
         content_disposition,
+        server_digest: server_digest(lines, false),
     })
 }
 
+/// Whole-file digest from a `Digest` header, else from `Content-MD5`. On a partial (206)
+/// response `Content-MD5` covers only the slice, so only `Digest` counts there.
+pub(crate) fn server_digest(lines: &[String], partial: bool) -> Option<(ChecksumAlgo, String)> {
+    let value = |wanted: &str| {
+        lines.iter().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case(wanted)
+                .then(|| value.trim())
+        })
+    };
+    if let Some(d) = value("digest").and_then(checksum::parse_digest_header) {
+        return Some(d);
+    }
+    if partial {
+        return None;
+    }
+    value("content-md5")
+        .and_then(checksum::parse_content_md5)
+        .map(|hex| (ChecksumAlgo::Md5, hex))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -62,7 +91,7 @@ mod tests {
         ];
         let r = parse_headers(&lines).unwrap();
         assert_eq!(r.content_length, Some(12345));
-        assert!(r.accept_ranges);
+        assert_eq!(r.range_support, RangeSupport::Supported);
         assert!(r.etag.is_none());
     }
 
@@ -88,7 +117,15 @@ mod tests {
         ];
         let r = parse_headers(&lines).unwrap();
         assert_eq!(r.content_length, Some(999));
-        assert!(!r.accept_ranges);
+        assert_eq!(r.range_support, RangeSupport::NotSupported);
+    }
+
+    #[test]
+    fn parse_headers_missing_accept_ranges_is_unknown() {
+        let lines = ["Content-Length: 999".to_string()];
+        let r = parse_headers(&lines).unwrap();
+        assert_eq!(r.range_support, RangeSupport::Unknown);
+        assert!(!r.accepts_ranges());
     }
 
     #[test]
@@ -102,4 +139,23 @@ mod tests {
             .unwrap()
             .contains("report.pdf"));
     }
+
+    #[test]
+    fn parse_headers_server_digest() {
+        let md5 = "b1946ac92492d2347c6235b4d2611184";
+        let lines = ["Content-MD5: sZRqySSS0jR8YjW00mERhA==".to_string()];
+        let r = parse_headers(&lines).unwrap();
+        assert_eq!(r.server_digest, Some((ChecksumAlgo::Md5, md5.to_string())));
+        // A 206's Content-MD5 is the slice's digest.
+        assert_eq!(server_digest(&lines, true), None);
+
+        let lines = [
+            "Content-MD5: sZRqySSS0jR8YjW00mERhA==".to_string(),
+            "Digest: SHA-256=WJG1tSLV3whtD/CxEPvZ0hu0/HFjrzTQgoai6Eb2vgM=".to_string(),
+        ];
+        let sha = "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03";
+        let expected = Some((ChecksumAlgo::Sha256, sha.to_string()));
+        assert_eq!(parse_headers(&lines).unwrap().server_digest, expected);
+        assert_eq!(server_digest(&lines, true), expected);
+    }
 }
@@ -0,0 +1,132 @@
+//! Replay a captured POST (from HAR) to obtain the real download URL.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::str;
+use std::time::Duration;
+
+use crate::downloader::CurlOptions;
+use crate::resolver::PostRequest;
+
+/// Sends `post` to `url` without following redirects and returns the redirect target
+/// (the `Location` of a 3xx reply, resolved against `url`). The response body is discarded.
+/// Flows that return the file directly in the POST body are not supported.
+pub fn resolve_post(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    post: &PostRequest,
+    curl: &CurlOptions,
+) -> Result<String> {
+    let mut headers: Vec<String> = Vec::new();
+
+    let mut easy = curl::easy::Easy::new();
+    easy.url(url).context("invalid URL")?;
+    easy.post(true)?;
+    easy.post_fields_copy(post.body.as_bytes())?;
+    curl.apply_to_easy(&mut easy)?;
+    easy.follow_location(false)?;
+    easy.connect_timeout(Duration::from_secs(15))?;
+    easy.timeout(Duration::from_secs(30))?;
+
+    let mut list = curl::easy::List::new();
+    for (k, v) in custom_headers {
+        list.append(&format!("{}: {}", k.trim(), v.trim()))?;
+    }
+    if let Some(ref ct) = post.content_type {
+        list.append(&format!("Content-Type: {}", ct))?;
+    }
+    easy.http_headers(list)?;
+
+    {
+        let mut transfer = easy.transfer();
+        transfer.header_function(|data| {
+            if let Ok(s) = str::from_utf8(data) {
+                headers.push(s.trim_end().to_string());
+            }
+            true
+        })?;
+        transfer.write_function(|data| Ok(data.len()))?;
+        transfer.perform().context("POST request failed")?;
+    }
+
+    let code = easy.response_code().context("no response code")?;
+    if !(300..400).contains(&code) {
+        anyhow::bail!(
+            "POST {} returned HTTP {} without a redirect to the download URL",
+            url,
+            code
+        );
+    }
+    let location = headers
+        .iter()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+        .map(|(_, v)| v.trim().to_string())
+        .ok_or_else(|| anyhow::anyhow!("POST {} redirect has no Location header", url))?;
+    let target = url::Url::parse(url)
+        .and_then(|base| base.join(&location))
+        .with_context(|| format!("invalid redirect Location: {}", location))?;
+    Ok(target.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// One-shot server: replies with `response` and returns the raw request it received.
+    fn one_shot(response: &'static str) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/download", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_millis(500)))
+                .unwrap();
+            let mut req = Vec::new();
+            let mut buf = [0u8; 4096];
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                req.extend_from_slice(&buf[..n]);
+                if String::from_utf8_lossy(&req).contains("{\"id\":42}") {
+                    break;
+                }
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            String::from_utf8_lossy(&req).to_string()
+        });
+        (url, handle)
+    }
+
+    fn post() -> PostRequest {
+        PostRequest {
+            body: "{\"id\":42}".to_string(),
+            content_type: Some("application/json".to_string()),
+        }
+    }
+
+    #[test]
+    fn resolve_post_returns_redirect_target() {
+        let (url, server) = one_shot(
+            "HTTP/1.1 302 Found\r\nLocation: /files/signed.zip?t=1\r\nContent-Length: 0\r\n\r\n",
+        );
+        let target = resolve_post(&url, &HashMap::new(), &post(), &CurlOptions::default()).unwrap();
+        assert!(target.ends_with("/files/signed.zip?t=1"), "{target}");
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /api/download"));
+        assert!(request.contains("Content-Type: application/json"));
+        assert!(request.contains("{\"id\":42}"));
+    }
+
+    #[test]
+    fn resolve_post_without_redirect_fails() {
+        let (url, server) = one_shot("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+        let err =
+            resolve_post(&url, &HashMap::new(), &post(), &CurlOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("without a redirect"));
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,110 @@
+//! Small GET of a text resource (e.g. a `SHA256SUMS` file) into memory.
+
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::downloader::CurlOptions;
+
+/// Largest body [`fetch_text`] accepts; checksum lists are a few KiB.
+const MAX_TEXT_BYTES: usize = 1024 * 1024;
+
+/// GETs `url` (following redirects) and returns the body as UTF-8 (lossy). Fails on a non-2xx
+/// status or a body over 1 MiB. Runs in the current thread; call from `spawn_blocking`.
+pub fn fetch_text(
+    url: &str,
+    custom_headers: &HashMap<String, String>,
+    curl: &CurlOptions,
+) -> Result<String> {
+    let mut body: Vec<u8> = Vec::new();
+
+    let mut easy = curl::easy::Easy::new();
+    easy.url(url).context("invalid URL")?;
+    curl.apply_to_easy(&mut easy)?;
+    easy.connect_timeout(Duration::from_secs(15))?;
+    easy.timeout(Duration::from_secs(60))?;
+
+    let mut list = curl::easy::List::new();
+    for (k, v) in custom_headers {
+        list.append(&format!("{}: {}", k.trim(), v.trim()))?;
+    }
+    if !custom_headers.is_empty() {
+        easy.http_headers(list)?;
+    }
+
+    let mut too_large = false;
+    {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            if body.len() + data.len() > MAX_TEXT_BYTES {
+                too_large = true;
+                return Ok(0); // abort transfer
+            }
+            body.extend_from_slice(data);
+            Ok(data.len())
+        })?;
+        let res = transfer.perform();
+        drop(transfer);
+        if too_large {
+            anyhow::bail!("GET {}: body larger than {} bytes", url, MAX_TEXT_BYTES);
+        }
+        res.with_context(|| format!("GET {} failed", url))?;
+    }
+
+    let code = easy.response_code().context("no response code")?;
+    // file:// transfers report no status code.
+    if !crate::url_model::is_file_url(url) && !(200..300).contains(&code) {
+        anyhow::bail!("GET {} returned HTTP {}", url, code);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    /// Serves `/old` as a 302 to `/new` and `/new` as "moved"; one request per connection.
+    fn redirect_server() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { break };
+                let mut buf = [0u8; 4096];
+                let n = stream.read(&mut buf).unwrap_or(0);
+                let response = if buf[..n].starts_with(b"GET /old ") {
+                    "HTTP/1.1 302 Found\r\nLocation: /new\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                } else {
+                    "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nmoved"
+                };
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        base
+    }
+
+    #[test]
+    fn max_redirects_zero_stops_at_the_redirect() {
+        let url = format!("{}/old", redirect_server());
+        let text = fetch_text(&url, &HashMap::new(), &CurlOptions::default()).unwrap();
+        assert_eq!(text, "moved");
+
+        let no_follow = CurlOptions {
+            max_redirects: Some(0),
+            ..CurlOptions::default()
+        };
+        let err = fetch_text(&url, &HashMap::new(), &no_follow).unwrap_err();
+        assert!(err.to_string().contains("HTTP 302"), "{err:#}");
+    }
+
+    #[test]
+    fn fetch_text_reads_file_url() {
+        let mut f = tempfile::NamedTempFile::new().unwrap();
+        f.write_all(b"abc  file.iso\n").unwrap();
+        f.flush().unwrap();
+        let url = url::Url::from_file_path(f.path()).unwrap().to_string();
+        let text = fetch_text(&url, &HashMap::new(), &CurlOptions::default()).unwrap();
+        assert_eq!(text, "abc  file.iso\n");
+    }
+}
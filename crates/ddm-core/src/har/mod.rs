@@ -2,7 +2,8 @@
 //!
 //! Detects the 302 → direct file URL pattern: follows redirects from entries and
 //! returns the final URL. Optionally extracts Cookie from the request (only when
-//! the caller requests it, e.g. --allow-cookies).
+//! the caller requests it, e.g. --allow-cookies), and captures POST bodies for
+//! replay (only with --allow-post).
 
 mod parse;
 mod resolve;
@@ -35,7 +36,7 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(har.as_bytes()).unwrap();
         f.flush().unwrap();
-        let spec = resolve_har(f.path(), false).unwrap();
+        let spec = resolve_har(f.path(), false, false).unwrap();
         assert_eq!(spec.url, "https://cdn.example.com/file.zip");
         assert!(spec.headers.is_empty());
     }
@@ -56,7 +57,7 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(har.as_bytes()).unwrap();
         f.flush().unwrap();
-        let spec = resolve_har(f.path(), false).unwrap();
+        let spec = resolve_har(f.path(), false, false).unwrap();
         assert_eq!(spec.url, "https://direct.example.com/f.bin");
     }
 
@@ -79,7 +80,7 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(har.as_bytes()).unwrap();
         f.flush().unwrap();
-        let spec = resolve_har(f.path(), true).unwrap();
+        let spec = resolve_har(f.path(), true, false).unwrap();
         assert_eq!(spec.url, "https://cdn.example.com/file.zip");
         assert_eq!(
             spec.headers.get("Cookie").map(|s| s.as_str()),
@@ -87,13 +88,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn resolve_har_merges_all_cookies() {
+        let har = r#"{
+            "log": {
+                "version": "1.2",
+                "entries": [
+                    {
+                        "request": {
+                            "url": "https://cdn.example.com/file.zip",
+                            "headers": [
+                                { "name": "cookie", "value": "session=abc123; theme=dark" },
+                                { "name": "Cookie", "value": "cf_clearance=xyz" }
+                            ],
+                            "cookies": [
+                                { "name": "session", "value": "abc123", "domain": "example.com" },
+                                { "name": "auth", "value": "t0k=n" }
+                            ]
+                        },
+                        "response": { "status": 200, "headers": [] }
+                    }
+                ]
+            }
+        }"#;
+        let mut f = NamedTempFile::new().unwrap();
+        f.write_all(har.as_bytes()).unwrap();
+        f.flush().unwrap();
+        let spec = resolve_har(f.path(), true, false).unwrap();
+        assert_eq!(
+            spec.headers.get("Cookie").map(|s| s.as_str()),
+            Some("session=abc123; theme=dark; cf_clearance=xyz; auth=t0k=n")
+        );
+        let spec = resolve_har(f.path(), false, false).unwrap();
+        assert!(spec.headers.is_empty());
+    }
+
     #[test]
     fn resolve_har_empty_entries_err() {
         let har = r#"{"log":{"version":"1.2","entries":[]}}"#;
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(har.as_bytes()).unwrap();
         f.flush().unwrap();
-        assert!(resolve_har(f.path(), false).is_err());
+        assert!(resolve_har(f.path(), false, false).is_err());
     }
 
     #[test]
@@ -126,7 +162,49 @@ mod tests {
         let mut f = NamedTempFile::new().unwrap();
         f.write_all(har.as_bytes()).unwrap();
         f.flush().unwrap();
-        let spec = resolve_har(f.path(), false).unwrap();
+        let spec = resolve_har(f.path(), false, false).unwrap();
         assert_eq!(spec.url, "https://cdn.example.com/file.zip");
     }
+
+    #[test]
+    fn resolve_har_post_requires_allow_post() {
+        let har = r#"{
+            "log": {
+                "version": "1.2",
+                "entries": [
+                    {
+                        "request": {
+                            "method": "POST",
+                            "url": "https://example.com/api/download",
+                            "headers": [],
+                            "postData": { "mimeType": "application/json", "text": "{\"id\":42}" }
+                        },
+                        "response": { "status": 302, "redirectURL": "https://cdn.example.com/signed.zip", "headers": [] }
+                    }
+                ]
+            }
+        }"#;
+        let mut f = NamedTempFile::new().unwrap();
+        f.write_all(har.as_bytes()).unwrap();
+        f.flush().unwrap();
+        let err = resolve_har(f.path(), false, false).unwrap_err();
+        assert!(err.to_string().contains("--allow-post"));
+
+        let spec = resolve_har(f.path(), false, true).unwrap();
+        assert_eq!(spec.url, "https://example.com/api/download");
+        let post = spec.post.expect("post captured");
+        assert_eq!(post.body, "{\"id\":42}");
+        assert_eq!(post.content_type.as_deref(), Some("application/json"));
+    }
+
+    #[test]
+    fn resolve_har_get_has_no_post() {
+        let har = r#"{"log":{"version":"1.2","entries":[
+            {"request":{"url":"https://direct.example.com/f.bin","headers":[]},
+             "response":{"status":200,"headers":[]}}]}}"#;
+        let mut f = NamedTempFile::new().unwrap();
+        f.write_all(har.as_bytes()).unwrap();
+        f.flush().unwrap();
+        assert!(resolve_har(f.path(), false, true).unwrap().post.is_none());
+    }
 }
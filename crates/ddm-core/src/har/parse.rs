@@ -21,9 +21,27 @@ pub struct HarEntry {
 
 #[derive(Debug, Deserialize)]
 pub struct HarRequest {
+    #[serde(default = "default_method")]
+    pub method: String,
     pub url: String,
     #[serde(default)]
     pub headers: Vec<HarHeader>,
+    #[serde(default, rename = "postData")]
+    pub post_data: Option<HarPostData>,
+    #[serde(default)]
+    pub cookies: Vec<HarCookie>,
+}
+
+fn default_method() -> String {
+    "GET".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HarPostData {
+    #[serde(default, rename = "mimeType")]
+    pub mime_type: Option<String>,
+    #[serde(default)]
+    pub text: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -36,6 +54,12 @@ pub struct HarResponse {
     pub headers: Vec<HarHeader>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct HarCookie {
+    pub name: String,
+    pub value: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct HarHeader {
     pub name: String,
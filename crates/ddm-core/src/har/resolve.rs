@@ -4,9 +4,9 @@ use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
 
-use crate::resolver::ResolvedJobSpec;
+use crate::resolver::{PostRequest, ResolvedJobSpec};
 
-use super::parse::{HarEntry, HarHeader, HarLog};
+use super::parse::{HarEntry, HarHeader, HarLog, HarRequest};
 
 /// Resolves a HAR file to a direct URL (and optional headers).
 ///
@@ -15,9 +15,17 @@ use super::parse::{HarEntry, HarHeader, HarLog};
 /// an unrelated redirect when the HAR has mixed entries. If no such entry exists,
 /// falls back to the previous redirect-chain behavior.
 ///
-/// If `include_cookies` is true, the `Cookie` header from the chosen request
-/// is included (for cookie-based CDN auth).
-pub fn resolve_har(path: &Path, include_cookies: bool) -> Result<ResolvedJobSpec> {
+/// If `include_cookies` is true, every cookie the chosen request sent (all `Cookie`
+/// headers plus the HAR `cookies` list) is included as one `Cookie` header (for
+/// cookie-based CDN auth).
+///
+/// If the chosen request is a POST, its body and content type are captured so the
+/// scheduler can replay it; this requires `allow_post` since bodies may carry secrets.
+pub fn resolve_har(
+    path: &Path,
+    include_cookies: bool,
+    allow_post: bool,
+) -> Result<ResolvedJobSpec> {
     let bytes =
         std::fs::read(path).with_context(|| format!("read HAR file: {}", path.display()))?;
     let har: HarLog = serde_json::from_slice(&bytes)
@@ -54,16 +62,33 @@ pub fn resolve_har(path: &Path, include_cookies: bool) -> Result<ResolvedJobSpec
     let final_url = entry.request.url.clone();
     let mut headers = HashMap::new();
     if include_cookies {
-        if let Some(cookie) = get_header(&entry.request.headers, "Cookie") {
-            if !cookie.is_empty() {
-                headers.insert("Cookie".to_string(), cookie.to_string());
-            }
+        if let Some(cookie) = cookie_header(&entry.request) {
+            headers.insert("Cookie".to_string(), cookie);
         }
     }
 
+    let post = if entry.request.method.eq_ignore_ascii_case("POST") {
+        if !allow_post {
+            anyhow::bail!(
+                "HAR download entry is a POST to {}; pass --allow-post to replay its body",
+                final_url
+            );
+        }
+        let data = entry.request.post_data.as_ref();
+        Some(PostRequest {
+            body: data.and_then(|d| d.text.clone()).unwrap_or_default(),
+            content_type: data
+                .and_then(|d| d.mime_type.clone())
+                .filter(|m| !m.is_empty()),
+        })
+    } else {
+        None
+    };
+
     Ok(ResolvedJobSpec {
         url: final_url,
         headers,
+        post,
     })
 }
 
@@ -94,6 +119,34 @@ fn select_download_entry(entries: &[HarEntry]) -> Option<usize> {
         .map(|(i, _)| i)
 }
 
+/// All cookies of `request` as one `Cookie` value (`a=1; b=2`), in the order sent. A name
+/// seen twice keeps its first value. None when the request carried no cookies.
+fn cookie_header(request: &HarRequest) -> Option<String> {
+    let from_headers = request
+        .headers
+        .iter()
+        .filter(|h| h.name.eq_ignore_ascii_case("Cookie"))
+        .flat_map(|h| h.value.split(';'))
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            Some((name.trim(), value.trim()))
+        });
+    let from_list = request
+        .cookies
+        .iter()
+        .map(|c| (c.name.trim(), c.value.trim()));
+    let mut seen: Vec<&str> = Vec::new();
+    let mut pairs: Vec<String> = Vec::new();
+    for (name, value) in from_headers.chain(from_list) {
+        if name.is_empty() || seen.contains(&name) {
+            continue;
+        }
+        seen.push(name);
+        pairs.push(format!("{name}={value}"));
+    }
+    (!pairs.is_empty()).then(|| pairs.join("; "))
+}
+
 fn get_header<'a>(headers: &'a [HarHeader], name: &str) -> Option<&'a str> {
     headers
         .iter()
@@ -0,0 +1,166 @@
+//! Per-host circuit breaker: after repeated connection failures a host's jobs are skipped
+//! (left queued) until a cooldown passes.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use super::{HostFilter, HostKey, HostPolicy};
+
+/// Default for `circuit_failure_threshold`.
+pub const DEFAULT_CIRCUIT_FAILURE_THRESHOLD: u32 = 3;
+/// Default for `circuit_window_secs`.
+pub const DEFAULT_CIRCUIT_WINDOW_SECS: u64 = 60;
+/// Default for `circuit_cooldown_secs`.
+pub const DEFAULT_CIRCUIT_COOLDOWN_SECS: u64 = 300;
+
+/// When to open a host's circuit: `threshold` consecutive failures within `window`
+/// keep it open for `cooldown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreaker {
+    pub threshold: u32,
+    pub window: Duration,
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+            window: Duration::from_secs(DEFAULT_CIRCUIT_WINDOW_SECS),
+            cooldown: Duration::from_secs(DEFAULT_CIRCUIT_COOLDOWN_SECS),
+        }
+    }
+}
+
+impl HostPolicy {
+    /// Record that the host of `url` could not be reached. Failures older than the
+    /// breaker's window start a new count; reaching the threshold trips the circuit.
+    /// Returns true when this failure opened it.
+    pub fn record_connect_failure(
+        &mut self,
+        url: &str,
+        breaker: &CircuitBreaker,
+        now: Instant,
+    ) -> Result<bool> {
+        let entry = self.entry_mut_for_url(url)?;
+        let fresh = entry
+            .first_failure_at
+            .is_some_and(|at| now.saturating_duration_since(at) <= breaker.window);
+        if !fresh {
+            entry.consecutive_failures = 0;
+            entry.first_failure_at = Some(now);
+        }
+        entry.consecutive_failures = entry.consecutive_failures.saturating_add(1);
+        if entry.consecutive_failures < breaker.threshold.max(1) {
+            return Ok(false);
+        }
+        self.trip_circuit(url, breaker.cooldown, now)?;
+        Ok(true)
+    }
+
+    /// Record that the host of `url` answered; clears its failure count and closes the circuit.
+    pub fn record_connect_success(&mut self, url: &str) -> Result<()> {
+        let entry = self.entry_mut_for_url(url)?;
+        entry.consecutive_failures = 0;
+        entry.first_failure_at = None;
+        entry.circuit_open_until = None;
+        Ok(())
+    }
+
+    /// Open the circuit for the host of `url` until `now + cooldown`.
+    pub fn trip_circuit(&mut self, url: &str, cooldown: Duration, now: Instant) -> Result<()> {
+        let entry = self.entry_mut_for_url(url)?;
+        entry.circuit_open_until = Some(now + cooldown);
+        entry.consecutive_failures = 0;
+        entry.first_failure_at = None;
+        Ok(())
+    }
+
+    /// Until when the circuit for `key` is open, or None when jobs may run.
+    pub fn circuit_open_until(&self, key: &HostKey, now: Instant) -> Option<Instant> {
+        self.get(key)?
+            .circuit_open_until
+            .filter(|&until| until > now)
+    }
+
+    /// Origins whose circuit is open at `now`.
+    pub fn open_circuits(&self, now: Instant) -> Vec<HostKey> {
+        self.entries
+            .keys()
+            .filter(|k| self.circuit_open_until(k, now).is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// `host:port` of every origin whose circuit is open at `now`, sorted.
+    pub fn open_circuit_hosts(&self, now: Instant) -> Vec<String> {
+        let mut hosts: Vec<String> = self
+            .open_circuits(now)
+            .into_iter()
+            .map(|k| format!("{}:{}", k.host.to_ascii_lowercase(), k.port))
+            .collect();
+        hosts.sort_unstable();
+        hosts.dedup();
+        hosts
+    }
+
+    /// `filter` with every origin whose circuit is open at `now` excluded; the scheduler
+    /// picks jobs through this so a dead origin's jobs stay queued. Other ports on the same
+    /// host are unaffected.
+    pub fn skip_open_circuits(&self, filter: &HostFilter, now: Instant) -> HostFilter {
+        filter.excluding_origins(self.open_circuits(now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_opens_after_threshold_failures_within_window() {
+        let mut policy = HostPolicy::new(1, 16);
+        let breaker = CircuitBreaker::default();
+        let url = "https://down.example/a.iso";
+        let key = HostKey::from_url(url).unwrap();
+        let t0 = Instant::now();
+
+        assert!(!policy.record_connect_failure(url, &breaker, t0).unwrap());
+        // The window lapses: the count starts over.
+        let t1 = t0 + breaker.window + Duration::from_secs(1);
+        assert!(!policy.record_connect_failure(url, &breaker, t1).unwrap());
+        assert!(!policy.record_connect_failure(url, &breaker, t1).unwrap());
+        assert!(policy.circuit_open_until(&key, t1).is_none());
+        assert!(policy.record_connect_failure(url, &breaker, t1).unwrap());
+        assert_eq!(
+            policy.circuit_open_until(&key, t1),
+            Some(t1 + breaker.cooldown)
+        );
+
+        let filter = policy.skip_open_circuits(&HostFilter::default(), t1);
+        assert!(!filter.matches("https://down.example/b.iso"));
+        assert!(filter.matches("https://up.example/b.iso"));
+        assert!(filter.matches("https://down.example:8443/b.iso"));
+        assert_eq!(policy.open_circuit_hosts(t1), vec!["down.example:443"]);
+
+        // After the cooldown the host is tried again.
+        let later = t1 + breaker.cooldown;
+        assert!(policy.circuit_open_until(&key, later).is_none());
+        assert!(policy
+            .skip_open_circuits(&HostFilter::default(), later)
+            .matches(url));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let mut policy = HostPolicy::new(1, 16);
+        let breaker = CircuitBreaker::default();
+        let url = "https://flaky.example/a.iso";
+        let now = Instant::now();
+        for _ in 0..breaker.threshold - 1 {
+            policy.record_connect_failure(url, &breaker, now).unwrap();
+        }
+        policy.record_connect_success(url).unwrap();
+        assert!(!policy.record_connect_failure(url, &breaker, now).unwrap());
+    }
+}
@@ -4,22 +4,7 @@ use std::time::Instant;
 
 use super::HostKey;
 
-/// Observed range support for a given host.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
-pub enum RangeSupport {
-    /// No information yet.
-    Unknown,
-    /// Server has advertised `Accept-Ranges: bytes`.
-    Supported,
-    /// Server has explicitly indicated that ranges are not supported.
-    NotSupported,
-}
-
-impl Default for RangeSupport {
-    fn default() -> Self {
-        RangeSupport::Unknown
-    }
-}
+pub use crate::fetch_head::RangeSupport;
 
 /// Per-host statistics and observations.
 #[derive(Debug, Clone)]
@@ -34,8 +19,17 @@ pub struct HostEntry {
     pub success_events: u32,
     /// Last observed throughput (bytes/sec) for adaptive stepping.
     pub last_throughput_bytes_per_sec: Option<f64>,
+    /// Exponentially weighted moving average of run throughput (bytes/sec); adaptive stepping
+    /// uses this so one slow run does not decide the host's segment limit.
+    pub throughput_ewma_bytes_per_sec: Option<f64>,
     /// Adaptive segment limit: start at 4, step up to 8/16 on good throughput, down on throttle/error.
     pub adaptive_segment_limit: usize,
+    /// Connection failures in a row (the probe could not reach the host); reset on success.
+    pub consecutive_failures: u32,
+    /// When the current run of consecutive failures started.
+    pub first_failure_at: Option<Instant>,
+    /// While in the future the circuit is open: the scheduler leaves this host's jobs queued.
+    pub circuit_open_until: Option<Instant>,
 }
 
 impl HostEntry {
@@ -50,7 +44,11 @@ impl HostEntry {
             last_success_at: None,
             success_events: 0,
             last_throughput_bytes_per_sec: None,
+            throughput_ewma_bytes_per_sec: None,
             adaptive_segment_limit: default_adaptive_limit,
+            consecutive_failures: 0,
+            first_failure_at: None,
+            circuit_open_until: None,
         }
     }
 }
@@ -0,0 +1,106 @@
+//! Host allow/deny lists for `ddm run --host` / `--exclude-host`.
+
+use super::key::HostKey;
+
+/// Restricts which queued jobs a run picks up, by the host of the job URL.
+/// Hosts are compared case-insensitively; an empty filter matches every job.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    /// Origins (scheme, host and port) to skip, e.g. those with an open circuit.
+    exclude_origins: Vec<HostKey>,
+}
+
+impl HostFilter {
+    /// Builds a filter: a job matches when its host is in `include` (or `include`
+    /// is empty) and not in `exclude`.
+    pub fn new<I, E>(include: I, exclude: E) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        E: IntoIterator<Item = String>,
+    {
+        let normalize = |h: String| h.trim().trim_end_matches('.').to_ascii_lowercase();
+        Self {
+            include: include.into_iter().map(normalize).collect(),
+            exclude: exclude.into_iter().map(normalize).collect(),
+            exclude_origins: Vec::new(),
+        }
+    }
+
+    /// This filter with the origins in `keys` excluded; other ports on the same host still match.
+    pub fn excluding_origins<I>(&self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = HostKey>,
+    {
+        let mut filter = self.clone();
+        filter.exclude_origins.extend(keys.into_iter().map(|mut k| {
+            k.host = k.host.to_ascii_lowercase();
+            k
+        }));
+        filter
+    }
+
+    /// True when the filter lets every job through.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && self.exclude_origins.is_empty()
+    }
+
+    /// Whether the job at `url` should be run. URLs without a parsable host only
+    /// match when no `include` list is set.
+    pub fn matches(&self, url: &str) -> bool {
+        let Ok(key) = HostKey::from_url(url) else {
+            return self.include.is_empty();
+        };
+        let host = key.host.to_ascii_lowercase();
+        (self.include.is_empty() || self.include.contains(&host))
+            && !self.exclude.contains(&host)
+            && !self
+                .exclude_origins
+                .iter()
+                .any(|k| k.scheme == key.scheme && k.host == host && k.port == key.port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let f = HostFilter::default();
+        assert!(f.is_empty());
+        assert!(f.matches("https://a.example/x"));
+        assert!(f.matches("not a url"));
+    }
+
+    #[test]
+    fn include_and_exclude_lists() {
+        let f = HostFilter::new(hosts(&["CDN.example.com", "b.example"]), hosts(&[]));
+        assert!(f.matches("https://cdn.example.com/a.iso"));
+        assert!(f.matches("http://b.example:8080/x"));
+        assert!(!f.matches("https://other.example/x"));
+        assert!(!f.matches("not a url"));
+
+        let f = HostFilter::new(hosts(&[]), hosts(&["slow.example"]));
+        assert!(!f.matches("https://slow.example/x"));
+        assert!(f.matches("https://fast.example/x"));
+
+        let f = HostFilter::new(hosts(&["a.example"]), hosts(&["a.example"]));
+        assert!(!f.matches("https://a.example/x"));
+    }
+
+    #[test]
+    fn excluded_origins_keep_other_ports() {
+        let key = HostKey::from_url("https://A.example:8443/x").unwrap();
+        let f = HostFilter::default().excluding_origins([key]);
+        assert!(!f.is_empty());
+        assert!(!f.matches("https://a.example:8443/y"));
+        assert!(f.matches("https://a.example/y"));
+        assert!(f.matches("http://a.example:8443/y"));
+    }
+}
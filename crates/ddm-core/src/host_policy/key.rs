@@ -32,12 +32,19 @@ impl HostKey {
         })
     }
 
-    /// Construct a host key from a URL string.
+    /// Construct a host key from a URL string. `file://` URLs share one `file:localhost:0` key.
     pub fn from_url(url: &str) -> Result<Self> {
         let parsed =
             url::Url::parse(url).with_context(|| format!("invalid URL for host policy: {url}"))?;
 
         let scheme = parsed.scheme().to_string();
+        if scheme == "file" {
+            return Ok(Self {
+                scheme,
+                host: "localhost".to_string(),
+                port: 0,
+            });
+        }
         let host = parsed
             .host_str()
             .ok_or_else(|| anyhow::anyhow!("URL missing host for host policy: {url}"))?
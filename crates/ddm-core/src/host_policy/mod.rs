@@ -4,17 +4,25 @@
 //! - observed range support (from HEAD responses)
 //! - throttling / error / success counters
 //! - a recommended maximum segment count for that host
+//! - a circuit breaker that skips a host's jobs after repeated connection failures
 //!
 //! The cache is intentionally lightweight and process-local; it is created by
 //! the CLI `run` loop and passed to the scheduler so multiple jobs in a single
 //! invocation can share observations.
 
+mod circuit;
 mod entry;
+mod filter;
 mod key;
 mod persist;
 mod state;
 
+pub use circuit::{
+    CircuitBreaker, DEFAULT_CIRCUIT_COOLDOWN_SECS, DEFAULT_CIRCUIT_FAILURE_THRESHOLD,
+    DEFAULT_CIRCUIT_WINDOW_SECS,
+};
 pub use entry::{HostEntry, RangeSupport};
+pub use filter::HostFilter;
 pub use key::HostKey;
 pub use state::HostPolicy;
 
@@ -23,13 +31,14 @@ mod tests {
     use super::*;
     use crate::fetch_head::HeadResult;
 
-    fn make_head(accept_ranges: bool) -> HeadResult {
+    fn make_head(range_support: RangeSupport) -> HeadResult {
         HeadResult {
             content_length: Some(1024),
-            accept_ranges,
+            range_support,
             etag: Some("etag-1".to_string()),
             last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
             content_disposition: None,
+            server_digest: None,
         }
     }
 
@@ -50,19 +59,35 @@ mod tests {
         assert_eq!(key.port, 80);
     }
 
+    #[test]
+    fn host_key_for_file_url_is_localhost() {
+        let key = HostKey::from_url("file:///mnt/share/big.iso").unwrap();
+        assert_eq!(
+            (key.scheme.as_str(), key.host.as_str(), key.port),
+            ("file", "localhost", 0)
+        );
+    }
+
     #[test]
     fn record_head_result_updates_range_support() {
         let mut policy = HostPolicy::new(4, 16);
         let url = "https://cdn.example.com/file.iso";
 
-        let head = make_head(true);
+        let head = make_head(RangeSupport::Supported);
         policy.record_head_result(url, &head).unwrap();
 
         let key = HostKey::from_url(url).unwrap();
         let entry = policy.get(&key).expect("entry should exist");
         assert_eq!(entry.range_support, RangeSupport::Supported);
 
-        let head2 = make_head(false);
+        // Unknown (no Accept-Ranges header) keeps the earlier observation.
+        policy
+            .record_head_result(url, &make_head(RangeSupport::Unknown))
+            .unwrap();
+        let entry = policy.get(&key).unwrap();
+        assert_eq!(entry.range_support, RangeSupport::Supported);
+
+        let head2 = make_head(RangeSupport::NotSupported);
         policy.record_head_result(url, &head2).unwrap();
         let entry2 = policy.get(&key).expect("entry should still exist");
         assert_eq!(entry2.range_support, RangeSupport::NotSupported);
@@ -124,6 +149,40 @@ mod tests {
         assert_eq!(n, 16, "after second good run should step to 16");
     }
 
+    #[test]
+    fn adaptive_stepping_uses_smoothed_throughput() {
+        use std::time::Duration;
+
+        let mut policy = HostPolicy::new(2, 16);
+        let url = "https://blip.example.com/file";
+        let key = HostKey::from_url(url).unwrap();
+
+        // 2 MB/s seeds the average and steps 4 -> 8.
+        policy
+            .record_job_outcome(url, 4, 10_000_000, Duration::from_secs(5), 0, 0)
+            .unwrap();
+        assert_eq!(policy.adaptive_segment_count_for_url(url).unwrap(), 8);
+
+        // One 100 KB/s blip: the average (0.3 * 0.1 + 0.7 * 2 = 1.43 MB/s) stays good, so the
+        // host still steps up instead of being judged on the blip alone.
+        policy
+            .record_job_outcome(url, 8, 100_000, Duration::from_secs(1), 0, 0)
+            .unwrap();
+        let entry = policy.get(&key).unwrap();
+        assert_eq!(entry.last_throughput_bytes_per_sec, Some(100_000.0));
+        let ewma = entry.throughput_ewma_bytes_per_sec.unwrap();
+        assert!((ewma - 1_430_000.0).abs() < 1.0, "{ewma}");
+        assert_eq!(policy.adaptive_segment_count_for_url(url).unwrap(), 16);
+
+        // A run of slow results drags the average below the threshold: no further step.
+        let mut slow = HostPolicy::new(2, 16);
+        for _ in 0..3 {
+            slow.record_job_outcome(url, 4, 100_000, Duration::from_secs(1), 0, 0)
+                .unwrap();
+        }
+        assert_eq!(slow.adaptive_segment_count_for_url(url).unwrap(), 4);
+    }
+
     #[test]
     fn adaptive_segment_count_steps_down_on_throttle() {
         use std::time::Duration;
@@ -10,24 +10,64 @@ use crate::host_policy::HostKey;
 /// Minimum bytes/sec to consider throughput "good" for stepping up segment count (4 -> 8 -> 16).
 const THROUGHPUT_GOOD_BPS: f64 = 1_000_000.0; // 1 MiB/s
 
+/// Weight of the newest run in the throughput EWMA (the rest carries over from history).
+pub(super) const THROUGHPUT_EWMA_ALPHA: f64 = 0.3;
+
+/// Default window after which a host's throttle events stop counting against it.
+pub(super) const DEFAULT_THROTTLE_DECAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Throttle events that still count at `now`: all of them if the last throttle is
+/// within the decay window, none if it is older (the host has behaved since).
+pub(super) fn effective_throttled_events(
+    policy: &HostPolicy,
+    entry: &crate::host_policy::HostEntry,
+    now: Instant,
+) -> u32 {
+    match entry.last_throttled_at {
+        Some(at) if now.saturating_duration_since(at) > policy.throttle_decay => 0,
+        _ => entry.throttled_events,
+    }
+}
+
 /// Default adaptive segment count for a new host (start at 4 per spec).
-pub(super) fn default_adaptive_limit(policy: &HostPolicy) -> usize {
-    (4_usize).max(policy.min_segments).min(policy.max_segments)
+pub(super) fn default_adaptive_limit(min_segments: usize, max_segments: usize) -> usize {
+    (4_usize).max(min_segments).min(max_segments)
 }
 
 /// Compute the recommended maximum number of segments for a host key.
 ///
 /// Conservative heuristic: start from global max, halve for each group of three
-/// throttling events, never below min_segments.
+/// throttling events, never below min_segments. Throttling older than the decay
+/// window is forgiven.
 pub(super) fn recommended_max_segments(policy: &HostPolicy, key: &HostKey) -> usize {
-    let base = policy.max_segments.max(policy.min_segments).max(1);
+    recommended_max_segments_at(policy, key, Instant::now())
+}
+
+/// Like `recommended_max_segments` but evaluated at `now` (for tests).
+pub(super) fn recommended_max_segments_at(
+    policy: &HostPolicy,
+    key: &HostKey,
+    now: Instant,
+) -> usize {
+    recommended_max_segments_within(policy, key, now, policy.min_segments, policy.max_segments)
+}
+
+/// `recommended_max_segments_at` with explicit bounds (per-job overrides of the global ones).
+pub(super) fn recommended_max_segments_within(
+    policy: &HostPolicy,
+    key: &HostKey,
+    now: Instant,
+    min_segments: usize,
+    max_segments: usize,
+) -> usize {
+    let base = max_segments.max(min_segments).max(1);
     let Some(entry) = policy.entries.get(key) else {
         return base;
     };
-    let penalty_steps = (entry.throttled_events / 3).min(3);
+    let penalty_steps = (effective_throttled_events(policy, entry, now) / 3).min(3);
     let mut recommended = base;
     for _ in 0..penalty_steps {
-        recommended = (recommended / 2).max(policy.min_segments.max(1));
+        recommended = (recommended / 2).max(min_segments.max(1));
     }
     recommended
 }
@@ -46,27 +86,41 @@ pub(super) fn record_job_outcome(
     let min_seg = policy.min_segments.max(1);
     let max_seg = policy.max_segments;
     let cap = recommended_max_segments(policy, &key);
+    let now = Instant::now();
+    let decayed = policy
+        .entries
+        .get(&key)
+        .map(|e| e.throttled_events > 0 && effective_throttled_events(policy, e, now) == 0)
+        .unwrap_or(false);
 
     let entry = policy.entry_mut_for_url(url)?;
+    if decayed {
+        entry.throttled_events = 0;
+    }
     let bps = if duration.as_secs_f64() > 0.0 {
         bytes_downloaded as f64 / duration.as_secs_f64()
     } else {
         0.0
     };
     entry.last_throughput_bytes_per_sec = Some(bps);
+    let ewma = match entry.throughput_ewma_bytes_per_sec {
+        Some(prev) => THROUGHPUT_EWMA_ALPHA * bps + (1.0 - THROUGHPUT_EWMA_ALPHA) * prev,
+        None => bps,
+    };
+    entry.throughput_ewma_bytes_per_sec = Some(ewma);
 
     if throttle_events > 0 {
         entry.throttled_events = entry.throttled_events.saturating_add(throttle_events);
-        entry.last_throttled_at = Some(Instant::now());
+        entry.last_throttled_at = Some(now);
     }
     if error_events > 0 {
         entry.error_events = entry.error_events.saturating_add(error_events);
-        entry.last_error_at = Some(Instant::now());
+        entry.last_error_at = Some(now);
     }
 
     if throttle_events > 0 || error_events > 0 {
         entry.adaptive_segment_limit = (entry.adaptive_segment_limit / 2).max(min_seg).min(max_seg);
-    } else if bps >= THROUGHPUT_GOOD_BPS {
+    } else if ewma >= THROUGHPUT_GOOD_BPS {
         let next = match entry.adaptive_segment_limit {
             n if n < 8 => 8,
             n if n < 16 => 16,
@@ -79,13 +133,24 @@ pub(super) fn record_job_outcome(
 
 /// Adaptive segment count for a host key.
 pub(super) fn adaptive_segment_count(policy: &HostPolicy, key: &HostKey) -> usize {
-    let cap = recommended_max_segments(policy, key);
+    adaptive_segment_count_within(policy, key, policy.min_segments, policy.max_segments)
+}
+
+/// Adaptive segment count for a host key, clamped to the given bounds instead of the policy's.
+pub(super) fn adaptive_segment_count_within(
+    policy: &HostPolicy,
+    key: &HostKey,
+    min_segments: usize,
+    max_segments: usize,
+) -> usize {
+    let cap =
+        recommended_max_segments_within(policy, key, Instant::now(), min_segments, max_segments);
     let Some(entry) = policy.entries.get(key) else {
-        return default_adaptive_limit(policy).min(cap);
+        return default_adaptive_limit(min_segments, max_segments).min(cap);
     };
     entry
         .adaptive_segment_limit
         .min(cap)
-        .max(policy.min_segments)
-        .min(policy.max_segments)
+        .max(min_segments)
+        .min(max_segments)
 }
@@ -4,6 +4,7 @@ mod adaptive;
 mod snapshot;
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use anyhow::Result;
 
@@ -12,7 +13,8 @@ use crate::fetch_head::HeadResult;
 use super::entry::{HostEntry, RangeSupport};
 use super::HostKey;
 use adaptive::{
-    adaptive_segment_count, default_adaptive_limit, recommended_max_segments, record_job_outcome,
+    adaptive_segment_count, adaptive_segment_count_within, default_adaptive_limit,
+    recommended_max_segments, record_job_outcome, DEFAULT_THROTTLE_DECAY,
 };
 
 pub use snapshot::PersistedHostPolicy;
@@ -27,6 +29,8 @@ pub struct HostPolicy {
     pub(super) entries: HashMap<HostKey, HostEntry>,
     pub(super) min_segments: usize,
     pub(super) max_segments: usize,
+    /// Throttle events older than this no longer reduce the recommended segment count.
+    pub(super) throttle_decay: Duration,
 }
 
 impl HostPolicy {
@@ -38,9 +42,15 @@ impl HostPolicy {
             entries: HashMap::new(),
             min_segments: min,
             max_segments: max,
+            throttle_decay: DEFAULT_THROTTLE_DECAY,
         }
     }
 
+    /// Set how long throttle events keep penalizing a host (default 24h).
+    pub fn set_throttle_decay(&mut self, window: Duration) {
+        self.throttle_decay = window;
+    }
+
     /// Look up an entry for the given key, if present.
     pub fn get(&self, key: &HostKey) -> Option<&HostEntry> {
         self.entries.get(key)
@@ -48,7 +58,7 @@ impl HostPolicy {
 
     pub(super) fn entry_mut_for_url(&mut self, url: &str) -> Result<&mut HostEntry> {
         let key = HostKey::from_url(url)?;
-        let default = default_adaptive_limit(self);
+        let default = default_adaptive_limit(self.min_segments, self.max_segments);
         Ok(self
             .entries
             .entry(key.clone())
@@ -56,13 +66,12 @@ impl HostPolicy {
     }
 
     /// Record the outcome of a HEAD probe for the given URL.
+    /// An `Unknown` result does not overwrite an earlier definite observation.
     pub fn record_head_result(&mut self, url: &str, head: &HeadResult) -> Result<()> {
         let entry = self.entry_mut_for_url(url)?;
-        entry.range_support = if head.accept_ranges {
-            RangeSupport::Supported
-        } else {
-            RangeSupport::NotSupported
-        };
+        if head.range_support != RangeSupport::Unknown {
+            entry.range_support = head.range_support;
+        }
         Ok(())
     }
 
@@ -128,6 +137,23 @@ impl HostPolicy {
         Ok(adaptive_segment_count(self, &key))
     }
 
+    /// Like `adaptive_segment_count_for_url` but clamped to a job's own segment bounds
+    /// (`ddm add --min-segments/--max-segments`) instead of the global ones.
+    pub fn adaptive_segment_count_for_url_within(
+        &self,
+        url: &str,
+        min_segments: usize,
+        max_segments: usize,
+    ) -> Result<usize> {
+        let key = HostKey::from_url(url)?;
+        Ok(adaptive_segment_count_within(
+            self,
+            &key,
+            min_segments,
+            max_segments,
+        ))
+    }
+
     /// Adaptive segment count for a host key.
     pub fn adaptive_segment_count(&self, key: &HostKey) -> usize {
         adaptive_segment_count(self, key)
@@ -1,6 +1,7 @@
 //! Serializable snapshot types and conversion for HostPolicy persistence.
 
 use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 
@@ -18,7 +19,12 @@ pub struct PersistedEntry {
     pub success_events: u32,
     #[serde(default)]
     pub last_throughput_bytes_per_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub throughput_ewma_bytes_per_sec: Option<f64>,
     pub adaptive_segment_limit: usize,
+    /// Wall-clock time (Unix seconds) of the last throttle event, for decay across runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_throttled_unix_secs: Option<u64>,
 }
 
 /// Snapshot of HostPolicy for JSON serialization. Keys are "scheme:host:port" strings.
@@ -35,8 +41,16 @@ fn default_version() -> u8 {
     1
 }
 
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 /// Build a serializable snapshot from the in-memory policy.
 pub(super) fn to_snapshot(policy: &HostPolicy) -> PersistedHostPolicy {
+    let now_unix = unix_now();
     let entries = policy
         .entries
         .iter()
@@ -49,7 +63,11 @@ pub(super) fn to_snapshot(policy: &HostPolicy) -> PersistedHostPolicy {
                     error_events: e.error_events,
                     success_events: e.success_events,
                     last_throughput_bytes_per_sec: e.last_throughput_bytes_per_sec,
+                    throughput_ewma_bytes_per_sec: e.throughput_ewma_bytes_per_sec,
                     adaptive_segment_limit: e.adaptive_segment_limit,
+                    last_throttled_unix_secs: e
+                        .last_throttled_at
+                        .map(|at| now_unix.saturating_sub(at.elapsed().as_secs())),
                 },
             )
         })
@@ -71,6 +89,7 @@ pub(super) fn from_snapshot(
 ) -> HostPolicy {
     let min = min_segments.max(1);
     let max = max_segments.max(min);
+    let now_unix = unix_now();
     let entries = snapshot
         .entries
         .into_iter()
@@ -79,14 +98,23 @@ pub(super) fn from_snapshot(
             let entry = HostEntry {
                 key: key.clone(),
                 range_support: pe.range_support,
-                last_throttled_at: None,
+                last_throttled_at: pe.last_throttled_unix_secs.and_then(|ts| {
+                    Instant::now().checked_sub(Duration::from_secs(now_unix.saturating_sub(ts)))
+                }),
                 throttled_events: pe.throttled_events,
                 last_error_at: None,
                 error_events: pe.error_events,
                 last_success_at: None,
                 success_events: pe.success_events,
                 last_throughput_bytes_per_sec: pe.last_throughput_bytes_per_sec,
+                // Older snapshots have no EWMA; seed it from the last observed throughput.
+                throughput_ewma_bytes_per_sec: pe
+                    .throughput_ewma_bytes_per_sec
+                    .or(pe.last_throughput_bytes_per_sec),
                 adaptive_segment_limit: pe.adaptive_segment_limit.max(min).min(max),
+                consecutive_failures: 0,
+                first_failure_at: None,
+                circuit_open_until: None,
             };
             Some((key, entry))
         })
@@ -95,5 +123,6 @@ pub(super) fn from_snapshot(
         entries,
         min_segments: min,
         max_segments: max,
+        throttle_decay: super::DEFAULT_THROTTLE_DECAY,
     }
 }
@@ -1,11 +1,14 @@
 //! Tests for HostPolicy state and persistence.
 
+use std::time::{Duration, Instant};
+
 use tempfile::NamedTempFile;
 
 use crate::fetch_head::HeadResult;
 
 use super::super::entry::RangeSupport;
 use super::super::HostKey;
+use super::adaptive::recommended_max_segments_at;
 use super::HostPolicy;
 
 #[test]
@@ -15,10 +18,11 @@ fn to_snapshot_roundtrip() {
         "https://example.com/file",
         &HeadResult {
             content_length: Some(1000),
-            accept_ranges: true,
+            range_support: RangeSupport::Supported,
             etag: None,
             last_modified: None,
             content_disposition: None,
+            server_digest: None,
         },
     );
     let snapshot = policy.to_snapshot();
@@ -35,6 +39,37 @@ fn to_snapshot_roundtrip() {
     );
 }
 
+#[test]
+fn snapshot_preserves_throughput_ewma() {
+    let url = "https://ewma.example.com/file";
+    let mut policy = HostPolicy::new(2, 16);
+    policy
+        .record_job_outcome(url, 4, 3_000_000, Duration::from_secs(1), 0, 0)
+        .unwrap();
+    policy
+        .record_job_outcome(url, 4, 1_000_000, Duration::from_secs(1), 0, 0)
+        .unwrap();
+    let key = HostKey::from_url(url).unwrap();
+    let ewma = policy.get(&key).unwrap().throughput_ewma_bytes_per_sec;
+    let json = serde_json::to_string(&policy.to_snapshot()).unwrap();
+    let restored = HostPolicy::from_snapshot(serde_json::from_str(&json).unwrap(), 2, 16);
+    assert_eq!(
+        restored.get(&key).unwrap().throughput_ewma_bytes_per_sec,
+        ewma
+    );
+
+    // Snapshots from before the EWMA existed seed it from the last throughput.
+    let mut snapshot = policy.to_snapshot();
+    for e in snapshot.entries.values_mut() {
+        e.throughput_ewma_bytes_per_sec = None;
+    }
+    let restored = HostPolicy::from_snapshot(snapshot, 2, 16);
+    assert_eq!(
+        restored.get(&key).unwrap().throughput_ewma_bytes_per_sec,
+        Some(1_000_000.0)
+    );
+}
+
 #[test]
 fn save_and_load_from_path() {
     let mut policy = HostPolicy::new(4, 16);
@@ -42,10 +77,11 @@ fn save_and_load_from_path() {
         "https://cdn.test/saved",
         &HeadResult {
             content_length: Some(2000),
-            accept_ranges: true,
+            range_support: RangeSupport::Supported,
             etag: None,
             last_modified: None,
             content_disposition: None,
+            server_digest: None,
         },
     );
     let f = NamedTempFile::new().unwrap();
@@ -55,3 +91,49 @@ fn save_and_load_from_path() {
     let key = HostKey::from_url("https://cdn.test/").unwrap();
     assert!(loaded.get(&key).is_some());
 }
+
+fn throttled_policy(events: u32, at: Instant) -> (HostPolicy, HostKey) {
+    let mut policy = HostPolicy::new(2, 16);
+    let url = "https://throttle.test/file";
+    let entry = policy.entry_mut_for_url(url).unwrap();
+    entry.throttled_events = events;
+    entry.last_throttled_at = Some(at);
+    (policy, HostKey::from_url(url).unwrap())
+}
+
+#[test]
+fn recent_throttling_reduces_recommended_segments() {
+    let at = Instant::now();
+    let (policy, key) = throttled_policy(6, at);
+    assert_eq!(
+        recommended_max_segments_at(&policy, &key, at + Duration::from_secs(60)),
+        4
+    );
+}
+
+#[test]
+fn throttling_older_than_decay_window_is_forgiven() {
+    let at = Instant::now();
+    let (mut policy, key) = throttled_policy(9, at);
+    policy.set_throttle_decay(Duration::from_secs(3600));
+    assert_eq!(
+        recommended_max_segments_at(&policy, &key, at + Duration::from_secs(3599)),
+        2
+    );
+    assert_eq!(
+        recommended_max_segments_at(&policy, &key, at + Duration::from_secs(3601)),
+        16
+    );
+}
+
+#[test]
+fn snapshot_preserves_last_throttled_for_decay() {
+    let mut policy = HostPolicy::new(2, 16);
+    policy.record_throttled("https://throttle.test/x").unwrap();
+    let snapshot = policy.to_snapshot();
+    let pe = snapshot.entries.values().next().unwrap();
+    assert!(pe.last_throttled_unix_secs.is_some());
+    let restored = HostPolicy::from_snapshot(snapshot, 2, 16);
+    let key = HostKey::from_url("https://throttle.test/").unwrap();
+    assert!(restored.get(&key).unwrap().last_throttled_at.is_some());
+}
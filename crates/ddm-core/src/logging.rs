@@ -30,9 +30,20 @@ impl io::Write for FileOrStderr {
     }
 }
 
+/// Filter for `level` (e.g. "error", "trace"), else `RUST_LOG`, else `info,ddm=debug`.
+fn env_filter(level: Option<&str>) -> EnvFilter {
+    match level {
+        Some(level) => EnvFilter::new(level),
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,ddm=debug"))
+        }
+    }
+}
+
 /// Initialize structured logging to `~/.local/state/ddm/ddm.log`.
+/// `level` overrides the filter (from `--quiet` / `-v`); None keeps `RUST_LOG` or the default.
 /// On failure (e.g. log dir unwritable), returns Err so the caller can fall back to stderr.
-pub fn init_logging() -> Result<()> {
+pub fn init_logging(level: Option<&str>) -> Result<()> {
     let xdg_dirs = xdg::BaseDirectories::with_prefix("ddm")?;
     let log_dir = xdg_dirs.get_state_home().join("ddm");
 
@@ -59,11 +70,8 @@ pub fn init_logging() -> Result<()> {
 
     let writer: BoxMakeWriter = BoxMakeWriter::new(FileMakeWriter(file));
 
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,ddm=debug"));
-
     tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
+        .with_env_filter(env_filter(level))
         .with_writer(writer)
         .with_ansi(false)
         .init();
@@ -74,11 +82,9 @@ pub fn init_logging() -> Result<()> {
 }
 
 /// Initialize logging to stderr only (no file). Use when init_logging() fails so the CLI doesn't crash.
-pub fn init_logging_stderr() {
-    let env_filter =
-        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info,ddm=debug"));
+pub fn init_logging_stderr(level: Option<&str>) {
     tracing_subscriber::fmt()
-        .with_env_filter(env_filter)
+        .with_env_filter(env_filter(level))
         .with_writer(std::io::stderr)
         .with_ansi(false)
         .init();
@@ -11,6 +11,18 @@ pub struct ResolvedJobSpec {
     pub url: String,
     /// Minimal headers required to perform the GET.
     pub headers: HashMap<String, String>,
+    /// When set, `url` must be POSTed with this body to obtain the real download URL.
+    pub post: Option<PostRequest>,
+}
+
+/// A POST that is replayed before downloading (e.g. to mint a one-time signed URL).
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PostRequest {
+    /// Raw request body.
+    pub body: String,
+    /// `Content-Type` of the body, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
 }
 
 /// Trait implemented by optional resolver plugins (e.g. HAR resolver).
@@ -29,6 +41,7 @@ mod tests {
         let spec = ResolvedJobSpec {
             url: "https://cdn.example.com/file.zip".to_string(),
             headers: headers.clone(),
+            post: None,
         };
         assert_eq!(spec.url, "https://cdn.example.com/file.zip");
         assert_eq!(spec.headers.get("Authorization").unwrap(), "Bearer xyz");
@@ -43,6 +56,7 @@ mod tests {
         let spec = ResolvedJobSpec {
             url: "https://example.com/direct".to_string(),
             headers: HashMap::new(),
+            post: None,
         };
         assert!(spec.headers.is_empty());
     }
@@ -5,7 +5,7 @@
 use anyhow::Result;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::{Pool, Sqlite};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Percent-encode a path for use in a sqlite:// URI so spaces and special chars don't break parsing.
@@ -32,6 +32,8 @@ fn path_to_sqlite_uri(path: &Path) -> String {
 #[derive(Clone)]
 pub struct ResumeDb {
     pub(crate) pool: Pool<Sqlite>,
+    /// Directory holding the database file; the run lock and control socket live beside it.
+    dir: PathBuf,
 }
 
 impl ResumeDb {
@@ -50,13 +52,16 @@ impl ResumeDb {
             .connect(&uri)
             .await?;
 
-        let db = ResumeDb { pool };
+        let db = ResumeDb {
+            pool,
+            dir: state_dir,
+        };
         db.migrate().await?;
         Ok(db)
     }
 
     /// Open (or create) the database at a specific path. Creates parent dirs if needed.
-    /// Intended for tests so the DB can be placed in a temp directory.
+    /// Used by tests and `ddm --state-db` to keep the DB outside the default state dir.
     pub async fn open_at(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
         if let Some(parent) = path.parent() {
@@ -67,14 +72,24 @@ impl ResumeDb {
             .max_connections(8)
             .connect(&uri)
             .await?;
-        let db = ResumeDb { pool };
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let db = ResumeDb { pool, dir };
         db.migrate().await?;
         Ok(db)
     }
 
+    /// Directory holding the database file (`~/.local/state/ddm` for the default DB).
+    /// `ddm run` keeps its lock file and control socket here, so runs against different
+    /// `--state-db` directories do not collide.
+    pub fn state_dir(&self) -> &Path {
+        &self.dir
+    }
+
     async fn migrate(&self) -> Result<()> {
-        // Single-table schema focused on jobs. Additional tables (per-segment
-        // metadata, host policy, etc.) can be added later.
+        // Schema focused on jobs, plus an append-only `job_events` history.
         //
         // - `completed_bitmap` is a compact bitmap of finished segments.
         // - `settings_json` holds per-job settings as JSON for flexibility.
@@ -100,10 +115,109 @@ impl ResumeDb {
         .execute(&self.pool)
         .await?;
 
+        // Columns added after the initial schema (existing databases are upgraded in place).
+        self.add_column_if_missing("jobs", "priority", "INTEGER NOT NULL DEFAULT 0")
+            .await?;
+        self.add_column_if_missing("jobs", "segment_hashes", "TEXT")
+            .await?;
+        self.add_column_if_missing("jobs", "segment_progress", "TEXT")
+            .await?;
+        self.add_column_if_missing("jobs", "last_error", "TEXT")
+            .await?;
+        self.add_column_if_missing("jobs", "error_category", "TEXT")
+            .await?;
+        // Filename dedup looks up final filenames per download directory (stored in settings_json).
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS jobs_dir_final_filename ON jobs ({}, final_filename)",
+            JOB_DIR_EXPR
+        ))
+        .execute(&self.pool)
+        .await?;
+        // Owner of a `running` job: the process downloading it and when it last said so.
+        self.add_column_if_missing("jobs", "owner_pid", "INTEGER")
+            .await?;
+        self.add_column_if_missing("jobs", "heartbeat_at", "INTEGER")
+            .await?;
+        // Manual queue order (`ddm queue move`); NULL means the job's id (insertion order).
+        self.add_column_if_missing("jobs", "queue_position", "INTEGER")
+            .await?;
+
+        // One row per completed download run, for per-job/host throughput history.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS job_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                bytes_this_run INTEGER NOT NULL,
+                duration_secs REAL NOT NULL,
+                throttle_events INTEGER NOT NULL,
+                error_events INTEGER NOT NULL,
+                segment_count INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS job_events_job_id ON job_events (job_id);
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Append-only log of every job state transition (`ddm events`), filled by triggers so
+        // no write path can skip it. `from_state` is NULL for the row written when a job is added.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS job_state_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                job_id INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                from_state TEXT,
+                to_state TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS job_state_log_timestamp ON job_state_log (timestamp);
+            CREATE TRIGGER IF NOT EXISTS jobs_log_insert AFTER INSERT ON jobs
+            BEGIN
+                INSERT INTO job_state_log (job_id, timestamp, from_state, to_state)
+                VALUES (NEW.id, NEW.created_at, NULL, NEW.state);
+            END;
+            CREATE TRIGGER IF NOT EXISTS jobs_log_state AFTER UPDATE OF state ON jobs
+            WHEN OLD.state IS NOT NEW.state
+            BEGIN
+                INSERT INTO job_state_log (job_id, timestamp, from_state, to_state)
+                VALUES (NEW.id, NEW.updated_at, OLD.state, NEW.state);
+            END;
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// `ALTER TABLE ... ADD COLUMN` unless the column already exists (SQLite has no IF NOT EXISTS).
+    async fn add_column_if_missing(&self, table: &str, column: &str, decl: &str) -> Result<()> {
+        let rows = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await?;
+        let exists = rows
+            .iter()
+            .any(|r| sqlx::Row::get::<String, _>(r, "name") == column);
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                table, column, decl
+            ))
+            .execute(&self.pool)
+            .await?;
+        }
         Ok(())
     }
 }
 
+/// SQL expression for a job's stored download directory (`settings_json.download_dir`),
+/// NULL when unset or when the settings are not valid JSON. Shared by the index and the
+/// queries that must use it, so the text has to match exactly.
+pub(crate) const JOB_DIR_EXPR: &str =
+    "(CASE WHEN json_valid(settings_json) THEN json_extract(settings_json, '$.download_dir') END)";
+
 /// Current time as Unix seconds (for DB timestamps). Pub for use by `jobs`.
 pub(crate) fn unix_timestamp() -> i64 {
     SystemTime::now()
@@ -119,7 +233,10 @@ pub(crate) async fn open_memory() -> Result<ResumeDb> {
         .max_connections(1)
         .connect("sqlite::memory:")
         .await?;
-    let db = ResumeDb { pool };
+    let db = ResumeDb {
+        pool,
+        dir: PathBuf::from("."),
+    };
     db.migrate().await?;
     Ok(db)
 }
@@ -0,0 +1,93 @@
+//! Per-job download history (`job_events` table): one row per download run; plus the
+//! global state transition log (`job_state_log`, written by triggers).
+
+use anyhow::Result;
+use sqlx::Row;
+
+use super::db::ResumeDb;
+use super::types::{JobEvent, JobId, JobState, JobStateChange};
+
+impl ResumeDb {
+    /// Append a download-run record for a job.
+    pub async fn record_job_event(&self, event: &JobEvent) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO job_events (
+                job_id, timestamp, bytes_this_run, duration_secs,
+                throttle_events, error_events, segment_count
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(event.job_id)
+        .bind(event.timestamp)
+        .bind(event.bytes_this_run as i64)
+        .bind(event.duration_secs)
+        .bind(event.throttle_events as i64)
+        .bind(event.error_events as i64)
+        .bind(event.segment_count as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// List recorded download runs for a job, oldest first.
+    pub async fn list_job_events(&self, job_id: JobId) -> Result<Vec<JobEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT job_id, timestamp, bytes_this_run, duration_secs,
+                   throttle_events, error_events, segment_count
+            FROM job_events
+            WHERE job_id = ?1
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )
+        .bind(job_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| JobEvent {
+                job_id: row.get("job_id"),
+                timestamp: row.get("timestamp"),
+                bytes_this_run: row.get::<i64, _>("bytes_this_run") as u64,
+                duration_secs: row.get("duration_secs"),
+                throttle_events: row.get::<i64, _>("throttle_events") as u32,
+                error_events: row.get::<i64, _>("error_events") as u32,
+                segment_count: row.get::<i64, _>("segment_count") as u32,
+            })
+            .collect())
+    }
+    /// State transitions of all jobs (or only `job_id`) at or after `since` (Unix seconds),
+    /// oldest first. Entries of removed jobs are kept.
+    pub async fn list_state_changes(
+        &self,
+        since: Option<i64>,
+        job_id: Option<JobId>,
+    ) -> Result<Vec<JobStateChange>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT job_id, timestamp, from_state, to_state
+            FROM job_state_log
+            WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR job_id = ?2)
+            ORDER BY timestamp ASC, id ASC
+            "#,
+        )
+        .bind(since)
+        .bind(job_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| JobStateChange {
+                job_id: row.get("job_id"),
+                timestamp: row.get("timestamp"),
+                from: row
+                    .get::<Option<String>, _>("from_state")
+                    .map(|s| JobState::from_str(&s)),
+                to: JobState::from_str(&row.get::<String, _>("to_state")),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,146 @@
+//! Job plan export/import (`ddm export` / `ddm import`): a JSON sidecar with the URL, size,
+//! validators, segment layout and per-segment SHA-256 hashes, so a plan can move between
+//! machines. Progress is not carried over; the imported job starts with no segment done.
+
+use anyhow::{Context, Result};
+
+use super::db::ResumeDb;
+use super::types::{JobId, JobMetadata, JobSettings};
+use crate::segmenter::{plan_segments, SegmentBitmap};
+use crate::url_model;
+
+/// Sidecar format version written by [`ResumeDb::export_job`].
+pub const EXPORT_VERSION: u32 = 1;
+
+/// Exported job plan (serialized as the sidecar JSON).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JobExport {
+    pub version: u32,
+    pub url: String,
+    pub final_filename: Option<String>,
+    pub total_size: Option<u64>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Segment byte ranges as `[start, end)`; empty when the job has no plan yet.
+    pub segments: Vec<[u64; 2]>,
+    /// SHA-256 (hex) per segment where one was recorded.
+    pub segment_hashes: Vec<Option<String>>,
+    /// Job settings. Without `include_sensitive`, custom headers (cookies included), a
+    /// stored POST body, the completion hook and `insecure` are dropped; the download
+    /// directory is never exported.
+    pub settings: JobSettings,
+}
+
+impl ResumeDb {
+    /// Job `id` as an exportable plan. `include_sensitive` keeps custom headers, cookies, the
+    /// POST body, the completion hook and `insecure` in the settings.
+    pub async fn export_job(&self, id: JobId, include_sensitive: bool) -> Result<JobExport> {
+        let job = self
+            .get_job(id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("job {} not found", id))?;
+        let total_size = job.total_size.map(|n| n as u64);
+        let segments = total_size
+            .map(|total| plan_segments(total, job.segment_count as usize))
+            .unwrap_or_default()
+            .iter()
+            .map(|s| [s.start, s.end])
+            .collect::<Vec<_>>();
+        let mut segment_hashes = job.segment_hashes;
+        segment_hashes.resize(segments.len(), None);
+        let mut settings = job.settings;
+        settings.download_dir = None;
+        if !include_sensitive {
+            settings.custom_headers = None;
+            settings.cookie_session = false;
+            settings.post = None;
+            settings.on_complete = None;
+            settings.insecure = false;
+        }
+        Ok(JobExport {
+            version: EXPORT_VERSION,
+            url: job.url,
+            final_filename: job.final_filename,
+            total_size,
+            etag: job.etag,
+            last_modified: job.last_modified,
+            segments,
+            segment_hashes,
+            settings,
+        })
+    }
+
+    /// Creates a queued job from an exported plan: same URL, settings, size, validators and
+    /// segment layout, with the exported hashes stored and no segment marked done. Unless
+    /// `trusted`, a completion hook and `insecure` in the plan are cleared, since they would
+    /// run a command or skip TLS checks on this machine. A `final_filename` with a path
+    /// separator is refused.
+    pub async fn import_job(&self, export: &JobExport, trusted: bool) -> Result<JobId> {
+        if export.version != EXPORT_VERSION {
+            anyhow::bail!(
+                "unsupported export version {} (expected {})",
+                export.version,
+                EXPORT_VERSION
+            );
+        }
+        let count = export.segments.len();
+        if let Some(total) = export.total_size {
+            let planned: Vec<[u64; 2]> = plan_segments(total, count)
+                .iter()
+                .map(|s| [s.start, s.end])
+                .collect();
+            if planned != export.segments {
+                anyhow::bail!(
+                    "segment layout does not match {} segments of a {}-byte file",
+                    count,
+                    total
+                );
+            }
+        } else if count > 0 {
+            anyhow::bail!("segment layout without a total size");
+        }
+        if export.segment_hashes.len() > count {
+            anyhow::bail!(
+                "{} segment hashes for {} segments",
+                export.segment_hashes.len(),
+                count
+            );
+        }
+
+        let final_filename = match export.final_filename.as_deref() {
+            Some(name) if name.contains(['/', '\\']) => {
+                anyhow::bail!("final filename {name:?} contains a path separator")
+            }
+            Some(name) => Some(
+                url_model::forced_filename(name)
+                    .ok_or_else(|| anyhow::anyhow!("unusable final filename {name:?}"))?,
+            ),
+            None => None,
+        };
+        let mut settings = export.settings.clone();
+        settings.download_dir = None;
+        if !trusted {
+            settings.on_complete = None;
+            settings.insecure = false;
+        }
+
+        let id = self
+            .add_job(&export.url, &settings)
+            .await
+            .context("add imported job")?;
+        if let Some(total) = export.total_size {
+            let meta = JobMetadata {
+                final_filename,
+                temp_filename: None,
+                total_size: Some(total as i64),
+                etag: export.etag.clone(),
+                last_modified: export.last_modified.clone(),
+                segment_count: count as i64,
+                completed_bitmap: SegmentBitmap::new(count).to_bytes(count),
+            };
+            self.update_metadata(id, &meta).await?;
+            self.set_segment_hashes(id, &export.segment_hashes).await?;
+        }
+        Ok(id)
+    }
+}
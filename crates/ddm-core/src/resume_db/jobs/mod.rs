@@ -2,3 +2,5 @@
 
 mod read;
 mod write;
+
+pub use write::HEARTBEAT_STALE_SECS;
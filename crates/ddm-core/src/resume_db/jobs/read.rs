@@ -3,15 +3,16 @@
 use anyhow::Result;
 use sqlx::Row;
 
-use super::super::db::ResumeDb;
-use super::super::types::{JobDetails, JobId, JobSettings, JobState, JobSummary};
+use super::super::db::{ResumeDb, JOB_DIR_EXPR};
+use super::super::types::{ErrorCategory, JobDetails, JobId, JobSettings, JobState, JobSummary};
 
 impl ResumeDb {
     /// List all jobs in the database, newest first.
     pub async fn list_jobs(&self) -> Result<Vec<JobSummary>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, url, state, final_filename, total_size
+            SELECT id, url, state, final_filename, total_size, priority, settings_json, error_category,
+                   COALESCE(queue_position, id) AS queue_position
             FROM jobs
             ORDER BY created_at DESC, id DESC
             "#,
@@ -26,6 +27,14 @@ impl ResumeDb {
             let state_str: String = row.get("state");
             let final_filename: Option<String> = row.get("final_filename");
             let total_size: Option<i64> = row.get("total_size");
+            let priority: i64 = row.get("priority");
+            let queue_position: i64 = row.get("queue_position");
+            let settings_json: Option<String> = row.get("settings_json");
+            let note = settings_json
+                .as_deref()
+                .and_then(|s| serde_json::from_str::<JobSettings>(s).ok())
+                .and_then(|s| s.note);
+            let error_category: Option<String> = row.get("error_category");
 
             out.push(JobSummary {
                 id,
@@ -33,6 +42,10 @@ impl ResumeDb {
                 state: JobState::from_str(&state_str),
                 final_filename,
                 total_size,
+                priority: priority as i32,
+                queue_position,
+                note,
+                error_category: error_category.as_deref().map(ErrorCategory::parse),
             });
         }
 
@@ -42,40 +55,41 @@ impl ResumeDb {
     /// Returns final_filename of all jobs that use the given download_dir (for collision detection).
     /// If download_dir is None, only jobs with no stored download_dir are considered (legacy).
     /// exclude_job_id, if set, is omitted from the list (e.g. current job when updating metadata).
+    /// Filtered in SQL via the `jobs_dir_final_filename` index, so it does not scan every job.
     pub async fn list_final_filenames_in_dir(
         &self,
         download_dir: Option<&str>,
         exclude_job_id: Option<JobId>,
     ) -> Result<Vec<String>> {
-        let rows = sqlx::query(r#"SELECT id, final_filename, settings_json FROM jobs"#)
+        let dir_filter = if download_dir.is_some() {
+            "= ?1"
+        } else {
+            "IS NULL"
+        };
+        let sql = format!(
+            "SELECT final_filename FROM jobs \
+             WHERE {JOB_DIR_EXPR} {dir_filter} AND final_filename IS NOT NULL \
+             AND (?2 IS NULL OR id != ?2)"
+        );
+        let names = sqlx::query_scalar::<_, String>(&sql)
+            .bind(download_dir)
+            .bind(exclude_job_id)
             .fetch_all(&self.pool)
             .await?;
+        Ok(names)
+    }
 
-        let mut out = Vec::new();
-        for row in rows {
-            let id: i64 = row.get("id");
-            if exclude_job_id == Some(id) {
-                continue;
-            }
-            let final_filename: Option<String> = row.get("final_filename");
-            let settings_json: Option<String> = row.get("settings_json");
-            let job_dir = settings_json
-                .as_deref()
-                .filter(|s| !s.is_empty())
-                .and_then(|s| serde_json::from_str::<JobSettings>(s).ok())
-                .and_then(|s| s.download_dir);
-            let same_dir = match (download_dir, job_dir.as_deref()) {
-                (None, None) => true,
-                (Some(a), Some(b)) => a == b,
-                _ => false,
-            };
-            if same_dir {
-                if let Some(name) = final_filename {
-                    out.push(name);
-                }
-            }
-        }
-        Ok(out)
+    /// `(download_dir, temp_filename)` of every job that has been assigned a temp file, so
+    /// `ddm cleanup` can tell orphaned `.part` files from ones a job still owns.
+    pub async fn list_temp_filenames(&self) -> Result<Vec<(Option<String>, String)>> {
+        let sql = format!(
+            "SELECT {JOB_DIR_EXPR} AS dir, temp_filename FROM jobs WHERE temp_filename IS NOT NULL"
+        );
+        let rows = sqlx::query(&sql).fetch_all(&self.pool).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.get("dir"), row.get("temp_filename")))
+            .collect())
     }
 
     /// Fetch a single job row with full metadata for the scheduler.
@@ -85,7 +99,8 @@ impl ResumeDb {
             SELECT
                 id, url, final_filename, temp_filename, total_size,
                 etag, last_modified, segment_count, completed_bitmap,
-                state, created_at, updated_at, settings_json
+                state, created_at, updated_at, settings_json, segment_hashes,
+                segment_progress, last_error, error_category
             FROM jobs
             WHERE id = ?1
             "#,
@@ -111,6 +126,10 @@ impl ResumeDb {
         let created_at: i64 = row.get("created_at");
         let updated_at: i64 = row.get("updated_at");
         let settings_json: Option<String> = row.get("settings_json");
+        let segment_hashes: Option<String> = row.get("segment_hashes");
+        let segment_progress: Option<String> = row.get("segment_progress");
+        let last_error: Option<String> = row.get("last_error");
+        let error_category: Option<String> = row.get("error_category");
 
         let settings = settings_json
             .as_deref()
@@ -118,6 +137,16 @@ impl ResumeDb {
             .map(|s| serde_json::from_str::<JobSettings>(s))
             .transpose()?
             .unwrap_or_default();
+        let segment_hashes = segment_hashes
+            .as_deref()
+            .map(serde_json::from_str::<Vec<Option<String>>>)
+            .transpose()?
+            .unwrap_or_default();
+        let segment_progress = segment_progress
+            .as_deref()
+            .map(serde_json::from_str::<Vec<u64>>)
+            .transpose()?
+            .unwrap_or_default();
 
         Ok(Some(JobDetails {
             id,
@@ -133,6 +162,10 @@ impl ResumeDb {
             created_at,
             updated_at,
             settings,
+            segment_hashes,
+            segment_progress,
+            last_error,
+            error_category: error_category.as_deref().map(ErrorCategory::parse),
         }))
     }
 }
@@ -4,27 +4,46 @@ use anyhow::Result;
 use sqlx::Row;
 
 use super::super::db::{unix_timestamp, ResumeDb};
-use super::super::types::{JobId, JobMetadata, JobSettings, JobState};
+use super::super::lock::process_alive;
+use super::super::types::{ErrorCategory, JobId, JobMetadata, JobSettings, JobState};
+use crate::host_policy::HostFilter;
 
 impl ResumeDb {
-    /// Atomically claim the next queued job (smallest id) by setting its state to Running.
+    /// Atomically claim the next queued job (highest priority, then queue position) by setting its state to Running
+    /// and recording this process as its owner.
     /// Returns the claimed job id, or None if no job is queued. Used by the parallel scheduler
     /// so multiple workers never pick the same job. Stranded Running jobs are reset by
     /// `recover_running_jobs()` before scheduling.
     pub async fn claim_next_queued_job(&self) -> Result<Option<JobId>> {
+        self.claim_next_queued_job_matching(&HostFilter::default())
+            .await
+    }
+
+    /// Like [`Self::claim_next_queued_job`] but only considers queued jobs whose URL
+    /// host passes `filter`; others stay queued.
+    pub async fn claim_next_queued_job_matching(
+        &self,
+        filter: &HostFilter,
+    ) -> Result<Option<JobId>> {
         let now = unix_timestamp();
-        let mut tx = self.pool.begin().await?;
-        let row = sqlx::query(
+        // IMMEDIATE takes the write lock up front: a deferred transaction upgrading from its
+        // SELECT fails with SQLITE_BUSY at once (no busy wait) if another writer got in first.
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        let rows = sqlx::query(
             r#"
-            SELECT id FROM jobs
+            SELECT id, url FROM jobs
             WHERE state = 'queued'
-            ORDER BY id ASC
-            LIMIT 1
+            ORDER BY priority DESC, COALESCE(queue_position, id) ASC, id ASC
+            LIMIT ?1
             "#,
         )
-        .fetch_optional(&mut *tx)
+        .bind(if filter.is_empty() { 1 } else { -1 })
+        .fetch_all(&mut *tx)
         .await?;
-        let Some(row) = row else {
+        let next = rows
+            .iter()
+            .find(|row| filter.matches(&row.get::<String, _>("url")));
+        let Some(row) = next else {
             tx.commit().await?;
             return Ok(None);
         };
@@ -33,10 +52,13 @@ impl ResumeDb {
             r#"
             UPDATE jobs
             SET state = 'running',
-                updated_at = ?1
-            WHERE id = ?2
+                owner_pid = ?1,
+                heartbeat_at = ?2,
+                updated_at = ?2
+            WHERE id = ?3
             "#,
         )
+        .bind(std::process::id())
         .bind(now)
         .bind(id)
         .execute(&mut *tx)
@@ -49,8 +71,19 @@ impl ResumeDb {
     /// Metadata such as size, ETag, and segment layout will be filled in
     /// later by the HEAD/segmenter logic.
     pub async fn add_job(&self, url: &str, settings: &JobSettings) -> Result<JobId> {
+        self.add_job_in_state(url, settings, JobState::Queued).await
+    }
+
+    /// Like [`Self::add_job`] but inserts the job in `state` (e.g. `Paused` for
+    /// `ddm add --start-paused`, so `run` skips it until it is resumed).
+    pub async fn add_job_in_state(
+        &self,
+        url: &str,
+        settings: &JobSettings,
+        state: JobState,
+    ) -> Result<JobId> {
         let now = unix_timestamp();
-        let state = JobState::Queued.as_str();
+        let state = state.as_str();
         let settings_json = serde_json::to_string(settings)?;
 
         let row_id = sqlx::query(
@@ -58,10 +91,10 @@ impl ResumeDb {
             INSERT INTO jobs (
                 url, final_filename, temp_filename, total_size,
                 etag, last_modified, segment_count, completed_bitmap,
-                state, created_at, updated_at, settings_json
+                state, created_at, updated_at, settings_json, priority
             ) VALUES (?1, NULL, NULL, NULL,
                       NULL, NULL, 0, x'',
-                      ?2, ?3, ?4, ?5)
+                      ?2, ?3, ?4, ?5, ?6)
             "#,
         )
         .bind(url)
@@ -69,6 +102,7 @@ impl ResumeDb {
         .bind(now)
         .bind(now)
         .bind(settings_json)
+        .bind(settings.priority)
         .execute(&self.pool)
         .await?
         .last_insert_rowid();
@@ -89,6 +123,7 @@ impl ResumeDb {
                 last_modified = ?5,
                 segment_count = ?6,
                 completed_bitmap = ?7,
+                segment_progress = NULL,
                 updated_at = ?8
             WHERE id = ?9
             "#,
@@ -128,49 +163,225 @@ impl ResumeDb {
         Ok(())
     }
 
-    /// Normalize any job left in `running` to `queued` (e.g. after a crash).
-    /// Call before scheduling so stranded jobs are picked up again.
-    /// Returns the number of jobs reset.
-    pub async fn recover_running_jobs(&self) -> Result<u64> {
+    /// Store per-segment SHA-256 digests (index = segment). All-None clears the column.
+    pub async fn set_segment_hashes(&self, id: JobId, hashes: &[Option<String>]) -> Result<()> {
+        let json = if hashes.iter().any(Option::is_some) {
+            Some(serde_json::to_string(hashes)?)
+        } else {
+            None
+        };
+        sqlx::query(r#"UPDATE jobs SET segment_hashes = ?1 WHERE id = ?2"#)
+            .bind(json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Store per-segment byte offsets for partial resume (index = segment). All-zero clears the column.
+    pub async fn set_segment_progress(&self, id: JobId, offsets: &[u64]) -> Result<()> {
+        let json = if offsets.iter().any(|&o| o > 0) {
+            Some(serde_json::to_string(offsets)?)
+        } else {
+            None
+        };
+        sqlx::query(r#"UPDATE jobs SET segment_progress = ?1 WHERE id = ?2"#)
+            .bind(json)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Forget the job's probed metadata and segment plan so the next run re-probes the URL
+    /// and re-plans segments with the current config. The filenames are kept; the .part file
+    /// is recreated because the job no longer has a known size.
+    pub async fn reset_plan(&self, id: JobId) -> Result<()> {
         let now = unix_timestamp();
-        let r = sqlx::query(
+        sqlx::query(
             r#"
             UPDATE jobs
-            SET state = 'queued',
+            SET total_size = NULL,
+                etag = NULL,
+                last_modified = NULL,
+                segment_count = 0,
+                completed_bitmap = x'',
+                segment_progress = NULL,
+                segment_hashes = NULL,
                 updated_at = ?1
-            WHERE state = 'running'
+            WHERE id = ?2
             "#,
         )
         .bind(now)
+        .bind(id)
         .execute(&self.pool)
         .await?;
-        Ok(r.rows_affected())
+
+        Ok(())
     }
 
-    /// Update the state of an existing job.
+    /// Refresh the heartbeat of a job this process is running or verifying (no-op if another
+    /// process owns it).
+    pub async fn heartbeat(&self, id: JobId) -> Result<()> {
+        sqlx::query(
+            r#"UPDATE jobs SET heartbeat_at = ?1
+               WHERE id = ?2 AND state IN ('running', 'verifying') AND owner_pid = ?3"#,
+        )
+        .bind(unix_timestamp())
+        .bind(id)
+        .bind(std::process::id())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Normalize jobs stranded in `running` or `verifying` (e.g. after a crash) to `queued`.
+    /// A job is stranded when its owner process is gone, its heartbeat is older than
+    /// [`HEARTBEAT_STALE_SECS`], or it has no owner recorded; jobs still being downloaded
+    /// by another live process are left alone. Call before scheduling so stranded jobs
+    /// are picked up again. Returns the number of jobs reset.
+    pub async fn recover_running_jobs(&self) -> Result<u64> {
+        let now = unix_timestamp();
+        let rows =
+            sqlx::query(r#"SELECT id, owner_pid, heartbeat_at FROM jobs WHERE state IN ('running', 'verifying')"#)
+                .fetch_all(&self.pool)
+                .await?;
+        let mut reset = 0;
+        for row in rows {
+            let id: i64 = row.get("id");
+            let owner_pid: Option<i64> = row.get("owner_pid");
+            let heartbeat_at: Option<i64> = row.get("heartbeat_at");
+            if !is_stranded(owner_pid, heartbeat_at, now) {
+                tracing::info!(
+                    job_id = id,
+                    owner_pid,
+                    "leaving job owned by a live process"
+                );
+                continue;
+            }
+            let r = sqlx::query(
+                r#"
+                UPDATE jobs
+                SET state = 'queued',
+                    owner_pid = NULL,
+                    heartbeat_at = NULL,
+                    updated_at = ?1
+                WHERE id = ?2 AND state IN ('running', 'verifying')
+                "#,
+            )
+            .bind(now)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+            reset += r.rows_affected();
+        }
+        Ok(reset)
+    }
+
+    /// Update the state of an existing job. Running and Verifying record this process as the
+    /// owner; any other state clears the owner.
     pub async fn set_state(&self, id: JobId, state: JobState) -> Result<()> {
         let now = unix_timestamp();
+        let owner = matches!(state, JobState::Running | JobState::Verifying).then(std::process::id);
         sqlx::query(
             r#"
             UPDATE jobs
             SET state = ?1,
-                updated_at = ?2
-            WHERE id = ?3
+                owner_pid = ?2,
+                heartbeat_at = CASE WHEN ?2 IS NULL THEN NULL ELSE ?3 END,
+                updated_at = ?3
+            WHERE id = ?4
             "#,
         )
         .bind(state.as_str())
+        .bind(owner)
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Move queued job `id` ahead of (`front`) or behind every other queued job of the same
+    /// priority. Priorities are unchanged, so higher-priority jobs still run first.
+    pub async fn move_in_queue(&self, id: JobId, front: bool) -> Result<()> {
+        let now = unix_timestamp();
+        let position = if front {
+            "(SELECT MIN(COALESCE(queue_position, id)) - 1 FROM jobs WHERE state = 'queued')"
+        } else {
+            "(SELECT MAX(COALESCE(queue_position, id)) + 1 FROM jobs WHERE state = 'queued')"
+        };
+        let result = sqlx::query(&format!(
+            "UPDATE jobs SET queue_position = {}, updated_at = ?1 WHERE id = ?2 AND state = 'queued'",
+            position
+        ))
         .bind(now)
         .bind(id)
         .execute(&self.pool)
         .await?;
+        if result.rows_affected() == 0 {
+            anyhow::bail!("job {} is not queued", id);
+        }
+        Ok(())
+    }
 
+    /// Point the job at a different final file name (`on_conflict = "rename"`).
+    pub async fn set_final_filename(&self, id: JobId, final_filename: &str) -> Result<()> {
+        let now = unix_timestamp();
+        sqlx::query(r#"UPDATE jobs SET final_filename = ?1, updated_at = ?2 WHERE id = ?3"#)
+            .bind(final_filename)
+            .bind(now)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
-    /// Permanently remove a job row from the database.
+    /// Move the job to [`JobState::Error`] and record why it failed (shown by `inspect`/`status`).
+    pub async fn set_error(&self, id: JobId, category: ErrorCategory, message: &str) -> Result<()> {
+        let now = unix_timestamp();
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET state = ?1,
+                last_error = ?2,
+                error_category = ?3,
+                owner_pid = NULL,
+                heartbeat_at = NULL,
+                updated_at = ?4
+            WHERE id = ?5
+            "#,
+        )
+        .bind(JobState::Error.as_str())
+        .bind(message)
+        .bind(category.as_str())
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Forget the recorded failure (`last_error` and its category).
+    pub async fn clear_error(&self, id: JobId) -> Result<()> {
+        sqlx::query(r#"UPDATE jobs SET last_error = NULL, error_category = NULL WHERE id = ?1"#)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Permanently remove a job row (and its recorded history) from the database.
+    /// Its entries in the append-only state log are kept.
     ///
     /// File cleanup is handled separately by higher layers.
     pub async fn remove_job(&self, id: JobId) -> Result<()> {
+        sqlx::query("DELETE FROM job_events WHERE job_id = ?1")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
         sqlx::query(
             r#"
             DELETE FROM jobs
@@ -184,3 +395,18 @@ impl ResumeDb {
         Ok(())
     }
 }
+
+/// A running job's owner counts as gone after this long without a heartbeat
+/// (the scheduler refreshes it every few seconds while the job runs).
+pub const HEARTBEAT_STALE_SECS: i64 = 120;
+
+/// True if a `running` job is not being downloaded by any live process. A job owned by
+/// this very process is stranded too: at recovery time the owner must be a dead process
+/// whose PID was reused.
+fn is_stranded(owner_pid: Option<i64>, heartbeat_at: Option<i64>, now: i64) -> bool {
+    let Some(pid) = owner_pid.and_then(|p| u32::try_from(p).ok()) else {
+        return true;
+    };
+    let stale = heartbeat_at.is_none_or(|t| now - t > HEARTBEAT_STALE_SECS);
+    stale || pid == std::process::id() || !process_alive(pid)
+}
@@ -0,0 +1,159 @@
+//! Advisory run lock so only one `ddm run` processes the job database at a time.
+//!
+//! The lock is an exclusive `flock` on `run.lock`, held through an fd that stays open
+//! for the life of [`RunLock`]. The kernel releases it when the owner exits or crashes,
+//! so there is no stale lock to detect. The file holds the owner's PID only so the
+//! error message can name it.
+
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Held while a `ddm run` loop is active; the lock is released when it is dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    file: File,
+}
+
+impl RunLock {
+    /// Lock path for the database in `state_dir`: `run.lock` beside it
+    /// (`~/.local/state/ddm/run.lock` for the default DB).
+    pub fn path_in(state_dir: &Path) -> PathBuf {
+        state_dir.join("run.lock")
+    }
+
+    /// Acquire the lock at `path`. Fails if another process holds it.
+    pub fn acquire_at(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create lock dir: {}", parent.display()))?;
+        }
+        // Not truncated on open: until we hold the lock the contents are the owner's.
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("open run lock: {}", path.display()))?;
+        if !try_lock_exclusive(&file)
+            .with_context(|| format!("lock run lock: {}", path.display()))?
+        {
+            let mut owner = String::new();
+            let _ = file.read_to_string(&mut owner);
+            let owner = match owner.trim().parse::<u32>() {
+                Ok(pid) => format!("pid {pid}"),
+                Err(_) => "pid unknown".to_string(),
+            };
+            anyhow::bail!(
+                "another `ddm run` is already active ({}); lock file: {}",
+                owner,
+                path.display()
+            );
+        }
+        file.set_len(0)
+            .and_then(|()| file.rewind())
+            .and_then(|()| writeln!(file, "{}", std::process::id()))
+            .with_context(|| format!("write run lock: {}", path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        // The file stays: unlinking it could split the next two runs across two inodes.
+        // Closing the fd releases the lock.
+        let _ = self.file.set_len(0);
+    }
+}
+
+/// Takes an exclusive `flock` on `file` without blocking; false if another fd holds it.
+#[cfg(unix)]
+fn try_lock_exclusive(file: &File) -> std::io::Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EWOULDBLOCK) {
+        Ok(false)
+    } else {
+        Err(err)
+    }
+}
+
+#[cfg(not(unix))]
+fn try_lock_exclusive(_file: &File) -> std::io::Result<bool> {
+    Ok(true)
+}
+
+/// True if a process with this PID exists (always true where this cannot be checked).
+/// PID 0 and values beyond `pid_t` are never a single live process.
+#[cfg(unix)]
+pub(crate) fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    if pid <= 0 {
+        return false;
+    }
+    // Signal 0 performs permission/existence checks without sending anything.
+    let r = unsafe { libc::kill(pid, 0) };
+    r == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_acquire_fails_while_held() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.lock");
+        let lock = RunLock::acquire_at(&path).unwrap();
+        let err = RunLock::acquire_at(&path).unwrap_err();
+        assert!(err.to_string().contains("already active"));
+        assert!(err
+            .to_string()
+            .contains(&format!("pid {}", std::process::id())));
+        drop(lock);
+        RunLock::acquire_at(&path).unwrap();
+    }
+
+    #[test]
+    fn empty_lock_file_is_still_held_by_its_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.lock");
+        let _lock = RunLock::acquire_at(&path).unwrap();
+        // The owner has locked the file but not yet written its PID.
+        std::fs::write(&path, "").unwrap();
+        let err = RunLock::acquire_at(&path).unwrap_err();
+        assert!(err.to_string().contains("already active (pid unknown)"));
+    }
+
+    #[test]
+    fn leftover_lock_file_from_dead_process_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.lock");
+        std::fs::write(&path, "2147483646\n").unwrap();
+        let _lock = RunLock::acquire_at(&path).unwrap();
+        let owner = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(owner.trim(), std::process::id().to_string());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn process_alive_rejects_pids_that_name_a_group() {
+        assert!(process_alive(std::process::id()));
+        assert!(!process_alive(0));
+        assert!(!process_alive(u32::MAX));
+        assert!(!process_alive(i32::MAX as u32 + 1));
+    }
+}
@@ -1,14 +1,20 @@
 //! Persistent resume/job database (SQLite via sqlx).
 //!
 //! Stores jobs, filenames, sizes, segment completion bitmaps, and
-//! ETag/Last-Modified metadata for safe resume.
+//! ETag/Last-Modified metadata for safe resume, plus a per-job history of
+//! download runs.
 
 pub mod db;
+pub mod events;
+pub mod export;
 pub mod jobs;
+pub mod lock;
 pub mod types;
 
 #[cfg(test)]
 mod tests;
 
 pub use db::ResumeDb;
+pub use export::JobExport;
+pub use lock::RunLock;
 pub use types::*;
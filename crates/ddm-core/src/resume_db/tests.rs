@@ -1,7 +1,10 @@
 //! Tests for resume_db (use in-memory DB helper from db).
 
+use crate::checksum::ChecksumAlgo;
+use crate::host_policy::HostFilter;
 use crate::resume_db::db::open_memory;
-use crate::resume_db::{JobMetadata, JobSettings, JobState, ResumeDb};
+use crate::resume_db::{ErrorCategory, JobEvent, JobMetadata, JobSettings, JobState, ResumeDb};
+use crate::segmenter::SegmentOrder;
 
 #[tokio::test]
 async fn job_state_roundtrip_via_db() {
@@ -20,6 +23,10 @@ async fn job_state_roundtrip_via_db() {
     let jobs = db.list_jobs().await.unwrap();
     assert_eq!(jobs[0].state, JobState::Running);
 
+    db.set_state(id, JobState::Verifying).await.unwrap();
+    let jobs = db.list_jobs().await.unwrap();
+    assert_eq!(jobs[0].state, JobState::Verifying);
+
     db.set_state(id, JobState::Paused).await.unwrap();
     let jobs = db.list_jobs().await.unwrap();
     assert_eq!(jobs[0].state, JobState::Paused);
@@ -36,13 +43,83 @@ async fn recover_running_jobs_resets_to_queued() {
         .add_job("https://example.com/x", &JobSettings::default())
         .await
         .unwrap();
+    let verifying = db
+        .add_job("https://example.com/y", &JobSettings::default())
+        .await
+        .unwrap();
     db.set_state(id, JobState::Running).await.unwrap();
-    assert_eq!(db.list_jobs().await.unwrap()[0].state, JobState::Running);
+    db.set_state(verifying, JobState::Verifying).await.unwrap();
+    assert_eq!(
+        db.get_job(verifying).await.unwrap().unwrap().state,
+        JobState::Verifying
+    );
 
     let n = db.recover_running_jobs().await.unwrap();
-    assert_eq!(n, 1);
+    assert_eq!(n, 2);
     let jobs = db.list_jobs().await.unwrap();
-    assert_eq!(jobs[0].state, JobState::Queued);
+    assert!(jobs.iter().all(|j| j.state == JobState::Queued));
+}
+
+/// Mark `id` as running, owned by `pid` with a heartbeat `age` seconds old.
+async fn set_owner(db: &ResumeDb, id: i64, pid: i64, age: i64) {
+    let now = crate::resume_db::db::unix_timestamp();
+    sqlx::query(
+        "UPDATE jobs SET state = 'running', owner_pid = ?1, heartbeat_at = ?2 WHERE id = ?3",
+    )
+    .bind(pid)
+    .bind(now - age)
+    .bind(id)
+    .execute(&db.pool)
+    .await
+    .unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn recover_running_jobs_leaves_jobs_of_live_owners() {
+    use crate::resume_db::jobs::HEARTBEAT_STALE_SECS;
+    let db = open_memory().await.unwrap();
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let url = format!("https://example.com/{i}");
+        ids.push(db.add_job(&url, &JobSettings::default()).await.unwrap());
+    }
+    // pid 1 (init) is always alive; 2147483646 is never a live pid.
+    set_owner(&db, ids[0], 1, 0).await;
+    set_owner(&db, ids[1], 1, HEARTBEAT_STALE_SECS + 10).await;
+    set_owner(&db, ids[2], 2_147_483_646, 0).await;
+
+    assert_eq!(db.recover_running_jobs().await.unwrap(), 2);
+    let expected = [JobState::Running, JobState::Queued, JobState::Queued];
+    for (id, want) in ids.into_iter().zip(expected) {
+        assert_eq!(db.get_job(id).await.unwrap().unwrap().state, want);
+    }
+}
+
+#[tokio::test]
+async fn heartbeat_only_touches_jobs_owned_by_this_process() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job("https://example.com/x", &JobSettings::default())
+        .await
+        .unwrap();
+    set_owner(&db, id, 1, 1000).await;
+    db.heartbeat(id).await.unwrap();
+    let (hb,): (i64,) = sqlx::query_as("SELECT heartbeat_at FROM jobs WHERE id = ?1")
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert!(crate::resume_db::db::unix_timestamp() - hb >= 1000);
+
+    set_owner(&db, id, std::process::id() as i64, 1000).await;
+    db.heartbeat(id).await.unwrap();
+    let (hb,): (i64,) = sqlx::query_as("SELECT heartbeat_at FROM jobs WHERE id = ?1")
+        .bind(id)
+        .fetch_one(&db.pool)
+        .await
+        .unwrap();
+    assert!(crate::resume_db::db::unix_timestamp() - hb < 5);
 }
 
 #[tokio::test]
@@ -79,6 +156,22 @@ async fn job_settings_serialized_in_db() {
         note: Some("test job".to_string()),
         custom_headers: None,
         download_dir: None,
+        priority: 0,
+        post: None,
+        segment_count: None,
+        deadline_secs: None,
+        mirrors: Some(vec!["https://mirror.example.com/x".to_string()]),
+        expected_checksum: Some((ChecksumAlgo::Sha256, "ab".repeat(32))),
+        output_filename: None,
+        expected_size: None,
+        insecure: false,
+        cookie_session: false,
+        min_segments: None,
+        max_segments: None,
+        user_agent: None,
+        on_complete: None,
+        order: SegmentOrder::Sequential,
+        max_bytes_per_sec: Some(4096),
     };
     let id = db
         .add_job("https://example.com/x", &settings)
@@ -87,6 +180,38 @@ async fn job_settings_serialized_in_db() {
     let jobs = db.list_jobs().await.unwrap();
     assert_eq!(jobs.len(), 1);
     assert_eq!(jobs[0].id, id);
+    assert_eq!(jobs[0].note.as_deref(), Some("test job"));
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.settings.mirrors, settings.mirrors);
+    assert_eq!(job.settings.expected_checksum, settings.expected_checksum);
+    assert_eq!(job.settings.order, SegmentOrder::Sequential);
+    assert_eq!(job.settings.max_bytes_per_sec, Some(4096));
+}
+
+#[test]
+fn trickle_preset_keeps_stricter_explicit_values() {
+    let mut settings = JobSettings::default();
+    settings.apply_trickle();
+    assert_eq!(
+        settings.max_segments,
+        Some(crate::resume_db::TRICKLE_MAX_SEGMENTS)
+    );
+    assert_eq!(
+        settings.max_bytes_per_sec,
+        Some(crate::resume_db::TRICKLE_MAX_BYTES_PER_SEC)
+    );
+    assert_eq!(settings.priority, crate::resume_db::TRICKLE_PRIORITY);
+
+    let mut settings = JobSettings {
+        priority: 5,
+        min_segments: Some(8),
+        max_segments: Some(1),
+        ..JobSettings::default()
+    };
+    settings.apply_trickle();
+    assert_eq!(settings.max_segments, Some(1));
+    assert_eq!(settings.min_segments, Some(1));
+    assert_eq!(settings.priority, 5, "explicit --priority wins");
 }
 
 #[tokio::test]
@@ -131,6 +256,141 @@ async fn claim_next_queued_job_atomic() {
     assert_eq!(claimed3, None);
 }
 
+#[tokio::test]
+async fn job_added_paused_is_not_claimed_until_resumed() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job_in_state(
+            "https://a.com/staged",
+            &JobSettings::default(),
+            JobState::Paused,
+        )
+        .await
+        .unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.state, JobState::Paused);
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), None);
+
+    db.set_state(id, JobState::Queued).await.unwrap();
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(id));
+}
+
+#[tokio::test]
+async fn move_in_queue_reorders_within_priority() {
+    let db = open_memory().await.unwrap();
+    let mut ids = Vec::new();
+    for name in ["a", "b", "c"] {
+        let url = format!("https://a.com/{name}");
+        ids.push(db.add_job(&url, &JobSettings::default()).await.unwrap());
+    }
+    let (a, b, c) = (ids[0], ids[1], ids[2]);
+    let high = db
+        .add_job(
+            "https://a.com/high",
+            &JobSettings {
+                priority: 5,
+                ..JobSettings::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    db.move_in_queue(c, true).await.unwrap();
+    db.move_in_queue(a, false).await.unwrap();
+    let next = crate::scheduler::next_queued_job_id(&db, &Default::default())
+        .await
+        .unwrap();
+    assert_eq!(next, Some(high), "priority still wins over queue position");
+    for expected in [high, c, b, a] {
+        assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(expected));
+    }
+    let err = db.move_in_queue(a, true).await.unwrap_err();
+    assert!(err.to_string().contains("not queued"), "{err}");
+}
+
+#[tokio::test]
+async fn claim_next_queued_job_prefers_priority() {
+    let db = open_memory().await.unwrap();
+    let low = db
+        .add_job("https://a.com/low", &JobSettings::default())
+        .await
+        .unwrap();
+    let high = db
+        .add_job(
+            "https://a.com/high",
+            &JobSettings {
+                priority: 10,
+                ..JobSettings::default()
+            },
+        )
+        .await
+        .unwrap();
+    let low2 = db
+        .add_job("https://a.com/low2", &JobSettings::default())
+        .await
+        .unwrap();
+
+    let jobs = db.list_jobs().await.unwrap();
+    assert_eq!(jobs.iter().find(|j| j.id == high).unwrap().priority, 10);
+
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(high));
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(low));
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(low2));
+}
+
+#[tokio::test]
+async fn claim_next_queued_job_matching_skips_filtered_hosts() {
+    let db = open_memory().await.unwrap();
+    let a = db
+        .add_job("https://a.com/one", &JobSettings::default())
+        .await
+        .unwrap();
+    let b = db
+        .add_job("https://cdn.b.com/two", &JobSettings::default())
+        .await
+        .unwrap();
+
+    let only_b = HostFilter::new(["cdn.b.com".to_string()], []);
+    assert_eq!(
+        db.claim_next_queued_job_matching(&only_b).await.unwrap(),
+        Some(b)
+    );
+    assert_eq!(
+        db.claim_next_queued_job_matching(&only_b).await.unwrap(),
+        None
+    );
+    assert_eq!(
+        db.get_job(a).await.unwrap().unwrap().state,
+        JobState::Queued
+    );
+
+    let not_a = HostFilter::new([], ["a.com".to_string()]);
+    assert_eq!(
+        db.claim_next_queued_job_matching(&not_a).await.unwrap(),
+        None
+    );
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(a));
+}
+
+/// Reopening an existing database re-runs migrations without failing on added columns.
+#[tokio::test]
+async fn reopen_existing_db_keeps_priority() {
+    let dir = tempfile::tempdir().unwrap();
+    let db_path = dir.path().join("jobs.db");
+    let db = ResumeDb::open_at(&db_path).await.unwrap();
+    let settings = JobSettings {
+        priority: 3,
+        ..JobSettings::default()
+    };
+    let id = db.add_job("https://a.com/x", &settings).await.unwrap();
+    drop(db);
+
+    let db = ResumeDb::open_at(&db_path).await.unwrap();
+    let jobs = db.list_jobs().await.unwrap();
+    assert_eq!(jobs[0].id, id);
+    assert_eq!(jobs[0].priority, 3);
+}
+
 #[tokio::test]
 async fn get_job_and_update_metadata_roundtrip() {
     let db = open_memory().await.unwrap();
@@ -180,6 +440,180 @@ async fn get_job_and_update_metadata_roundtrip() {
     assert_eq!(job3.segment_count, 4);
 }
 
+#[tokio::test]
+async fn segment_progress_roundtrip_and_reset_by_new_plan() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job("https://example.com/f", &JobSettings::default())
+        .await
+        .unwrap();
+    assert!(db
+        .get_job(id)
+        .await
+        .unwrap()
+        .unwrap()
+        .segment_progress
+        .is_empty());
+
+    db.set_segment_progress(id, &[0, 512, 0]).await.unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.segment_progress, vec![0, 512, 0]);
+
+    // A new segment plan invalidates offsets recorded against the old one.
+    let meta = JobMetadata {
+        final_filename: None,
+        temp_filename: None,
+        total_size: Some(2048),
+        etag: None,
+        last_modified: None,
+        segment_count: 2,
+        completed_bitmap: vec![0],
+    };
+    db.update_metadata(id, &meta).await.unwrap();
+    assert!(db
+        .get_job(id)
+        .await
+        .unwrap()
+        .unwrap()
+        .segment_progress
+        .is_empty());
+}
+
+#[tokio::test]
+async fn reset_plan_clears_metadata_and_progress() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job("https://example.com/f", &JobSettings::default())
+        .await
+        .unwrap();
+    let meta = JobMetadata {
+        final_filename: Some("f".to_string()),
+        temp_filename: Some("f.part".to_string()),
+        total_size: Some(2048),
+        etag: Some("\"abc\"".to_string()),
+        last_modified: None,
+        segment_count: 2,
+        completed_bitmap: vec![1],
+    };
+    db.update_metadata(id, &meta).await.unwrap();
+    db.set_segment_progress(id, &[0, 512]).await.unwrap();
+    db.set_segment_hashes(id, &[Some("00".to_string()), None])
+        .await
+        .unwrap();
+
+    db.reset_plan(id).await.unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.total_size, None);
+    assert_eq!(job.etag, None);
+    assert_eq!(job.segment_count, 0);
+    assert!(job.completed_bitmap.is_empty());
+    assert!(job.segment_progress.is_empty());
+    assert!(job.segment_hashes.is_empty());
+    assert_eq!(job.final_filename.as_deref(), Some("f"));
+}
+
+#[tokio::test]
+async fn set_error_records_message_and_category() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job("https://example.com/f", &JobSettings::default())
+        .await
+        .unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.last_error, None);
+    assert_eq!(job.error_category, None);
+
+    db.set_error(
+        id,
+        ErrorCategory::Storage,
+        "segment 2: No space left on device",
+    )
+    .await
+    .unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.state, JobState::Error);
+    assert_eq!(
+        job.last_error.as_deref(),
+        Some("segment 2: No space left on device")
+    );
+    assert_eq!(job.error_category, Some(ErrorCategory::Storage));
+    let summary = db.list_jobs().await.unwrap();
+    assert_eq!(summary[0].error_category, Some(ErrorCategory::Storage));
+
+    db.clear_error(id).await.unwrap();
+    let job = db.get_job(id).await.unwrap().unwrap();
+    assert_eq!(job.last_error, None);
+    assert_eq!(job.error_category, None);
+    assert_eq!(job.state, JobState::Error);
+}
+
+#[tokio::test]
+async fn final_filenames_are_listed_per_download_dir() {
+    let db = open_memory().await.unwrap();
+    let add = |dir: Option<&str>, name: &'static str| {
+        let settings = JobSettings {
+            download_dir: dir.map(str::to_string),
+            ..Default::default()
+        };
+        let db = &db;
+        async move {
+            let id = db
+                .add_job("https://example.com/f", &settings)
+                .await
+                .unwrap();
+            let meta = JobMetadata {
+                final_filename: Some(name.to_string()),
+                temp_filename: None,
+                total_size: None,
+                etag: None,
+                last_modified: None,
+                segment_count: 0,
+                completed_bitmap: Vec::new(),
+            };
+            db.update_metadata(id, &meta).await.unwrap();
+            id
+        }
+    };
+    let a = add(Some("/data"), "a.iso").await;
+    add(Some("/data"), "b.iso").await;
+    add(Some("/other"), "c.iso").await;
+    add(None, "legacy.iso").await;
+    let unnamed = JobSettings {
+        download_dir: Some("/data".to_string()),
+        ..Default::default()
+    };
+    db.add_job("https://example.com/g", &unnamed).await.unwrap();
+
+    let mut names = db
+        .list_final_filenames_in_dir(Some("/data"), None)
+        .await
+        .unwrap();
+    names.sort();
+    assert_eq!(names, vec!["a.iso", "b.iso"]);
+    let names = db
+        .list_final_filenames_in_dir(Some("/data"), Some(a))
+        .await
+        .unwrap();
+    assert_eq!(names, vec!["b.iso"]);
+    let names = db.list_final_filenames_in_dir(None, None).await.unwrap();
+    assert_eq!(names, vec!["legacy.iso"]);
+
+    // The directory lookup is served by the index rather than a table scan.
+    let plan: Vec<(i64, i64, i64, String)> = sqlx::query_as(&format!(
+        "EXPLAIN QUERY PLAN SELECT final_filename FROM jobs WHERE {} = ?1",
+        crate::resume_db::db::JOB_DIR_EXPR
+    ))
+    .bind("/data")
+    .fetch_all(&db.pool)
+    .await
+    .unwrap();
+    assert!(
+        plan.iter()
+            .any(|(.., d)| d.contains("jobs_dir_final_filename")),
+        "{plan:?}"
+    );
+}
+
 /// open_at with a path containing a space must work (URI percent-encoding).
 #[tokio::test]
 async fn open_at_path_with_space() {
@@ -195,4 +629,212 @@ async fn open_at_path_with_space() {
     let jobs = db.list_jobs().await.unwrap();
     assert_eq!(jobs.len(), 1);
     assert_eq!(jobs[0].id, id);
+    assert_eq!(db.state_dir(), sub);
+}
+
+#[tokio::test]
+async fn job_events_record_list_and_remove() {
+    let db = open_memory().await.unwrap();
+    let id = db
+        .add_job("https://example.com/f", &JobSettings::default())
+        .await
+        .unwrap();
+    assert!(db.list_job_events(id).await.unwrap().is_empty());
+
+    let first = JobEvent {
+        job_id: id,
+        timestamp: 1_000,
+        bytes_this_run: 4096,
+        duration_secs: 1.5,
+        throttle_events: 2,
+        error_events: 1,
+        segment_count: 8,
+    };
+    let second = JobEvent {
+        timestamp: 2_000,
+        bytes_this_run: 1024,
+        ..first.clone()
+    };
+    db.record_job_event(&second).await.unwrap();
+    db.record_job_event(&first).await.unwrap();
+
+    let events = db.list_job_events(id).await.unwrap();
+    assert_eq!(events, vec![first, second]);
+
+    db.remove_job(id).await.unwrap();
+    assert!(db.list_job_events(id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn state_transitions_are_logged() {
+    let db = open_memory().await.unwrap();
+    let a = db
+        .add_job("https://example.com/a", &JobSettings::default())
+        .await
+        .unwrap();
+    let b = db
+        .add_job_in_state(
+            "https://example.com/b",
+            &JobSettings::default(),
+            JobState::Paused,
+        )
+        .await
+        .unwrap();
+    assert_eq!(db.claim_next_queued_job().await.unwrap(), Some(a));
+    db.set_state(a, JobState::Running).await.unwrap();
+    db.set_error(a, ErrorCategory::Network, "reset")
+        .await
+        .unwrap();
+    db.set_state(a, JobState::Queued).await.unwrap();
+    db.set_state(a, JobState::Completed).await.unwrap();
+    db.remove_job(a).await.unwrap();
+
+    let transitions: Vec<_> = db
+        .list_state_changes(None, Some(a))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|c| (c.from, c.to))
+        .collect();
+    assert_eq!(
+        transitions,
+        [
+            (None, JobState::Queued),
+            (Some(JobState::Queued), JobState::Running),
+            (Some(JobState::Running), JobState::Error),
+            (Some(JobState::Error), JobState::Queued),
+            (Some(JobState::Queued), JobState::Completed),
+        ],
+        "a no-op Running -> Running update is not logged; removal keeps the log"
+    );
+    let all = db.list_state_changes(None, None).await.unwrap();
+    assert_eq!(all.len(), 6);
+    assert!(all
+        .iter()
+        .any(|c| c.job_id == b && c.to == JobState::Paused));
+    let future = all.last().unwrap().timestamp + 1;
+    assert!(db
+        .list_state_changes(Some(future), None)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn temp_filenames_listed_with_their_download_dir() {
+    let db = open_memory().await.unwrap();
+    for (dir, temp) in [
+        (Some("/data"), Some("a.iso.part")),
+        (None, Some("legacy.iso.part")),
+        (Some("/data"), None),
+    ] {
+        let settings = JobSettings {
+            download_dir: dir.map(str::to_string),
+            ..Default::default()
+        };
+        let id = db
+            .add_job("https://example.com/f", &settings)
+            .await
+            .unwrap();
+        let meta = JobMetadata {
+            final_filename: None,
+            temp_filename: temp.map(str::to_string),
+            total_size: None,
+            etag: None,
+            last_modified: None,
+            segment_count: 0,
+            completed_bitmap: Vec::new(),
+        };
+        db.update_metadata(id, &meta).await.unwrap();
+    }
+    let mut temps = db.list_temp_filenames().await.unwrap();
+    temps.sort();
+    assert_eq!(
+        temps,
+        vec![
+            (None, "legacy.iso.part".to_string()),
+            (Some("/data".to_string()), "a.iso.part".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn export_import_roundtrips_the_plan_without_secrets() {
+    let db = open_memory().await.unwrap();
+    let settings = JobSettings {
+        custom_headers: Some([("Cookie".to_string(), "session=abc".to_string())].into()),
+        cookie_session: true,
+        download_dir: Some("/srv/isos".to_string()),
+        note: Some("mirror test".to_string()),
+        on_complete: Some("notify-send done".to_string()),
+        insecure: true,
+        ..JobSettings::default()
+    };
+    let id = db
+        .add_job("https://example.com/debian.iso", &settings)
+        .await
+        .unwrap();
+    let meta = JobMetadata {
+        final_filename: Some("debian.iso".to_string()),
+        temp_filename: Some("debian.iso.part".to_string()),
+        total_size: Some(1000),
+        etag: Some("\"v1\"".to_string()),
+        last_modified: None,
+        segment_count: 3,
+        completed_bitmap: vec![0b011],
+    };
+    db.update_metadata(id, &meta).await.unwrap();
+    let hashes = vec![Some("aa".repeat(32)), None, Some("cc".repeat(32))];
+    db.set_segment_hashes(id, &hashes).await.unwrap();
+
+    let export = db.export_job(id, false).await.unwrap();
+    assert_eq!(export.segments, [[0, 334], [334, 667], [667, 1000]]);
+    assert!(export.settings.custom_headers.is_none());
+    assert!(!export.settings.cookie_session);
+    assert!(export.settings.download_dir.is_none());
+    assert!(export.settings.on_complete.is_none());
+    assert!(!export.settings.insecure);
+    let sensitive = db.export_job(id, true).await.unwrap();
+    assert!(sensitive.settings.cookie_session);
+    assert!(sensitive.settings.insecure);
+
+    // An untrusted plan cannot bring a hook or `insecure` along; a trusted one can.
+    let untrusted = db.import_job(&sensitive, false).await.unwrap();
+    let job = db.get_job(untrusted).await.unwrap().unwrap();
+    assert!(job.settings.on_complete.is_none());
+    assert!(!job.settings.insecure);
+    let trusted = db.import_job(&sensitive, true).await.unwrap();
+    let job = db.get_job(trusted).await.unwrap().unwrap();
+    assert_eq!(
+        job.settings.on_complete.as_deref(),
+        Some("notify-send done")
+    );
+    assert!(job.settings.insecure);
+
+    let json = serde_json::to_string(&export).unwrap();
+    let imported = db
+        .import_job(&serde_json::from_str(&json).unwrap(), false)
+        .await
+        .unwrap();
+    let job = db.get_job(imported).await.unwrap().unwrap();
+    assert_eq!(job.state, JobState::Queued);
+    assert_eq!(job.url, "https://example.com/debian.iso");
+    assert_eq!(job.final_filename.as_deref(), Some("debian.iso"));
+    assert_eq!(job.temp_filename, None);
+    assert_eq!(job.total_size, Some(1000));
+    assert_eq!(job.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(job.segment_count, 3);
+    assert_eq!(job.completed_bitmap, vec![0]);
+    assert_eq!(job.segment_hashes, hashes);
+    assert_eq!(job.settings.note.as_deref(), Some("mirror test"));
+
+    let mut bad = export.clone();
+    bad.segments[1] = [334, 700];
+    assert!(db.import_job(&bad, false).await.is_err());
+    let mut bad = export.clone();
+    bad.final_filename = Some("../../.bashrc".to_string());
+    assert!(db.import_job(&bad, true).await.is_err());
+    let mut bad = export;
+    bad.version = 99;
+    assert!(db.import_job(&bad, false).await.is_err());
 }
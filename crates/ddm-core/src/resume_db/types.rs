@@ -8,6 +8,9 @@ pub type JobId = i64;
 pub enum JobState {
     Queued,
     Running,
+    /// Downloaded; the whole file is being hashed against `--md5/--sha1/--sha256` before
+    /// it is moved into place. Ends in Completed or Error.
+    Verifying,
     Paused,
     Completed,
     Error,
@@ -18,6 +21,7 @@ impl JobState {
         match self {
             JobState::Queued => "queued",
             JobState::Running => "running",
+            JobState::Verifying => "verifying",
             JobState::Paused => "paused",
             JobState::Completed => "completed",
             JobState::Error => "error",
@@ -28,6 +32,7 @@ impl JobState {
         match s {
             "queued" => JobState::Queued,
             "running" => JobState::Running,
+            "verifying" => JobState::Verifying,
             "paused" => JobState::Paused,
             "completed" => JobState::Completed,
             "error" => JobState::Error,
@@ -36,6 +41,42 @@ impl JobState {
     }
 }
 
+/// Why a job ended in [`JobState::Error`], stored next to the error message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// Connection, timeout, HTTP status, or malformed response after retries.
+    Network,
+    /// The remote file changed since the job's validators were stored.
+    RemoteChanged,
+    /// Local disk/filesystem failure (e.g. disk full, permission denied).
+    Storage,
+    /// The job's `--deadline` ran out.
+    Deadline,
+    Other,
+}
+
+impl ErrorCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "network",
+            ErrorCategory::RemoteChanged => "remote_changed",
+            ErrorCategory::Storage => "storage",
+            ErrorCategory::Deadline => "deadline",
+            ErrorCategory::Other => "other",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "network" => ErrorCategory::Network,
+            "remote_changed" => ErrorCategory::RemoteChanged,
+            "storage" => ErrorCategory::Storage,
+            "deadline" => ErrorCategory::Deadline,
+            _ => ErrorCategory::Other,
+        }
+    }
+}
+
 /// Minimal per-job settings container, stored as JSON in the DB.
 ///
 /// This keeps the schema flexible while still allowing structured config
@@ -51,6 +92,97 @@ pub struct JobSettings {
     /// Directory where this job's files are (or will be) stored. If set, run uses this instead of the CLI's current directory.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub download_dir: Option<String>,
+    /// Scheduling priority: higher runs first; equal priorities run in insertion order.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub priority: i32,
+    /// POST to replay before each run to obtain the download URL (HAR import with --allow-post).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post: Option<crate::resolver::PostRequest>,
+    /// Forced segment count (from `ddm add --auto-bench`); overrides the adaptive choice.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub segment_count: Option<usize>,
+    /// Per-run time limit in seconds (`ddm add --deadline`); the run stops with an error when it elapses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_secs: Option<u64>,
+    /// Mirror URLs serving the same file (`ddm add --mirror-list`); segments are spread across
+    /// the primary URL and these.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirrors: Option<Vec<String>>,
+    /// Whole-file digest the finished download must match (`ddm add --md5/--sha1/--sha256`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_checksum: Option<(crate::checksum::ChecksumAlgo, String)>,
+    /// Final filename chosen with `ddm add --output`; used as-is (sanitized) instead of the
+    /// derived name, without collision renaming.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_filename: Option<String>,
+    /// Exact size in bytes the server must report (`ddm add --expected-size`); a different
+    /// Content-Length fails the run before any segment is planned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_size: Option<u64>,
+    /// Skip TLS certificate verification for this job (`ddm add --insecure`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure: bool,
+    /// Per-job lower bound on segments (`ddm add --min-segments`); overrides `min_segments` in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_segments: Option<usize>,
+    /// Per-job upper bound on segments (`ddm add --max-segments`); overrides `max_segments` in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_segments: Option<usize>,
+    /// Per-job `User-Agent` (`ddm add --user-agent`); overrides `user_agent` in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Per-job completion hook (`ddm add --on-complete`); overrides `on_complete` in config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_complete: Option<String>,
+    /// Segment fetch order (`ddm add --order`); `sequential` fills the file from the front.
+    #[serde(
+        default,
+        skip_serializing_if = "crate::segmenter::SegmentOrder::is_parallel"
+    )]
+    pub order: crate::segmenter::SegmentOrder,
+    /// Send the `Cookie` from `custom_headers` through curl's cookie engine instead of as a
+    /// plain header, so it survives redirects to other hosts and cookies set along the
+    /// redirect chain are kept (`ddm import-har --allow-cookies`).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cookie_session: bool,
+    /// Per-job bandwidth cap in bytes/sec (`ddm add --trickle`); the tighter of this and the
+    /// configured cap (including `bandwidth_schedule`) applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+/// Segment ceiling set by `ddm add --trickle`.
+pub const TRICKLE_MAX_SEGMENTS: usize = 2;
+/// Bandwidth cap set by `ddm add --trickle` (256 KiB/s).
+pub const TRICKLE_MAX_BYTES_PER_SEC: u64 = 256 * 1024;
+/// Priority set by `ddm add --trickle`, below the default 0 so other jobs run first.
+pub const TRICKLE_PRIORITY: i32 = -10;
+
+impl JobSettings {
+    /// Low-impact preset (`ddm add --trickle`): at most [`TRICKLE_MAX_SEGMENTS`] segments,
+    /// a [`TRICKLE_MAX_BYTES_PER_SEC`] cap and [`TRICKLE_PRIORITY`]. Values set explicitly
+    /// (a lower segment ceiling or cap, a non-default priority) are kept.
+    pub fn apply_trickle(&mut self) {
+        let max = self
+            .max_segments
+            .map_or(TRICKLE_MAX_SEGMENTS, |m| m.min(TRICKLE_MAX_SEGMENTS));
+        self.max_segments = Some(max);
+        self.min_segments = self.min_segments.map(|m| m.min(max));
+        self.segment_count = self.segment_count.map(|n| n.min(max));
+        self.max_bytes_per_sec = Some(
+            self.max_bytes_per_sec
+                .map_or(TRICKLE_MAX_BYTES_PER_SEC, |b| {
+                    b.min(TRICKLE_MAX_BYTES_PER_SEC)
+                }),
+        );
+        if self.priority == 0 {
+            self.priority = TRICKLE_PRIORITY;
+        }
+    }
+}
+
+fn is_zero(n: &i32) -> bool {
+    *n == 0
 }
 
 /// Summary view used by the CLI `status` command.
@@ -61,6 +193,14 @@ pub struct JobSummary {
     pub state: JobState,
     pub final_filename: Option<String>,
     pub total_size: Option<i64>,
+    pub priority: i32,
+    /// Order among queued jobs of equal priority, lowest first (`ddm queue move`; the id
+    /// unless the job was moved).
+    pub queue_position: i64,
+    /// User label from `ddm add --note`.
+    pub note: Option<String>,
+    /// Category of the last failure, if the job has failed.
+    pub error_category: Option<ErrorCategory>,
 }
 
 /// Full job record used by the scheduler / downloader.
@@ -79,6 +219,15 @@ pub struct JobDetails {
     pub created_at: i64,
     pub updated_at: i64,
     pub settings: JobSettings,
+    /// Per-segment SHA-256 (hex) recorded while downloading; None where not hashed.
+    pub segment_hashes: Vec<Option<String>>,
+    /// Bytes written from the start of each segment as of the last progress flush
+    /// (index = segment; empty when none). Only meaningful for incomplete segments.
+    pub segment_progress: Vec<u64>,
+    /// Message of the last failure that put the job in [`JobState::Error`].
+    pub last_error: Option<String>,
+    /// Category of `last_error`.
+    pub error_category: Option<ErrorCategory>,
 }
 
 /// Metadata fields updated after HEAD / segment planning.
@@ -92,3 +241,27 @@ pub struct JobMetadata {
     pub segment_count: i64,
     pub completed_bitmap: Vec<u8>,
 }
+
+/// One recorded download run for a job (appended after each download phase).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobEvent {
+    pub job_id: JobId,
+    /// Unix seconds when the run finished.
+    pub timestamp: i64,
+    pub bytes_this_run: u64,
+    pub duration_secs: f64,
+    pub throttle_events: u32,
+    pub error_events: u32,
+    pub segment_count: u32,
+}
+
+/// One job state transition from the `job_state_log` (`ddm events`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobStateChange {
+    pub job_id: JobId,
+    /// Unix seconds of the transition.
+    pub timestamp: i64,
+    /// Previous state; None when the job was added.
+    pub from: Option<JobState>,
+    pub to: JobState,
+}
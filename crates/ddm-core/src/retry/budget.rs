@@ -0,0 +1,57 @@
+//! Job-wide retry budget shared by all segments of one download run.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Counts retries across every segment of a job and refuses further retries once
+/// `limit` is spent (`0` = unlimited). Shared between worker threads.
+#[derive(Debug)]
+pub struct RetryBudget {
+    limit: u32,
+    used: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            used: AtomicU32::new(0),
+        }
+    }
+
+    /// Claim one retry. Returns false (without counting it) when the budget is exhausted.
+    pub fn try_spend(&self) -> bool {
+        self.used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                (self.limit == 0 || n < self.limit).then_some(n + 1)
+            })
+            .is_ok()
+    }
+
+    /// Retries performed so far.
+    pub fn used(&self) -> u32 {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops_at_limit() {
+        let b = RetryBudget::new(2);
+        assert!(b.try_spend());
+        assert!(b.try_spend());
+        assert!(!b.try_spend());
+        assert_eq!(b.used(), 2);
+    }
+
+    #[test]
+    fn zero_is_unlimited() {
+        let b = RetryBudget::new(0);
+        for _ in 0..1000 {
+            assert!(b.try_spend());
+        }
+        assert_eq!(b.used(), 1000);
+    }
+}
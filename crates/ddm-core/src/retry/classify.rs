@@ -27,6 +27,7 @@ pub fn classify_curl_error(e: &curl::Error) -> ErrorKind {
         || e.is_recv_error()
         || e.is_send_error()
         || e.is_got_nothing()
+        || e.is_partial_file()
     {
         return ErrorKind::Connection;
     }
@@ -40,7 +41,12 @@ pub fn classify(e: &SegmentError) -> ErrorKind {
         SegmentError::Http(code) => classify_http_status(*code),
         SegmentError::InvalidRangeResponse(_) => ErrorKind::Other,
         SegmentError::PartialTransfer { .. } => ErrorKind::Connection,
+        SegmentError::Stalled { .. } => ErrorKind::Timeout,
+        SegmentError::RemoteChanged => ErrorKind::Other,
+        SegmentError::TotalSizeMismatch { .. } => ErrorKind::Other,
+        SegmentError::UnexpectedEncoding(_) => ErrorKind::Other,
         SegmentError::Storage(_) => ErrorKind::Other,
+        SegmentError::RetryBudgetExhausted { .. } => ErrorKind::Other,
     }
 }
 
@@ -66,6 +72,26 @@ mod tests {
         assert_eq!(classify_http_status(403), ErrorKind::Other);
     }
 
+    #[test]
+    fn dns_resolution_failure_is_retryable_connection_error() {
+        // CURLE_COULDNT_RESOLVE_PROXY (5) and CURLE_COULDNT_RESOLVE_HOST (6).
+        for code in [5, 6] {
+            let e = SegmentError::Curl(curl::Error::new(code));
+            assert_eq!(classify(&e), ErrorKind::Connection, "curl code {code}");
+            assert!(matches!(
+                super::super::RetryPolicy::default().decide(1, classify(&e)),
+                super::super::RetryDecision::RetryAfter(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn curl_partial_file_is_retryable_connection_error() {
+        // CURLE_PARTIAL_FILE (18): the server closed before sending Content-Length bytes.
+        let e = SegmentError::Curl(curl::Error::new(18));
+        assert_eq!(classify(&e), ErrorKind::Connection);
+    }
+
     #[test]
     fn partial_transfer_classified_as_connection() {
         let e = SegmentError::PartialTransfer {
@@ -84,6 +110,26 @@ mod tests {
         assert_eq!(classify(&e), ErrorKind::Other);
     }
 
+    #[test]
+    fn unexpected_encoding_classified_as_other() {
+        let e = SegmentError::UnexpectedEncoding("gzip".to_string());
+        assert_eq!(classify(&e), ErrorKind::Other);
+    }
+
+    #[test]
+    fn remote_changed_classified_as_other() {
+        assert_eq!(classify(&SegmentError::RemoteChanged), ErrorKind::Other);
+    }
+
+    #[test]
+    fn total_size_mismatch_classified_as_other() {
+        let e = SegmentError::TotalSizeMismatch {
+            expected: 1000,
+            reported: 2000,
+        };
+        assert_eq!(classify(&e), ErrorKind::Other);
+    }
+
     #[test]
     fn invalid_range_response_classified_as_other() {
         let e = SegmentError::InvalidRangeResponse(200);
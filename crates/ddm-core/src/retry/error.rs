@@ -16,8 +16,36 @@ pub enum SegmentError {
     /// Transfer completed but fewer bytes were written than the segment length
     /// (e.g. server closed early). Enables retry instead of silent corruption.
     PartialTransfer { expected: u64, received: u64 },
+    /// We sent `If-Range` and the server answered 200 instead of 206: the remote file
+    /// changed since the job's validators were stored. Not retried.
+    RemoteChanged,
+    /// The response carried a `Content-Encoding` other than `identity` (e.g. gzip);
+    /// writing encoded bytes at segment offsets would corrupt the file. Not retried.
+    UnexpectedEncoding(String),
+    /// The `Content-Range` total disagrees with the job's known size: the server is
+    /// serving a different version of the file (e.g. a mirror mid-update). Not retried.
+    TotalSizeMismatch { expected: u64, reported: u64 },
+    /// The segment's throughput stayed below `min_segment_bps` for `stall_window_secs`
+    /// and the watchdog aborted it after `received` bytes. Retried like a timeout.
+    Stalled { received: u64 },
     /// Disk/storage write failed (e.g. disk full, permission denied). Not retried.
     Storage(std::io::Error),
+    /// The job-wide retry budget (`[retry] max_job_retries`) ran out; `last` is the error
+    /// that would have been retried. Not retried.
+    RetryBudgetExhausted {
+        retries: u32,
+        last: Box<SegmentError>,
+    },
+}
+
+impl SegmentError {
+    /// Wrap `last` as [`SegmentError::RetryBudgetExhausted`] with the budget's retry count.
+    pub fn budget_exhausted(budget: &super::RetryBudget, last: SegmentError) -> Self {
+        SegmentError::RetryBudgetExhausted {
+            retries: budget.used(),
+            last: Box::new(last),
+        }
+    }
 }
 
 impl fmt::Display for SegmentError {
@@ -39,7 +67,33 @@ impl fmt::Display for SegmentError {
                     expected, received
                 )
             }
+            SegmentError::RemoteChanged => write!(
+                f,
+                "remote file changed during download (If-Range validator no longer matches)"
+            ),
+            SegmentError::UnexpectedEncoding(enc) => write!(
+                f,
+                "server applied Content-Encoding: {} to a range response; \
+                 the URL does not look directly downloadable",
+                enc
+            ),
+            SegmentError::TotalSizeMismatch { expected, reported } => write!(
+                f,
+                "Content-Range reports a total of {} bytes but the file is {} bytes \
+                 (the server is serving a different version)",
+                reported, expected
+            ),
+            SegmentError::Stalled { received } => write!(
+                f,
+                "segment stalled below min_segment_bps after {} bytes",
+                received
+            ),
             SegmentError::Storage(e) => write!(f, "storage: {}", e),
+            SegmentError::RetryBudgetExhausted { retries, last } => write!(
+                f,
+                "retry budget exhausted after {} retries across the job (last error: {})",
+                retries, last
+            ),
         }
     }
 }
@@ -49,9 +103,14 @@ impl std::error::Error for SegmentError {
         match self {
             SegmentError::Curl(e) => Some(e),
             SegmentError::Storage(e) => Some(e),
+            SegmentError::RetryBudgetExhausted { last, .. } => Some(last.as_ref()),
             SegmentError::Http(_)
             | SegmentError::InvalidRangeResponse(_)
-            | SegmentError::PartialTransfer { .. } => None,
+            | SegmentError::PartialTransfer { .. }
+            | SegmentError::Stalled { .. }
+            | SegmentError::RemoteChanged
+            | SegmentError::TotalSizeMismatch { .. }
+            | SegmentError::UnexpectedEncoding(_) => None,
         }
     }
 }
@@ -4,12 +4,14 @@
 //! connection failures) and exponential backoff decisions so that higher
 //! layers (scheduler, downloader) can share a consistent policy.
 
+mod budget;
 mod classify;
 mod error;
 mod policy;
 mod run;
 
+pub use budget::RetryBudget;
 pub use classify::{classify, classify_curl_error, classify_http_status};
 pub use error::SegmentError;
-pub use policy::{ErrorKind, RetryDecision, RetryPolicy};
+pub use policy::{ErrorKind, RetryDecision, RetryPolicy, DEFAULT_MAX_JOB_RETRIES};
 pub use run::run_with_retry;
@@ -1,3 +1,5 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
 use std::time::Duration;
 
 /// High-level classification of an error for retry purposes.
@@ -18,6 +20,9 @@ pub enum ErrorKind {
     Other,
 }
 
+/// Default job-wide retry budget (see `RetryPolicy::max_job_retries`).
+pub const DEFAULT_MAX_JOB_RETRIES: u32 = 100;
+
 /// Decision returned by the retry policy.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RetryDecision {
@@ -29,8 +34,7 @@ pub enum RetryDecision {
 
 /// Simple exponential backoff policy with caps.
 ///
-/// For now this is hard-coded; later it can be made configurable via
-/// `DdmConfig` once we extend the config schema.
+/// Built from `DdmConfig.retry` when present, otherwise the defaults below.
 #[derive(Debug, Clone, Copy)]
 pub struct RetryPolicy {
     /// Maximum number of attempts (including the first).
@@ -39,6 +43,11 @@ pub struct RetryPolicy {
     pub base_delay: Duration,
     /// Upper bound on backoff delay.
     pub max_delay: Duration,
+    /// Randomize each delay within `[base_delay, computed]` so segments that failed
+    /// together don't all retry at the same instant.
+    pub jitter: bool,
+    /// Total retries allowed across all segments of one job run (`0` = unlimited).
+    pub max_job_retries: u32,
 }
 
 impl Default for RetryPolicy {
@@ -47,6 +56,8 @@ impl Default for RetryPolicy {
             max_attempts: 5,
             base_delay: Duration::from_millis(250),
             max_delay: Duration::from_secs(30),
+            jitter: true,
+            max_job_retries: DEFAULT_MAX_JOB_RETRIES,
         }
     }
 }
@@ -57,6 +68,16 @@ impl RetryPolicy {
     /// `attempt` is 1-based (1 = first attempt). Returns `RetryDecision::NoRetry`
     /// when we should stop retrying.
     pub fn decide(&self, attempt: u32, kind: ErrorKind) -> RetryDecision {
+        self.decide_with_random(attempt, kind, random_u64())
+    }
+
+    /// `decide` with the jitter source supplied by the caller (for deterministic tests).
+    pub(crate) fn decide_with_random(
+        &self,
+        attempt: u32,
+        kind: ErrorKind,
+        random: u64,
+    ) -> RetryDecision {
         if attempt >= self.max_attempts {
             return RetryDecision::NoRetry;
         }
@@ -71,12 +92,40 @@ impl RetryPolicy {
                 let exp = 1u32.saturating_mul(1 << attempt.saturating_sub(1).min(8));
                 let raw = self.base_delay.saturating_mul(exp);
                 let delay = raw.min(self.max_delay);
-                RetryDecision::RetryAfter(delay)
+                if self.jitter {
+                    RetryDecision::RetryAfter(self.jittered(delay, random))
+                } else {
+                    RetryDecision::RetryAfter(delay)
+                }
             }
         }
     }
 }
 
+impl RetryPolicy {
+    /// Pick a delay in `[min(base_delay, delay), delay]` using `random`.
+    fn jittered(&self, delay: Duration, random: u64) -> Duration {
+        let low = self.base_delay.min(delay);
+        let span = (delay - low).as_nanos() as u64;
+        if span == 0 {
+            return delay;
+        }
+        low + Duration::from_nanos(random % (span + 1))
+    }
+}
+
+/// Per-call random value from std's randomly keyed hasher (no RNG dependency needed).
+fn random_u64() -> u64 {
+    let mut h = RandomState::new().build_hasher();
+    h.write_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+    );
+    h.finish()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -92,6 +141,7 @@ mod tests {
         let mut p = RetryPolicy::default();
         // Allow many attempts so we can observe capping behaviour.
         p.max_attempts = 20;
+        p.jitter = false;
         let d1 = match p.decide(1, ErrorKind::Timeout) {
             RetryDecision::RetryAfter(d) => d,
             _ => panic!("expected retry"),
@@ -124,4 +174,35 @@ mod tests {
         ));
         assert_eq!(p.decide(3, ErrorKind::Throttled), RetryDecision::NoRetry);
     }
+
+    #[test]
+    fn jitter_stays_within_base_and_computed_delay() {
+        let mut p = RetryPolicy {
+            max_attempts: 20,
+            ..RetryPolicy::default()
+        };
+        let computed = Duration::from_millis(250 * 8);
+        for random in [0, 1, 12_345, u64::MAX / 2, u64::MAX] {
+            match p.decide_with_random(4, ErrorKind::Throttled, random) {
+                RetryDecision::RetryAfter(d) => {
+                    assert!(d >= p.base_delay && d <= computed, "{d:?} out of bounds")
+                }
+                _ => panic!("expected retry"),
+            }
+        }
+        assert_eq!(
+            p.decide_with_random(4, ErrorKind::Throttled, 0),
+            RetryDecision::RetryAfter(p.base_delay)
+        );
+        // First attempt: computed == base, so there is nothing to randomize.
+        assert_eq!(
+            p.decide_with_random(1, ErrorKind::Timeout, 99),
+            RetryDecision::RetryAfter(p.base_delay)
+        );
+        p.jitter = false;
+        assert_eq!(
+            p.decide_with_random(4, ErrorKind::Throttled, 0),
+            RetryDecision::RetryAfter(computed)
+        );
+    }
 }
@@ -1,12 +1,19 @@
 //! Retry loop: run a closure until success or policy says stop.
 
+use super::budget::RetryBudget;
 use super::classify;
 use super::error::SegmentError;
 use super::policy::{RetryDecision, RetryPolicy};
 
 /// Runs a closure until it succeeds or the retry policy says to stop.
 /// On retryable failure, sleeps for the backoff duration then tries again.
-pub fn run_with_retry<F>(policy: &RetryPolicy, mut f: F) -> Result<(), SegmentError>
+/// Each retry is charged to `budget`; once it is spent the last error is returned
+/// as [`SegmentError::RetryBudgetExhausted`].
+pub fn run_with_retry<F>(
+    policy: &RetryPolicy,
+    budget: &RetryBudget,
+    mut f: F,
+) -> Result<(), SegmentError>
 where
     F: FnMut() -> Result<(), SegmentError>,
 {
@@ -18,6 +25,9 @@ where
                 let kind = classify::classify(&e);
                 match policy.decide(attempt, kind) {
                     RetryDecision::NoRetry => return Err(e),
+                    RetryDecision::RetryAfter(_) if !budget.try_spend() => {
+                        return Err(SegmentError::budget_exhausted(budget, e));
+                    }
                     RetryDecision::RetryAfter(d) => {
                         std::thread::sleep(d);
                         attempt += 1;
@@ -27,3 +37,35 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn budget_exhaustion_stops_retrying() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(1),
+            jitter: false,
+            max_job_retries: 2,
+        };
+        let budget = RetryBudget::new(policy.max_job_retries);
+        let mut calls = 0;
+        let res = run_with_retry(&policy, &budget, || {
+            calls += 1;
+            Err(SegmentError::Http(503))
+        });
+        assert_eq!(calls, 3);
+        assert!(matches!(
+            res,
+            Err(SegmentError::RetryBudgetExhausted { retries: 2, .. })
+        ));
+        assert_eq!(
+            classify::classify(&res.unwrap_err()),
+            super::super::ErrorKind::Other
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Choose the `If-Range` validator sent with segment Range requests.
+
+use crate::resume_db::JobDetails;
+
+/// Returns the validator to send as `If-Range` for this job's segment requests.
+///
+/// Prefers a strong ETag (weak `W/` ETags are not allowed in `If-Range`), then
+/// Last-Modified. If the server's copy no longer matches, it answers 200 with the
+/// full body instead of 206, which the downloader reports as a remote change.
+pub fn if_range_validator(job: &JobDetails) -> Option<String> {
+    job.etag
+        .as_deref()
+        .filter(|e| !e.starts_with("W/"))
+        .or(job.last_modified.as_deref())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resume_db::{JobSettings, JobState};
+
+    fn job(etag: Option<&str>, last_modified: Option<&str>) -> JobDetails {
+        JobDetails {
+            id: 1,
+            url: "https://example.com/f".to_string(),
+            final_filename: None,
+            temp_filename: None,
+            total_size: Some(10),
+            etag: etag.map(str::to_string),
+            last_modified: last_modified.map(str::to_string),
+            segment_count: 1,
+            completed_bitmap: Vec::new(),
+            state: JobState::Queued,
+            created_at: 0,
+            updated_at: 0,
+            settings: JobSettings::default(),
+            segment_hashes: Vec::new(),
+            segment_progress: Vec::new(),
+            last_error: None,
+            error_category: None,
+        }
+    }
+
+    #[test]
+    fn prefers_strong_etag() {
+        let j = job(Some("\"abc\""), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(if_range_validator(&j).as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn weak_etag_falls_back_to_last_modified() {
+        let j = job(Some("W/\"abc\""), Some("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(
+            if_range_validator(&j).as_deref(),
+            Some("Wed, 21 Oct 2015 07:28:00 GMT")
+        );
+        assert_eq!(if_range_validator(&job(Some("W/\"x\""), None)), None);
+    }
+
+    #[test]
+    fn no_validators() {
+        assert_eq!(if_range_validator(&job(None, None)), None);
+    }
+}
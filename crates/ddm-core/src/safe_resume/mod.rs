@@ -4,6 +4,8 @@
 //! job metadata. If anything changed, the caller must require an explicit user
 //! override (e.g. `--force-restart`) before discarding progress and re-downloading.
 
+mod if_range;
 mod validate;
 
+pub use if_range::if_range_validator;
 pub use validate::{validate_for_resume, ValidationError, ValidationErrorKind};
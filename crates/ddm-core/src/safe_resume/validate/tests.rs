@@ -1,6 +1,6 @@
 //! Tests for safe-resume validation.
 
-use crate::fetch_head::HeadResult;
+use crate::fetch_head::{HeadResult, RangeSupport};
 use crate::resume_db::{JobDetails, JobSettings, JobState};
 
 use super::{validate_for_resume, ValidationErrorKind};
@@ -24,6 +24,10 @@ fn job_details(
         created_at: 0,
         updated_at: 0,
         settings: JobSettings::default(),
+        segment_hashes: Vec::new(),
+        segment_progress: Vec::new(),
+        last_error: None,
+        error_category: None,
     }
 }
 
@@ -34,10 +38,11 @@ fn head_result(
 ) -> HeadResult {
     HeadResult {
         content_length,
-        accept_ranges: true,
+        range_support: RangeSupport::Supported,
         etag: etag.map(String::from),
         last_modified: last_modified.map(String::from),
         content_disposition: None,
+        server_digest: None,
     }
 }
 
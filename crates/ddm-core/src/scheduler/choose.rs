@@ -2,20 +2,172 @@
 
 use crate::config::DdmConfig;
 use crate::host_policy::HostPolicy;
+use crate::resume_db::JobSettings;
+use crate::segmenter::segment_count_for_size;
 
-/// Chooses segment count: adaptive (4/8/16) capped by host policy and config.
-pub(crate) fn choose_segment_count(
+/// Segment bounds for a job: its own `min_segments`/`max_segments` when set, else the
+/// global ones, with the maximum clamped to `absolute_max_segments`. The minimum never
+/// exceeds the maximum.
+pub fn segment_bounds(job: &JobSettings, cfg: &DdmConfig) -> (usize, usize) {
+    let max = cfg.clamp_segments(job.max_segments.unwrap_or(cfg.max_segments).max(1));
+    let min = job.min_segments.unwrap_or(cfg.min_segments).clamp(1, max);
+    (min, max)
+}
+
+/// Chooses segment count: adaptive (4/8/16) capped by host policy and the job's segment
+/// bounds (see [`segment_bounds`]). When `target_segment_bytes` is set and the size is
+/// known, pieces near that size are preferred instead (capped by the max). A per-job
+/// forced count (from `ddm add --auto-bench`) takes precedence over both. Whatever the
+/// source, the result never exceeds `absolute_max_segments`.
+pub fn choose_segment_count(
     total_size: u64,
+    job: &JobSettings,
     cfg: &DdmConfig,
     url: &str,
     host_policy: &HostPolicy,
 ) -> usize {
+    let (min_segments, max_segments) = segment_bounds(job, cfg);
+    if let Some(n) = job
+        .segment_count
+        .filter(|&n| n > 0)
+        .map(|n| cfg.clamp_segments(n))
+    {
+        return if total_size == 0 {
+            n
+        } else {
+            n.min(total_size as usize)
+        };
+    }
+    if let (Some(target), true) = (cfg.target_segment_bytes, total_size > 0) {
+        return segment_count_for_size(total_size, target, max_segments);
+    }
     let adaptive = host_policy
-        .adaptive_segment_count_for_url(url)
-        .unwrap_or_else(|_| cfg.min_segments.max(1).min(cfg.max_segments));
-    let n = adaptive.max(cfg.min_segments).min(cfg.max_segments).max(1);
+        .adaptive_segment_count_for_url_within(url, min_segments, max_segments)
+        .unwrap_or(min_segments);
+    let n = adaptive.max(min_segments).min(max_segments);
     if total_size == 0 {
         return n;
     }
     n.min(total_size as usize)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_segment_bytes_overrides_adaptive_count() {
+        let policy = HostPolicy::new(4, 16);
+        let cfg = DdmConfig {
+            target_segment_bytes: Some(100),
+            ..DdmConfig::default()
+        };
+        assert_eq!(
+            choose_segment_count(
+                1000,
+                &JobSettings::default(),
+                &cfg,
+                "https://example.com/f",
+                &policy
+            ),
+            10
+        );
+        assert_eq!(
+            choose_segment_count(
+                100_000,
+                &JobSettings::default(),
+                &cfg,
+                "https://example.com/f",
+                &policy
+            ),
+            cfg.max_segments
+        );
+    }
+
+    fn forced(n: usize) -> JobSettings {
+        JobSettings {
+            segment_count: Some(n),
+            ..JobSettings::default()
+        }
+    }
+
+    #[test]
+    fn forced_segment_count_wins() {
+        let policy = HostPolicy::new(4, 16);
+        let cfg = DdmConfig {
+            target_segment_bytes: Some(100),
+            ..DdmConfig::default()
+        };
+        let url = "https://example.com/f";
+        assert_eq!(
+            choose_segment_count(1000, &forced(8), &cfg, url, &policy),
+            8
+        );
+        assert_eq!(choose_segment_count(5, &forced(8), &cfg, url, &policy), 5);
+        assert_eq!(
+            choose_segment_count(1000, &forced(0), &cfg, url, &policy),
+            10
+        );
+    }
+
+    #[test]
+    fn per_job_bounds_override_global_ones() {
+        let policy = HostPolicy::new(4, 16);
+        let cfg = DdmConfig::default();
+        let url = "https://example.com/f";
+        let bounded = |min, max| JobSettings {
+            min_segments: min,
+            max_segments: max,
+            ..JobSettings::default()
+        };
+        // New host: adaptive start of 4, raised by the job minimum past the global max.
+        let job = bounded(Some(24), Some(32));
+        assert_eq!(segment_bounds(&job, &cfg), (24, 32));
+        assert_eq!(choose_segment_count(1 << 30, &job, &cfg, url, &policy), 24);
+        // Job max below the global min wins; the min is pulled down to it.
+        let job = bounded(None, Some(2));
+        assert_eq!(segment_bounds(&job, &cfg), (2, 2));
+        assert_eq!(choose_segment_count(1 << 30, &job, &cfg, url, &policy), 2);
+        // Target size is capped by the job max rather than the global one.
+        let cfg = DdmConfig {
+            target_segment_bytes: Some(1),
+            ..DdmConfig::default()
+        };
+        let job = bounded(None, Some(32));
+        assert_eq!(choose_segment_count(1 << 30, &job, &cfg, url, &policy), 32);
+    }
+
+    #[test]
+    fn absolute_max_segments_caps_every_source() {
+        let policy = HostPolicy::new(4, 16);
+        let url = "https://example.com/f";
+        let cfg = DdmConfig {
+            absolute_max_segments: Some(6),
+            ..DdmConfig::default()
+        };
+        let job = JobSettings {
+            min_segments: Some(24),
+            max_segments: Some(32),
+            ..JobSettings::default()
+        };
+        assert_eq!(segment_bounds(&job, &cfg), (6, 6));
+        assert_eq!(choose_segment_count(1 << 30, &job, &cfg, url, &policy), 6);
+        assert_eq!(
+            choose_segment_count(1 << 30, &forced(64), &cfg, url, &policy),
+            6
+        );
+        let cfg = DdmConfig {
+            target_segment_bytes: Some(1),
+            ..cfg
+        };
+        assert_eq!(
+            choose_segment_count(1 << 30, &JobSettings::default(), &cfg, url, &policy),
+            6
+        );
+        // The default ceiling leaves ordinary configurations alone.
+        assert_eq!(
+            DdmConfig::default().clamp_segments(128),
+            crate::config::DEFAULT_ABSOLUTE_MAX_SEGMENTS.min(128)
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Per-run job deadline (`ddm add --deadline`): trips the abort flag when time runs out.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Watchdog task that sets `abort` once `limit` has elapsed. Cancelled on drop.
+pub(super) struct DeadlineWatchdog {
+    limit: Duration,
+    fired: Arc<AtomicBool>,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl DeadlineWatchdog {
+    pub(super) fn start(limit: Duration, abort: &Arc<AtomicBool>) -> Self {
+        let fired = Arc::new(AtomicBool::new(false));
+        let handle = tokio::spawn({
+            let fired = Arc::clone(&fired);
+            let abort = Arc::clone(abort);
+            async move {
+                tokio::time::sleep(limit).await;
+                fired.store(true, Ordering::Relaxed);
+                abort.store(true, Ordering::Relaxed);
+            }
+        });
+        Self {
+            limit,
+            fired,
+            handle,
+        }
+    }
+
+    /// True if the deadline (rather than the user) triggered the abort.
+    pub(super) fn fired(&self) -> bool {
+        self.fired.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn error(&self) -> anyhow::Error {
+        anyhow::Error::new(DeadlineExceeded {
+            limit_secs: self.limit.as_secs(),
+        })
+    }
+}
+
+/// Error returned when a job's deadline stops the download (progress is kept).
+#[derive(Debug)]
+pub(crate) struct DeadlineExceeded {
+    limit_secs: u64,
+}
+
+impl std::fmt::Display for DeadlineExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "job deadline of {}s exceeded; progress saved (use `ddm resume` to continue)",
+            self.limit_secs
+        )
+    }
+}
+
+impl std::error::Error for DeadlineExceeded {}
+
+impl Drop for DeadlineWatchdog {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
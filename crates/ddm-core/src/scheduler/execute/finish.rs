@@ -6,12 +6,14 @@ use std::time::Duration;
 
 use crate::downloader::DownloadSummary;
 use crate::host_policy::HostPolicy;
-use crate::resume_db::{JobMetadata, JobState, ResumeDb};
+use crate::resume_db::db::unix_timestamp;
+use crate::resume_db::{JobEvent, JobMetadata, JobState, ResumeDb};
 use crate::segmenter;
 use crate::storage;
 
-/// After download completes (or is aborted with pause): record host policy outcome,
-/// sync storage, update DB metadata, and finalize file + set state if all segments done.
+/// After download completes (or is aborted with pause): record host policy outcome and
+/// job history, sync storage, update DB metadata (including per-segment hashes), and
+/// finalize file + set state if all segments done. Returns true when the job completed.
 pub(super) async fn finish_after_download(
     db: &ResumeDb,
     job_id: i64,
@@ -22,11 +24,12 @@ pub(super) async fn finish_after_download(
     download_elapsed: Duration,
     summary: &DownloadSummary,
     bitmap: &segmenter::SegmentBitmap,
+    segment_hashes: &[Option<String>],
     storage_writer: &storage::StorageWriter,
     final_path: &std::path::Path,
     host_policy: Option<&mut HostPolicy>,
     shared_policy: Option<&Arc<tokio::sync::Mutex<HostPolicy>>>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
     if let Some(p) = host_policy {
         p.record_job_outcome(
             url,
@@ -51,6 +54,18 @@ pub(super) async fn finish_after_download(
             .context("record job outcome for adaptive policy")?;
     }
 
+    db.record_job_event(&JobEvent {
+        job_id,
+        timestamp: unix_timestamp(),
+        bytes_this_run,
+        duration_secs: download_elapsed.as_secs_f64(),
+        throttle_events: summary.throttle_events,
+        error_events: summary.error_events,
+        segment_count: segment_count_u as u32,
+    })
+    .await
+    .context("record job history")?;
+
     storage_writer.sync()?;
 
     let meta = JobMetadata {
@@ -63,12 +78,21 @@ pub(super) async fn finish_after_download(
         completed_bitmap: bitmap.to_bytes(segment_count_u),
     };
     db.update_metadata(job_id, &meta).await?;
+    db.set_segment_hashes(job_id, segment_hashes).await?;
 
     if bitmap.all_completed(segment_count_u) {
+        super::verify::verify_expected_checksum(
+            db,
+            job_id,
+            storage_writer.temp_path(),
+            job.settings.expected_checksum.as_ref(),
+        )
+        .await?;
         storage_writer.clone().finalize(final_path)?;
         db.set_state(job_id, JobState::Completed).await?;
         tracing::info!("job {} completed: {}", job_id, final_path.display());
+        return Ok(true);
     }
 
-    Ok(())
+    Ok(false)
 }
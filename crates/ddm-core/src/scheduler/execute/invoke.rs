@@ -4,6 +4,7 @@ use anyhow::{Context, Result};
 use std::sync::Arc;
 
 use crate::downloader::DownloadSummary;
+use crate::downloader::SourceUrls;
 use crate::segmenter;
 
 use super::run_download::run_download_blocking;
@@ -11,33 +12,37 @@ use super::run_download::run_download_blocking;
 /// Runs download in spawn_blocking. Returns Ok((bitmap, summary)) or Err.
 /// Caller handles JobAborted (set state to Paused, etc.).
 pub(super) async fn run_download_blocking_async(
-    url: &str,
+    urls: &SourceUrls,
     headers: &std::collections::HashMap<String, String>,
+    if_range: Option<&str>,
     segments: &[segmenter::Segment],
     storage: &crate::storage::StorageWriter,
     bitmap: &segmenter::SegmentBitmap,
     actual_concurrent: usize,
     retry_policy: &crate::retry::RetryPolicy,
-    bitmap_tx: tokio::sync::mpsc::Sender<Vec<u8>>,
+    bitmap_tx: crate::downloader::BitmapSender,
     in_flight_bytes: Arc<Vec<std::sync::atomic::AtomicU64>>,
-    abort: Option<Arc<std::sync::atomic::AtomicBool>>,
+    segment_hashes: Option<Arc<crate::checksum::SegmentHashes>>,
     use_multi: bool,
     curl_opts: crate::downloader::CurlOptions,
+    ctx: crate::downloader::SegmentRunContext,
 ) -> Result<(segmenter::SegmentBitmap, DownloadSummary)> {
-    let url = url.to_string();
+    let urls = urls.clone();
     let headers = headers.clone();
+    let if_range = if_range.map(str::to_string);
     let segments = segments.to_vec();
     let storage = storage.clone();
     let mut bitmap_copy = bitmap.clone();
     let policy = retry_policy.clone();
     let in_flight = in_flight_bytes;
-    let curl = curl_opts;
+    let curl = curl_opts.clone();
 
     tokio::task::spawn_blocking(move || {
         let mut summary = DownloadSummary::default();
         run_download_blocking(
-            &url,
+            &urls,
             &headers,
+            if_range.as_deref(),
             &segments,
             &storage,
             &mut bitmap_copy,
@@ -46,9 +51,10 @@ pub(super) async fn run_download_blocking_async(
             &mut summary,
             Some(&bitmap_tx),
             Some(in_flight),
-            abort,
+            segment_hashes,
             use_multi,
             curl,
+            ctx,
         )?;
         Ok((bitmap_copy, summary))
     })
@@ -1,5 +1,6 @@
 //! Execute the download phase of a single job: storage, segments, progress, finalize.
 
+mod deadline;
 mod finish;
 mod guard;
 mod invoke;
@@ -7,6 +8,8 @@ mod progress_worker;
 mod run_download;
 mod setup;
 mod single;
+mod stall;
+mod verify;
 
 use anyhow::{Context, Result};
 use std::collections::HashMap;
@@ -14,21 +17,24 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use crate::config::{DdmConfig, DownloadBackend};
+use crate::checksum::{merge_segment_hashes, SegmentHashes};
 use crate::control::JobAborted;
+use crate::downloader::SourceUrls;
 use crate::host_policy::HostPolicy;
 use crate::resume_db::{JobState, ResumeDb};
 use crate::segmenter;
 
+pub(crate) use self::deadline::DeadlineExceeded;
 pub(super) use self::single::execute_single_download_phase;
 use crate::scheduler::budget::GlobalConnectionBudget;
+use crate::scheduler::options::JobConfig;
 use crate::scheduler::progress::ProgressStats;
 
 use self::invoke::run_download_blocking_async;
 use self::setup::setup_storage_and_progress;
 
 /// Runs the download phase: open/create storage, download incomplete segments,
-/// persist progress, update metadata, and finalize if complete.
+/// persist progress, update metadata, and finalize if complete (then run the `on_complete` hook).
 /// If `progress_tx` is `Some`, progress stats (bytes done, elapsed) are sent
 /// when the bitmap is updated so the caller can show ETA/rate.
 pub(super) async fn execute_download_phase(
@@ -44,7 +50,7 @@ pub(super) async fn execute_download_phase(
     segment_count_u: usize,
     segments: &[segmenter::Segment],
     bitmap: &mut segmenter::SegmentBitmap,
-    cfg: &DdmConfig,
+    cfg: &JobConfig<'_>,
     host_policy: Option<&mut HostPolicy>,
     shared_policy: Option<Arc<tokio::sync::Mutex<HostPolicy>>>,
     progress_tx: Option<&tokio::sync::mpsc::Sender<ProgressStats>>,
@@ -60,57 +66,160 @@ pub(super) async fn execute_download_phase(
         })?;
         tracing::debug!(path = %temp_path.display(), "removed existing .part for clean restart");
     }
+    #[cfg(unix)]
+    crate::storage::ensure_space_for(temp_path, total_size_u)?;
+
+    // Mid-segment offsets are trusted only when If-Range can confirm the remote file is
+    // unchanged, and not when hashing (the segment digest needs the skipped prefix).
+    // Mirrors never get If-Range, so a job with mirrors always restarts whole segments.
+    let urls = SourceUrls::new(url, job.settings.mirrors.as_deref().unwrap_or_default());
+    let if_range = crate::safe_resume::if_range_validator(job);
+    let hash_segments = cfg.hash_segments.unwrap_or(false);
+    // Stored offsets describe bytes in a `.part` of the planned length; a missing or
+    // resized file (checked before the recheck resizes it) no longer holds them.
+    let part_intact = !needs_metadata
+        && tokio::fs::metadata(temp_path)
+            .await
+            .is_ok_and(|m| m.len() == total_size_u);
+    // Re-downloading a cleared segment must start from its beginning, so a recheck that
+    // cleared anything also stops mid-segment offsets from being trusted this run.
+    let rechecked = if cfg.verify_on_resume.unwrap_or(false) && !needs_metadata {
+        verify::recheck_completed_segments(
+            db,
+            job_id,
+            temp_path,
+            total_size_u,
+            segments,
+            bitmap,
+            &job.segment_hashes,
+        )
+        .await?
+    } else {
+        0
+    };
+    let trust_offsets = !needs_metadata
+        && rechecked == 0
+        && !hash_segments
+        && !urls.has_mirrors()
+        && if_range.is_some()
+        && part_intact;
+    let run_segments = if trust_offsets {
+        segmenter::resume_segments(segments, &job.segment_progress)
+    } else {
+        if !job.segment_progress.is_empty() {
+            db.set_segment_progress(job_id, &[]).await?;
+        }
+        segments.to_vec()
+    };
+    let base_offsets: Vec<u64> = segments
+        .iter()
+        .zip(&run_segments)
+        .map(|(planned, run)| run.start - planned.start)
+        .collect();
+    if base_offsets.iter().any(|&o| o > 0) {
+        tracing::info!(
+            job_id,
+            bytes = base_offsets.iter().sum::<u64>(),
+            "resuming partially downloaded segments"
+        );
+    }
 
     let (
         storage_writer,
         actual_concurrent,
         retry_policy,
         curl_opts,
+        mut ctx,
         bytes_this_run,
         download_start,
         progress_handle,
         bitmap_tx,
         in_flight_bytes,
         _budget_guard,
-    ): (_, _, _, _, _, Instant, tokio::task::JoinHandle<()>, _, _, _) = setup_storage_and_progress(
+    ): (
+        _,
+        _,
+        _,
+        _,
+        _,
+        _,
+        Instant,
+        tokio::task::JoinHandle<()>,
+        _,
+        _,
+        _,
+    ) = setup_storage_and_progress(
         temp_path,
         total_size_u,
         segment_count_u,
-        segments,
+        &run_segments,
         bitmap,
         cfg,
         db,
         job_id,
         global_budget,
         progress_tx,
+        base_offsets,
     )?;
 
-    let use_multi = cfg.download_backend == Some(DownloadBackend::Multi);
+    let deadline = job
+        .settings
+        .deadline_secs
+        .map(std::time::Duration::from_secs);
+    let abort = match (abort, deadline) {
+        (None, Some(_)) => Some(Arc::new(std::sync::atomic::AtomicBool::new(false))),
+        (abort, _) => abort,
+    };
+    ctx.cancel = abort.clone();
+    ctx.expected_total = Some(total_size_u);
+    ctx.segment_lookahead = job.settings.order.lookahead();
+    let watchdog = deadline.zip(abort.as_ref()).map(|(limit, abort)| {
+        ctx.deadline = Some(download_start + limit);
+        deadline::DeadlineWatchdog::start(limit, abort)
+    });
+
+    let use_multi = cfg.use_multi_backend(actual_concurrent);
+    let segment_hashes = hash_segments.then(|| Arc::new(SegmentHashes::new(segment_count_u)));
+    let prior_hashes: &[Option<String>] = if needs_metadata {
+        &[]
+    } else {
+        &job.segment_hashes
+    };
     let download_result = run_download_blocking_async(
-        url,
+        &urls,
         headers,
-        segments,
+        if_range.as_deref(),
+        &run_segments,
         &storage_writer,
         bitmap,
         actual_concurrent,
         &retry_policy,
         bitmap_tx,
         in_flight_bytes,
-        abort,
+        segment_hashes.clone(),
         use_multi,
         curl_opts,
+        ctx,
     )
     .await;
+    let merged_hashes =
+        merge_segment_hashes(prior_hashes, segment_hashes.as_deref(), segment_count_u);
 
     let (bitmap_result, summary) = match download_result {
         Ok((bm, s)) => (bm, s),
         Err(e) => {
             if e.downcast_ref::<JobAborted>().is_some() {
                 let _ = progress_handle.await;
+                db.set_segment_hashes(job_id, &merged_hashes).await?;
+                if let Some(w) = watchdog.as_ref().filter(|w| w.fired()) {
+                    return Err(w.error());
+                }
                 db.set_state(job_id, JobState::Paused).await?;
                 tracing::info!("job {} paused by user", job_id);
                 return Ok(());
             }
+            // Let the writer persist every bitmap sent before the failure is recorded.
+            let _ = progress_handle.await;
             return Err(e);
         }
     };
@@ -118,7 +227,7 @@ pub(super) async fn execute_download_phase(
     *bitmap = bitmap_result;
     progress_handle.await.context("progress writer join")?;
     let download_elapsed = download_start.elapsed();
-    finish::finish_after_download(
+    let completed = finish::finish_after_download(
         db,
         job_id,
         job,
@@ -128,12 +237,16 @@ pub(super) async fn execute_download_phase(
         download_elapsed,
         &summary,
         bitmap,
+        &merged_hashes,
         &storage_writer,
         final_path,
         host_policy,
         shared_policy.as_ref(),
     )
     .await?;
+    if completed {
+        super::hook::run_on_complete(cfg, job_id, final_path, &job.url).await;
+    }
 
     Ok(())
 }
@@ -2,17 +2,27 @@
 
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::resume_db::ResumeDb;
 use crate::segmenter;
 
 use crate::scheduler::progress::ProgressStats;
 
-/// Runs the progress persistence loop: receive bitmap blobs, persist to DB,
-/// and optionally send ProgressStats to the CLI. Spawn this with tokio::spawn.
+use super::stall::{StallWatchdog, STALL_CHECK_INTERVAL};
+
+/// How often partial (mid-segment) offsets are written to the DB.
+const OFFSET_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the progress persistence loop: receive bitmap blobs, persist every one to DB (durable),
+/// and optionally send ProgressStats to the CLI (display only: `try_send`, dropped when the
+/// receiver lags). Also flushes per-segment partial
+/// offsets (`base_offsets` + in-flight bytes) periodically, re-sending stats so live
+/// views advance inside long segments, and once more on exit. With a `stall` watchdog it
+/// also checks segment throughput every [`STALL_CHECK_INTERVAL`].
+/// Spawn this with tokio::spawn.
 pub(super) async fn run_progress_persistence_loop(
-    mut progress_rx: tokio::sync::mpsc::Receiver<Vec<u8>>,
+    mut progress_rx: tokio::sync::mpsc::UnboundedReceiver<Vec<u8>>,
     db: ResumeDb,
     job_id: i64,
     segment_count_u: usize,
@@ -20,39 +30,85 @@ pub(super) async fn run_progress_persistence_loop(
     total_size_u: u64,
     stats_tx: Option<tokio::sync::mpsc::Sender<ProgressStats>>,
     in_flight: Arc<Vec<AtomicU64>>,
+    base_offsets: Vec<u64>,
     download_start: Instant,
+    mut stall: Option<StallWatchdog>,
 ) {
-    while let Some(blob) = progress_rx.recv().await {
-        if db.update_bitmap(job_id, &blob).await.is_err() {
-            tracing::warn!(job_id, "durable progress update failed");
+    let stats_for = |blob: &[u8]| {
+        let bitmap = segmenter::SegmentBitmap::from_bytes(blob, segment_count_u);
+        let bytes_done: u64 = segments
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| bitmap.is_completed(*i))
+            .map(|(_, s)| s.end - s.start)
+            .sum();
+        let bytes_in_flight: u64 = in_flight
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !bitmap.is_completed(*i))
+            .map(|(i, a)| base_offsets.get(i).copied().unwrap_or(0) + a.load(Ordering::Relaxed))
+            .sum();
+        let segments_done = (0..segment_count_u)
+            .filter(|i| bitmap.is_completed(*i))
+            .count();
+        ProgressStats {
+            job_id,
+            bytes_done,
+            bytes_in_flight,
+            total_bytes: total_size_u,
+            elapsed_secs: download_start.elapsed().as_secs_f64(),
+            segments_done,
+            segment_count: segment_count_u,
         }
-        if let Some(ref tx) = stats_tx {
-            let bitmap = segmenter::SegmentBitmap::from_bytes(&blob, segment_count_u);
-            let bytes_done: u64 = segments
-                .iter()
-                .enumerate()
-                .filter(|(i, _)| bitmap.is_completed(*i))
-                .map(|(_, s)| s.end - s.start)
-                .sum();
-            let bytes_in_flight: u64 = in_flight
-                .iter()
-                .enumerate()
-                .filter(|(i, _)| !bitmap.is_completed(*i))
-                .map(|(_, a)| a.load(Ordering::Relaxed))
-                .sum();
-            let elapsed_secs = download_start.elapsed().as_secs_f64();
-            let segments_done = (0..segment_count_u)
-                .filter(|i| bitmap.is_completed(*i))
-                .count();
-            let stats = ProgressStats {
-                bytes_done,
-                bytes_in_flight,
-                total_bytes: total_size_u,
-                elapsed_secs,
-                segments_done,
-                segment_count: segment_count_u,
-            };
-            let _ = tx.try_send(stats);
+    };
+
+    let mut flush = tokio::time::interval(OFFSET_FLUSH_INTERVAL);
+    flush.tick().await;
+    let mut stall_tick = tokio::time::interval(STALL_CHECK_INTERVAL);
+    let lens: Vec<u64> = segments.iter().map(|s| s.end - s.start).collect();
+    let mut last_blob: Option<Vec<u8>> = None;
+    loop {
+        tokio::select! {
+            blob = progress_rx.recv() => {
+                let Some(blob) = blob else { break };
+                if db.update_bitmap(job_id, &blob).await.is_err() {
+                    tracing::warn!(job_id, "durable progress update failed");
+                }
+                if let Some(ref tx) = stats_tx {
+                    let _ = tx.try_send(stats_for(&blob));
+                }
+                last_blob = Some(blob);
+            }
+            _ = flush.tick() => {
+                persist_offsets(&db, job_id, &base_offsets, &in_flight).await;
+                if let (Some(tx), Some(blob)) = (stats_tx.as_ref(), last_blob.as_deref()) {
+                    let _ = tx.try_send(stats_for(blob));
+                }
+            }
+            _ = stall_tick.tick(), if stall.is_some() => {
+                let bitmap = last_blob
+                    .as_deref()
+                    .map(|b| segmenter::SegmentBitmap::from_bytes(b, segment_count_u));
+                let finished = |i| bitmap.as_ref().is_some_and(|b| b.is_completed(i));
+                if let Some(w) = stall.as_mut() {
+                    for i in w.check(&in_flight, &lens, finished, Instant::now()) {
+                        tracing::warn!(job_id, segment = i, "segment below min_segment_bps; retrying");
+                    }
+                }
+            }
         }
     }
+    persist_offsets(&db, job_id, &base_offsets, &in_flight).await;
+}
+
+/// Bytes written from each segment's planned start: the resumed prefix plus this run's bytes.
+async fn persist_offsets(db: &ResumeDb, job_id: i64, base: &[u64], in_flight: &[AtomicU64]) {
+    let offsets: Vec<u64> = in_flight
+        .iter()
+        .enumerate()
+        .map(|(i, a)| base.get(i).copied().unwrap_or(0) + a.load(Ordering::Relaxed))
+        .collect();
+    if db.set_segment_progress(job_id, &offsets).await.is_err() {
+        tracing::warn!(job_id, "partial segment progress update failed");
+    }
 }
@@ -2,35 +2,41 @@
 
 use std::sync::Arc;
 
+use crate::checksum::SegmentHashes;
 use crate::downloader;
 use crate::downloader::CurlOptions;
 use crate::downloader::DownloadSummary;
+use crate::downloader::SegmentRunContext;
+use crate::downloader::SourceUrls;
 use crate::retry::RetryPolicy;
 use crate::segmenter;
 use crate::storage;
 
 /// Runs segment download on a blocking thread. Chooses Easy (threads) or Multi
-/// based on `use_multi`. If `abort` is set and becomes true, returns JobAborted.
+/// based on `use_multi`. If `ctx.cancel` is set and becomes true, returns JobAborted.
 pub(super) fn run_download_blocking(
-    url: &str,
+    urls: &SourceUrls,
     headers: &std::collections::HashMap<String, String>,
+    if_range: Option<&str>,
     segments: &[segmenter::Segment],
     storage: &storage::StorageWriter,
     bitmap: &mut segmenter::SegmentBitmap,
     max_concurrent: usize,
     policy: &RetryPolicy,
     summary: &mut DownloadSummary,
-    bitmap_tx: Option<&tokio::sync::mpsc::Sender<Vec<u8>>>,
+    bitmap_tx: Option<&crate::downloader::BitmapSender>,
     in_flight: Option<Arc<Vec<std::sync::atomic::AtomicU64>>>,
-    abort: Option<Arc<std::sync::atomic::AtomicBool>>,
+    segment_hashes: Option<Arc<SegmentHashes>>,
     use_multi: bool,
     curl: CurlOptions,
+    ctx: SegmentRunContext,
 ) -> anyhow::Result<()> {
     let max_concurrent = max_concurrent.max(1);
     if use_multi {
         downloader::multi::download_segments_multi(
-            url,
+            urls,
             headers,
+            if_range,
             segments,
             storage,
             bitmap,
@@ -39,13 +45,15 @@ pub(super) fn run_download_blocking(
             summary,
             bitmap_tx,
             in_flight,
-            abort,
+            segment_hashes,
             curl,
+            ctx,
         )
     } else {
         downloader::download_segments(
-            url,
+            urls,
             headers,
+            if_range,
             segments,
             storage,
             bitmap,
@@ -54,8 +62,9 @@ pub(super) fn run_download_blocking(
             summary,
             bitmap_tx,
             in_flight,
-            abort,
+            segment_hashes,
             curl,
+            ctx,
         )
     }
 }
@@ -5,7 +5,7 @@ use std::path::Path;
 use std::sync::Arc;
 use std::time::Instant;
 
-use crate::config::DdmConfig;
+use crate::downloader::SegmentRunContext;
 use crate::resume_db::ResumeDb;
 use crate::retry::RetryPolicy;
 use crate::segmenter;
@@ -13,7 +13,9 @@ use crate::storage;
 
 use super::guard::BudgetGuard;
 use super::progress_worker::run_progress_persistence_loop;
+use super::stall::StallWatchdog;
 use crate::scheduler::budget::GlobalConnectionBudget;
+use crate::scheduler::options::JobConfig;
 use crate::scheduler::progress::ProgressStats;
 
 /// Opens or creates temp storage, reserves connection budget, builds retry policy and
@@ -25,20 +27,22 @@ pub(super) fn setup_storage_and_progress<'a>(
     segment_count_u: usize,
     segments: &[segmenter::Segment],
     bitmap: &segmenter::SegmentBitmap,
-    cfg: &DdmConfig,
+    cfg: &JobConfig<'_>,
     db: &ResumeDb,
     job_id: i64,
     global_budget: Option<&'a GlobalConnectionBudget>,
     progress_tx: Option<&tokio::sync::mpsc::Sender<ProgressStats>>,
+    base_offsets: Vec<u64>,
 ) -> Result<(
     storage::StorageWriter,
     usize,
     RetryPolicy,
     crate::downloader::CurlOptions,
+    SegmentRunContext,
     u64,
     Instant,
     tokio::task::JoinHandle<()>,
-    tokio::sync::mpsc::Sender<Vec<u8>>,
+    crate::downloader::BitmapSender,
     Arc<Vec<std::sync::atomic::AtomicU64>>,
     Option<BudgetGuard<'a>>,
 )> {
@@ -48,7 +52,7 @@ pub(super) fn setup_storage_and_progress<'a>(
     } else {
         let mut builder = storage::StorageWriterBuilder::create(temp_path)
             .with_context(|| format!("create temp file: {}", temp_path.display()))?;
-        builder.preallocate(total_size_u)?;
+        builder.preallocate_with(cfg.preallocation.unwrap_or_default(), total_size_u)?;
         builder.build()
     };
 
@@ -70,14 +74,15 @@ pub(super) fn setup_storage_and_progress<'a>(
             max_attempts: r.max_attempts,
             base_delay: std::time::Duration::from_secs_f64(r.base_delay_secs),
             max_delay: std::time::Duration::from_secs(r.max_delay_secs),
+            jitter: r.jitter,
+            max_job_retries: r.max_job_retries,
         })
         .unwrap_or_else(RetryPolicy::default);
 
-    let curl_opts = crate::downloader::CurlOptions::per_handle(
-        cfg.max_bytes_per_sec,
-        actual_concurrent,
-        cfg.segment_buffer_bytes,
-    );
+    let curl_opts = cfg.curl_options(actual_concurrent);
+    let mut ctx = SegmentRunContext::from_config(cfg);
+    let stall_watchdog = StallWatchdog::from_config(cfg, segment_count_u);
+    ctx.stalled = stall_watchdog.as_ref().map(StallWatchdog::flags);
     let bytes_this_run: u64 = segments
         .iter()
         .enumerate()
@@ -91,7 +96,7 @@ pub(super) fn setup_storage_and_progress<'a>(
             .map(|_| std::sync::atomic::AtomicU64::new(0))
             .collect(),
     );
-    let (bitmap_tx, progress_rx) = tokio::sync::mpsc::channel::<Vec<u8>>(8);
+    let (bitmap_tx, progress_rx) = tokio::sync::mpsc::unbounded_channel::<Vec<u8>>();
     let progress_handle = tokio::spawn(run_progress_persistence_loop(
         progress_rx,
         db.clone(),
@@ -101,7 +106,9 @@ pub(super) fn setup_storage_and_progress<'a>(
         total_size_u,
         progress_tx.cloned(),
         Arc::clone(&in_flight_bytes),
+        base_offsets,
         download_start,
+        stall_watchdog,
     ));
 
     Ok((
@@ -109,6 +116,7 @@ pub(super) fn setup_storage_and_progress<'a>(
         actual_concurrent,
         retry_policy,
         curl_opts,
+        ctx,
         bytes_this_run,
         download_start,
         progress_handle,
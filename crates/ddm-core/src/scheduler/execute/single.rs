@@ -3,14 +3,22 @@
 use anyhow::{Context, Result};
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
+use crate::checksum::ChecksumAlgo;
+use crate::config::Preallocation;
+use crate::control::JobAborted;
 use crate::downloader;
-use crate::downloader::CurlOptions;
+use crate::downloader::{CurlOptions, SegmentRunContext};
 use crate::resume_db::{JobState, ResumeDb};
 use crate::storage;
 
-/// Runs a single-stream GET download: (re)create temp file, stream bytes, sync, finalize, set Completed.
-/// Returns bytes written.
+/// Runs a single-stream GET download: (re)create temp file, stream bytes, sync, check
+/// `expected_checksum`, finalize, set Completed.
+/// A known zero length skips the GET and finalizes an empty file. Returns bytes written,
+/// or None when `abort` stopped the transfer (the job is then Paused; the next run starts
+/// over). `deadline` trips `abort` and fails the job with `DeadlineExceeded`.
 pub(crate) async fn execute_single_download_phase(
     db: &ResumeDb,
     job_id: i64,
@@ -19,8 +27,12 @@ pub(crate) async fn execute_single_download_phase(
     temp_path: &Path,
     final_path: &Path,
     expected_len: Option<u64>,
+    expected_checksum: Option<&(ChecksumAlgo, String)>,
+    preallocation: Preallocation,
     curl: CurlOptions,
-) -> Result<u64> {
+    abort: Option<Arc<AtomicBool>>,
+    deadline: Option<std::time::Duration>,
+) -> Result<Option<u64>> {
     if temp_path.exists() {
         tokio::fs::remove_file(temp_path)
             .await
@@ -30,22 +42,55 @@ pub(crate) async fn execute_single_download_phase(
     let mut builder = storage::StorageWriterBuilder::create(temp_path)
         .with_context(|| format!("create temp file: {}", temp_path.display()))?;
     if let Some(n) = expected_len {
-        builder.preallocate(n)?;
+        builder.preallocate_with(preallocation, n)?;
     }
     let storage_writer = builder.build();
 
-    let bytes_written = tokio::task::spawn_blocking({
+    if expected_len == Some(0) {
+        super::verify::verify_expected_checksum(db, job_id, temp_path, expected_checksum).await?;
+        storage_writer.finalize(final_path)?;
+        db.set_state(job_id, JobState::Completed).await?;
+        tracing::info!("job {} completed (empty): {}", job_id, final_path.display());
+        return Ok(Some(0));
+    }
+
+    let abort = match (abort, deadline) {
+        (None, Some(_)) => Some(Arc::new(AtomicBool::new(false))),
+        (abort, _) => abort,
+    };
+    let mut ctx = SegmentRunContext {
+        cancel: abort.clone(),
+        ..SegmentRunContext::default()
+    };
+    let watchdog = deadline.zip(abort.as_ref()).map(|(limit, abort)| {
+        ctx.deadline = Some(std::time::Instant::now() + limit);
+        super::deadline::DeadlineWatchdog::start(limit, abort)
+    });
+    let download = tokio::task::spawn_blocking({
         let url = url.to_string();
         let headers = headers.clone();
         let storage = storage_writer.clone();
         move || -> Result<u64> {
-            downloader::download_single(&url, &headers, &storage, expected_len, curl)
+            downloader::download_single(&url, &headers, &storage, expected_len, curl, ctx)
         }
     })
     .await
-    .context("download task join")??;
+    .context("download task join")?;
+    let bytes_written = match download {
+        Ok(n) => n,
+        Err(e) if e.downcast_ref::<JobAborted>().is_some() => {
+            if let Some(w) = watchdog.as_ref().filter(|w| w.fired()) {
+                return Err(w.error());
+            }
+            db.set_state(job_id, JobState::Paused).await?;
+            tracing::info!("job {} paused by user", job_id);
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
 
     storage_writer.sync()?;
+    super::verify::verify_expected_checksum(db, job_id, temp_path, expected_checksum).await?;
     storage_writer.finalize(final_path)?;
     db.set_state(job_id, JobState::Completed).await?;
     tracing::info!(
@@ -54,5 +99,5 @@ pub(crate) async fn execute_single_download_phase(
         final_path.display()
     );
 
-    Ok(bytes_written)
+    Ok(Some(bytes_written))
 }
@@ -0,0 +1,132 @@
+//! Per-segment throughput watchdog (`min_segment_bps`, `stall_window_secs`): flags running
+//! segments whose in-flight bytes advance too slowly so the downloader aborts and retries them.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::{DdmConfig, DEFAULT_STALL_WINDOW_SECS};
+
+/// How often the progress loop runs [`StallWatchdog::check`].
+pub(super) const STALL_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Tracks each segment's in-flight byte count over `window`; shares its flags with the
+/// downloader through `SegmentRunContext::stalled`.
+#[derive(Debug)]
+pub(super) struct StallWatchdog {
+    min_bps: u64,
+    window: Duration,
+    flags: Arc<Vec<AtomicBool>>,
+    /// Per segment: when the current window started and the byte count then.
+    marks: Vec<Option<(Instant, u64)>>,
+}
+
+impl StallWatchdog {
+    /// None unless `min_segment_bps` is set above 0.
+    pub(super) fn from_config(cfg: &DdmConfig, segment_count: usize) -> Option<Self> {
+        let min_bps = cfg.min_segment_bps.filter(|&b| b > 0)?;
+        let window = cfg
+            .stall_window_secs
+            .unwrap_or(DEFAULT_STALL_WINDOW_SECS)
+            .max(1);
+        Some(Self {
+            min_bps,
+            window: Duration::from_secs(window),
+            flags: Arc::new((0..segment_count).map(|_| AtomicBool::new(false)).collect()),
+            marks: vec![None; segment_count],
+        })
+    }
+
+    pub(super) fn flags(&self) -> Arc<Vec<AtomicBool>> {
+        Arc::clone(&self.flags)
+    }
+
+    /// Flags every running segment that averaged under the floor over a full window and
+    /// returns their indices. `finished(i)` is true for segments already done; segments
+    /// with no bytes yet (not started) or still flagged (awaiting their retry) are skipped.
+    pub(super) fn check(
+        &mut self,
+        in_flight: &[AtomicU64],
+        lens: &[u64],
+        finished: impl Fn(usize) -> bool,
+        now: Instant,
+    ) -> Vec<usize> {
+        let mut flagged = Vec::new();
+        for (i, mark) in self.marks.iter_mut().enumerate() {
+            let bytes = in_flight.get(i).map_or(0, |a| a.load(Ordering::Relaxed));
+            let len = lens.get(i).copied().unwrap_or(0);
+            if bytes == 0 || bytes >= len || finished(i) || self.flags[i].load(Ordering::Relaxed) {
+                *mark = None;
+                continue;
+            }
+            match *mark {
+                // A retried segment starts again from zero.
+                Some((since, start)) if bytes >= start => {
+                    let elapsed = now.saturating_duration_since(since);
+                    if elapsed < self.window {
+                        continue;
+                    }
+                    let bps = (bytes - start) as f64 / elapsed.as_secs_f64();
+                    if bps < self.min_bps as f64 {
+                        self.flags[i].store(true, Ordering::Relaxed);
+                        flagged.push(i);
+                        *mark = None;
+                    } else {
+                        *mark = Some((now, bytes));
+                    }
+                }
+                _ => *mark = Some((now, bytes)),
+            }
+        }
+        flagged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchdog(min_bps: u64, window_secs: u64, segments: usize) -> StallWatchdog {
+        let cfg = DdmConfig {
+            min_segment_bps: Some(min_bps),
+            stall_window_secs: Some(window_secs),
+            ..DdmConfig::default()
+        };
+        StallWatchdog::from_config(&cfg, segments).unwrap()
+    }
+
+    #[test]
+    fn flags_only_running_segments_below_the_floor() {
+        let mut w = watchdog(1000, 10, 4);
+        let in_flight: Vec<AtomicU64> = [500, 500, 0, 500].map(AtomicU64::new).into();
+        let lens = [100_000; 4];
+        let t0 = Instant::now();
+        assert!(w.check(&in_flight, &lens, |i| i == 3, t0).is_empty());
+
+        // Segment 0 moves 20 KB in 10s (2 KB/s), segment 1 only 5 KB (500 B/s).
+        in_flight[0].store(20_500, Ordering::Relaxed);
+        in_flight[1].store(5_500, Ordering::Relaxed);
+        let t1 = t0 + Duration::from_secs(5);
+        assert!(
+            w.check(&in_flight, &lens, |i| i == 3, t1).is_empty(),
+            "window not over"
+        );
+        let t2 = t0 + Duration::from_secs(10);
+        assert_eq!(w.check(&in_flight, &lens, |i| i == 3, t2), vec![1]);
+        assert!(w.flags()[1].load(Ordering::Relaxed));
+
+        // Still flagged (retry pending): not reported again.
+        let t3 = t2 + Duration::from_secs(20);
+        assert_eq!(w.check(&in_flight, &lens, |i| i == 3, t3), vec![0]);
+    }
+
+    #[test]
+    fn disabled_without_a_floor() {
+        assert!(StallWatchdog::from_config(&DdmConfig::default(), 2).is_none());
+        let cfg = DdmConfig {
+            min_segment_bps: Some(0),
+            ..DdmConfig::default()
+        };
+        assert!(StallWatchdog::from_config(&cfg, 2).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! Whole-file check against the job's expected checksum (`ddm add --md5/--sha1/--sha256`)
+//! and the resume-time re-check of completed segments (`verify_on_resume`).
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+use crate::checksum::{self, ChecksumAlgo};
+use crate::resume_db::{JobState, ResumeDb};
+use crate::segmenter::{Segment, SegmentBitmap};
+
+/// Hashes the finished `.part` at `path` before it is moved into place, with the job in
+/// [`JobState::Verifying`] meanwhile; the caller then sets Completed, or the failure marks
+/// it Error. On a mismatch the job's plan is reset (see [`ResumeDb::reset_plan`]) so
+/// `ddm retry` downloads it afresh instead of re-checking the same bytes. No-op when the
+/// job has no expected checksum.
+pub(super) async fn verify_expected_checksum(
+    db: &ResumeDb,
+    job_id: i64,
+    path: &Path,
+    expected: Option<&(ChecksumAlgo, String)>,
+) -> Result<()> {
+    let Some((algo, hex)) = expected.cloned() else {
+        return Ok(());
+    };
+    db.set_state(job_id, JobState::Verifying).await?;
+    let result = tokio::task::spawn_blocking({
+        let path = path.to_path_buf();
+        move || checksum::verify_file(&path, algo, &hex)
+    })
+    .await
+    .context("checksum task join")?;
+    match result {
+        Ok(()) => {
+            tracing::info!("job {} {} checksum OK", job_id, algo.as_str());
+            Ok(())
+        }
+        Err(e) => {
+            if e.downcast_ref::<checksum::ChecksumMismatch>().is_some() {
+                db.reset_plan(job_id).await?;
+            }
+            Err(e.context(format!("verify {}", path.display())))
+        }
+    }
+}
+
+/// `verify_on_resume`: re-checks the segments `bitmap` marks completed against the `.part` at
+/// `path`. Segments reaching past the end of the file (truncated or missing `.part`) and
+/// segments whose stored SHA-256 no longer matches are cleared, persisted, and downloaded
+/// again. A file of the wrong length is resized to `total_size`. Returns how many were cleared.
+pub(super) async fn recheck_completed_segments(
+    db: &ResumeDb,
+    job_id: i64,
+    path: &Path,
+    total_size: u64,
+    segments: &[Segment],
+    bitmap: &mut SegmentBitmap,
+    hashes: &[Option<String>],
+) -> Result<usize> {
+    let completed: Vec<usize> = (0..segments.len())
+        .filter(|&i| bitmap.is_completed(i))
+        .collect();
+    if completed.is_empty() {
+        return Ok(0);
+    }
+    let bad = tokio::task::spawn_blocking({
+        let path = path.to_path_buf();
+        let segments = segments.to_vec();
+        let hashes: Vec<Option<String>> = (0..segments.len())
+            .map(|i| hashes.get(i).cloned().flatten())
+            .collect();
+        move || recheck_blocking(&path, total_size, &segments, &completed, hashes)
+    })
+    .await
+    .context("resume check task join")??;
+    if bad.is_empty() {
+        tracing::debug!(job_id, "verify-on-resume: completed segments OK");
+        return Ok(0);
+    }
+    for &i in &bad {
+        bitmap.clear(i);
+    }
+    db.update_bitmap(job_id, &bitmap.to_bytes(segments.len()))
+        .await?;
+    tracing::warn!(
+        job_id,
+        segments = ?bad,
+        "verify-on-resume: completed segments failed the check; downloading them again"
+    );
+    Ok(bad.len())
+}
+
+fn recheck_blocking(
+    path: &Path,
+    total_size: u64,
+    segments: &[Segment],
+    completed: &[usize],
+    hashes: Vec<Option<String>>,
+) -> Result<Vec<usize>> {
+    let len = match std::fs::metadata(path) {
+        Ok(m) => Some(m.len()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("stat {}", path.display())),
+    };
+    let available = len.unwrap_or(0);
+    let mut bad: Vec<usize> = completed
+        .iter()
+        .copied()
+        .filter(|&i| segments[i].end > available)
+        .collect();
+    // Only hash segments that are completed and still fully inside the file.
+    let to_hash: Vec<Option<String>> = hashes
+        .into_iter()
+        .enumerate()
+        .map(|(i, h)| h.filter(|_| completed.contains(&i) && !bad.contains(&i)))
+        .collect();
+    if len.is_some() {
+        bad.extend(checksum::verify_segments(path, segments, &to_hash));
+    }
+    bad.sort_unstable();
+    bad.dedup();
+    if len.is_some_and(|l| l != total_size) {
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(path)
+            .and_then(|f| f.set_len(total_size))
+            .with_context(|| format!("resize {}", path.display()))?;
+    }
+    Ok(bad)
+}
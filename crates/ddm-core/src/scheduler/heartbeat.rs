@@ -0,0 +1,36 @@
+//! Periodic heartbeat for a running job, so other processes can tell it is still owned.
+
+use std::time::Duration;
+
+use crate::resume_db::jobs::HEARTBEAT_STALE_SECS;
+use crate::resume_db::ResumeDb;
+
+/// Heartbeat period: well inside the staleness window used by `recover_running_jobs`.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(HEARTBEAT_STALE_SECS as u64 / 8);
+
+/// Background task refreshing a job's heartbeat while it runs. Cancelled on drop.
+pub(super) struct Heartbeat {
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl Heartbeat {
+    pub(super) fn start(db: &ResumeDb, job_id: i64) -> Self {
+        let db = db.clone();
+        let handle = tokio::spawn(async move {
+            let mut tick = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                tick.tick().await;
+                if let Err(e) = db.heartbeat(job_id).await {
+                    tracing::warn!(job_id, "heartbeat update failed: {}", e);
+                }
+            }
+        });
+        Self { handle }
+    }
+}
+
+impl Drop for Heartbeat {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
@@ -0,0 +1,77 @@
+//! `on_complete` hook: run a shell command after a job finishes.
+
+use std::path::Path;
+
+use crate::config::DdmConfig;
+
+/// Run `cfg.on_complete` (if set) for a finished download via `sh -c`. `{path}` and `{url}`
+/// are replaced with the shell-quoted final path and URL. Failures and non-zero exits are
+/// logged as warnings; they never fail the job.
+pub(crate) async fn run_on_complete(cfg: &DdmConfig, job_id: i64, path: &Path, url: &str) {
+    let Some(template) = cfg.on_complete.as_deref() else {
+        return;
+    };
+    let command = expand_template(template, path, url);
+    let status = tokio::task::spawn_blocking(move || {
+        std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&command)
+            .status()
+    })
+    .await;
+    match status {
+        Ok(Ok(s)) if s.success() => tracing::info!("job {} on_complete hook finished", job_id),
+        Ok(Ok(s)) => tracing::warn!("job {} on_complete hook exited with {}", job_id, s),
+        Ok(Err(e)) => tracing::warn!("job {} on_complete hook failed to start: {}", job_id, e),
+        Err(e) => tracing::warn!("job {} on_complete hook task failed: {}", job_id, e),
+    }
+}
+
+/// Substitute `{path}` and `{url}` in `template` with single-quoted values.
+fn expand_template(template: &str, path: &Path, url: &str) -> String {
+    template
+        .replace("{path}", &shell_quote(&path.to_string_lossy()))
+        .replace("{url}", &shell_quote(url))
+}
+
+/// Quote `s` for POSIX sh: wrap in single quotes, escaping embedded ones.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_and_quotes_placeholders() {
+        let cmd = expand_template(
+            "mv {path} /srv/done/ && notify {url}",
+            Path::new("/tmp/it's here.iso"),
+            "https://example.com/a?b=1&c=2",
+        );
+        assert_eq!(
+            cmd,
+            r#"mv '/tmp/it'\''s here.iso' /srv/done/ && notify 'https://example.com/a?b=1&c=2'"#
+        );
+    }
+
+    #[tokio::test]
+    async fn hook_runs_and_failures_do_not_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("marker");
+        let cfg = DdmConfig {
+            on_complete: Some(format!("echo {{url}} > '{}'", marker.display())),
+            ..DdmConfig::default()
+        };
+        run_on_complete(&cfg, 1, Path::new("/tmp/x"), "https://example.com/x").await;
+        let written = std::fs::read_to_string(&marker).unwrap();
+        assert_eq!(written.trim(), "https://example.com/x");
+
+        let failing = DdmConfig {
+            on_complete: Some("exit 3".to_string()),
+            ..DdmConfig::default()
+        };
+        run_on_complete(&failing, 1, Path::new("/tmp/x"), "u").await;
+    }
+}
@@ -7,11 +7,18 @@
 mod budget;
 mod choose;
 mod execute;
+mod heartbeat;
+mod hook;
+mod options;
 mod parallel;
 mod progress;
 mod run;
 
 pub use budget::GlobalConnectionBudget;
+pub use choose::choose_segment_count;
+pub use options::{JobConfig, RunOptions};
 pub use parallel::run_jobs_parallel;
-pub use progress::ProgressStats;
+pub use progress::{AggregateProgress, ProgressStats};
+#[cfg(test)]
+pub(crate) use run::next_queued_job_id;
 pub use run::{run_next_job, run_one_job};
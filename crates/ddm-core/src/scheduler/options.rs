@@ -0,0 +1,68 @@
+//! Run-time settings that never come from the config file: `ddm run` flags that apply to
+//! every job, and the per-job view of the config a job runs with.
+
+use std::borrow::Cow;
+use std::ops::Deref;
+
+use crate::config::DdmConfig;
+use crate::downloader::CurlOptions;
+use crate::host_policy::HostFilter;
+
+/// `ddm run` options that are not config overrides.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Skip TLS certificate and host name verification for every job (`ddm run --insecure`).
+    pub insecure: bool,
+    /// Which queued jobs the run picks up, by URL host (`--host`, `--exclude-host`).
+    pub host_filter: HostFilter,
+}
+
+/// The config one job runs with: `cfg` with the job's overrides applied, plus the
+/// curl settings that only a job or the run can turn on.
+#[derive(Debug, Clone)]
+pub struct JobConfig<'a> {
+    pub cfg: Cow<'a, DdmConfig>,
+    /// TLS verification off (`--insecure` on the run or the job).
+    pub insecure: bool,
+    /// Cookies for curl's cookie engine (a job imported with `--allow-cookies`).
+    pub cookie: Option<String>,
+}
+
+impl<'a> From<&'a DdmConfig> for JobConfig<'a> {
+    /// `cfg` as is, with TLS verification and the cookie engine left at their defaults.
+    fn from(cfg: &'a DdmConfig) -> Self {
+        JobConfig {
+            cfg: Cow::Borrowed(cfg),
+            insecure: false,
+            cookie: None,
+        }
+    }
+}
+
+impl JobConfig<'_> {
+    /// A copy that owns its config, for moving into a blocking task.
+    pub fn into_owned(self) -> JobConfig<'static> {
+        JobConfig {
+            cfg: Cow::Owned(self.cfg.into_owned()),
+            insecure: self.insecure,
+            cookie: self.cookie,
+        }
+    }
+
+    /// Curl options for `concurrency` simultaneous handles of this job.
+    pub fn curl_options(&self, concurrency: usize) -> CurlOptions {
+        CurlOptions {
+            insecure: self.insecure,
+            cookie: self.cookie.clone(),
+            ..CurlOptions::from_config(&self.cfg, concurrency)
+        }
+    }
+}
+
+impl Deref for JobConfig<'_> {
+    type Target = DdmConfig;
+
+    fn deref(&self) -> &DdmConfig {
+        &self.cfg
+    }
+}
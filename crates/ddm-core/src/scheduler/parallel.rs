@@ -12,8 +12,9 @@ use crate::host_policy::HostPolicy;
 use crate::resume_db::ResumeDb;
 
 use super::budget::GlobalConnectionBudget;
+use super::options::RunOptions;
 use super::progress::ProgressStats;
-use super::run::run_one_job_shared;
+use super::run::{is_host_unreachable, run_one_job_shared};
 
 /// Runs queued jobs with up to `max_concurrent` jobs in flight at once.
 /// Uses a shared `Arc<Mutex<HostPolicy>>` and `Arc<GlobalConnectionBudget>>`
@@ -22,9 +23,13 @@ use super::run::run_one_job_shared;
 /// Replaces `host_policy` with a temporary for the run and restores the
 /// updated policy when done (so the caller can save it).
 /// If `job_control` is `Some`, running jobs can be paused via the control socket.
+/// If `max_jobs` is `Some(n)`, no more than `n` jobs are started; the rest stay queued.
+/// Jobs for hosts whose circuit is open are not started (left queued), and a job whose host
+/// cannot be reached is marked failed without stopping the others.
 pub async fn run_jobs_parallel(
     db: &ResumeDb,
     cfg: &DdmConfig,
+    run: &RunOptions,
     download_dir: PathBuf,
     host_policy: &mut HostPolicy,
     force_restart: bool,
@@ -32,6 +37,7 @@ pub async fn run_jobs_parallel(
     progress_tx: Option<tokio::sync::mpsc::Sender<ProgressStats>>,
     global_budget: Arc<GlobalConnectionBudget>,
     max_concurrent: usize,
+    max_jobs: Option<u32>,
     job_control: Option<std::sync::Arc<crate::control::JobControl>>,
 ) -> Result<u32> {
     let max_concurrent = max_concurrent.max(1);
@@ -41,15 +47,28 @@ pub async fn run_jobs_parallel(
     )));
 
     let mut run_count = 0u32;
+    let mut started = 0u32;
     let mut join_set = tokio::task::JoinSet::new();
 
     loop {
         while join_set.len() < max_concurrent {
-            let Some(job_id) = db.claim_next_queued_job().await? else {
+            if job_control.as_ref().is_some_and(|c| c.is_shutting_down()) {
+                break;
+            }
+            if max_jobs.is_some_and(|max| started >= max) {
+                break;
+            }
+            let filter = shared_policy
+                .lock()
+                .await
+                .skip_open_circuits(&run.host_filter, std::time::Instant::now());
+            let Some(job_id) = db.claim_next_queued_job_matching(&filter).await? else {
                 break;
             };
+            started += 1;
             let db = db.clone();
             let cfg = cfg.clone();
+            let run = run.clone();
             let download_dir = download_dir.clone();
             let policy = Arc::clone(&shared_policy);
             let tx = progress_tx.clone();
@@ -63,6 +82,7 @@ pub async fn run_jobs_parallel(
                     force_restart,
                     overwrite,
                     &cfg,
+                    &run,
                     &download_dir,
                     policy,
                     tx,
@@ -81,7 +101,10 @@ pub async fn run_jobs_parallel(
             break;
         };
         run_count += 1;
-        res.map_err(|e| anyhow::anyhow!("job task join: {}", e))??;
+        match res.map_err(|e| anyhow::anyhow!("job task join: {}", e))? {
+            Err(e) if is_host_unreachable(&e) => tracing::info!("{:#}", e),
+            other => other?,
+        }
     }
 
     // Restore updated policy; if a clone is still held (e.g. by a task), clone out instead of failing.
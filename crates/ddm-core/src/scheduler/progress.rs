@@ -2,10 +2,16 @@
 //!
 //! Used by the scheduler to report progress to the CLI; consumers can compute
 //! rate = bytes_done / elapsed_secs and ETA = (total_bytes - bytes_done) / rate.
+//! [`AggregateProgress`] combines the stats of jobs running in parallel.
 
-/// Snapshot of download progress for one job (CLI-friendly).
-#[derive(Debug, Clone)]
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Snapshot of download progress for one job (CLI-friendly; JSON over the control socket).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ProgressStats {
+    /// Job these stats belong to.
+    pub job_id: i64,
     /// Bytes written so far (completed segments).
     pub bytes_done: u64,
     /// Bytes currently being received for in-flight segments (smoother rate/ETA).
@@ -56,3 +62,109 @@ impl ProgressStats {
         (self.bytes_done as f64 / self.total_bytes as f64).min(1.0)
     }
 }
+
+/// A job whose stats are older than this no longer counts toward the combined rate (it
+/// finished, paused or failed). Running jobs report at least every few seconds.
+const AGGREGATE_STALE_AFTER: Duration = Duration::from_secs(10);
+
+/// Combined progress across the jobs of a parallel run (`ddm run --jobs N`): the latest
+/// [`ProgressStats`] of every job seen, summed.
+#[derive(Debug, Default)]
+pub struct AggregateProgress {
+    jobs: HashMap<i64, (ProgressStats, Instant)>,
+}
+
+impl AggregateProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `stats` as its job's latest snapshot, received at `now`.
+    pub fn record(&mut self, stats: &ProgressStats, now: Instant) {
+        self.jobs.insert(stats.job_id, (stats.clone(), now));
+    }
+
+    /// Jobs still downloading: not finished and reported within the last few seconds.
+    fn active(&self, now: Instant) -> impl Iterator<Item = &ProgressStats> {
+        self.jobs.values().filter_map(move |(s, at)| {
+            (s.bytes_done < s.total_bytes
+                && now.saturating_duration_since(*at) < AGGREGATE_STALE_AFTER)
+                .then_some(s)
+        })
+    }
+
+    /// Number of jobs counted in [`Self::bytes_per_sec`].
+    pub fn active_jobs(&self, now: Instant) -> usize {
+        self.active(now).count()
+    }
+
+    /// Bytes received by every job seen this run (completed plus in-flight).
+    pub fn bytes_done(&self) -> u64 {
+        self.jobs.values().map(|(s, _)| s.effective_bytes()).sum()
+    }
+
+    /// Combined size of every job seen this run.
+    pub fn total_bytes(&self) -> u64 {
+        self.jobs.values().map(|(s, _)| s.total_bytes).sum()
+    }
+
+    /// Sum of the active jobs' rates (bytes/sec, in-flight bytes included).
+    pub fn bytes_per_sec(&self, now: Instant) -> f64 {
+        self.active(now)
+            .filter(|s| s.elapsed_secs > 0.0)
+            .map(|s| s.effective_bytes() as f64 / s.elapsed_secs)
+            .sum()
+    }
+
+    /// Seconds until the active jobs finish at the combined rate (None while it is 0).
+    pub fn eta_secs(&self, now: Instant) -> Option<f64> {
+        let remaining: u64 = self
+            .active(now)
+            .map(|s| s.total_bytes.saturating_sub(s.effective_bytes()))
+            .sum();
+        if remaining == 0 {
+            return Some(0.0);
+        }
+        let rate = self.bytes_per_sec(now);
+        (rate > 0.0).then(|| remaining as f64 / rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(job_id: i64, done: u64, total: u64, elapsed_secs: f64) -> ProgressStats {
+        ProgressStats {
+            job_id,
+            bytes_done: done,
+            bytes_in_flight: 0,
+            total_bytes: total,
+            elapsed_secs,
+            segments_done: 0,
+            segment_count: 4,
+        }
+    }
+
+    #[test]
+    fn aggregate_sums_active_job_rates() {
+        let mut agg = AggregateProgress::new();
+        let t0 = Instant::now();
+        agg.record(&stats(1, 100, 1000, 1.0), t0);
+        agg.record(&stats(2, 300, 1000, 1.0), t0);
+        agg.record(&stats(2, 600, 1000, 2.0), t0);
+        agg.record(&stats(3, 500, 500, 1.0), t0);
+
+        assert_eq!(agg.bytes_done(), 1200);
+        assert_eq!(agg.total_bytes(), 2500);
+        assert_eq!(agg.active_jobs(t0), 2, "finished job 3 is not active");
+        assert_eq!(agg.bytes_per_sec(t0), 400.0);
+        assert_eq!(agg.eta_secs(t0), Some(1300.0 / 400.0));
+
+        // Job 1 stops reporting (paused): only job 2 counts.
+        let later = t0 + AGGREGATE_STALE_AFTER;
+        agg.record(&stats(2, 800, 1000, 3.0), later);
+        assert_eq!(agg.active_jobs(later), 1);
+        assert_eq!(agg.eta_secs(later), Some(200.0 / (800.0 / 3.0)));
+    }
+}
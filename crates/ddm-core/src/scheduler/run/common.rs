@@ -1,14 +1,301 @@
-//! Shared helpers for single and parallel job run (filename resolution, paths).
+//! Shared helpers for single and parallel job run (filename resolution, paths, failures).
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::path::Path;
 
-use crate::resume_db::ResumeDb;
+use crate::config::ConflictPolicy;
+use crate::resume_db::{ErrorCategory, JobState, ResumeDb};
+use crate::retry::SegmentError;
+use crate::scheduler::options::{JobConfig, RunOptions};
 use crate::storage;
 use crate::url_model;
 
+/// Config for running `job`: `cfg` with the job's own overrides (`user_agent`, `on_complete`,
+/// `max_bytes_per_sec`) applied, and TLS verification and the cookie engine set from `run`
+/// and the job's `insecure` and `cookie_session`. Warns every time a job runs with TLS
+/// verification disabled.
+pub fn job_config<'a>(
+    cfg: &'a crate::config::DdmConfig,
+    run: &RunOptions,
+    job: &crate::resume_db::JobDetails,
+) -> JobConfig<'a> {
+    let s = &job.settings;
+    let insecure = run.insecure || s.insecure;
+    if insecure {
+        tracing::warn!(
+            job_id = job.id,
+            "TLS certificate verification is DISABLED for this job (--insecure); \
+             the server's identity is not checked"
+        );
+    }
+    let cookie = session_cookie(job).map(str::to_string);
+    if s.user_agent.is_none() && s.on_complete.is_none() && s.max_bytes_per_sec.is_none() {
+        return JobConfig {
+            cfg: std::borrow::Cow::Borrowed(cfg),
+            insecure,
+            cookie,
+        };
+    }
+    let cap = |bps: u64| s.max_bytes_per_sec.map_or(bps, |job| job.min(bps));
+    let cfg = crate::config::DdmConfig {
+        user_agent: s.user_agent.clone().or_else(|| cfg.user_agent.clone()),
+        on_complete: s.on_complete.clone().or_else(|| cfg.on_complete.clone()),
+        max_bytes_per_sec: cfg.max_bytes_per_sec.map(cap).or(s.max_bytes_per_sec),
+        bandwidth_schedule: cfg
+            .bandwidth_schedule
+            .iter()
+            .map(|w| crate::config::BandwidthWindow {
+                max_bytes_per_sec: cap(w.max_bytes_per_sec),
+                ..w.clone()
+            })
+            .collect(),
+        ..cfg.clone()
+    };
+    JobConfig {
+        cfg: std::borrow::Cow::Owned(cfg),
+        insecure,
+        cookie,
+    }
+}
+
+/// The job's `Cookie` header when it is sent through curl's cookie engine (`cookie_session`).
+fn session_cookie(job: &crate::resume_db::JobDetails) -> Option<&str> {
+    if !job.settings.cookie_session {
+        return None;
+    }
+    job.settings
+        .custom_headers
+        .as_ref()?
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("Cookie"))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Headers sent with the job's requests: its `custom_headers`, minus the `Cookie` that
+/// `job_config` hands to the cookie engine for a `cookie_session` job.
+pub fn job_headers(
+    job: &crate::resume_db::JobDetails,
+) -> std::collections::HashMap<String, String> {
+    let mut headers = job.settings.custom_headers.clone().unwrap_or_default();
+    if job.settings.cookie_session {
+        headers.retain(|k, _| !k.eq_ignore_ascii_case("Cookie"));
+    }
+    headers
+}
+
+/// URL to probe and download for this run: `job.url`, or, for jobs carrying a POST
+/// (HAR import with --allow-post), the redirect target obtained by replaying it.
+pub async fn resolve_download_url(
+    job: &crate::resume_db::JobDetails,
+    headers: &std::collections::HashMap<String, String>,
+    cfg: &JobConfig<'_>,
+) -> Result<String> {
+    let Some(post) = job.settings.post.clone() else {
+        return Ok(job.url.clone());
+    };
+    let url = job.url.clone();
+    let headers = headers.clone();
+    let curl = cfg.curl_options(1);
+    let target = tokio::task::spawn_blocking(move || {
+        crate::fetch_head::resolve_post(&url, &headers, &post, &curl)
+    })
+    .await
+    .context("POST task join")??;
+    tracing::debug!(job_id = job.id, "POST resolved download URL");
+    Ok(target)
+}
+
+/// Fails when the job has an expected size (`ddm add --expected-size`) and the probe reports a
+/// different Content-Length. An unknown length is only logged; the size is not known up front.
+pub fn check_expected_size(
+    job: &crate::resume_db::JobDetails,
+    head: &crate::fetch_head::HeadResult,
+) -> Result<()> {
+    let Some(expected) = job.settings.expected_size else {
+        return Ok(());
+    };
+    match head.content_length {
+        Some(len) if len != expected => anyhow::bail!(
+            "size mismatch: server reports {} bytes, expected {} (--expected-size)",
+            len,
+            expected
+        ),
+        Some(_) => Ok(()),
+        None => {
+            tracing::warn!(
+                job_id = job.id,
+                "server did not report a length; cannot check --expected-size {}",
+                expected
+            );
+            Ok(())
+        }
+    }
+}
+
+/// True when the probe allows a segmented download: Range support, a known non-zero
+/// length, and a size at or above `single_stream_threshold_bytes`. Otherwise the job
+/// runs as a single unranged GET.
+pub fn is_segmentable(
+    head: &crate::fetch_head::HeadResult,
+    cfg: &crate::config::DdmConfig,
+) -> bool {
+    head.accepts_ranges()
+        && head
+            .content_length
+            .is_some_and(|len| len > 0 && len >= cfg.single_stream_threshold())
+}
+
+/// With `verify_ranges` on, asks for byte 0 (`probe_range0`) to confirm the server really
+/// answers Range requests with 206 before a segmented plan is made. Returns false when it
+/// replies 200 with the whole body; a failed probe is logged and trusts the earlier probe.
+pub async fn confirm_ranges(
+    cfg: &JobConfig<'_>,
+    job_id: i64,
+    url: &str,
+    headers: &std::collections::HashMap<String, String>,
+) -> bool {
+    if !cfg.verify_ranges.unwrap_or(false) {
+        return true;
+    }
+    let probe = tokio::task::spawn_blocking({
+        let url = url.to_string();
+        let headers = headers.clone();
+        let curl = cfg.curl_options(1);
+        move || crate::fetch_head::probe_range0(&url, &headers, &curl)
+    })
+    .await;
+    match probe {
+        Ok(Ok(r)) if !r.accepts_ranges() => {
+            tracing::warn!(
+                job_id,
+                "server advertises Range support but ignored a range request; \
+                 downloading as a single stream"
+            );
+            false
+        }
+        Ok(Ok(_)) => true,
+        Ok(Err(e)) => {
+            tracing::debug!(job_id, "range confirmation probe failed: {:#}", e);
+            true
+        }
+        Err(e) => {
+            tracing::debug!(job_id, "range confirmation probe task failed: {}", e);
+            true
+        }
+    }
+}
+
+/// With no user-supplied checksum, verify the finished file against the digest the server
+/// advertised (`Digest` / `Content-MD5`). Only the in-memory job changes; every run re-probes.
+pub fn adopt_server_digest(
+    job: &mut crate::resume_db::JobDetails,
+    head: &crate::fetch_head::HeadResult,
+) {
+    if job.settings.expected_checksum.is_some() {
+        return;
+    }
+    if let Some((algo, hex)) = &head.server_digest {
+        tracing::debug!(job_id = job.id, algo = algo.as_str(), %hex, "verifying against server digest");
+        job.settings.expected_checksum = head.server_digest.clone();
+    }
+}
+
+/// When the probe found Range support but no length, asks for `bytes=0-`
+/// (`probe_total_size`) and fills `head.content_length` from the reply. If the size is
+/// still unknown afterwards, `is_segmentable` sends the job down the single-stream path.
+pub async fn fill_missing_length(
+    cfg: &JobConfig<'_>,
+    job_id: i64,
+    url: &str,
+    headers: &std::collections::HashMap<String, String>,
+    head: &mut crate::fetch_head::HeadResult,
+) {
+    if !head.accepts_ranges() || head.content_length.is_some() {
+        return;
+    }
+    let probe = tokio::task::spawn_blocking({
+        let url = url.to_string();
+        let headers = headers.clone();
+        let curl = cfg.curl_options(1);
+        move || crate::fetch_head::probe_total_size(&url, &headers, &curl)
+    })
+    .await;
+    match probe {
+        Ok(Ok(Some(total))) => {
+            tracing::debug!(job_id, total, "open-ended range probe reported the size");
+            head.content_length = Some(total);
+        }
+        Ok(Ok(None)) => tracing::info!(
+            job_id,
+            "server accepts ranges but reports no size; downloading as a single stream"
+        ),
+        Ok(Err(e)) => tracing::debug!(job_id, "open-ended range probe failed: {:#}", e),
+        Err(e) => tracing::debug!(job_id, "open-ended range probe task failed: {}", e),
+    }
+}
+
+/// Marks a job whose probe could not reach its host; the run loops record it and move on
+/// to the next job instead of stopping.
+#[derive(Debug)]
+pub(crate) struct HostUnreachable;
+
+impl std::fmt::Display for HostUnreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("host unreachable")
+    }
+}
+
+/// True if `e` is a [`HostUnreachable`] failure.
+pub(crate) fn is_host_unreachable(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<HostUnreachable>().is_some()
+}
+
+/// True if the chain holds a curl error meaning the host was never reached (connection
+/// refused, name not resolved, timed out, TLS handshake failed), as opposed to an HTTP reply.
+fn is_connect_failure(e: &anyhow::Error) -> bool {
+    e.chain()
+        .filter_map(|c| c.downcast_ref::<curl::Error>())
+        .any(|c| {
+            c.is_couldnt_connect()
+                || c.is_couldnt_resolve_host()
+                || c.is_operation_timedout()
+                || c.is_ssl_connect_error()
+        })
+}
+
+/// Handles a failed probe. A connection-level failure counts toward the host's circuit
+/// breaker (`circuit_*` in config), fails the job with a network error and comes back
+/// marked [`HostUnreachable`]; any other failure is returned unchanged.
+pub async fn probe_failed(
+    db: &ResumeDb,
+    job_id: i64,
+    url: &str,
+    cfg: &crate::config::DdmConfig,
+    host_policy: &mut crate::host_policy::HostPolicy,
+    e: anyhow::Error,
+) -> anyhow::Error {
+    if !is_connect_failure(&e) {
+        return e;
+    }
+    let breaker = cfg.circuit_breaker();
+    match host_policy.record_connect_failure(url, &breaker, std::time::Instant::now()) {
+        Ok(true) => tracing::warn!(
+            job_id,
+            url,
+            "host unreachable {} times in a row; skipping its jobs for {}s",
+            breaker.threshold,
+            breaker.cooldown.as_secs()
+        ),
+        Ok(false) => {}
+        Err(err) => tracing::debug!(job_id, "could not record connection failure: {:#}", err),
+    }
+    record_failure(db, job_id, &e).await;
+    e.context(HostUnreachable)
+}
+
 /// Resolve final and temp filenames and whether metadata must be (re)fetched.
-/// Uses job's download_dir or `download_dir`; checks DB for existing names to avoid collisions.
+/// Uses job's download_dir or `download_dir`; checks DB for existing names to avoid collisions,
+/// except for a name forced with `ddm add --output`, which is used verbatim.
 pub async fn resolve_filenames(
     db: &ResumeDb,
     job_id: i64,
@@ -24,7 +311,14 @@ pub async fn resolve_filenames(
         .download_dir
         .as_deref()
         .or_else(|| download_dir.to_str());
-    let final_name = if job.total_size.is_none() || force_restart || validation_failed {
+    let forced = job
+        .settings
+        .output_filename
+        .as_deref()
+        .and_then(url_model::forced_filename);
+    let final_name = if let Some(name) = forced {
+        name
+    } else if job.total_size.is_none() || force_restart || validation_failed {
         let existing = db
             .list_final_filenames_in_dir(effective_dir_str, Some(job_id))
             .await?;
@@ -41,27 +335,230 @@ pub async fn resolve_filenames(
     Ok((final_name, temp_name_str, needs_metadata))
 }
 
-/// Build temp and final paths from job and names; error if final exists and overwrite is false.
-pub fn paths_and_overwrite_check(
-    job: &crate::resume_db::JobDetails,
+/// Temp name to store for a job: `temp_name` itself, or with `temp_dir` configured, an absolute
+/// path in that directory prefixed with the job id (so jobs from different download dirs cannot
+/// collide). Joining an absolute name onto the download dir yields it unchanged.
+pub fn staged_temp_name(cfg: &crate::config::DdmConfig, job_id: i64, temp_name: String) -> String {
+    match &cfg.temp_dir {
+        Some(dir) => dir
+            .join(format!("{job_id}-{temp_name}"))
+            .to_string_lossy()
+            .into_owned(),
+        None => temp_name,
+    }
+}
+
+/// Build temp and final paths from job and names. When the final file already exists,
+/// `overwrite` (`--overwrite`) or else `on_conflict` decides: `error` fails, `overwrite` keeps
+/// the path, `rename` moves the job to a free `name (n).ext` and stores it, and `skip` marks
+/// the job completed and returns None.
+pub async fn resolve_paths(
+    db: &ResumeDb,
+    job: &mut crate::resume_db::JobDetails,
     final_name: &str,
     temp_name_str: &str,
     download_dir: &Path,
     overwrite: bool,
-) -> Result<(std::path::PathBuf, std::path::PathBuf)> {
+    cfg: &crate::config::DdmConfig,
+) -> Result<Option<(std::path::PathBuf, std::path::PathBuf)>> {
     let effective_dir = job
         .settings
         .download_dir
         .as_deref()
         .map(std::path::Path::new)
-        .unwrap_or(download_dir);
+        .unwrap_or(download_dir)
+        .to_path_buf();
     let temp_path = effective_dir.join(job.temp_filename.as_deref().unwrap_or(temp_name_str));
-    let final_path = effective_dir.join(job.final_filename.as_deref().unwrap_or(final_name));
-    if final_path.exists() && !overwrite {
-        anyhow::bail!(
-            "final file already exists: {} (use --overwrite to replace)",
+    let name = job
+        .final_filename
+        .as_deref()
+        .unwrap_or(final_name)
+        .to_string();
+    let final_path = effective_dir.join(&name);
+    if !final_path.exists() {
+        return Ok(Some((temp_path, final_path)));
+    }
+    let policy = if overwrite {
+        ConflictPolicy::Overwrite
+    } else {
+        cfg.on_conflict.unwrap_or_default()
+    };
+    match policy {
+        ConflictPolicy::Error => anyhow::bail!(
+            "final file already exists: {} (use --overwrite or --on-conflict to choose)",
             final_path.display()
+        ),
+        ConflictPolicy::Overwrite => Ok(Some((temp_path, final_path))),
+        ConflictPolicy::Rename => {
+            let mut taken = db
+                .list_final_filenames_in_dir(effective_dir.to_str(), Some(job.id))
+                .await?;
+            if let Ok(entries) = std::fs::read_dir(&effective_dir) {
+                taken.extend(
+                    entries
+                        .flatten()
+                        .filter_map(|e| e.file_name().into_string().ok()),
+                );
+            }
+            let renamed = url_model::unique_filename_among(&name, &taken);
+            tracing::info!(
+                job_id = job.id,
+                "{} exists; downloading to {} instead",
+                name,
+                renamed
+            );
+            db.set_final_filename(job.id, &renamed).await?;
+            let final_path = effective_dir.join(&renamed);
+            job.final_filename = Some(renamed);
+            Ok(Some((temp_path, final_path)))
+        }
+        ConflictPolicy::Skip => {
+            tracing::info!(
+                job_id = job.id,
+                "{} exists; skipping download (--on-conflict skip)",
+                final_path.display()
+            );
+            db.set_state(job.id, JobState::Completed).await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Put the job in the Error state, recording `e` (with its context chain) and its category.
+pub async fn record_failure(db: &ResumeDb, job_id: i64, e: &anyhow::Error) {
+    let message = format!("{:#}", e);
+    if let Err(db_err) = db.set_error(job_id, error_category(e), &message).await {
+        tracing::warn!("job {}: failed to record error: {}", job_id, db_err);
+    }
+}
+
+/// Category of a job failure, from the first recognised error in the chain.
+pub fn error_category(e: &anyhow::Error) -> ErrorCategory {
+    for cause in e.chain() {
+        if let Some(se) = cause.downcast_ref::<SegmentError>() {
+            return segment_error_category(se);
+        }
+        if cause
+            .downcast_ref::<super::super::execute::DeadlineExceeded>()
+            .is_some()
+        {
+            return ErrorCategory::Deadline;
+        }
+        if cause
+            .downcast_ref::<crate::safe_resume::ValidationError>()
+            .is_some()
+        {
+            return ErrorCategory::RemoteChanged;
+        }
+        if cause.downcast_ref::<std::io::Error>().is_some() {
+            return ErrorCategory::Storage;
+        }
+        if cause.downcast_ref::<curl::Error>().is_some() {
+            return ErrorCategory::Network;
+        }
+    }
+    ErrorCategory::Other
+}
+
+fn segment_error_category(e: &SegmentError) -> ErrorCategory {
+    match e {
+        SegmentError::Storage(_) => ErrorCategory::Storage,
+        SegmentError::RemoteChanged | SegmentError::TotalSizeMismatch { .. } => {
+            ErrorCategory::RemoteChanged
+        }
+        SegmentError::RetryBudgetExhausted { last, .. } => segment_error_category(last),
+        SegmentError::Curl(_)
+        | SegmentError::Http(_)
+        | SegmentError::InvalidRangeResponse(_)
+        | SegmentError::PartialTransfer { .. }
+        | SegmentError::Stalled { .. }
+        | SegmentError::UnexpectedEncoding(_) => ErrorCategory::Network,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged_temp_name_uses_temp_dir_when_set() {
+        let mut cfg = crate::config::DdmConfig::default();
+        assert_eq!(staged_temp_name(&cfg, 7, "a.iso.part".into()), "a.iso.part");
+        cfg.temp_dir = Some("/mnt/ssd/ddm".into());
+        let staged = staged_temp_name(&cfg, 7, "a.iso.part".into());
+        assert_eq!(staged, "/mnt/ssd/ddm/7-a.iso.part");
+        assert_eq!(
+            Path::new("/srv/isos").join(&staged),
+            Path::new("/mnt/ssd/ddm/7-a.iso.part")
+        );
+    }
+
+    #[test]
+    fn job_config_applies_the_tighter_bandwidth_cap() {
+        let mut settings = crate::resume_db::JobSettings::default();
+        settings.apply_trickle();
+        let job = crate::resume_db::JobDetails {
+            id: 1,
+            url: "https://example.com/a.iso".into(),
+            final_filename: None,
+            temp_filename: None,
+            total_size: None,
+            etag: None,
+            last_modified: None,
+            segment_count: 0,
+            completed_bitmap: Vec::new(),
+            state: JobState::Queued,
+            created_at: 0,
+            updated_at: 0,
+            settings,
+            segment_hashes: Vec::new(),
+            segment_progress: Vec::new(),
+            last_error: None,
+            error_category: None,
+        };
+        let trickle = crate::resume_db::TRICKLE_MAX_BYTES_PER_SEC;
+
+        let cfg = crate::config::DdmConfig::default();
+        assert_eq!(
+            job_config(&cfg, &RunOptions::default(), &job).max_bytes_per_sec,
+            Some(trickle)
+        );
+
+        let cfg = crate::config::DdmConfig {
+            max_bytes_per_sec: Some(1024),
+            bandwidth_schedule: vec![crate::config::BandwidthWindow {
+                start: "00:00".into(),
+                end: "23:59".into(),
+                max_bytes_per_sec: 10 * trickle,
+            }],
+            ..Default::default()
+        };
+        let job_cfg = job_config(&cfg, &RunOptions::default(), &job);
+        assert_eq!(job_cfg.max_bytes_per_sec, Some(1024), "config cap is lower");
+        assert_eq!(job_cfg.max_bytes_per_sec_at(60), Some(trickle));
+    }
+
+    #[test]
+    fn error_category_follows_the_error_chain() {
+        let disk = anyhow::Error::new(SegmentError::Storage(std::io::Error::other("disk full")))
+            .context("segment 3");
+        assert_eq!(error_category(&disk), ErrorCategory::Storage);
+
+        let exhausted = anyhow::Error::new(SegmentError::RetryBudgetExhausted {
+            retries: 5,
+            last: Box::new(SegmentError::Http(503)),
+        });
+        assert_eq!(error_category(&exhausted), ErrorCategory::Network);
+
+        let changed = anyhow::Error::new(SegmentError::RemoteChanged).context("segment 0");
+        assert_eq!(error_category(&changed), ErrorCategory::RemoteChanged);
+
+        let rename = anyhow::Error::new(std::io::Error::other("rename failed")).context("finalize");
+        assert_eq!(error_category(&rename), ErrorCategory::Storage);
+
+        assert_eq!(
+            error_category(&anyhow::anyhow!("worker panicked")),
+            ErrorCategory::Other
         );
     }
-    Ok((temp_path, final_path))
 }
@@ -3,15 +3,17 @@
 use anyhow::Result;
 use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
-use crate::config::DdmConfig;
-use crate::downloader::CurlOptions;
 use crate::fetch_head::HeadResult;
 use crate::resume_db::{JobDetails, JobMetadata, JobState, ResumeDb};
 use crate::scheduler::execute;
+use crate::scheduler::options::JobConfig;
 
 /// Runs a single-stream GET download for a job (non-Range fallback) and completes the job.
-/// Intended to be called from the scheduler run paths after a metadata probe.
+/// Intended to be called from the scheduler run paths after a metadata probe. `abort`
+/// (pause, cancel, Ctrl-C) stops the transfer and leaves the job Paused.
 pub(super) async fn run_single_stream(
     db: &ResumeDb,
     job_id: i64,
@@ -20,11 +22,12 @@ pub(super) async fn run_single_stream(
     headers: &HashMap<String, String>,
     head: &HeadResult,
     overwrite: bool,
-    cfg: &DdmConfig,
+    cfg: &JobConfig<'_>,
     default_download_dir: &Path,
     final_name: &str,
     temp_name_str: &str,
     needs_metadata: bool,
+    abort: Option<Arc<AtomicBool>>,
 ) -> Result<()> {
     if needs_metadata {
         let meta = JobMetadata {
@@ -40,24 +43,22 @@ pub(super) async fn run_single_stream(
         *job = db.get_job(job_id).await?.expect("job exists after update");
     }
 
-    let effective_dir = job
-        .settings
-        .download_dir
-        .as_deref()
-        .map(Path::new)
-        .unwrap_or(default_download_dir);
-    let temp_path = effective_dir.join(job.temp_filename.as_deref().unwrap_or(temp_name_str));
-    let final_path = effective_dir.join(job.final_filename.as_deref().unwrap_or(final_name));
-
-    if final_path.exists() && !overwrite {
-        anyhow::bail!(
-            "final file already exists: {} (use --overwrite to replace)",
-            final_path.display()
-        );
-    }
+    let Some((temp_path, final_path)) = super::common::resolve_paths(
+        db,
+        job,
+        final_name,
+        temp_name_str,
+        default_download_dir,
+        overwrite,
+        cfg,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
 
+    super::common::adopt_server_digest(job, head);
     db.set_state(job_id, JobState::Running).await?;
-    let curl = CurlOptions::per_handle(cfg.max_bytes_per_sec, 1, cfg.segment_buffer_bytes);
     let bytes_written = execute::execute_single_download_phase(
         db,
         job_id,
@@ -66,13 +67,27 @@ pub(super) async fn run_single_stream(
         &temp_path,
         &final_path,
         head.content_length,
-        curl,
+        job.settings.expected_checksum.as_ref(),
+        cfg.preallocation.unwrap_or_default(),
+        cfg.curl_options(1),
+        abort,
+        job.settings
+            .deadline_secs
+            .map(std::time::Duration::from_secs),
     )
-    .await?;
+    .await;
+    let bytes_written = match bytes_written {
+        Ok(Some(n)) => n,
+        Ok(None) => return Ok(()),
+        Err(e) => {
+            super::common::record_failure(db, job_id, &e).await;
+            return Err(e);
+        }
+    };
 
     if job.total_size.is_none() {
         let meta = JobMetadata {
-            final_filename: Some(final_name.to_string()),
+            final_filename: job.final_filename.clone().or(Some(final_name.to_string())),
             temp_filename: Some(temp_name_str.to_string()),
             total_size: Some(bytes_written as i64),
             etag: head.etag.clone(),
@@ -82,6 +97,7 @@ pub(super) async fn run_single_stream(
         };
         db.update_metadata(job_id, &meta).await?;
     }
+    super::super::hook::run_on_complete(cfg, job_id, &final_path, &job.url).await;
 
     Ok(())
 }
@@ -9,27 +9,32 @@ use anyhow::Result;
 use std::path::Path;
 
 use crate::config::DdmConfig;
-use crate::host_policy::HostPolicy;
+use crate::host_policy::{HostFilter, HostPolicy};
 use crate::resume_db::{JobState, ResumeDb};
 
 use super::budget::GlobalConnectionBudget;
+use super::options::RunOptions;
 use super::progress::ProgressStats;
 
+pub(crate) use common::is_host_unreachable;
 pub use shared::run_one_job_shared;
 pub use single::run_one_job;
 
-/// Returns the id of the next queued job (smallest id first), or None if none queued.
-pub async fn next_queued_job_id(db: &ResumeDb) -> Result<Option<i64>> {
+/// Returns the id of the next queued job (highest priority, then queue position) whose host passes
+/// `filter`, or None if none queued.
+pub async fn next_queued_job_id(db: &ResumeDb, filter: &HostFilter) -> Result<Option<i64>> {
     let jobs = db.list_jobs().await?;
     let next = jobs
         .into_iter()
-        .filter(|j| j.state == JobState::Queued)
-        .min_by_key(|j| j.id)
+        .filter(|j| j.state == JobState::Queued && filter.matches(&j.url))
+        .min_by_key(|j| (std::cmp::Reverse(j.priority), j.queue_position, j.id))
         .map(|j| j.id);
     Ok(next)
 }
 
-/// Runs the next queued job (smallest id first, FIFO). Returns true if a job was run, false if none queued.
+/// Runs the next queued job (highest priority first, then queue order; FIFO unless moved with `ddm queue move`). Returns true if a job was run, false if none queued.
+/// Jobs for hosts whose circuit is open are skipped (left queued); a job whose host cannot be
+/// reached is marked failed without failing the call.
 /// If `progress_tx` is `Some`, progress stats are sent during the download.
 /// If `job_control` is `Some`, the job can be paused via the control socket.
 pub async fn run_next_job(
@@ -37,27 +42,37 @@ pub async fn run_next_job(
     force_restart: bool,
     overwrite: bool,
     cfg: &DdmConfig,
+    run: &RunOptions,
     download_dir: &Path,
     host_policy: &mut HostPolicy,
     progress_tx: Option<&tokio::sync::mpsc::Sender<ProgressStats>>,
     global_budget: Option<&GlobalConnectionBudget>,
     job_control: Option<std::sync::Arc<crate::control::JobControl>>,
 ) -> Result<bool> {
-    let Some(job_id) = next_queued_job_id(db).await? else {
+    if job_control.as_ref().is_some_and(|c| c.is_shutting_down()) {
+        return Ok(false);
+    }
+    let filter = host_policy.skip_open_circuits(&run.host_filter, std::time::Instant::now());
+    let Some(job_id) = next_queued_job_id(db, &filter).await? else {
         return Ok(false);
     };
-    run_one_job(
+    let result = run_one_job(
         db,
         job_id,
         force_restart,
         overwrite,
         cfg,
+        run,
         download_dir,
         host_policy,
         progress_tx,
         global_budget,
         job_control,
     )
-    .await?;
+    .await;
+    match result {
+        Err(e) if is_host_unreachable(&e) => tracing::info!(job_id, "{:#}", e),
+        other => other?,
+    }
     Ok(true)
 }
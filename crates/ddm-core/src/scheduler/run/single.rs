@@ -15,6 +15,7 @@ use crate::segmenter;
 use super::super::budget::GlobalConnectionBudget;
 use super::super::choose;
 use super::super::execute;
+use super::super::options::RunOptions;
 use super::super::progress::ProgressStats;
 
 /// Runs a single job: re-validates with HEAD, then downloads only incomplete segments.
@@ -30,6 +31,7 @@ pub async fn run_one_job(
     force_restart: bool,
     overwrite: bool,
     cfg: &DdmConfig,
+    run: &RunOptions,
     download_dir: &Path,
     host_policy: &mut HostPolicy,
     progress_tx: Option<&tokio::sync::mpsc::Sender<ProgressStats>>,
@@ -40,45 +42,69 @@ pub async fn run_one_job(
         .get_job(job_id)
         .await?
         .ok_or_else(|| anyhow::anyhow!("job {} not found", job_id))?;
+    let _heartbeat = super::super::heartbeat::Heartbeat::start(db, job_id);
+    let job_cfg = super::common::job_config(cfg, run, &job);
+    let cfg = &job_cfg;
 
-    let url = job.url.clone();
-    let headers: HashMap<String, String> = job.settings.custom_headers.clone().unwrap_or_default();
+    let headers: HashMap<String, String> = super::common::job_headers(&job);
+    let url = super::common::resolve_download_url(&job, &headers, cfg).await?;
 
-    let head = tokio::task::spawn_blocking({
+    let probe = tokio::task::spawn_blocking({
         let url = url.clone();
         let headers = headers.clone();
-        move || fetch_head::probe_best_effort(&url, &headers)
+        let curl = cfg.curl_options(1);
+        let strategy = cfg.probe_strategy.unwrap_or_default();
+        move || fetch_head::probe_with_strategy(&url, &headers, &curl, strategy)
     })
     .await
-    .context("probe task join")?
-    .context("probe failed")?;
+    .context("probe task join")?;
+    let mut head = match probe {
+        Ok(head) => head,
+        Err(e) => {
+            let e = e.context("probe failed");
+            return Err(super::common::probe_failed(db, job_id, &url, cfg, host_policy, e).await);
+        }
+    };
+    super::common::fill_missing_length(cfg, job_id, &url, &headers, &mut head).await;
 
     host_policy
         .record_head_result(&url, &head)
         .context("update host policy from HEAD")?;
+    host_policy.record_connect_success(&url)?;
 
-    let validation = safe_resume::validate_for_resume(&job, &head);
-    if let Err(ref e) = validation {
-        if !force_restart {
-            return Err(anyhow::anyhow!("{}", e));
-        }
-        tracing::info!("force-restart: discarding progress and re-downloading (remote changed)");
+    if let Err(e) = super::common::check_expected_size(&job, &head) {
+        super::common::record_failure(db, job_id, &e).await;
+        return Err(e);
     }
 
+    let validation_failed = match safe_resume::validate_for_resume(&job, &head) {
+        Ok(()) => false,
+        Err(e) if !force_restart => return Err(e.into()),
+        Err(_) => {
+            tracing::info!(
+                "force-restart: discarding progress and re-downloading (remote changed)"
+            );
+            true
+        }
+    };
+
     let (final_name, temp_name_str, needs_metadata) = super::common::resolve_filenames(
         db,
         job_id,
         &job,
         &head,
         force_restart,
-        validation.is_err(),
+        validation_failed,
         download_dir,
     )
     .await?;
+    let temp_name_str = super::common::staged_temp_name(cfg, job_id, temp_name_str);
 
-    let segmentable = head.accept_ranges && head.content_length.is_some();
+    let segmentable = super::common::is_segmentable(&head, cfg)
+        && (!needs_metadata || super::common::confirm_ranges(cfg, job_id, &url, &headers).await);
     if !segmentable {
-        return super::fallback::run_single_stream(
+        let abort = job_control.as_ref().map(|c| c.register(job_id));
+        let result = super::fallback::run_single_stream(
             db,
             job_id,
             &mut job,
@@ -91,14 +117,20 @@ pub async fn run_one_job(
             &final_name,
             &temp_name_str,
             needs_metadata,
+            abort,
         )
         .await;
+        if let Some(ref c) = job_control {
+            c.unregister(job_id);
+        }
+        return result;
     }
 
     let total_size = head
         .content_length
         .ok_or_else(|| anyhow::anyhow!("server did not send Content-Length"))?;
-    let segment_count = choose::choose_segment_count(total_size, cfg, &url, host_policy);
+    let segment_count =
+        choose::choose_segment_count(total_size, &job.settings, cfg, &url, host_policy);
 
     if needs_metadata {
         let bitmap = segmenter::SegmentBitmap::new(segment_count);
@@ -120,14 +152,21 @@ pub async fn run_one_job(
     let segments = segmenter::plan_segments(total_size_u, segment_count_u);
     let mut bitmap = segmenter::SegmentBitmap::from_bytes(&job.completed_bitmap, segment_count_u);
 
-    let (temp_path, final_path) = super::common::paths_and_overwrite_check(
-        &job,
+    let Some((temp_path, final_path)) = super::common::resolve_paths(
+        db,
+        &mut job,
         &final_name,
         &temp_name_str,
         download_dir,
         overwrite,
-    )?;
+        cfg,
+    )
+    .await?
+    else {
+        return Ok(());
+    };
 
+    super::common::adopt_server_digest(&mut job, &head);
     db.set_state(job_id, JobState::Running).await?;
 
     let abort = job_control.as_ref().map(|c| c.register(job_id));
@@ -158,7 +197,7 @@ pub async fn run_one_job(
 
     if let Err(ref e) = &run_result {
         if e.downcast_ref::<crate::control::JobAborted>().is_none() {
-            let _ = db.set_state(job_id, JobState::Error).await;
+            super::common::record_failure(db, job_id, e).await;
         }
     }
     run_result
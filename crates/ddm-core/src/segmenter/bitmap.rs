@@ -43,6 +43,13 @@ impl SegmentBitmap {
         self.bytes[byte_idx] |= 1 << bit;
     }
 
+    /// Mark segment at `index` as not completed (it will be downloaded again).
+    pub fn clear(&mut self, index: usize) {
+        if let Some(b) = self.bytes.get_mut(index / 8) {
+            *b &= !(1 << (index % 8));
+        }
+    }
+
     /// True if segment at `index` is marked completed.
     pub fn is_completed(&self, index: usize) -> bool {
         let byte_idx = index / 8;
@@ -77,6 +84,13 @@ impl SegmentBitmap {
         let needed_bytes = (segment_count + 7) / 8;
         self.bytes.len() >= needed_bytes
     }
+
+    /// Indices in [0, segment_count) not yet marked completed, ascending.
+    pub fn incomplete_indices(&self, segment_count: usize) -> Vec<usize> {
+        (0..segment_count)
+            .filter(|&i| !self.is_completed(i))
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -91,6 +105,9 @@ mod tests {
         b.set_completed(3);
         b.set_completed(9);
         assert!(b.is_completed(0));
+        b.clear(3);
+        assert!(!b.is_completed(3) && b.is_completed(9));
+        b.set_completed(3);
         assert!(!b.is_completed(1));
         assert!(b.is_completed(3));
         assert!(b.is_completed(9));
@@ -123,6 +140,16 @@ mod tests {
         assert_eq!(out[0], 0xFF);
     }
 
+    #[test]
+    fn bitmap_incomplete_indices() {
+        let mut b = SegmentBitmap::new(10);
+        for i in [0, 1, 4, 9] {
+            b.set_completed(i);
+        }
+        assert_eq!(b.incomplete_indices(10), vec![2, 3, 5, 6, 7, 8]);
+        assert!(SegmentBitmap::new(0).incomplete_indices(0).is_empty());
+    }
+
     #[test]
     fn bitmap_from_bytes_short() {
         let bytes = vec![0xFF];
@@ -1,10 +1,58 @@
 //! Range math and segment planning.
 //!
-//! Splits a download into N segments, computes HTTP Range header bounds,
+//! Splits a download into N segments (or into pieces near a target size),
+//! computes HTTP Range header bounds,
 //! and provides a completion bitmap for resume (serialized to DB BLOB).
 
 mod bitmap;
 mod range;
 
 pub use bitmap::SegmentBitmap;
-pub use range::{plan_segments, Segment};
+pub use range::{
+    plan_segments, plan_segments_by_size, resume_segments, segment_count_for_size, Segment,
+};
+
+/// How far past its lowest unfinished segment a `Sequential` job may start segments.
+pub const SEQUENTIAL_LOOKAHEAD: usize = 4;
+
+/// Order in which a job's segments are fetched (`ddm add --order`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentOrder {
+    /// Any incomplete segment may run as soon as a connection is free.
+    #[default]
+    Parallel,
+    /// Segments run in index order, starting less than [`SEQUENTIAL_LOOKAHEAD`] past the
+    /// lowest unfinished one, so the file fills from the front.
+    Sequential,
+}
+
+impl SegmentOrder {
+    /// Parses `parallel` or `sequential` (case-insensitive).
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "parallel" => Some(Self::Parallel),
+            "sequential" => Some(Self::Sequential),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Parallel => "parallel",
+            Self::Sequential => "sequential",
+        }
+    }
+
+    pub fn is_parallel(&self) -> bool {
+        *self == Self::Parallel
+    }
+
+    /// Lookahead window for the download loops; None = unlimited.
+    pub fn lookahead(self) -> Option<usize> {
+        match self {
+            Self::Parallel => None,
+            Self::Sequential => Some(SEQUENTIAL_LOOKAHEAD),
+        }
+    }
+}
@@ -51,10 +51,71 @@ pub fn plan_segments(total_size: u64, segment_count: usize) -> Vec<Segment> {
     out
 }
 
+/// Segment count that makes each piece close to `target_segment_bytes`, capped by
+/// `max_segments` (at least 1). A zero target is treated as 1 byte.
+pub fn segment_count_for_size(
+    total_size: u64,
+    target_segment_bytes: u64,
+    max_segments: usize,
+) -> usize {
+    let wanted = total_size.div_ceil(target_segment_bytes.max(1)).max(1);
+    wanted.min(max_segments.max(1) as u64) as usize
+}
+
+/// Applies persisted per-segment progress (bytes already written from each segment's
+/// start) so partially downloaded segments resume mid-way. Offsets of 0 or at/after the
+/// segment end leave the segment unchanged.
+pub fn resume_segments(segments: &[Segment], progress: &[u64]) -> Vec<Segment> {
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, s)| match progress.get(i) {
+            Some(&p) if p > 0 && p < s.len() => Segment {
+                start: s.start + p,
+                end: s.end,
+            },
+            _ => *s,
+        })
+        .collect()
+}
+
+/// Builds a segment plan whose pieces are near `target_segment_bytes` each, capped by
+/// `max_segments`. Same layout as `plan_segments` with the chosen count, so it can be
+/// reproduced on resume from the stored segment count.
+pub fn plan_segments_by_size(
+    total_size: u64,
+    target_segment_bytes: u64,
+    max_segments: usize,
+) -> Vec<Segment> {
+    plan_segments(
+        total_size,
+        segment_count_for_size(total_size, target_segment_bytes, max_segments),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn resume_segments_skips_persisted_prefix() {
+        let plan = plan_segments(300, 3);
+        let resumed = resume_segments(&plan, &[50, 0, 100]);
+        assert_eq!(
+            resumed[0],
+            Segment {
+                start: 50,
+                end: 100
+            }
+        );
+        assert_eq!(resumed[1], plan[1]);
+        assert_eq!(
+            resumed[2], plan[2],
+            "full offset leaves segment to the bitmap"
+        );
+        assert_eq!(resume_segments(&plan, &[]), plan);
+    }
+
     #[test]
     fn plan_segments_even() {
         let segs = plan_segments(1000, 4);
@@ -98,6 +159,27 @@ mod tests {
         assert!(plan_segments(100, 0).is_empty());
     }
 
+    #[test]
+    fn plan_segments_by_size_targets_piece_size() {
+        const MIB: u64 = 1024 * 1024;
+        let segs = plan_segments_by_size(256 * MIB, 64 * MIB, 16);
+        assert_eq!(segs.len(), 4);
+        assert!(segs.iter().all(|s| s.len() == 64 * MIB));
+        // Partial last piece rounds up to one more segment.
+        assert_eq!(segment_count_for_size(65 * MIB, 64 * MIB, 16), 2);
+        // Small files still get one segment.
+        assert_eq!(segment_count_for_size(10, 64 * MIB, 16), 1);
+    }
+
+    #[test]
+    fn plan_segments_by_size_respects_max_segments() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        assert_eq!(segment_count_for_size(20 * GIB, 64 * 1024 * 1024, 32), 32);
+        assert_eq!(segment_count_for_size(100, 0, 8), 8);
+        assert_eq!(segment_count_for_size(100, 10, 0), 1);
+        assert!(plan_segments_by_size(0, 10, 4).is_empty());
+    }
+
     #[test]
     fn segment_range_header() {
         let s = Segment { start: 0, end: 99 };
@@ -5,6 +5,7 @@ use std::fs::File;
 use std::path::Path;
 
 use super::writer::StorageWriter;
+use crate::config::Preallocation;
 #[cfg(unix)]
 use std::os::unix::io::AsRawFd;
 
@@ -49,6 +50,19 @@ impl StorageWriterBuilder {
         Ok(())
     }
 
+    /// Preallocate `size` bytes as `mode` says: `Full` is [`Self::preallocate`], `Sparse` only
+    /// sets the length, `None` leaves the file empty.
+    pub fn preallocate_with(&mut self, mode: Preallocation, size: u64) -> Result<()> {
+        match mode {
+            Preallocation::Full => self.preallocate(size),
+            Preallocation::Sparse => self
+                .file
+                .set_len(size)
+                .context("failed to set temp file length"),
+            Preallocation::None => Ok(()),
+        }
+    }
+
     /// Finish building and return a writer that can be shared for concurrent writes.
     pub fn build(self) -> StorageWriter {
         StorageWriter::from_file_and_path(self.file, self.temp_path)
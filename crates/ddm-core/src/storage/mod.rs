@@ -2,7 +2,7 @@
 //!
 //! Preallocates temp files (fallocate on Linux when available, else set_len),
 //! supports concurrent offset writes (pwrite), fsync policy, and atomic
-//! finalize (rename from `.part` to final name).
+//! finalize (rename from `.part` to final name, via a copy across filesystems).
 
 mod builder;
 mod writer;
@@ -10,6 +10,9 @@ mod writer;
 pub use builder::StorageWriterBuilder;
 pub use writer::StorageWriter;
 
+#[cfg(unix)]
+use anyhow::Context;
+
 /// Temporary file suffix used before atomic rename.
 pub const TEMP_SUFFIX: &str = ".part";
 
@@ -20,6 +23,49 @@ pub fn temp_path(final_path: &std::path::Path) -> std::path::PathBuf {
     std::path::PathBuf::from(o)
 }
 
+/// Bytes available to unprivileged users on the filesystem containing `path` (statvfs).
+#[cfg(unix)]
+pub fn available_space(path: &std::path::Path) -> anyhow::Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .with_context(|| format!("path contains NUL: {}", path.display()))?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("statvfs failed: {}", path.display()));
+    }
+    Ok((st.f_bavail as u64).saturating_mul(st.f_frsize as u64))
+}
+
+/// Fails fast when the filesystem holding `temp_path` cannot fit the rest of a
+/// `total_size`-byte download. Blocks already allocated to an existing `.part` count
+/// as reserved, so resuming a preallocated file needs no extra space.
+#[cfg(unix)]
+pub fn ensure_space_for(temp_path: &std::path::Path, total_size: u64) -> anyhow::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let allocated = std::fs::metadata(temp_path)
+        .map(|m| m.blocks().saturating_mul(512))
+        .unwrap_or(0);
+    let need = total_size.saturating_sub(allocated);
+    if need == 0 {
+        return Ok(());
+    }
+    let dir = match temp_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => std::path::Path::new("."),
+    };
+    let have = available_space(dir)?;
+    if have < need {
+        anyhow::bail!(
+            "insufficient disk space in {}: need {} bytes, have {} bytes",
+            dir.display(),
+            need,
+            have
+        );
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,6 +80,17 @@ mod tests {
         assert_eq!(p2.to_string_lossy(), "/tmp/archive.zip.part");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn space_preflight_rejects_oversized_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let tp = dir.path().join("big.part");
+        assert!(available_space(dir.path()).unwrap() > 0);
+        ensure_space_for(&tp, 1024).unwrap();
+        let err = ensure_space_for(&tp, u64::MAX).unwrap_err();
+        assert!(err.to_string().contains("insufficient disk space"));
+    }
+
     #[test]
     fn create_preallocate_write_finalize() {
         let dir = tempfile::tempdir().unwrap();
@@ -60,6 +117,54 @@ mod tests {
         assert_eq!(&buf[95..97], b"xy");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn preallocation_modes_size_the_temp_file() {
+        use crate::config::Preallocation;
+        use std::os::unix::fs::MetadataExt;
+        let dir = tempfile::tempdir().unwrap();
+        let size = 4 * 1024 * 1024;
+
+        let sparse = dir.path().join("sparse.part");
+        let mut builder = StorageWriterBuilder::create(&sparse).unwrap();
+        builder
+            .preallocate_with(Preallocation::Sparse, size)
+            .unwrap();
+        let meta = std::fs::metadata(&sparse).unwrap();
+        assert_eq!(meta.len(), size);
+        assert!(
+            meta.blocks() * 512 < size,
+            "sparse file has no blocks reserved"
+        );
+
+        let none = dir.path().join("none.part");
+        let mut builder = StorageWriterBuilder::create(&none).unwrap();
+        builder.preallocate_with(Preallocation::None, size).unwrap();
+        let writer = builder.build();
+        writer.write_at(10, b"x").unwrap();
+        assert_eq!(std::fs::metadata(&none).unwrap().len(), 11);
+    }
+
+    #[test]
+    fn move_by_copy_replaces_destination_and_removes_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("out.bin.part");
+        let dst = dir.path().join("out.bin");
+        std::fs::write(&src, b"new contents").unwrap();
+        std::fs::write(&dst, b"old").unwrap();
+
+        writer::move_by_copy(&src, &dst).unwrap();
+
+        assert!(!src.exists());
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new contents");
+        assert!(!dir.path().join("out.bin.ddm-copy").exists());
+
+        let err = writer::move_by_copy(&src, &dst).unwrap_err();
+        assert!(err.to_string().contains("failed to open"));
+        assert_eq!(std::fs::read(&dst).unwrap(), b"new contents");
+        assert!(!dir.path().join("out.bin.ddm-copy").exists());
+    }
+
     #[test]
     fn write_at_concurrent_style() {
         let dir = tempfile::tempdir().unwrap();
@@ -74,19 +74,64 @@ impl StorageWriter {
         &self.temp_path
     }
 
-    /// Atomically rename the temp file to the final path. Consumes the writer and closes the file.
-    /// Call `sync` before this if you need durability. Fails if `final_path` is on a different filesystem.
+    /// Move the temp file to the final path. Consumes the writer and closes the file.
+    /// Call `sync` before this if you need durability. The move is an atomic rename; when
+    /// `final_path` is on a different filesystem (`EXDEV`), see [`move_by_copy`].
     pub fn finalize(self, final_path: &Path) -> Result<()> {
         let temp_path = self.temp_path.clone();
         drop(self.file);
 
-        std::fs::rename(&temp_path, final_path).with_context(|| {
+        match std::fs::rename(&temp_path, final_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {
+                tracing::debug!(
+                    from = %temp_path.display(),
+                    to = %final_path.display(),
+                    "rename crosses filesystems; copying"
+                );
+                move_by_copy(&temp_path, final_path)
+            }
+            Err(e) => Err(e).with_context(|| {
+                format!(
+                    "failed to rename {} to {}",
+                    temp_path.display(),
+                    final_path.display()
+                )
+            }),
+        }
+    }
+}
+
+/// Move `src` to `dst` across filesystems: stream it into a sibling of `dst` (`<dst>.ddm-copy`),
+/// fsync, atomically rename that over `dst`, then remove `src`. `dst` never holds a partial copy;
+/// on failure the staging file is removed and `src` is left in place.
+pub(crate) fn move_by_copy(src: &Path, dst: &Path) -> Result<()> {
+    let mut staging = dst.as_os_str().to_owned();
+    staging.push(".ddm-copy");
+    let staging = std::path::PathBuf::from(staging);
+
+    let copy = || -> Result<()> {
+        let mut from =
+            File::open(src).with_context(|| format!("failed to open {}", src.display()))?;
+        let mut to = File::create(&staging)
+            .with_context(|| format!("failed to create {}", staging.display()))?;
+        std::io::copy(&mut from, &mut to).with_context(|| {
+            format!("failed to copy {} to {}", src.display(), staging.display())
+        })?;
+        to.sync_all()
+            .with_context(|| format!("failed to sync {}", staging.display()))?;
+        std::fs::rename(&staging, dst).with_context(|| {
             format!(
                 "failed to rename {} to {}",
-                temp_path.display(),
-                final_path.display()
+                staging.display(),
+                dst.display()
             )
-        })?;
-        Ok(())
+        })
+    };
+    if let Err(e) = copy() {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
     }
+    std::fs::remove_file(src).with_context(|| format!("failed to remove {}", src.display()))?;
+    Ok(())
 }
@@ -5,8 +5,10 @@
 /// Supports:
 /// - `filename="value"` (quoted; strips quotes and unescapes)
 /// - `filename=value` (token)
-/// - `filename*=UTF-8''percent-encoded` (RFC 5987; decoded)
-/// If both `filename` and `filename*` exist, `filename*` takes precedence.
+/// - `filename*=charset'lang'percent-encoded` (RFC 5987; UTF-8 or ISO-8859-1,
+///   language tag optional and ignored)
+///
+/// If both `filename` and `filename*` exist, `filename*` takes precedence (RFC 6266).
 pub fn parse_content_disposition_filename(header_value: &str) -> Option<String> {
     let value = header_value.trim();
     let mut filename_from_token: Option<String> = None;
@@ -18,15 +20,9 @@ pub fn parse_content_disposition_filename(header_value: &str) -> Option<String>
             let v = v.trim();
 
             if name == "filename*" {
-                if let Some(rest) = v
-                    .strip_prefix("utf-8''")
-                    .or_else(|| v.strip_prefix("UTF-8''"))
-                {
-                    if let Ok(decoded) = percent_decode(rest) {
-                        let decoded = decode_quoted_filename(&decoded);
-                        if !decoded.is_empty() {
-                            return Some(decoded);
-                        }
+                if let Some(decoded) = decode_ext_value(v) {
+                    if !decoded.is_empty() {
+                        return Some(decoded);
                     }
                 }
             }
@@ -67,8 +63,23 @@ pub(super) fn decode_quoted_filename(s: &str) -> String {
     out
 }
 
+/// Decodes an RFC 5987 `ext-value` (`charset'[language]'value-chars`).
+///
+/// Returns `None` for a malformed value or an unsupported charset.
+fn decode_ext_value(v: &str) -> Option<String> {
+    let v = v.trim_matches('"');
+    let (charset, rest) = v.split_once('\'')?;
+    let (_language, encoded) = rest.split_once('\'')?;
+    let bytes = percent_decode(encoded);
+    match ascii_lowercase(charset).as_str() {
+        "utf-8" => Some(String::from_utf8_lossy(&bytes).into_owned()),
+        "iso-8859-1" => Some(bytes.iter().map(|&b| b as char).collect()),
+        _ => None,
+    }
+}
+
 /// Simple percent-decode for filename* value (RFC 5987).
-pub(super) fn percent_decode(input: &str) -> Result<String, std::str::Utf8Error> {
+pub(super) fn percent_decode(input: &str) -> Vec<u8> {
     let mut out = Vec::new();
     let mut bytes = input.as_bytes().iter().cloned();
     while let Some(b) = bytes.next() {
@@ -91,7 +102,7 @@ pub(super) fn percent_decode(input: &str) -> Result<String, std::str::Utf8Error>
             out.push(b);
         }
     }
-    Ok(String::from_utf8_lossy(&out).into_owned())
+    out
 }
 
 fn hex_digit(b: u8) -> Option<u8> {
@@ -144,4 +155,34 @@ mod tests {
         );
         assert_eq!(r.as_deref(), Some("real name.dat"));
     }
+
+    #[test]
+    fn parse_filename_star_lowercase_charset_with_space() {
+        let r = parse_content_disposition_filename("attachment; filename*=utf-8''debian%2012.iso");
+        assert_eq!(r.as_deref(), Some("debian 12.iso"));
+    }
+
+    #[test]
+    fn parse_filename_star_with_language_tag() {
+        let r = parse_content_disposition_filename(
+            "attachment; filename*=UTF-8'de'%C3%BCbersicht%20%E2%82%AC.pdf",
+        );
+        assert_eq!(r.as_deref(), Some("übersicht €.pdf"));
+    }
+
+    #[test]
+    fn parse_filename_star_iso_8859_1() {
+        let r = parse_content_disposition_filename(
+            "attachment; filename*=iso-8859-1'en'%A3%20rates.txt",
+        );
+        assert_eq!(r.as_deref(), Some("£ rates.txt"));
+    }
+
+    #[test]
+    fn parse_filename_star_unknown_charset_falls_back() {
+        let r = parse_content_disposition_filename(
+            "attachment; filename=\"plain.bin\"; filename*=koi8-r''%C1",
+        );
+        assert_eq!(r.as_deref(), Some("plain.bin"));
+    }
 }
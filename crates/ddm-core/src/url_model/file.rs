@@ -0,0 +1,41 @@
+//! `file://` URL helpers (local sources copied through the same segmented path).
+
+use std::path::PathBuf;
+
+/// True if `url` uses the `file` scheme (case-insensitive).
+pub fn is_file_url(url: &str) -> bool {
+    url.get(..7)
+        .is_some_and(|s| s.eq_ignore_ascii_case("file://"))
+}
+
+/// Local path of a `file://` URL (percent-decoded). None for other schemes or
+/// URLs naming a remote host.
+pub fn file_url_path(url: &str) -> Option<PathBuf> {
+    let parsed = url::Url::parse(url).ok()?;
+    if parsed.scheme() != "file" {
+        return None;
+    }
+    parsed.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_file_urls() {
+        assert!(is_file_url("file:///tmp/a.iso"));
+        assert!(is_file_url("FILE:///tmp/a.iso"));
+        assert!(!is_file_url("https://example.com/a.iso"));
+        assert!(!is_file_url("file"));
+    }
+
+    #[test]
+    fn decodes_path() {
+        assert_eq!(
+            file_url_path("file:///mnt/nfs/my%20file.iso"),
+            Some(PathBuf::from("/mnt/nfs/my file.iso"))
+        );
+        assert_eq!(file_url_path("https://example.com/a"), None);
+    }
+}
@@ -0,0 +1,167 @@
+//! File links on an Apache/nginx autoindex page (`ddm add-index`).
+
+use anyhow::{Context, Result};
+
+use super::content_disposition::percent_decode;
+
+/// Absolute URLs of the files listed on the autoindex page at `dir_url`, in page order.
+/// `dir_url` is treated as a directory (a missing trailing `/` is added). Only links to
+/// files directly in that directory count: subdirectories, the parent link, column-sort
+/// links (`?C=N;O=D`) and other sites are skipped, as are duplicates. With `pattern`, the
+/// decoded filename must match the glob (see [`glob_match`]).
+pub fn index_file_links(html: &str, dir_url: &str, pattern: Option<&str>) -> Result<Vec<String>> {
+    let mut base = url::Url::parse(dir_url).with_context(|| format!("invalid URL {dir_url}"))?;
+    if !base.path().ends_with('/') {
+        let path = format!("{}/", base.path());
+        base.set_path(&path);
+    }
+    base.set_query(None);
+    base.set_fragment(None);
+    let prefix = base.as_str();
+
+    let mut links: Vec<String> = Vec::new();
+    for href in hrefs(html) {
+        let Ok(mut link) = base.join(&href) else {
+            continue;
+        };
+        link.set_fragment(None);
+        if link.query().is_some() {
+            continue;
+        }
+        let Some(name) = link.as_str().strip_prefix(prefix) else {
+            continue;
+        };
+        if name.is_empty() || name.contains('/') {
+            continue;
+        }
+        let decoded = String::from_utf8_lossy(&percent_decode(name)).into_owned();
+        if pattern.is_some_and(|p| !glob_match(p, &decoded)) {
+            continue;
+        }
+        let link = link.to_string();
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    Ok(links)
+}
+
+/// Shell-style glob: `*` matches any run of characters, `?` exactly one; everything else
+/// matches itself (case-sensitive).
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // Position of the last `*` and the name index it is currently matched up to.
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+/// `href` attribute values in `html` (quoted or bare), with `&amp;` unescaped.
+fn hrefs(html: &str) -> Vec<String> {
+    let lower = html.to_ascii_lowercase();
+    let mut out = Vec::new();
+    let mut from = 0;
+    while let Some(at) = lower[from..].find("href") {
+        let mut i = from + at + "href".len();
+        from = i;
+        let rest = &html[i..];
+        let trimmed = rest.trim_start();
+        let Some(after_eq) = trimmed.strip_prefix('=') else {
+            continue;
+        };
+        i += rest.len() - after_eq.len();
+        let value = after_eq.trim_start();
+        i += after_eq.len() - value.len();
+        let (raw, len) = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => match value[1..].find(q) {
+                Some(end) => (&value[1..1 + end], end + 2),
+                None => continue,
+            },
+            _ => {
+                let end = value
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(value.len());
+                (&value[..end], end)
+            }
+        };
+        from = i + len;
+        if !raw.is_empty() {
+            out.push(raw.replace("&amp;", "&"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APACHE: &str = r#"<html><head><title>Index of /debian/pool/main/c/curl</title></head>
+<body><h1>Index of /debian/pool/main/c/curl</h1>
+<table>
+<tr><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=M;O=A">Last modified</a></th></tr>
+<tr><td><a href="/debian/pool/main/c/">Parent Directory</a></td></tr>
+<tr><td><a href="curl_7.88.1-10%2Bdeb12u8_amd64.deb">curl_7.88.1-10+deb12u8_amd64.deb</a></td></tr>
+<tr><td><a href="curl_7.88.1-10+deb12u8.dsc">curl_7.88.1-10+deb12u8.dsc</a></td></tr>
+<tr><td><a HREF='libcurl4_7.88.1-10+deb12u8_amd64.deb'>libcurl4</a></td></tr>
+<tr><td><a href=debian/>debian/</a></td></tr>
+<tr><td><a href="https://other.example/x.deb">elsewhere</a></td></tr>
+<tr><td><a href="curl_7.88.1-10+deb12u8.dsc#sig">again</a></td></tr>
+</table></body></html>"#;
+
+    #[test]
+    fn extracts_files_in_the_directory_only() {
+        let base = "https://deb.debian.org/debian/pool/main/c/curl";
+        let links = index_file_links(APACHE, base, None).unwrap();
+        assert_eq!(
+            links,
+            vec![
+                "https://deb.debian.org/debian/pool/main/c/curl/curl_7.88.1-10%2Bdeb12u8_amd64.deb",
+                "https://deb.debian.org/debian/pool/main/c/curl/curl_7.88.1-10+deb12u8.dsc",
+                "https://deb.debian.org/debian/pool/main/c/curl/libcurl4_7.88.1-10+deb12u8_amd64.deb",
+            ]
+        );
+    }
+
+    #[test]
+    fn pattern_matches_the_decoded_filename() {
+        let base = "https://deb.debian.org/debian/pool/main/c/curl/";
+        let links = index_file_links(APACHE, base, Some("curl_*+deb12u8_*.deb")).unwrap();
+        assert_eq!(
+            links,
+            vec![
+                "https://deb.debian.org/debian/pool/main/c/curl/curl_7.88.1-10%2Bdeb12u8_amd64.deb"
+            ]
+        );
+        assert!(index_file_links(APACHE, base, Some("*.iso"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn glob_supports_star_and_question_mark() {
+        assert!(glob_match("*.deb", "curl_amd64.deb"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a?c*", "abcdef"));
+        assert!(glob_match("*_amd64*.deb", "x_amd64_y_amd64.deb"));
+        assert!(!glob_match("*.deb", "curl.dsc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(!glob_match("*.DEB", "curl.deb"));
+    }
+}
@@ -4,10 +4,14 @@
 //! sanitized for Linux filesystems.
 
 mod content_disposition;
+mod file;
+mod index;
 mod path;
 mod sanitize;
 
 pub use content_disposition::parse_content_disposition_filename;
+pub use file::{file_url_path, is_file_url};
+pub use index::{glob_match, index_file_links};
 pub use path::filename_from_url_path;
 pub use sanitize::sanitize_filename_for_linux;
 
@@ -44,6 +48,26 @@ pub fn derive_filename(url: &str, content_disposition: Option<&str>) -> String {
     }
 }
 
+/// A user-chosen filename (`ddm add --output`) sanitized for Linux, or None when nothing
+/// usable is left (empty, `.` or `..`).
+pub fn forced_filename(name: &str) -> Option<String> {
+    let sanitized = sanitize_filename_for_linux(name);
+    (!sanitized.is_empty() && sanitized != "." && sanitized != "..").then_some(sanitized)
+}
+
+/// True if `url` parses as an absolute `http`, `https` (with a host) or `file` URL — the
+/// schemes a job can download from.
+pub fn is_download_url(url: &str) -> bool {
+    match url::Url::parse(url) {
+        Ok(u) => match u.scheme() {
+            "http" | "https" => u.host_str().is_some_and(|h| !h.is_empty()),
+            "file" => true,
+            _ => false,
+        },
+        Err(_) => false,
+    }
+}
+
 /// Returns a filename that does not collide with any in `existing`.
 /// If `candidate` is not in `existing`, returns it as-is; otherwise returns
 /// `stem (1).ext`, `stem (2).ext`, etc. (or `stem (1)` when there is no extension).
@@ -75,6 +99,20 @@ pub fn unique_filename_among(candidate: &str, existing: &[String]) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn forced_filename_is_sanitized_or_rejected() {
+        assert_eq!(
+            forced_filename("debian-netinst.iso").as_deref(),
+            Some("debian-netinst.iso")
+        );
+        assert_eq!(
+            forced_filename("../my iso.iso").as_deref(),
+            Some("my_iso.iso")
+        );
+        assert!(forced_filename("..").is_none());
+        assert!(forced_filename("  ").is_none());
+    }
+
     #[test]
     fn derive_filename_from_url_path() {
         assert_eq!(
@@ -116,6 +154,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn derive_filename_extended_content_disposition_is_sanitized() {
+        assert_eq!(
+            derive_filename(
+                "https://cdn.example.com/dl?id=1",
+                Some("attachment; filename=\"fallback.iso\"; filename*=UTF-8''debian%2012%2Fnetinst.iso")
+            ),
+            "debian_12_netinst.iso"
+        );
+    }
+
     #[test]
     fn derive_filename_empty_url_path_fallback() {
         assert_eq!(
@@ -137,6 +186,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn is_download_url_accepts_supported_schemes() {
+        assert!(is_download_url("https://deb.debian.org/debian/a.iso"));
+        assert!(is_download_url("http://mirror.example:8080/a.iso"));
+        assert!(is_download_url("file:///tmp/a.iso"));
+        assert!(!is_download_url("ftp://example.com/a.iso"));
+        assert!(!is_download_url("example.com/a.iso"));
+        assert!(!is_download_url("https://"));
+    }
+
     #[test]
     fn unique_filename_among_no_collision() {
         assert_eq!(unique_filename_among("file.iso", &[]), "file.iso");
@@ -2,12 +2,38 @@
 //!
 //! Serves a single static body. Responds to HEAD with Content-Length and
 //! Accept-Ranges: bytes; responds to GET with Range with 206 Partial Content.
+//! Connections are kept alive, and [`ServerStats`] counts them and records
+//! every Range GET.
 
 use std::io::{Read, Write};
 use std::net::TcpListener;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
 
+/// Connection counters for a running server.
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    /// Connections accepted so far.
+    pub connections: AtomicUsize,
+    /// Connections currently open.
+    pub active: AtomicUsize,
+    /// Highest number of connections open at once.
+    pub max_active: AtomicUsize,
+    /// `(start, end_inclusive)` of each Range GET, in arrival order.
+    pub ranges: Mutex<Vec<(u64, u64)>>,
+}
+
+impl ServerStats {
+    /// Range GETs received so far, sorted by start offset.
+    #[allow(dead_code)]
+    pub fn range_requests(&self) -> Vec<(u64, u64)> {
+        let mut ranges = self.ranges.lock().unwrap().clone();
+        ranges.sort_unstable();
+        ranges
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct RangeServerOptions {
     /// If false, HEAD returns 405 (simulates servers that block HEAD).
@@ -16,6 +42,31 @@ pub struct RangeServerOptions {
     pub support_ranges: bool,
     /// If false, omit `Accept-Ranges: bytes` header even if ranges work.
     pub advertise_ranges: bool,
+    /// If true (and ranges are unsupported), send an explicit `Accept-Ranges: none`.
+    pub refuse_ranges: bool,
+    /// If true, send `Accept-Ranges: bytes` even when GET ignores Range (a lying server).
+    pub claim_ranges: bool,
+    /// If set, HEAD and GET responses carry this `ETag` (quotes included).
+    pub etag: Option<&'static str>,
+    /// If set, 206 responses report this as the `Content-Range` total (a different file version).
+    pub range_total: Option<u64>,
+    /// If set, the first `truncate_requests` Range GETs send only this many body bytes
+    /// and then close the connection (a transfer dying mid-segment).
+    pub truncate_after_bytes: Option<usize>,
+    /// How many Range GETs `truncate_after_bytes` applies to.
+    pub truncate_requests: usize,
+    /// If set, the first `stall_requests` Range GETs send this many body bytes and then
+    /// trickle the rest at about 256 bytes/sec (a stalled but live connection).
+    pub stall_after_bytes: Option<usize>,
+    /// How many Range GETs `stall_after_bytes` applies to.
+    pub stall_requests: usize,
+    /// If set, requests whose `Cookie` header lacks this `name=value` pair get 403.
+    pub require_cookie: Option<&'static str>,
+    /// If true, HEAD omits `Content-Length` and 206 responses report the total as `*`
+    /// (a streaming mirror that never states the size up front).
+    pub unknown_total: bool,
+    /// If set, HEAD and GET responses carry this `Digest` header value (e.g. `sha-256=...`).
+    pub digest: Option<&'static str>,
 }
 
 impl Default for RangeServerOptions {
@@ -24,6 +75,17 @@ impl Default for RangeServerOptions {
             head_allowed: true,
             support_ranges: true,
             advertise_ranges: true,
+            refuse_ranges: false,
+            claim_ranges: false,
+            etag: None,
+            range_total: None,
+            truncate_after_bytes: None,
+            truncate_requests: 0,
+            stall_after_bytes: None,
+            stall_requests: 0,
+            require_cookie: None,
+            unknown_total: false,
+            digest: None,
         }
     }
 }
@@ -36,52 +98,100 @@ pub fn start(body: Vec<u8>) -> String {
 
 /// Like `start` but allows customizing server behavior (HEAD blocked, ranges missing, etc.).
 pub fn start_with_options(body: Vec<u8>, opts: RangeServerOptions) -> String {
+    start_with_stats(body, opts).0
+}
+
+/// Like `start_with_options` but also returns the server's connection counters and range log.
+#[allow(dead_code)]
+pub fn start_with_stats(body: Vec<u8>, opts: RangeServerOptions) -> (String, Arc<ServerStats>) {
     let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
     let port = listener.local_addr().unwrap().port();
     let body = Arc::new(body);
+    let stats = Arc::new(ServerStats::default());
+    let server_stats = Arc::clone(&stats);
     thread::spawn(move || {
         for stream in listener.incoming().flatten() {
             let body = Arc::clone(&body);
-            thread::spawn(move || handle(stream, &body, opts));
+            let stats = Arc::clone(&server_stats);
+            stats.connections.fetch_add(1, Ordering::SeqCst);
+            let open = stats.active.fetch_add(1, Ordering::SeqCst) + 1;
+            stats.max_active.fetch_max(open, Ordering::SeqCst);
+            thread::spawn(move || {
+                serve_connection(stream, &body, opts, &stats);
+                stats.active.fetch_sub(1, Ordering::SeqCst);
+            });
         }
     });
-    format!("http://127.0.0.1:{}/", port)
+    (format!("http://127.0.0.1:{}/", port), stats)
 }
 
-fn handle(mut stream: std::net::TcpStream, body: &[u8], opts: RangeServerOptions) {
+/// Serve requests on one connection until the client closes it or goes idle.
+fn serve_connection(
+    mut stream: std::net::TcpStream,
+    body: &[u8],
+    opts: RangeServerOptions,
+    stats: &ServerStats,
+) {
     let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(2)));
     let _ = stream.set_write_timeout(Some(std::time::Duration::from_secs(2)));
+    while handle(&mut stream, body, opts, stats) {}
+}
+
+/// Handle one request; returns true if the connection can serve another.
+fn handle(
+    stream: &mut std::net::TcpStream,
+    body: &[u8],
+    opts: RangeServerOptions,
+    stats: &ServerStats,
+) -> bool {
     let mut buf = [0u8; 8192];
     let n = match stream.read(&mut buf) {
-        Ok(0) => return,
+        Ok(0) => return false,
         Ok(n) => n,
-        Err(_) => return,
+        Err(_) => return false,
     };
     let request = match std::str::from_utf8(&buf[..n]) {
         Ok(s) => s,
-        Err(_) => return,
+        Err(_) => return false,
     };
     let (method, range) = parse_request(request);
+    if opts
+        .require_cookie
+        .is_some_and(|c| !request_has_cookie(request, c))
+    {
+        let _ = stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+        return true;
+    }
     let total = body.len() as u64;
     if method.eq_ignore_ascii_case("HEAD") {
         if !opts.head_allowed {
-            let _ = stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\n\r\n");
-            return;
+            let _ =
+                stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\nContent-Length: 0\r\n\r\n");
+            return true;
         }
-        let accept_ranges = if opts.advertise_ranges && opts.support_ranges {
-            "Accept-Ranges: bytes\r\n"
+        let accept_ranges = accept_ranges_header(opts);
+        let content_length = if opts.unknown_total {
+            String::new()
         } else {
-            ""
+            format!("Content-Length: {}\r\n", total)
         };
         let response = format!(
-            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n{}\
+            "HTTP/1.1 200 OK\r\n{}{}{}{}\
 \r\n",
-            total, accept_ranges
+            content_length,
+            accept_ranges,
+            etag_header(opts),
+            digest_header(opts)
         );
-        let _ = stream.write_all(response.as_bytes());
-        return;
+        return stream.write_all(response.as_bytes()).is_ok();
     }
     if method.eq_ignore_ascii_case("GET") {
+        let mut nth_range = 0;
+        if let Some(r) = range {
+            let mut ranges = stats.ranges.lock().unwrap();
+            ranges.push(r);
+            nth_range = ranges.len();
+        }
         let use_range = opts.support_ranges;
         let (status, range_header, slice) = if use_range {
             if let Some((start, end_incl)) = range {
@@ -99,7 +209,16 @@ fn handle(mut stream: std::net::TcpStream, body: &[u8], opts: RangeServerOptions
                     let slice = body.get(start..end_excl).unwrap_or(&body[0..0]);
                     (
                         "206 Partial Content",
-                        format!("bytes {}-{}/{}", start, end_excl.saturating_sub(1), total),
+                        format!(
+                            "bytes {}-{}/{}",
+                            start,
+                            end_excl.saturating_sub(1),
+                            if opts.unknown_total {
+                                "*".to_string()
+                            } else {
+                                opts.range_total.unwrap_or(total).to_string()
+                            }
+                        ),
                         slice,
                     )
                 }
@@ -117,24 +236,102 @@ fn handle(mut stream: std::net::TcpStream, body: &[u8], opts: RangeServerOptions
                 body,
             )
         };
-        let accept_ranges = if opts.advertise_ranges && opts.support_ranges {
-            "Accept-Ranges: bytes\r\n"
-        } else {
-            ""
-        };
+        let accept_ranges = accept_ranges_header(opts);
         let response = format!(
-            "HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Range: {}\r\n{}\
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Range: {}\r\n{}{}{}\
 \r\n",
             status,
             slice.len(),
             range_header,
-            accept_ranges
+            accept_ranges,
+            etag_header(opts),
+            digest_header(opts)
         );
-        let _ = stream.write_all(response.as_bytes());
-        let _ = stream.write_all(slice);
-        return;
+        if let Some(limit) = opts
+            .truncate_after_bytes
+            .filter(|_| nth_range > 0 && nth_range <= opts.truncate_requests && use_range)
+        {
+            let _ = stream.write_all(response.as_bytes());
+            let _ = stream.write_all(&slice[..limit.min(slice.len())]);
+            return false;
+        }
+        if let Some(limit) = opts
+            .stall_after_bytes
+            .filter(|_| nth_range > 0 && nth_range <= opts.stall_requests && use_range)
+        {
+            let (head, rest) = slice.split_at(limit.min(slice.len()));
+            if stream.write_all(response.as_bytes()).is_err() || stream.write_all(head).is_err() {
+                return false;
+            }
+            for chunk in rest.chunks(64) {
+                thread::sleep(std::time::Duration::from_millis(250));
+                if stream.write_all(chunk).is_err() {
+                    return false;
+                }
+            }
+            return true;
+        }
+        return stream.write_all(response.as_bytes()).is_ok() && stream.write_all(slice).is_ok();
     }
     let _ = stream.write_all(b"HTTP/1.1 405 Method Not Allowed\r\n\r\n");
+    false
+}
+
+fn accept_ranges_header(opts: RangeServerOptions) -> &'static str {
+    if opts.claim_ranges || (opts.advertise_ranges && opts.support_ranges) {
+        "Accept-Ranges: bytes\r\n"
+    } else if opts.refuse_ranges && !opts.support_ranges {
+        "Accept-Ranges: none\r\n"
+    } else {
+        ""
+    }
+}
+
+fn etag_header(opts: RangeServerOptions) -> String {
+    opts.etag
+        .map(|e| format!("ETag: {}\r\n", e))
+        .unwrap_or_default()
+}
+
+fn digest_header(opts: RangeServerOptions) -> String {
+    opts.digest
+        .map(|d| format!("Digest: {}\r\n", d))
+        .unwrap_or_default()
+}
+
+/// True if a `Cookie` header of `request` carries the `name=value` pair `cookie`.
+fn request_has_cookie(request: &str, cookie: &str) -> bool {
+    request
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("cookie"))
+        .any(|(_, value)| value.split(';').any(|pair| pair.trim() == cookie))
+}
+
+/// Starts a server that answers every request with a 302 to `target`. Returns its base URL.
+#[allow(dead_code)]
+pub fn start_redirect(target: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    let response = format!(
+        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+        target
+    );
+    thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let response = response.clone();
+            thread::spawn(move || {
+                let _ = stream.set_read_timeout(Some(std::time::Duration::from_secs(2)));
+                let mut buf = [0u8; 8192];
+                while matches!(stream.read(&mut buf), Ok(n) if n > 0) {
+                    if stream.write_all(response.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    format!("http://127.0.0.1:{}/", port)
 }
 
 /// Returns (method, optional (start, end_inclusive) for Range: bytes=X-Y).
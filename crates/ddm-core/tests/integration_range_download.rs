@@ -5,52 +5,138 @@
 
 mod common;
 
+use std::path::PathBuf;
+use std::sync::Arc;
+
 use ddm_core::config::{DdmConfig, DownloadBackend};
+use ddm_core::control::JobControl;
+use ddm_core::downloader::CurlOptions;
+use ddm_core::fetch_head::{self, RangeSupport};
 use ddm_core::host_policy::HostPolicy;
-use ddm_core::resume_db::{JobSettings, JobState, ResumeDb};
-use ddm_core::scheduler;
-use tempfile::tempdir;
+use ddm_core::resume_db::{
+    ErrorCategory, JobDetails, JobMetadata, JobSettings, JobState, ResumeDb,
+};
+use ddm_core::scheduler::{self, RunOptions};
+use tempfile::{tempdir, TempDir};
+
+/// Default config, except that even the tests' small bodies are downloaded in segments.
+fn segmented() -> DdmConfig {
+    DdmConfig {
+        single_stream_threshold_bytes: Some(0),
+        ..DdmConfig::default()
+    }
+}
+
+/// One job in a fresh database, downloading into its own temp directory.
+struct TestJob {
+    db: ResumeDb,
+    id: i64,
+    download_dir: TempDir,
+    _state_dir: TempDir,
+}
+
+impl TestJob {
+    async fn new(url: &str, settings: JobSettings) -> Self {
+        let download_dir = tempdir().unwrap();
+        let state_dir = tempdir().unwrap();
+        let db = ResumeDb::open_at(&state_dir.path().join("jobs.db"))
+            .await
+            .unwrap();
+        let id = db.add_job(url, &settings).await.unwrap();
+        Self {
+            db,
+            id,
+            download_dir,
+            _state_dir: state_dir,
+        }
+    }
+
+    /// Runs the job once with `cfg` and default run options.
+    async fn run(&self, cfg: &DdmConfig) -> anyhow::Result<()> {
+        self.run_with(cfg, &RunOptions::default(), false, None)
+            .await
+    }
+
+    /// Runs the job once through `scheduler::run_one_job` with a fresh host policy.
+    async fn run_with(
+        &self,
+        cfg: &DdmConfig,
+        run: &RunOptions,
+        overwrite: bool,
+        control: Option<Arc<JobControl>>,
+    ) -> anyhow::Result<()> {
+        let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
+        scheduler::run_one_job(
+            &self.db,
+            self.id,
+            false,
+            overwrite,
+            cfg,
+            run,
+            self.download_dir.path(),
+            &mut host_policy,
+            None,
+            None,
+            control,
+        )
+        .await
+    }
+
+    async fn job(&self) -> JobDetails {
+        self.db.get_job(self.id).await.unwrap().expect("job exists")
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.download_dir.path().join(name)
+    }
+
+    /// Contents of the job's final file.
+    async fn final_bytes(&self) -> Vec<u8> {
+        let job = self.job().await;
+        std::fs::read(self.path(job.final_filename.as_deref().unwrap_or("download.bin"))).unwrap()
+    }
+
+    /// Records a 4-segment plan saved as `out.bin` (etag `v1`, the servers' `etag` option)
+    /// with `completed_bitmap` done, as a previous run would have left it.
+    async fn set_resume_plan(&self, total_size: usize, completed_bitmap: u8) {
+        let meta = JobMetadata {
+            final_filename: Some("out.bin".to_string()),
+            temp_filename: Some("out.bin.part".to_string()),
+            total_size: Some(total_size as i64),
+            etag: Some("v1".to_string()),
+            last_modified: None,
+            segment_count: 4,
+            completed_bitmap: vec![completed_bitmap],
+        };
+        self.db.update_metadata(self.id, &meta).await.unwrap();
+    }
+}
+
+/// Adds a job for `url` with `settings` and runs it once with `cfg`.
+async fn run_job(
+    url: &str,
+    settings: JobSettings,
+    cfg: &DdmConfig,
+) -> (TestJob, anyhow::Result<()>) {
+    let t = TestJob::new(url, settings).await;
+    let result = t.run(cfg).await;
+    (t, result)
+}
 
 #[tokio::test]
 async fn multi_segment_download_completes_and_file_matches() {
     let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
     let url = common::range_server::start(body.clone());
 
-    let download_dir = tempdir().unwrap();
-    let state_dir = tempdir().unwrap();
-    let db_path = state_dir.path().join("jobs.db");
-    let db = ResumeDb::open_at(&db_path).await.unwrap();
-
-    db.add_job(&url, &JobSettings::default()).await.unwrap();
-    let jobs = db.list_jobs().await.unwrap();
-    let job_id = jobs[0].id;
-    db.recover_running_jobs().await.unwrap();
+    let (t, result) = run_job(&url, JobSettings::default(), &segmented()).await;
+    result.expect("run_one_job");
 
-    let cfg = DdmConfig::default();
-    let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
-    scheduler::run_one_job(
-        &db,
-        job_id,
-        false,
-        false,
-        &cfg,
-        download_dir.path(),
-        &mut host_policy,
-        None,
-        None,
-        None,
-    )
-    .await
-    .expect("run_one_job");
-
-    let job = db.get_job(job_id).await.unwrap().expect("job exists");
-    assert_eq!(job.state, JobState::Completed, "job should be completed");
-    let final_name = job.final_filename.as_deref().unwrap_or("download.bin");
-    let final_path = download_dir.path().join(final_name);
-    assert!(final_path.exists(), "final file should exist");
-    let content = std::fs::read(&final_path).unwrap();
-    assert_eq!(content.len(), body.len(), "file size must match");
-    assert_eq!(content, body, "file content must match");
+    assert_eq!(
+        t.job().await.state,
+        JobState::Completed,
+        "job should be completed"
+    );
+    assert_eq!(t.final_bytes().await, body, "file content must match");
 }
 
 #[tokio::test]
@@ -58,46 +144,19 @@ async fn multi_backend_download_completes_and_file_matches() {
     let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
     let url = common::range_server::start(body.clone());
 
-    let download_dir = tempdir().unwrap();
-    let state_dir = tempdir().unwrap();
-    let db_path = state_dir.path().join("jobs.db");
-    let db = ResumeDb::open_at(&db_path).await.unwrap();
-
-    db.add_job(&url, &JobSettings::default()).await.unwrap();
-    let jobs = db.list_jobs().await.unwrap();
-    let job_id = jobs[0].id;
-    db.recover_running_jobs().await.unwrap();
+    let cfg = DdmConfig {
+        download_backend: Some(DownloadBackend::Multi),
+        ..segmented()
+    };
+    let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+    result.expect("run_one_job with multi backend");
 
-    let mut cfg = DdmConfig::default();
-    cfg.download_backend = Some(DownloadBackend::Multi);
-    let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
-    scheduler::run_one_job(
-        &db,
-        job_id,
-        false,
-        false,
-        &cfg,
-        download_dir.path(),
-        &mut host_policy,
-        None,
-        None,
-        None,
-    )
-    .await
-    .expect("run_one_job with multi backend");
-
-    let job = db.get_job(job_id).await.unwrap().expect("job exists");
     assert_eq!(
-        job.state,
+        t.job().await.state,
         JobState::Completed,
         "multi backend should complete"
     );
-    let final_name = job.final_filename.as_deref().unwrap_or("download.bin");
-    let final_path = download_dir.path().join(final_name);
-    assert!(final_path.exists(), "final file should exist");
-    let content = std::fs::read(&final_path).unwrap();
-    assert_eq!(content.len(), body.len(), "file size must match");
-    assert_eq!(content, body, "file content must match");
+    assert_eq!(t.final_bytes().await, body, "file content must match");
 }
 
 #[tokio::test]
@@ -109,87 +168,1179 @@ async fn head_blocked_falls_back_to_range_probe_and_completes() {
             head_allowed: false,
             support_ranges: true,
             advertise_ranges: true,
+            ..Default::default()
         },
     );
 
-    let download_dir = tempdir().unwrap();
-    let state_dir = tempdir().unwrap();
-    let db_path = state_dir.path().join("jobs.db");
-    let db = ResumeDb::open_at(&db_path).await.unwrap();
+    let (t, result) = run_job(&url, JobSettings::default(), &segmented()).await;
+    result.expect("run_one_job");
 
-    db.add_job(&url, &JobSettings::default()).await.unwrap();
-    let job_id = db.list_jobs().await.unwrap()[0].id;
-    db.recover_running_jobs().await.unwrap();
+    assert_eq!(t.job().await.state, JobState::Completed);
+    assert_eq!(t.final_bytes().await, body);
+}
 
-    let cfg = DdmConfig::default();
-    let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
-    scheduler::run_one_job(
-        &db,
-        job_id,
-        false,
-        false,
-        &cfg,
-        download_dir.path(),
-        &mut host_policy,
-        None,
-        None,
-        None,
-    )
-    .await
-    .expect("run_one_job");
+#[test]
+fn probe_discovers_unadvertised_ranges_but_respects_explicit_none() {
+    let body: Vec<u8> = vec![1u8; 4096];
+    let headers = std::collections::HashMap::new();
+    let curl = CurlOptions::default();
 
-    let job = db.get_job(job_id).await.unwrap().expect("job exists");
-    assert_eq!(job.state, JobState::Completed);
-    let final_path = download_dir
-        .path()
-        .join(job.final_filename.as_deref().unwrap_or("download.bin"));
-    let content = std::fs::read(&final_path).unwrap();
-    assert_eq!(content, body);
+    let silent = common::range_server::start_with_options(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            advertise_ranges: false,
+            ..Default::default()
+        },
+    );
+    let head = fetch_head::probe(&silent, &headers, &curl).unwrap();
+    assert_eq!(head.range_support, RangeSupport::Unknown);
+    let best = fetch_head::probe_best_effort(&silent, &headers, &curl).unwrap();
+    assert_eq!(best.range_support, RangeSupport::Supported);
+    assert_eq!(best.content_length, Some(4096));
+
+    let refusing = common::range_server::start_with_options(
+        body,
+        common::range_server::RangeServerOptions {
+            support_ranges: false,
+            advertise_ranges: false,
+            refuse_ranges: true,
+            ..Default::default()
+        },
+    );
+    let best = fetch_head::probe_best_effort(&refusing, &headers, &curl).unwrap();
+    assert_eq!(best.range_support, RangeSupport::NotSupported);
+    assert!(!best.accepts_ranges());
+}
+
+#[test]
+fn probe_strategy_controls_the_requests_sent() {
+    use ddm_core::config::ProbeStrategy;
+
+    let body: Vec<u8> = vec![1u8; 4096];
+    let headers = std::collections::HashMap::new();
+    let curl = CurlOptions::default();
+
+    let (silent, stats) = common::range_server::start_with_stats(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            advertise_ranges: false,
+            ..Default::default()
+        },
+    );
+    let err = fetch_head::probe_with_strategy(&silent, &headers, &curl, ProbeStrategy::HeadOnly)
+        .unwrap_err();
+    assert!(err.to_string().contains("Accept-Ranges"), "{err:#}");
+    assert!(
+        stats.ranges.lock().unwrap().is_empty(),
+        "head_only sent a range GET"
+    );
+    let best = fetch_head::probe_with_strategy(&silent, &headers, &curl, ProbeStrategy::BestEffort)
+        .unwrap();
+    assert_eq!(best.range_support, RangeSupport::Supported);
+    assert_eq!(stats.ranges.lock().unwrap().len(), 1);
+
+    let no_head = common::range_server::start_with_options(
+        body,
+        common::range_server::RangeServerOptions {
+            head_allowed: false,
+            ..Default::default()
+        },
+    );
+    assert!(
+        fetch_head::probe_with_strategy(&no_head, &headers, &curl, ProbeStrategy::HeadOnly)
+            .is_err()
+    );
+    let range =
+        fetch_head::probe_with_strategy(&no_head, &headers, &curl, ProbeStrategy::RangeOnly)
+            .unwrap();
+    assert_eq!(range.range_support, RangeSupport::Supported);
+    assert_eq!(range.content_length, Some(4096));
 }
 
 #[tokio::test]
 async fn no_range_server_falls_back_to_single_stream_get() {
     let body: Vec<u8> = (0u8..100).cycle().take(32 * 1024).collect();
-    let url = common::range_server::start_with_options(
+    let (url, stats) = common::range_server::start_with_stats(
         body.clone(),
         common::range_server::RangeServerOptions {
             head_allowed: true,
             support_ranges: false,
             advertise_ranges: false,
+            ..Default::default()
+        },
+    );
+
+    // Segment even tiny files when Range works, so only the missing support forces one GET.
+    let (t, result) = run_job(&url, JobSettings::default(), &segmented()).await;
+    result.expect("run_one_job");
+
+    let job = t.job().await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(t.final_bytes().await, body);
+    assert_eq!(
+        job.segment_count, 0,
+        "no segment plan for a non-Range server"
+    );
+    assert_eq!(
+        stats.range_requests(),
+        vec![(0, 0)],
+        "only the probe asks for a range; the body is one unranged GET"
+    );
+}
+
+#[tokio::test]
+async fn open_range_probe_sizes_a_length_less_range_server() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    // HEAD has no Content-Length and 206 replies say `bytes a-b/*`: only the `bytes=0-`
+    // reply's own Content-Length reveals the size.
+    let (url, stats) = common::range_server::start_with_stats(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            unknown_total: true,
+            ..Default::default()
+        },
+    );
+    let curl = CurlOptions::default();
+    let head =
+        fetch_head::probe_best_effort(&url, &std::collections::HashMap::new(), &curl).unwrap();
+    assert!(head.accepts_ranges());
+    assert_eq!(
+        head.content_length, None,
+        "the 1-byte slice is not the size"
+    );
+
+    let (t, result) = run_job(&url, JobSettings::default(), &segmented()).await;
+    result.expect("run_one_job");
+
+    let job = t.job().await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.total_size, Some(body.len() as i64));
+    assert!(job.segment_count > 1, "sized by the probe, so segmented");
+    assert_eq!(t.final_bytes().await, body);
+    assert!(stats.range_requests().contains(&(0, u64::MAX)));
+}
+
+#[tokio::test]
+async fn verify_ranges_falls_back_when_advertised_ranges_are_ignored() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    // HEAD advertises `Accept-Ranges: bytes`, but GET always answers 200 with the full body.
+    let opts = common::range_server::RangeServerOptions {
+        support_ranges: false,
+        claim_ranges: true,
+        ..Default::default()
+    };
+
+    for verify_ranges in [false, true] {
+        let url = common::range_server::start_with_options(body.clone(), opts);
+        let cfg = DdmConfig {
+            verify_ranges: Some(verify_ranges),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+
+        let job = t.job().await;
+        if !verify_ranges {
+            assert!(result.is_err(), "segments must reject 200 responses");
+            assert_eq!(job.state, JobState::Error);
+            continue;
+        }
+        result.expect("run_one_job with verify_ranges");
+        assert_eq!(job.state, JobState::Completed);
+        assert_eq!(job.segment_count, 0, "no segmented plan was made");
+        assert_eq!(t.final_bytes().await, body);
+    }
+}
+
+#[tokio::test]
+async fn partially_written_segment_resumes_from_persisted_offset() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    let url = common::range_server::start_with_options(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            etag: Some("\"v1\""),
+            ..Default::default()
+        },
+    );
+
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    t.set_resume_plan(body.len(), 0).await;
+    // Segment 0 already has 1000 bytes on disk; mark them so a re-download would be visible.
+    let mut part = vec![0u8; body.len()];
+    part[..1000].fill(0xAA);
+    std::fs::write(t.path("out.bin.part"), &part).unwrap();
+    t.db.set_segment_progress(t.id, &[1000, 0, 0, 0])
+        .await
+        .unwrap();
+
+    t.run(&segmented()).await.expect("run_one_job");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    let content = std::fs::read(t.path("out.bin")).unwrap();
+    assert!(content[..1000].iter().all(|&b| b == 0xAA), "prefix reused");
+    assert_eq!(&content[1000..], &body[1000..]);
+}
+
+#[tokio::test]
+async fn truncated_part_discards_persisted_offsets() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    let url = common::range_server::start_with_options(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            etag: Some("\"v1\""),
+            ..Default::default()
+        },
+    );
+
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    t.set_resume_plan(body.len(), 0).await;
+    // The offset says 1000 bytes are on disk, but the `.part` was cut short.
+    std::fs::write(t.path("out.bin.part"), vec![0xAAu8; 500]).unwrap();
+    t.db.set_segment_progress(t.id, &[1000, 0, 0, 0])
+        .await
+        .unwrap();
+
+    t.run(&segmented()).await.expect("run_one_job");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    let content = std::fs::read(t.path("out.bin")).unwrap();
+    assert_eq!(content, body, "segment 0 downloaded again from its start");
+}
+
+#[tokio::test]
+async fn shutdown_before_download_pauses_job() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    // A Range-capable server takes the segmented path; one without ranges the single stream.
+    for support_ranges in [true, false] {
+        let url = common::range_server::start_with_options(
+            body.clone(),
+            common::range_server::RangeServerOptions {
+                support_ranges,
+                advertise_ranges: support_ranges,
+                ..Default::default()
+            },
+        );
+
+        let t = TestJob::new(&url, JobSettings::default()).await;
+        let control = Arc::new(JobControl::new());
+        control.request_shutdown();
+        t.run_with(&segmented(), &RunOptions::default(), false, Some(control))
+            .await
+            .expect("aborted run is not an error");
+
+        let job = t.job().await;
+        assert_eq!(
+            job.state,
+            JobState::Paused,
+            "support_ranges={support_ranges}"
+        );
+        assert!(!t.path(job.final_filename.as_deref().unwrap()).exists());
+    }
+}
+
+/// Runs one job for `url` with default config and returns its final record and file bytes.
+async fn run_default_job(url: &str) -> (JobDetails, Vec<u8>) {
+    let (t, result) = run_job(url, JobSettings::default(), &DdmConfig::default()).await;
+    result.expect("run_one_job");
+    (t.job().await, t.final_bytes().await)
+}
+
+#[tokio::test]
+async fn small_file_below_threshold_uses_single_stream() {
+    let body: Vec<u8> = (0u8..100).cycle().take(1000).collect();
+    let url = common::range_server::start(body.clone());
+    let (job, content) = run_default_job(&url).await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.segment_count, 0, "no segment plan for tiny files");
+    assert_eq!(content, body);
+}
+
+#[tokio::test]
+async fn zero_length_file_completes_empty() {
+    let url = common::range_server::start(Vec::new());
+    let (job, content) = run_default_job(&url).await;
+    assert_eq!(job.state, JobState::Completed);
+    assert!(content.is_empty());
+}
+
+#[tokio::test]
+async fn existing_final_file_requires_overwrite() {
+    let body: Vec<u8> = (0u8..100).cycle().take(16 * 1024).collect();
+    let url = common::range_server::start(body.clone());
+
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    let final_path = t.path("download.bin");
+    std::fs::write(&final_path, b"stale").unwrap();
+
+    let cfg = DdmConfig::default();
+    let err = t
+        .run(&cfg)
+        .await
+        .expect_err("existing final file without overwrite must fail");
+    assert!(err.to_string().contains("final file already exists"));
+    assert_eq!(std::fs::read(&final_path).unwrap(), b"stale");
+
+    t.run_with(&cfg, &RunOptions::default(), true, None)
+        .await
+        .expect("run_one_job with overwrite");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    assert_eq!(std::fs::read(&final_path).unwrap(), body);
+}
+
+#[tokio::test]
+async fn on_conflict_renames_or_skips_an_existing_final_file() {
+    use ddm_core::config::ConflictPolicy;
+
+    let body: Vec<u8> = (0u8..100).cycle().take(16 * 1024).collect();
+    // Single-stream (below the default threshold) and segmented paths.
+    for threshold in [None, Some(0)] {
+        for policy in [ConflictPolicy::Rename, ConflictPolicy::Skip] {
+            let url = common::range_server::start(body.clone());
+            let t = TestJob::new(&url, JobSettings::default()).await;
+            let existing = t.path("download.bin");
+            std::fs::write(&existing, b"stale").unwrap();
+
+            let cfg = DdmConfig {
+                single_stream_threshold_bytes: threshold,
+                on_conflict: Some(policy),
+                ..DdmConfig::default()
+            };
+            t.run(&cfg).await.expect("run_one_job");
+
+            let job = t.job().await;
+            assert_eq!(job.state, JobState::Completed, "{policy:?} {threshold:?}");
+            assert_eq!(std::fs::read(&existing).unwrap(), b"stale");
+            let renamed = t.path("download (1).bin");
+            if policy == ConflictPolicy::Rename {
+                assert_eq!(job.final_filename.as_deref(), Some("download (1).bin"));
+                assert_eq!(std::fs::read(&renamed).unwrap(), body, "{threshold:?}");
+            } else {
+                assert!(!renamed.exists());
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn on_conflict_rename_skips_names_other_jobs_claim_in_the_run_dir() {
+    use ddm_core::config::ConflictPolicy;
+
+    let body: Vec<u8> = (0u8..100).cycle().take(16 * 1024).collect();
+    let url = common::range_server::start(body.clone());
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    std::fs::write(t.path("download.bin"), b"stale").unwrap();
+    // A queued job pinned to the same directory has already claimed the first rename.
+    let settings = JobSettings {
+        download_dir: Some(t.download_dir.path().to_string_lossy().to_string()),
+        ..JobSettings::default()
+    };
+    let other = t.db.add_job(&url, &settings).await.unwrap();
+    t.db.set_final_filename(other, "download (1).bin")
+        .await
+        .unwrap();
+
+    let cfg = DdmConfig {
+        on_conflict: Some(ConflictPolicy::Rename),
+        ..DdmConfig::default()
+    };
+    t.run(&cfg).await.expect("run_one_job");
+
+    let job = t.job().await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.final_filename.as_deref(), Some("download (2).bin"));
+    assert_eq!(std::fs::read(t.path("download (2).bin")).unwrap(), body);
+}
+
+#[tokio::test]
+async fn file_url_copies_through_segmented_and_multi_paths() {
+    let src_dir = tempdir().unwrap();
+    let src = src_dir.path().join("source.bin");
+    let body: Vec<u8> = (0u8..251).cycle().take(200 * 1024).collect();
+    std::fs::write(&src, &body).unwrap();
+    let url = url::Url::from_file_path(&src).unwrap().to_string();
+
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let cfg = DdmConfig {
+            download_backend: Some(backend),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+        result.expect("run_one_job");
+
+        let job = t.job().await;
+        assert_eq!(job.state, JobState::Completed, "{backend:?}");
+        assert!(job.segment_count > 1, "{backend:?} should segment");
+        assert_eq!(job.final_filename.as_deref(), Some("source.bin"));
+        let content = std::fs::read(t.path("source.bin")).unwrap();
+        assert_eq!(content, body, "{backend:?}");
+    }
+
+    // Small local files take the single-GET path.
+    let small = src_dir.path().join("small.txt");
+    std::fs::write(&small, b"hello").unwrap();
+    let url = url::Url::from_file_path(&small).unwrap().to_string();
+    let (job, content) = run_default_job(&url).await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(content, b"hello");
+}
+
+#[tokio::test]
+async fn easy_backend_workers_reuse_connections() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let (url, stats) = common::range_server::start_with_stats(body.clone(), Default::default());
+
+    let cfg = DdmConfig {
+        min_segments: 16,
+        max_segments: 16,
+        max_connections_per_host: 4,
+        ..segmented()
+    };
+    let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+    result.expect("run_one_job");
+
+    let job = t.job().await;
+    assert_eq!(job.state, JobState::Completed);
+    assert_eq!(job.segment_count, 16);
+    assert_eq!(t.final_bytes().await, body);
+    // One probe connection plus one per worker, not one per segment.
+    let connections = stats.connections.load(std::sync::atomic::Ordering::SeqCst);
+    assert!(
+        connections <= 5,
+        "{connections} connections for 16 segments"
+    );
+}
+
+#[tokio::test]
+async fn segments_share_per_host_connection_cap_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(512 * 1024).collect();
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, stats) = common::range_server::start_with_stats(body.clone(), Default::default());
+        let cfg = DdmConfig {
+            min_segments: 32,
+            max_segments: 32,
+            max_connections_per_host: 4,
+            download_backend: Some(backend),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+        result.expect("run_one_job");
+
+        let job = t.job().await;
+        assert_eq!(job.state, JobState::Completed, "{backend:?}");
+        assert_eq!(job.segment_count, 32, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        let max_active = stats.max_active.load(std::sync::atomic::Ordering::SeqCst);
+        let connections = stats.connections.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(
+            max_active <= 4,
+            "{backend:?}: {max_active} connections open at once"
+        );
+        assert!(
+            connections <= 5,
+            "{backend:?}: {connections} connections for 32 segments"
+        );
+    }
+}
+
+#[tokio::test]
+async fn content_range_total_mismatch_fails_job_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let opts = common::range_server::RangeServerOptions {
+        range_total: Some(512 * 1024),
+        ..Default::default()
+    };
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let url = common::range_server::start_with_options(body.clone(), opts);
+        let cfg = DdmConfig {
+            min_segments: 4,
+            max_segments: 4,
+            download_backend: Some(backend),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+        let err = result.expect_err("mismatched Content-Range total must fail the job");
+        assert!(
+            format!("{err:#}").contains("Content-Range reports a total of 524288"),
+            "{backend:?}: {err:#}"
+        );
+
+        let job = t.job().await;
+        assert_eq!(job.state, JobState::Error, "{backend:?}");
+        assert_eq!(
+            job.error_category,
+            Some(ErrorCategory::RemoteChanged),
+            "{backend:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn mirror_list_spreads_segments_across_sources_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(512 * 1024).collect();
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, primary) =
+            common::range_server::start_with_stats(body.clone(), Default::default());
+        let (mirror_url, mirror) =
+            common::range_server::start_with_stats(body.clone(), Default::default());
+        let settings = JobSettings {
+            mirrors: Some(vec![mirror_url]),
+            ..JobSettings::default()
+        };
+        let cfg = DdmConfig {
+            min_segments: 8,
+            max_segments: 8,
+            download_backend: Some(backend),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, settings, &cfg).await;
+        result.expect("run_one_job");
+
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        // The primary also serves the probe; the mirror only sees segment requests.
+        let primary = primary
+            .connections
+            .load(std::sync::atomic::Ordering::SeqCst);
+        let mirror = mirror.connections.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(
+            primary >= 2,
+            "{backend:?}: primary saw {primary} connections"
+        );
+        assert!(mirror >= 1, "{backend:?}: mirror saw {mirror} connections");
+    }
+}
+
+#[tokio::test]
+async fn expected_checksum_gates_completion() {
+    use ddm_core::checksum::ChecksumAlgo;
+    use sha2::Digest;
+
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let good = hex::encode(sha2::Sha256::digest(&body));
+    for (expected, ok) in [(good, true), ("0".repeat(64), false)] {
+        let url = common::range_server::start(body.clone());
+        let settings = JobSettings {
+            expected_checksum: Some((ChecksumAlgo::Sha256, expected)),
+            ..JobSettings::default()
+        };
+        let cfg = DdmConfig {
+            min_segments: 4,
+            max_segments: 4,
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, settings, &cfg).await;
+
+        let job = t.job().await;
+        let final_path = t.path("download.bin");
+        let states: Vec<JobState> =
+            t.db.list_state_changes(None, Some(t.id))
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|c| c.to)
+                .collect();
+        let end = if ok {
+            JobState::Completed
+        } else {
+            JobState::Error
+        };
+        assert!(
+            states.ends_with(&[JobState::Running, JobState::Verifying, end]),
+            "{states:?}"
+        );
+        if ok {
+            result.expect("matching checksum completes");
+            assert_eq!(job.state, JobState::Completed);
+            assert_eq!(std::fs::read(final_path).unwrap(), body);
+        } else {
+            let err = result.expect_err("wrong checksum must fail the job");
+            assert!(format!("{err:#}").contains("sha256 mismatch"), "{err:#}");
+            assert_eq!(job.state, JobState::Error);
+            assert!(
+                !final_path.exists(),
+                "mismatched file must not be finalized"
+            );
+            assert_eq!(job.segment_count, 0, "plan is reset so a retry starts over");
+        }
+    }
+}
+
+#[tokio::test]
+async fn server_digest_is_verified_without_a_user_checksum() {
+    use base64::Engine;
+    use ddm_core::checksum::ChecksumAlgo;
+    use sha2::Digest;
+
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let b64 = |data: &[u8]| {
+        let d = base64::engine::general_purpose::STANDARD.encode(sha2::Sha256::digest(data));
+        &*Box::leak(format!("sha-256={d}").into_boxed_str())
+    };
+    let good = hex::encode(sha2::Sha256::digest(&body));
+    // (server Digest, user checksum, completes)
+    let cases = [
+        (b64(&body), None, true),
+        (b64(b"another file"), None, false),
+        // A user-supplied checksum wins over the server's.
+        (
+            b64(b"another file"),
+            Some((ChecksumAlgo::Sha256, good)),
+            true,
+        ),
+    ];
+    for (digest, expected_checksum, ok) in cases {
+        let url = common::range_server::start_with_options(
+            body.clone(),
+            common::range_server::RangeServerOptions {
+                digest: Some(digest),
+                ..Default::default()
+            },
+        );
+        let settings = JobSettings {
+            expected_checksum,
+            ..JobSettings::default()
+        };
+        let cfg = DdmConfig {
+            min_segments: 4,
+            max_segments: 4,
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, settings, &cfg).await;
+
+        let job = t.job().await;
+        if ok {
+            result.expect("matching digest completes");
+            assert_eq!(job.state, JobState::Completed);
+        } else {
+            let err = result.expect_err("server digest mismatch must fail the job");
+            assert!(format!("{err:#}").contains("sha256 mismatch"), "{err:#}");
+            assert_eq!(job.state, JobState::Error);
+            assert!(!t.path("download.bin").exists());
+        }
+    }
+}
+
+#[tokio::test]
+async fn connect_to_pins_an_unresolvable_host_to_the_server() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let server = common::range_server::start(body.clone());
+    let port = server
+        .trim_end_matches('/')
+        .rsplit(':')
+        .next()
+        .unwrap()
+        .to_string();
+    // `.invalid` never resolves, so the job only succeeds through the override.
+    let url = format!("http://mirror.invalid:{port}/");
+    let cfg = DdmConfig {
+        min_segments: 4,
+        max_segments: 4,
+        connect_to: vec![format!("mirror.invalid:{port}:127.0.0.1:{port}")],
+        ..segmented()
+    };
+    let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+    result.expect("run_one_job");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    assert_eq!(t.final_bytes().await, body);
+}
+
+#[tokio::test]
+async fn expected_size_mismatch_fails_before_planning() {
+    let body: Vec<u8> = vec![7u8; 64 * 1024];
+    let url = common::range_server::start(body);
+    let settings = JobSettings {
+        expected_size: Some(4_700_372_992),
+        ..JobSettings::default()
+    };
+    let (t, result) = run_job(&url, settings, &DdmConfig::default()).await;
+    let err = result.expect_err("size mismatch must fail the job");
+
+    assert!(format!("{err:#}").contains("size mismatch"), "{err:#}");
+    let job = t.job().await;
+    assert_eq!(job.state, JobState::Error);
+    assert!(job.total_size.is_none() && job.segment_count == 0);
+    assert_eq!(std::fs::read_dir(t.download_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn output_filename_overrides_derived_name_without_renaming() {
+    let body: Vec<u8> = (0u8..200).cycle().take(128 * 1024).collect();
+    let url = common::range_server::start(body.clone());
+    let download_dir = tempdir().unwrap();
+    let settings = JobSettings {
+        output_filename: Some("custom.iso".to_string()),
+        download_dir: Some(download_dir.path().to_string_lossy().to_string()),
+        ..JobSettings::default()
+    };
+    let t = TestJob::new(&url, settings).await;
+    // Another job already owns the name; a forced name is not renamed to "custom (1).iso".
+    let other = t.db.add_job(&url, &JobSettings::default()).await.unwrap();
+    t.db.update_metadata(
+        other,
+        &JobMetadata {
+            final_filename: Some("custom.iso".to_string()),
+            temp_filename: None,
+            total_size: None,
+            etag: None,
+            last_modified: None,
+            segment_count: 0,
+            completed_bitmap: Vec::new(),
+        },
+    )
+    .await
+    .unwrap();
+    t.run(&DdmConfig::default())
+        .await
+        .expect("download completes");
+
+    let job = t.job().await;
+    assert_eq!(job.final_filename.as_deref(), Some("custom.iso"));
+    assert_eq!(
+        std::fs::read(download_dir.path().join("custom.iso")).unwrap(),
+        body
+    );
+    assert!(!download_dir.path().join("download.bin").exists());
+}
+
+#[tokio::test]
+async fn verify_on_resume_redownloads_segments_of_a_truncated_part() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    let url = common::range_server::start_with_options(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            etag: Some("\"v1\""),
+            ..Default::default()
         },
     );
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    // All four segments recorded as done, but the .part was truncated to its first half.
+    t.set_resume_plan(body.len(), 0b1111).await;
+    std::fs::write(t.path("out.bin.part"), &body[..body.len() / 2]).unwrap();
+
+    let cfg = DdmConfig {
+        verify_on_resume: Some(true),
+        ..segmented()
+    };
+    t.run(&cfg).await.expect("run_one_job");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    assert_eq!(std::fs::read(t.path("out.bin")).unwrap(), body);
+}
+
+#[tokio::test]
+async fn parallel_run_stops_after_max_jobs() {
+    let body: Vec<u8> = (0u8..100).cycle().take(16 * 1024).collect();
+    let url = common::range_server::start(body);
 
     let download_dir = tempdir().unwrap();
     let state_dir = tempdir().unwrap();
-    let db_path = state_dir.path().join("jobs.db");
-    let db = ResumeDb::open_at(&db_path).await.unwrap();
-
-    db.add_job(&url, &JobSettings::default()).await.unwrap();
-    let job_id = db.list_jobs().await.unwrap()[0].id;
-    db.recover_running_jobs().await.unwrap();
+    let db = ResumeDb::open_at(&state_dir.path().join("jobs.db"))
+        .await
+        .unwrap();
+    for i in 0..3 {
+        let settings = JobSettings {
+            output_filename: Some(format!("part-{i}.bin")),
+            ..JobSettings::default()
+        };
+        db.add_job(&url, &settings).await.unwrap();
+    }
 
     let cfg = DdmConfig::default();
     let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
-    scheduler::run_one_job(
+    let budget = Arc::new(scheduler::GlobalConnectionBudget::new(
+        cfg.max_total_connections,
+    ));
+    let run_count = scheduler::run_jobs_parallel(
         &db,
-        job_id,
-        false,
-        false,
         &cfg,
-        download_dir.path(),
+        &RunOptions::default(),
+        download_dir.path().to_path_buf(),
         &mut host_policy,
+        false,
+        false,
         None,
-        None,
+        budget,
+        2,
+        Some(2),
         None,
     )
     .await
-    .expect("run_one_job");
+    .expect("run_jobs_parallel");
+
+    assert_eq!(run_count, 2);
+    let jobs = db.list_jobs().await.unwrap();
+    let count = |state| jobs.iter().filter(|j| j.state == state).count();
+    assert_eq!(count(JobState::Completed), 2);
+    assert_eq!(count(JobState::Queued), 1);
+}
+
+#[tokio::test]
+async fn circuit_breaker_leaves_a_dead_hosts_jobs_queued() {
+    let body: Vec<u8> = (0u8..100).cycle().take(16 * 1024).collect();
+    let live = common::range_server::start(body.clone());
+    // A port nothing listens on: every connection is refused.
+    let dead_port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let dead = format!("http://localhost:{dead_port}/");
+
+    for parallel in [false, true] {
+        let download_dir = tempdir().unwrap();
+        let state_dir = tempdir().unwrap();
+        let db = ResumeDb::open_at(&state_dir.path().join("jobs.db"))
+            .await
+            .unwrap();
+        let mut dead_ids = Vec::new();
+        for i in 0..4 {
+            dead_ids.push(
+                db.add_job(&format!("{dead}{i}.iso"), &JobSettings::default())
+                    .await
+                    .unwrap(),
+            );
+        }
+        let live_id = db.add_job(&live, &JobSettings::default()).await.unwrap();
+
+        let cfg = DdmConfig {
+            circuit_failure_threshold: Some(2),
+            ..DdmConfig::default()
+        };
+        let mut host_policy = HostPolicy::new(cfg.min_segments, cfg.max_segments);
+        if parallel {
+            let budget = Arc::new(scheduler::GlobalConnectionBudget::new(
+                cfg.max_total_connections,
+            ));
+            scheduler::run_jobs_parallel(
+                &db,
+                &cfg,
+                &RunOptions::default(),
+                download_dir.path().to_path_buf(),
+                &mut host_policy,
+                false,
+                false,
+                None,
+                budget,
+                1,
+                None,
+                None,
+            )
+            .await
+            .expect("an unreachable host does not stop the run");
+        } else {
+            while scheduler::run_next_job(
+                &db,
+                false,
+                false,
+                &cfg,
+                &RunOptions::default(),
+                download_dir.path(),
+                &mut host_policy,
+                None,
+                None,
+                None,
+            )
+            .await
+            .expect("an unreachable host does not stop the run")
+            {}
+        }
+
+        let state = |id| {
+            let db = db.clone();
+            async move { db.get_job(id).await.unwrap().unwrap() }
+        };
+        for &id in &dead_ids[..2] {
+            let job = state(id).await;
+            assert_eq!(job.state, JobState::Error, "parallel={parallel}");
+            assert_eq!(job.error_category, Some(ErrorCategory::Network));
+        }
+        for &id in &dead_ids[2..] {
+            assert_eq!(
+                state(id).await.state,
+                JobState::Queued,
+                "parallel={parallel}"
+            );
+        }
+        assert_eq!(state(live_id).await.state, JobState::Completed);
+        let key = ddm_core::host_policy::HostKey::from_url(&dead).unwrap();
+        assert!(host_policy
+            .circuit_open_until(&key, std::time::Instant::now())
+            .is_some());
+    }
+}
+
+#[tokio::test]
+async fn resume_requests_only_the_missing_segments() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    let seg = body.len() as u64 / 4;
+    let (url, stats) = common::range_server::start_with_stats(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            etag: Some("\"v1\""),
+            ..Default::default()
+        },
+    );
+
+    let t = TestJob::new(&url, JobSettings::default()).await;
+    // Segments 0 and 2 are complete on disk; 1 and 3 still need fetching.
+    t.set_resume_plan(body.len(), 0b0101).await;
+    let mut part = vec![0u8; body.len()];
+    for i in [0, 2] {
+        let r = (i * seg) as usize..((i + 1) * seg) as usize;
+        part[r.clone()].copy_from_slice(&body[r]);
+    }
+    std::fs::write(t.path("out.bin.part"), &part).unwrap();
+
+    t.run(&segmented()).await.expect("run_one_job");
+
+    assert_eq!(t.job().await.state, JobState::Completed);
+    assert_eq!(std::fs::read(t.path("out.bin")).unwrap(), body);
+    assert_eq!(
+        stats.range_requests(),
+        [(seg, 2 * seg - 1), (3 * seg, 4 * seg - 1)]
+    );
+}
 
-    let job = db.get_job(job_id).await.unwrap().expect("job exists");
+/// Server that cuts the first two Range GETs off after 1000 body bytes.
+fn start_truncating_server(body: &[u8]) -> (String, Arc<common::range_server::ServerStats>) {
+    common::range_server::start_with_stats(
+        body.to_vec(),
+        common::range_server::RangeServerOptions {
+            etag: Some("\"v1\""),
+            truncate_after_bytes: Some(1000),
+            truncate_requests: 2,
+            ..Default::default()
+        },
+    )
+}
+
+fn truncation_cfg(backend: DownloadBackend, max_attempts: Option<u32>) -> DdmConfig {
+    let retry = ddm_core::config::RetryConfig::default();
+    DdmConfig {
+        min_segments: 4,
+        max_segments: 4,
+        download_backend: Some(backend),
+        retry: Some(retry.with_overrides(max_attempts, Some(10))),
+        ..segmented()
+    }
+}
+
+#[tokio::test]
+async fn truncated_segment_transfers_are_retried_to_completion() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, stats) = start_truncating_server(&body);
+        let cfg = truncation_cfg(backend, None);
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+        result.expect("run_one_job");
+
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        let requests = stats.range_requests().len();
+        assert!(requests >= 6, "{backend:?}: only {requests} Range GETs");
+    }
+}
+
+#[tokio::test]
+async fn truncated_segments_are_never_marked_complete() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let seg = body.len() as u64 / 4;
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, stats) = start_truncating_server(&body);
+        let cfg = truncation_cfg(backend, Some(1));
+        let (t, result) = run_job(&url, JobSettings::default(), &cfg).await;
+        result.expect_err("a truncated segment with no retries left must fail the job");
+
+        let job = t.job().await;
+        assert_eq!(job.state, JobState::Error, "{backend:?}");
+        let bitmap = ddm_core::segmenter::SegmentBitmap::from_bytes(&job.completed_bitmap, 4);
+        let truncated: Vec<u64> = stats.ranges.lock().unwrap()[..2]
+            .iter()
+            .map(|&(start, _)| start / seg)
+            .collect();
+        for i in truncated {
+            assert!(!bitmap.is_completed(i as usize), "{backend:?}: segment {i}");
+        }
+        let part = std::fs::read(t.path(&job.temp_filename.unwrap())).unwrap();
+        for i in (0..4).filter(|&i| bitmap.is_completed(i)) {
+            let r = i * seg as usize..(i + 1) * seg as usize;
+            assert_eq!(&part[r.clone()], &body[r], "{backend:?}: segment {i}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn sequential_order_waits_for_a_stalled_front_segment_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(512 * 1024).collect();
+    let seg = body.len() as u64 / 16;
+    let window = ddm_core::segmenter::SEQUENTIAL_LOOKAHEAD as u64;
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        // The first Range GET is cut short, so that segment is retried after a backoff.
+        let (url, stats) = common::range_server::start_with_stats(
+            body.clone(),
+            common::range_server::RangeServerOptions {
+                truncate_after_bytes: Some(1000),
+                truncate_requests: 1,
+                ..Default::default()
+            },
+        );
+        let settings = JobSettings {
+            order: ddm_core::segmenter::SegmentOrder::Sequential,
+            ..JobSettings::default()
+        };
+        let retry = ddm_core::config::RetryConfig::default();
+        let cfg = DdmConfig {
+            min_segments: 16,
+            max_segments: 16,
+            max_connections_per_host: 8,
+            download_backend: Some(backend),
+            retry: Some(retry.with_overrides(None, Some(50))),
+            ..segmented()
+        };
+        let (t, result) = run_job(&url, settings, &cfg).await;
+        result.expect("run_one_job");
+
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        // Until the stalled segment is re-requested, nothing starts SEQUENTIAL_LOOKAHEAD past it.
+        let arrivals = stats.ranges.lock().unwrap().clone();
+        let stalled = arrivals[0].0 / seg;
+        let retried_at = 1 + arrivals[1..]
+            .iter()
+            .position(|&(start, _)| start / seg == stalled)
+            .expect("stalled segment retried");
+        for &(start, _) in &arrivals[..retried_at] {
+            assert!(
+                start / seg < stalled + window,
+                "{backend:?}: segment {} started while {} was stalled",
+                start / seg,
+                stalled
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn connection_ramp_staggers_connection_starts_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    let ramp = std::time::Duration::from_millis(150);
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let url = common::range_server::start(body.clone());
+        let cfg = DdmConfig {
+            min_segments: 4,
+            max_segments: 4,
+            max_connections_per_host: 4,
+            download_backend: Some(backend),
+            connection_ramp_ms: Some(ramp.as_millis() as u64),
+            ..segmented()
+        };
+        let t = TestJob::new(&url, JobSettings::default()).await;
+        let started = std::time::Instant::now();
+        t.run(&cfg).await.expect("run_one_job");
+
+        // The fourth connection may only open three ramp intervals after the first.
+        assert!(started.elapsed() >= ramp * 3, "{backend:?}");
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+    }
+}
+
+#[tokio::test]
+async fn min_segment_bps_retries_a_stalled_segment_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, stats) = common::range_server::start_with_stats(
+            body.clone(),
+            common::range_server::RangeServerOptions {
+                stall_after_bytes: Some(1000),
+                stall_requests: 1,
+                ..Default::default()
+            },
+        );
+        let cfg = DdmConfig {
+            min_segment_bps: Some(4096),
+            stall_window_secs: Some(2),
+            ..truncation_cfg(backend, None)
+        };
+        let t = TestJob::new(&url, JobSettings::default()).await;
+        let started = std::time::Instant::now();
+        t.run(&cfg).await.expect("run_one_job");
+
+        // Trickling the 64 KiB segment would take minutes; the watchdog retries it instead.
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(20),
+            "{backend:?}"
+        );
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        let ranges = stats.range_requests();
+        assert_eq!(ranges.len(), 5, "{backend:?}: {ranges:?}");
+    }
+}
+
+#[tokio::test]
+async fn min_expected_bps_times_out_a_stalled_segment_on_both_backends() {
+    let body: Vec<u8> = (0u8..100).cycle().take(256 * 1024).collect();
+    for backend in [DownloadBackend::Easy, DownloadBackend::Multi] {
+        let (url, stats) = common::range_server::start_with_stats(
+            body.clone(),
+            common::range_server::RangeServerOptions {
+                stall_after_bytes: Some(1000),
+                stall_requests: 1,
+                ..Default::default()
+            },
+        );
+        let cfg = DdmConfig {
+            // 64 KiB segments at 32 KiB/s: a 2s timeout instead of an hour.
+            min_expected_bps: Some(32 * 1024),
+            min_timeout_secs: Some(1),
+            ..truncation_cfg(backend, None)
+        };
+        let t = TestJob::new(&url, JobSettings::default()).await;
+        let started = std::time::Instant::now();
+        t.run(&cfg).await.expect("run_one_job");
+
+        // Trickling the 64 KiB segment would take minutes; its scaled timeout retries it instead.
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(20),
+            "{backend:?}"
+        );
+        assert_eq!(t.job().await.state, JobState::Completed, "{backend:?}");
+        assert_eq!(t.final_bytes().await, body, "{backend:?}");
+        let ranges = stats.range_requests();
+        assert_eq!(ranges.len(), 5, "{backend:?}: {ranges:?}");
+    }
+}
+
+#[tokio::test]
+async fn cookie_session_cookies_follow_redirects_to_another_host() {
+    let body: Vec<u8> = (0u8..100).cycle().take(64 * 1024).collect();
+    let target = common::range_server::start_with_options(
+        body.clone(),
+        common::range_server::RangeServerOptions {
+            require_cookie: Some("session=abc123"),
+            ..Default::default()
+        },
+    );
+    let url = common::range_server::start_redirect(&target);
+
+    let settings = JobSettings {
+        custom_headers: Some(
+            [(
+                "Cookie".to_string(),
+                "theme=dark; session=abc123".to_string(),
+            )]
+            .into(),
+        ),
+        cookie_session: true,
+        ..JobSettings::default()
+    };
+    let (t, result) = run_job(&url, settings, &segmented()).await;
+    result.expect("run_one_job");
+
+    let job = t.job().await;
     assert_eq!(job.state, JobState::Completed);
-    let final_path = download_dir
-        .path()
-        .join(job.final_filename.as_deref().unwrap_or("download.bin"));
-    let content = std::fs::read(&final_path).unwrap();
-    assert_eq!(content, body);
+    assert!(job.segment_count > 1, "probe and segments all got through");
+    assert_eq!(t.final_bytes().await, body);
 }